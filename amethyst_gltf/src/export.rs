@@ -0,0 +1,401 @@
+//! Writes the live scene back out as glTF: the node hierarchy with its current transforms,
+//! references to the meshes, skins and the animation clips.
+
+use std::{collections::HashMap, fs, path::Path};
+
+use serde_json::{json, Map, Value};
+
+use amethyst_animation::{
+    Animation, AnimationHierarchy, AnimationSet, InterpolationFunction, Sampler, SamplerPrimitive,
+    Skin, TransformChannel,
+};
+use amethyst_assets::AssetStorage;
+use amethyst_core::{
+    ecs::prelude::{Entity, Read, ReadExpect, ReadStorage, World, WorldExt},
+    Named,
+    transform::{ParentHierarchy, Transform},
+};
+use amethyst_error::{Error, format_err};
+
+use crate::GltfMeshSource;
+
+const FLOAT: u32 = 5126;
+const GLB_MAGIC: &[u8; 4] = b"glTF";
+const GLB_JSON: u32 = 0x4E4F_534A;
+const GLB_BIN: u32 = 0x004E_4942;
+
+/// Local transforms of nodes sampled from the live world at a fixed rate, e.g. to bake procedural motion.
+#[derive(Debug, Clone, Default)]
+pub struct GltfClip {
+    /// Name of the animation in the file
+    pub name: String,
+    /// Frames per second
+    pub frame_rate: f32,
+    /// The local transform of each node in every frame
+    pub tracks: Vec<(Entity, Vec<Transform>)>,
+}
+
+/// Writes the hierarchies under some root entities to a `.gltf` file, or to a `.glb` file if the path ends with it.
+///
+/// Meshes can't be read back once loaded, so nodes refer to the mesh they were loaded from in their extras,
+/// as `"mesh_source": { "scene": "cat.gltf", "mesh": 0, "primitive": 0 }`, along with the index of their `"skin"`.
+/// Animation sets on the nodes are written as they are, followed by the clips added to the exporter.
+#[derive(Debug, Clone, Default)]
+pub struct GltfExporter {
+    roots: Vec<Entity>,
+    clips: Vec<GltfClip>,
+}
+
+type ExportData<'a> = (
+    ReadStorage<'a, Transform>,
+    ReadStorage<'a, Named>,
+    ReadStorage<'a, GltfMeshSource>,
+    ReadStorage<'a, Skin>,
+    ReadStorage<'a, AnimationSet<usize, Transform>>,
+    ReadStorage<'a, AnimationHierarchy<Transform>>,
+    ReadExpect<'a, ParentHierarchy>,
+    Read<'a, AssetStorage<Animation<Transform>>>,
+    Read<'a, AssetStorage<Sampler<SamplerPrimitive<f32>>>>,
+);
+
+/// The binary buffer of the file with its views and accessors.
+#[derive(Debug, Default)]
+struct Buffer {
+    data: Vec<u8>,
+    views: Vec<Value>,
+    accessors: Vec<Value>,
+}
+
+impl Buffer {
+    /// Adds an accessor of `kind` over `values`, `components` floats per element, and returns its index.
+    fn push(&mut self, kind: &str, components: usize, values: &[f32]) -> usize {
+        while self.data.len() % 4 != 0 {
+            self.data.push(0);
+        }
+        let offset = self.data.len();
+        for value in values {
+            self.data.extend_from_slice(&value.to_le_bytes());
+        }
+        self.views.push(json!({ "buffer": 0, "byteOffset": offset, "byteLength": values.len() * 4 }));
+
+        let mut accessor = json!({
+            "bufferView": self.views.len() - 1,
+            "componentType": FLOAT,
+            "count": values.len() / components,
+            "type": kind,
+        });
+        // Animation inputs must give their bounds.
+        if kind == "SCALAR" && !values.is_empty() {
+            let min = values.iter().copied().fold(f32::INFINITY, f32::min);
+            let max = values.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+            accessor["min"] = json!([min]);
+            accessor["max"] = json!([max]);
+        }
+        self.accessors.push(accessor);
+        self.accessors.len() - 1
+    }
+}
+
+/// Animation samplers and channels being gathered for one animation.
+#[derive(Debug, Default)]
+struct Channels {
+    samplers: Vec<Value>,
+    channels: Vec<Value>,
+}
+
+impl Channels {
+    fn push(&mut self, node: usize, path: &str, input: usize, output: usize, interpolation: &str) {
+        self.samplers.push(json!({ "input": input, "output": output, "interpolation": interpolation }));
+        self.channels.push(json!({ "sampler": self.samplers.len() - 1, "target": { "node": node, "path": path } }));
+    }
+
+    fn into_animation(self, name: String) -> Option<Value> {
+        if self.channels.is_empty() { return None; }
+        Some(json!({ "name": name, "samplers": self.samplers, "channels": self.channels }))
+    }
+}
+
+fn channel_path(channel: &TransformChannel) -> &'static str {
+    match channel {
+        TransformChannel::Translation => "translation",
+        TransformChannel::Rotation => "rotation",
+        TransformChannel::Scale => "scale",
+    }
+}
+
+fn interpolation(function: &InterpolationFunction<SamplerPrimitive<f32>>) -> &'static str {
+    match function {
+        InterpolationFunction::Step => "STEP",
+        InterpolationFunction::CubicSpline => "CUBICSPLINE",
+        // Spherical linear is how glTF interpolates rotations linearly; Catmull-Rom splines have no counterpart.
+        _ => "LINEAR",
+    }
+}
+
+fn flatten(output: &[SamplerPrimitive<f32>]) -> (usize, Vec<f32>) {
+    let components = match output.first() {
+        Some(SamplerPrimitive::Scalar(_)) | None => 1,
+        Some(SamplerPrimitive::Vec2(_)) => 2,
+        Some(SamplerPrimitive::Vec3(_)) => 3,
+        Some(SamplerPrimitive::Vec4(_)) => 4,
+    };
+    let values = output
+        .iter()
+        .flat_map(|primitive| match primitive {
+            SamplerPrimitive::Scalar(value) => vec![*value],
+            SamplerPrimitive::Vec2(value) => value.to_vec(),
+            SamplerPrimitive::Vec3(value) => value.to_vec(),
+            SamplerPrimitive::Vec4(value) => value.to_vec(),
+        })
+        .collect();
+    (components, values)
+}
+
+fn accessor_type(components: usize) -> &'static str {
+    match components {
+        1 => "SCALAR",
+        2 => "VEC2",
+        3 => "VEC3",
+        _ => "VEC4",
+    }
+}
+
+impl GltfExporter {
+    /// Exports the hierarchies under `roots`, each a root node of the scene.
+    pub fn new(roots: Vec<Entity>) -> Self {
+        GltfExporter { roots, clips: vec![] }
+    }
+
+    /// Adds a baked clip; tracks of nodes outside the exported hierarchies are left out.
+    pub fn with_clip(mut self, clip: GltfClip) -> Self {
+        self.clips.push(clip);
+        self
+    }
+
+    /// Writes the scene to `path`, as binary glTF if its extension is `glb`.
+    pub fn write<P: AsRef<Path>>(&self, world: &World, path: P) -> Result<(), Error> {
+        let path = path.as_ref();
+        let binary = path.extension().map_or(false, |extension| extension.eq_ignore_ascii_case("glb"));
+        let (mut root, buffer) = self.export(world)?;
+
+        if binary {
+            if !buffer.is_empty() {
+                root["buffers"] = json!([{ "byteLength": buffer.len() }]);
+            }
+            fs::write(path, glb(serde_json::to_vec(&root)?, buffer))?;
+        } else {
+            if !buffer.is_empty() {
+                let uri = format!("data:application/octet-stream;base64,{}", base64::encode(&buffer));
+                root["buffers"] = json!([{ "byteLength": buffer.len(), "uri": uri }]);
+            }
+            fs::write(path, serde_json::to_vec_pretty(&root)?)?;
+        }
+        Ok(())
+    }
+
+    /// The json of the file without its buffer, and the data of the buffer.
+    fn export(&self, world: &World) -> Result<(Value, Vec<u8>), Error> {
+        let (transforms, names, mesh_sources, skins, animation_sets, hierarchies, parents, animations, samplers) =
+            world.system_data::<ExportData<'_>>();
+
+        // Nodes are numbered breadth first, so the roots come first.
+        let mut entities = self.roots.clone();
+        let mut index = 0;
+        while index < entities.len() {
+            let children = parents
+                .children(entities[index])
+                .iter()
+                .filter(|child| transforms.contains(**child))
+                .copied()
+                .collect::<Vec<_>>();
+            entities.extend(children);
+            index += 1;
+        }
+        if entities.is_empty() {
+            return Err(format_err!("There is nothing to export"));
+        }
+        let indices = entities
+            .iter()
+            .enumerate()
+            .map(|(index, entity)| (*entity, index))
+            .collect::<HashMap<_, _>>();
+
+        let mut buffer = Buffer::default();
+
+        // Skins whose joints are all exported, with the nodes of their meshes.
+        let mut skin_values = vec![];
+        let mut skinned = HashMap::new();
+        for entity in &entities {
+            let skin = match skins.get(*entity) {
+                Some(skin) => skin,
+                None => continue,
+            };
+            let joints = match skin.joints.iter().map(|joint| indices.get(joint).copied()).collect::<Option<Vec<_>>>() {
+                Some(joints) => joints,
+                None => continue,
+            };
+            let matrices = skin
+                .inverse_bind_matrices
+                .iter()
+                .flat_map(|matrix| matrix.as_slice().to_vec())
+                .collect::<Vec<_>>();
+            let inverse_bind_matrices = buffer.push("MAT4", 16, &matrices);
+            for mesh in entities.iter().filter(|mesh| skin.meshes.contains(mesh.id())) {
+                skinned.insert(*mesh, skin_values.len());
+            }
+            skin_values.push(json!({ "joints": joints, "inverseBindMatrices": inverse_bind_matrices }));
+        }
+
+        let nodes = entities
+            .iter()
+            .map(|entity| {
+                let mut node = Map::new();
+                if let Some(name) = names.get(*entity) {
+                    node.insert("name".into(), json!(name.name));
+                }
+                if let Some(transform) = transforms.get(*entity) {
+                    let translation = transform.translation();
+                    let rotation = transform.rotation().coords;
+                    let scale = transform.scale();
+                    node.insert("translation".into(), json!([translation.x, translation.y, translation.z]));
+                    node.insert("rotation".into(), json!([rotation.x, rotation.y, rotation.z, rotation.w]));
+                    node.insert("scale".into(), json!([scale.x, scale.y, scale.z]));
+                }
+                let children = parents
+                    .children(*entity)
+                    .iter()
+                    .filter_map(|child| indices.get(child))
+                    .collect::<Vec<_>>();
+                if !children.is_empty() {
+                    node.insert("children".into(), json!(children));
+                }
+                let mut extras = Map::new();
+                if let Some(source) = mesh_sources.get(*entity) {
+                    extras.insert("mesh_source".into(), json!(source));
+                }
+                if let Some(skin) = skinned.get(entity) {
+                    extras.insert("skin".into(), json!(skin));
+                }
+                if !extras.is_empty() {
+                    node.insert("extras".into(), Value::Object(extras));
+                }
+                Value::Object(node)
+            })
+            .collect::<Vec<_>>();
+
+        let mut animation_values = vec![];
+        for entity in &entities {
+            let (set, hierarchy) = match (animation_sets.get(*entity), hierarchies.get(*entity)) {
+                (Some(set), Some(hierarchy)) => (set, hierarchy),
+                _ => continue,
+            };
+            let mut ids = set.animations.keys().copied().collect::<Vec<_>>();
+            ids.sort();
+            for id in ids {
+                let animation = match animations.get(&set.animations[&id]) {
+                    Some(animation) => animation,
+                    None => continue,
+                };
+                let mut channels = Channels::default();
+                for (node, channel, sampler) in &animation.nodes {
+                    let node = hierarchy.nodes.get(node).and_then(|node| indices.get(node));
+                    let (node, sampler) = match (node, samplers.get(sampler)) {
+                        (Some(node), Some(sampler)) => (*node, sampler),
+                        _ => continue,
+                    };
+                    let input = buffer.push("SCALAR", 1, &sampler.input);
+                    let (components, values) = flatten(&sampler.output);
+                    let output = buffer.push(accessor_type(components), components, &values);
+                    channels.push(node, channel_path(channel), input, output, interpolation(&sampler.function));
+                }
+                let name = match names.get(*entity) {
+                    Some(name) => format!("{} {}", name.name, id),
+                    None => id.to_string(),
+                };
+                animation_values.extend(channels.into_animation(name));
+            }
+        }
+
+        for clip in &self.clips {
+            let period = 1.0 / clip.frame_rate.max(f32::EPSILON);
+            let mut channels = Channels::default();
+            for (entity, frames) in &clip.tracks {
+                let node = match indices.get(entity) {
+                    Some(node) if !frames.is_empty() => *node,
+                    _ => continue,
+                };
+                let times = (0..frames.len()).map(|frame| frame as f32 * period).collect::<Vec<_>>();
+                let input = buffer.push("SCALAR", 1, &times);
+                let translations = frames.iter().flat_map(|frame| frame.translation().as_slice().to_vec()).collect::<Vec<_>>();
+                let rotations = frames.iter().flat_map(|frame| frame.rotation().coords.as_slice().to_vec()).collect::<Vec<_>>();
+                let scales = frames.iter().flat_map(|frame| frame.scale().as_slice().to_vec()).collect::<Vec<_>>();
+                let translations = buffer.push("VEC3", 3, &translations);
+                let rotations = buffer.push("VEC4", 4, &rotations);
+                let scales = buffer.push("VEC3", 3, &scales);
+                channels.push(node, "translation", input, translations, "LINEAR");
+                channels.push(node, "rotation", input, rotations, "LINEAR");
+                channels.push(node, "scale", input, scales, "LINEAR");
+            }
+            animation_values.extend(channels.into_animation(clip.name.clone()));
+        }
+
+        let mut root = json!({
+            "asset": { "version": "2.0", "generator": "amethyst_gltf" },
+            "scene": 0,
+            "scenes": [{ "nodes": (0..self.roots.len()).collect::<Vec<_>>() }],
+            "nodes": nodes,
+        });
+        if !skin_values.is_empty() {
+            root["skins"] = json!(skin_values);
+        }
+        if !animation_values.is_empty() {
+            root["animations"] = json!(animation_values);
+        }
+        if !buffer.accessors.is_empty() {
+            root["bufferViews"] = json!(buffer.views);
+            root["accessors"] = json!(buffer.accessors);
+        }
+        Ok((root, buffer.data))
+    }
+}
+
+/// Packs the json and the buffer of a file into the binary container.
+fn glb(mut json: Vec<u8>, mut buffer: Vec<u8>) -> Vec<u8> {
+    while json.len() % 4 != 0 {
+        json.push(b' ');
+    }
+    while buffer.len() % 4 != 0 {
+        buffer.push(0);
+    }
+    let chunk = |data: &[u8]| 8 + data.len();
+    let length = 12 + chunk(&json) + if buffer.is_empty() { 0 } else { chunk(&buffer) };
+
+    let mut glb = Vec::with_capacity(length);
+    glb.extend_from_slice(GLB_MAGIC);
+    glb.extend_from_slice(&2u32.to_le_bytes());
+    glb.extend_from_slice(&(length as u32).to_le_bytes());
+    glb.extend_from_slice(&(json.len() as u32).to_le_bytes());
+    glb.extend_from_slice(&GLB_JSON.to_le_bytes());
+    glb.extend_from_slice(&json);
+    if !buffer.is_empty() {
+        glb.extend_from_slice(&(buffer.len() as u32).to_le_bytes());
+        glb.extend_from_slice(&GLB_BIN.to_le_bytes());
+        glb.extend_from_slice(&buffer);
+    }
+    glb
+}
+
+#[cfg(test)]
+mod tests {
+    use super::glb;
+
+    #[test]
+    fn pads_glb_chunks() {
+        let glb = glb(b"{}".to_vec(), vec![1, 2, 3]);
+        assert_eq!(&glb[0..4], b"glTF");
+        assert_eq!(glb.len() % 4, 0);
+        assert_eq!(u32::from_le_bytes([glb[8], glb[9], glb[10], glb[11]]) as usize, glb.len());
+        // The json chunk is padded with spaces to four bytes.
+        assert_eq!(&glb[20..24], b"{}  ");
+    }
+}
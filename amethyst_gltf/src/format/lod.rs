@@ -0,0 +1,78 @@
+use std::collections::{BTreeMap, HashMap};
+
+use amethyst_assets::Prefab;
+
+use crate::GltfPrefab;
+
+/// The base name and level of a node named after the `<name>_LOD<level>` convention.
+fn lod_level(name: &str) -> Option<(&str, usize)> {
+    let index = name.rfind("_LOD")?;
+    let level = &name[index + "_LOD".len()..];
+    if level.is_empty() || !level.bytes().all(|byte| byte.is_ascii_digit()) { return None; }
+    Some((&name[..index], level.parse().ok()?))
+}
+
+/// Whether the prefab entity at `index` has a mesh.
+fn has_mesh<T>(prefab: &mut Prefab<GltfPrefab<T>>, index: usize) -> bool {
+    prefab
+        .entity(index)
+        .and_then(|entity| entity.data())
+        .map_or(false, |data| data.mesh.is_some())
+}
+
+/// The prefab indices of the meshes of a node: the node itself, or its primitives if it has several.
+fn mesh_entities<T>(prefab: &mut Prefab<GltfPrefab<T>>, parents: &HashMap<usize, usize>, index: usize) -> Vec<usize> {
+    if has_mesh(prefab, index) { return vec![index]; }
+
+    let mut children = parents
+        .iter()
+        .filter(|&(_, &parent)| parent == index)
+        .map(|(&child, _)| child)
+        .collect::<Vec<_>>();
+    children.sort();
+    children.retain(|&child| has_mesh(prefab, child));
+    children
+}
+
+/// Groups sibling nodes named `<name>_LOD0`, `<name>_LOD1`, ... into levels of detail.
+///
+/// The group goes on the lowest level, which stays visible; the meshes of the other levels start hidden.
+/// `parents` holds the prefab index of the parent of each entity loaded so far.
+pub(super) fn group_lods<T: Default>(
+    prefab: &mut Prefab<GltfPrefab<T>>,
+    parents: &HashMap<usize, usize>,
+    siblings: &[(Option<String>, usize)],
+) {
+    let mut groups = BTreeMap::<&str, BTreeMap<usize, usize>>::new();
+    for (name, index) in siblings {
+        if let Some((base, level)) = name.as_deref().and_then(lod_level) {
+            groups.entry(base).or_default().insert(level, *index);
+        }
+    }
+
+    for levels in groups.values().filter(|levels| levels.len() > 1) {
+        let meshes = levels
+            .values()
+            .map(|&index| mesh_entities(prefab, parents, index))
+            .collect::<Vec<_>>();
+        for &mesh in meshes.iter().skip(1).flatten() {
+            prefab.data_or_default(mesh).hidden = true;
+        }
+        let (_, &first) = levels.iter().next().expect("Unreachable: groups have more than one level");
+        prefab.data_or_default(first).lod_levels = Some(meshes);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::lod_level;
+
+    #[test]
+    fn parses_lod_suffix() {
+        assert_eq!(lod_level("Rock_LOD0"), Some(("Rock", 0)));
+        assert_eq!(lod_level("Big_Rock_LOD12"), Some(("Big_Rock", 12)));
+        assert_eq!(lod_level("Rock_LOD"), None);
+        assert_eq!(lod_level("Rock_LODx"), None);
+        assert_eq!(lod_level("Rock"), None);
+    }
+}
@@ -14,8 +14,26 @@ use amethyst_rendy::{
     },
 };
 
-use gltf::{self, material::AlphaMode};
-use std::sync::Arc;
+use gltf::{self, material::AlphaMode, Gltf};
+use rayon::prelude::*;
+use std::{collections::HashMap, sync::Arc};
+
+// Decode all materials of the document in parallel, keyed by material index
+pub fn load_materials(
+    gltf: &Gltf,
+    buffers: &Buffers,
+    source: Arc<dyn Source>,
+    name: &str,
+) -> Result<HashMap<usize, MaterialPrefab>, Error> {
+    gltf.materials()
+        .enumerate()
+        .collect::<Vec<_>>()
+        .into_par_iter()
+        .map(|(index, material)| {
+            load_material(&material, buffers, source.clone(), name).map(|prefab| (index, prefab))
+        })
+        .collect()
+}
 
 // Load a single material, and transform into a format usable by the engine
 pub fn load_material(
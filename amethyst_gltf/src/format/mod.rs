@@ -15,26 +15,33 @@ use amethyst_core::{
 use amethyst_error::{Error, format_err, ResultExt};
 use amethyst_rendy::{
     camera::CameraPrefab,
+    formats::mtl::MaterialPrefab,
     light::{DirectionalLight, PointLight, SpotLight},
     palette::Srgb,
 };
 use redirect::Redirect;
 
-use crate::{error, GltfMaterialSet, GltfNodeExtent, GltfPrefab, GltfSceneOptions, Named};
+use crate::{error, GltfMaterialSet, GltfMeshSource, GltfNodeExtent, GltfPrefab, GltfSceneOptions, Named};
 
 use self::{
     animation::load_animations,
     importer::{Buffers, get_image_data, ImageFormat, import},
-    material::load_material,
+    lod::group_lods,
+    material::load_materials,
     mesh::load_mesh,
     skin::load_skin,
 };
 
+pub use self::{sidecar::Sidecar, symmetry::Symmetry};
+
 mod animation;
 mod importer;
+mod lod;
 mod material;
 mod mesh;
+mod sidecar;
 mod skin;
+mod symmetry;
 
 pub trait Extra<'a> = Default + Redirect<String, usize> + Serialize + DeserializeOwned + PrefabData<'a>;
 
@@ -132,8 +139,22 @@ fn load_scene<'a, T: Extra<'a>>(
     let mut skin_map = HashMap::new();
     let mut bounding_box = GltfNodeExtent::default();
     let mut material_set = GltfMaterialSet::default();
+    // texture decoding dominates load time, so decode all materials up front in parallel
+    let mut materials = load_materials(gltf, buffers, source.clone(), name)?;
+    let sidecar = if options.load_extras_sidecar {
+        Sidecar::load(source.clone(), name)?
+    } else {
+        Sidecar::default()
+    }.with_overrides(&options.extras_overrides);
+    let sidecar = match &options.symmetry {
+        Some(symmetry) => sidecar.with_mirrored(&node_extras(gltf)?, symmetry),
+        None => sidecar,
+    };
+    let mut parents = HashMap::new();
+    let mut top_level = vec![];
     for node in scene.nodes() {
         let index = prefab.add(None, None);
+        top_level.push((node.name().map(String::from), index));
         load_node(
             gltf,
             &node,
@@ -146,10 +167,14 @@ fn load_scene<'a, T: Extra<'a>>(
             &mut node_map,
             &mut name_map,
             &mut skin_map,
+            &mut parents,
             &mut bounding_box,
             &mut material_set,
+            &mut materials,
+            &sidecar,
         )?;
     }
+    group_lods(prefab, &parents, &top_level);
     if bounding_box.valid() {
         prefab.data_or_default(0).extent = Some(bounding_box);
     }
@@ -197,6 +222,27 @@ fn load_scene<'a, T: Extra<'a>>(
     Ok(())
 }
 
+/// The embedded extras of every named node.
+fn node_extras(gltf: &Gltf) -> Result<HashMap<String, Option<serde_json::Value>>, Error> {
+    let mut extras = HashMap::new();
+    for node in gltf.nodes() {
+        if let Some(name) = node.name() {
+            let value = match node.extras() {
+                Some(value) => Some(serde_json::from_str(&*value.get())?),
+                None => None,
+            };
+            extras.insert(name.to_string(), value);
+        }
+    }
+    Ok(extras)
+}
+
+/// The embedded extras of every named node of a glTF or GLB file, read without loading its buffers.
+pub fn read_node_extras(data: &[u8]) -> Result<HashMap<String, Option<serde_json::Value>>, Error> {
+    let gltf = Gltf::from_slice(data)?;
+    node_extras(&gltf)
+}
+
 fn redirect_extras<'a, T: Extra<'a>>(
     gltf: &Gltf,
     prefab: &mut Prefab<GltfPrefab<T>>,
@@ -241,8 +287,11 @@ fn load_node<'a, T: Extra<'a>>(
     node_map: &mut HashMap<usize, usize>,
     name_map: &mut HashMap<String, usize>,
     skin_map: &mut HashMap<usize, SkinInfo>,
+    parents: &mut HashMap<usize, usize>,
     parent_bounding_box: &mut GltfNodeExtent,
     material_set: &mut GltfMaterialSet,
+    materials: &mut HashMap<usize, MaterialPrefab>,
+    sidecar: &Sidecar,
 ) -> Result<(), Error> {
     node_map.insert(node.index(), entity_index);
 
@@ -260,8 +309,14 @@ fn load_node<'a, T: Extra<'a>>(
         Quaternion::from(Vector4::from(rotation)),
     ));
     *local_transform.scale_mut() = convert::<_, Vector3<f32>>(Vector3::from(scale));
+    if let Some(translation) = sidecar.translation(node.name()) {
+        *local_transform.translation_mut() = Vector3::from(translation);
+    }
+    let local_matrix = local_transform.matrix();
     prefab.data_or_default(entity_index).transform = Some(local_transform);
 
+    let mut extras: Option<serde_json::Value> = None;
+
     // Load camera
     if let Some(camera) = node.camera() {
         prefab.data_or_default(entity_index).camera = Some(match camera.projection() {
@@ -291,17 +346,19 @@ fn load_node<'a, T: Extra<'a>>(
             },
         });
 
-        if let Some(extras) = camera.extras() {
-            prefab.data_or_default(entity_index).extras = Some(
-                serde_json::from_str(&*extras.get())?
-            );
+        if let Some(camera_extras) = camera.extras() {
+            extras = Some(serde_json::from_str(&*camera_extras.get())?);
         }
     }
 
-    // load extras
-    if let Some(extras) = node.extras() {
+    // load extras, combined with the sidecar entry of this node
+    if let Some(node_extras) = node.extras() {
+        extras = Some(serde_json::from_str(&*node_extras.get())?);
+    }
+    if let Some(extras) = sidecar.apply(node.name(), extras) {
         prefab.data_or_default(entity_index).extras = Some(
-            serde_json::from_str(&*extras.get())?
+            serde_json::from_value(extras)
+                .with_context(|_| format_err!("Invalid extras on node {:?}", node.name()))?
         );
     }
 
@@ -355,6 +412,7 @@ fn load_node<'a, T: Extra<'a>>(
     // load graphics
     if let Some(mesh) = node.mesh() {
         let mut graphics = load_mesh(&mesh, buffers, options)?;
+        let mesh_source = |primitive| GltfMeshSource { scene: name.to_string(), mesh: mesh.index(), primitive };
         match graphics.len().cmp(&1) {
             Ordering::Equal => {
                 // single primitive can be loaded directly onto the node
@@ -362,13 +420,11 @@ fn load_node<'a, T: Extra<'a>>(
                 bounding_box.extend_range(&bounds);
                 let prefab_data = prefab.data_or_default(entity_index);
                 prefab_data.mesh = Some(mesh);
-                if let Some((material_id, material)) =
-                material_index.and_then(|index| gltf.materials().nth(index).map(|m| (index, m)))
-                {
-                    material_set
-                        .materials
-                        .entry(material_id)
-                        .or_insert(load_material(&material, buffers, source.clone(), name)?);
+                prefab_data.mesh_source = Some(mesh_source(0));
+                if let Some(material_id) = material_index {
+                    if let Some(material) = materials.remove(&material_id) {
+                        material_set.materials.insert(material_id, material);
+                    }
                     prefab_data.material_id = Some(material_id);
                 }
                 // if we have a skin we need to track the mesh entities
@@ -379,18 +435,17 @@ fn load_node<'a, T: Extra<'a>>(
             Ordering::Greater => {
                 // if we have multiple primitives,
                 // we need to add each primitive as a child entity to the node
-                for (mesh, material_index, bounds) in graphics {
+                for (primitive, (mesh, material_index, bounds)) in graphics.into_iter().enumerate() {
                     let mesh_entity = prefab.add(Some(entity_index), None);
+                    parents.insert(mesh_entity, entity_index);
                     let prefab_data = prefab.data_or_default(mesh_entity);
                     prefab_data.transform = Some(Transform::default());
                     prefab_data.mesh = Some(mesh);
-                    if let Some((material_id, material)) = material_index
-                        .and_then(|index| gltf.materials().nth(index).map(|m| (index, m)))
-                    {
-                        material_set
-                            .materials
-                            .entry(material_id)
-                            .or_insert(load_material(&material, buffers, source.clone(), name)?);
+                    prefab_data.mesh_source = Some(mesh_source(primitive));
+                    if let Some(material_id) = material_index {
+                        if let Some(material) = materials.remove(&material_id) {
+                            material_set.materials.insert(material_id, material);
+                        }
                         prefab_data.material_id = Some(material_id);
                     }
 
@@ -409,8 +464,11 @@ fn load_node<'a, T: Extra<'a>>(
     }
 
     // load children
+    let mut children = vec![];
     for child in node.children() {
         let index = prefab.add(Some(entity_index), None);
+        parents.insert(index, entity_index);
+        children.push((child.name().map(String::from), index));
         load_node(
             gltf,
             &child,
@@ -423,12 +481,17 @@ fn load_node<'a, T: Extra<'a>>(
             node_map,
             name_map,
             skin_map,
+            parents,
             &mut bounding_box,
             material_set,
+            materials,
+            sidecar,
         )?;
     }
+    group_lods(prefab, parents, &children);
     if bounding_box.valid() {
-        parent_bounding_box.extend(&bounding_box);
+        // the extent of a node is in its own space, the parent's in the space above
+        parent_bounding_box.extend(&bounding_box.transformed(&local_matrix));
         prefab.data_or_default(entity_index).extent = Some(bounding_box);
     }
 
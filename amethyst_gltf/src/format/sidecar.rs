@@ -0,0 +1,163 @@
+use std::{collections::HashMap, path::Path, sync::Arc};
+
+use amethyst_assets::Source;
+use amethyst_error::{Error, format_err, ResultExt};
+use log::debug;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use super::Symmetry;
+
+/// How a sidecar entry is combined with the extras embedded in the glTF node.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SidecarMode {
+    /// Fields of the entry are merged recursively over the embedded extras.
+    Merge,
+    /// The entry replaces the embedded extras entirely.
+    Replace,
+}
+
+impl Default for SidecarMode {
+    fn default() -> Self {
+        SidecarMode::Merge
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct SidecarEntry {
+    #[serde(default)]
+    mode: SidecarMode,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    extras: Option<Value>,
+    /// Replaces the translation of the node in the file, e.g. after nudging it in game.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    translation: Option<[f32; 3]>,
+}
+
+/// Extras read from a `<model>.extras.ron` file next to the glTF, keyed by node name.
+///
+/// ```ron
+/// {
+///     "Head": (extras: { "tracker": { "target": "Target", "speed": 4.0 } }),
+///     "Tail": (mode: Replace, extras: { "tail": { "stiffness": 2.0 } }),
+///     "Pole-f.L": (translation: (0.0, 0.4, 0.3)),
+/// }
+/// ```
+#[derive(Debug, Default, Serialize)]
+pub struct Sidecar(HashMap<String, SidecarEntry>);
+
+impl Sidecar {
+    /// Path of the sidecar belonging to the model at `name`.
+    pub fn path(name: &str) -> String {
+        Path::new(name)
+            .with_extension("extras.ron")
+            .to_string_lossy()
+            .into_owned()
+    }
+
+    /// Load the sidecar of the model at `name`; a missing file yields an empty sidecar.
+    pub fn load(source: Arc<dyn Source>, name: &str) -> Result<Self, Error> {
+        let path = Self::path(name);
+        let bytes = match source.load(&path) {
+            Ok(bytes) => bytes,
+            Err(_) => return Ok(Self::default()),
+        };
+        debug!("Loading extras sidecar '{}'", path);
+        Self::parse(&bytes).with_context(|_| format_err!("Failed to parse extras sidecar '{}'", path))
+    }
+
+    pub fn parse(bytes: &[u8]) -> Result<Self, Error> {
+        let entries = ron::de::from_bytes(bytes)?;
+        Ok(Sidecar(entries))
+    }
+
+    /// The sidecar as it is written to its file.
+    pub fn to_ron(&self) -> Result<String, Error> {
+        let config = ron::ser::PrettyConfig::default();
+        ron::ser::to_string_pretty(&self.0, config).map_err(Into::into)
+    }
+
+    /// The translation replacing the one in the file of the node called `name`, if any.
+    pub fn translation(&self, name: Option<&str>) -> Option<[f32; 3]> {
+        name.and_then(|name| self.0.get(name)).and_then(|entry| entry.translation)
+    }
+
+    pub fn set_translation(&mut self, name: &str, translation: [f32; 3]) {
+        self.0.entry(name.to_string()).or_default().translation = Some(translation);
+    }
+
+    /// Merge per-instance patches, keyed by node name, over the entries of the sidecar.
+    ///
+    /// This happens before the extras are deserialized, so patches are checked, redirected and mirrored
+    /// like the extras they patch. The resulting prefab is specific to the overrides it was loaded with.
+    pub fn with_overrides(mut self, overrides: &HashMap<String, Value>) -> Self {
+        for (name, patch) in overrides {
+            match self.0.get_mut(name) {
+                Some(SidecarEntry { extras: Some(extras), .. }) => merge(extras, patch.clone()),
+                Some(entry) => entry.extras = Some(patch.clone()),
+                None => {
+                    let entry = SidecarEntry { extras: Some(patch.clone()), ..Default::default() };
+                    self.0.insert(name.clone(), entry);
+                }
+            }
+        }
+        self
+    }
+
+    /// Give right nodes without extras of their own the mirrored extras of their left counterpart.
+    ///
+    /// `nodes` holds the embedded extras of every named node. Extras from the sidecar or from overrides
+    /// count as the node's own, so either side can still be set explicitly.
+    pub fn with_mirrored(mut self, nodes: &HashMap<String, Option<Value>>, symmetry: &Symmetry) -> Self {
+        let mirrored = nodes
+            .iter()
+            .filter_map(|(name, extras)| {
+                let right = symmetry.to_right(name)?;
+                let own = nodes.get(&right)?.is_some()
+                    || self.0.get(&right).map_or(false, |entry| entry.extras.is_some());
+                if own { return None; }
+                let extras = self.apply(Some(name), extras.clone())?;
+                Some((right, symmetry.mirror_value(&extras)))
+            })
+            .collect::<Vec<_>>();
+        for (name, extras) in mirrored {
+            debug!("Mirroring extras onto '{}'", name);
+            let entry = self.0.entry(name).or_default();
+            entry.mode = SidecarMode::Replace;
+            entry.extras = Some(extras);
+        }
+        self
+    }
+
+    /// Combine the embedded extras of the node called `name` with its sidecar entry, if any.
+    pub fn apply(&self, name: Option<&str>, extras: Option<Value>) -> Option<Value> {
+        match name.and_then(|name| self.0.get(name)) {
+            Some(SidecarEntry { mode, extras: Some(entry), .. }) => match (mode, extras) {
+                (SidecarMode::Merge, Some(mut extras)) => {
+                    merge(&mut extras, entry.clone());
+                    Some(extras)
+                }
+                _ => Some(entry.clone()),
+            },
+            _ => extras,
+        }
+    }
+}
+
+fn merge(target: &mut Value, source: Value) {
+    match (target, source) {
+        (Value::Object(target), Value::Object(source)) => merge_map(target, source),
+        (target, source) => *target = source,
+    }
+}
+
+fn merge_map(target: &mut Map<String, Value>, source: Map<String, Value>) {
+    for (key, value) in source {
+        match target.get_mut(&key) {
+            Some(existing) => merge(existing, value),
+            None => {
+                target.insert(key, value);
+            }
+        }
+    }
+}
@@ -0,0 +1,95 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// The naming convention of the two sides of a rig, e.g. `"upper_arm.L"` and `"upper_arm.R"`.
+///
+/// A side marker only counts as a whole token, set apart by punctuation or the ends of the name,
+/// so with the markers `"L"` and `"R"` both `"Handle-f.L"` and `"DEF-thigh.L.001"` are on the left,
+/// but `"Lid"` is on neither side.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Symmetry {
+    pub left: String,
+    pub right: String,
+}
+
+impl Default for Symmetry {
+    fn default() -> Self {
+        Symmetry { left: "L".into(), right: "R".into() }
+    }
+}
+
+/// Replace the whole-token occurrences of `from` in `name` with `to`, or `None` if there are none.
+fn replace_token(name: &str, from: &str, to: &str) -> Option<String> {
+    if from.is_empty() { return None; }
+
+    let is_boundary = |c: Option<char>| c.map_or(true, |c| !c.is_alphanumeric());
+    let mut result = String::with_capacity(name.len());
+    let mut last = 0;
+    for (index, _) in name.match_indices(from) {
+        let end = index + from.len();
+        if !is_boundary(name[..index].chars().next_back()) || !is_boundary(name[end..].chars().next()) {
+            continue;
+        }
+        result.push_str(&name[last..index]);
+        result.push_str(to);
+        last = end;
+    }
+    if last == 0 { return None; }
+
+    result.push_str(&name[last..]);
+    Some(result)
+}
+
+impl Symmetry {
+    /// The name of the right counterpart of a node on the left side.
+    pub fn to_right(&self, name: &str) -> Option<String> {
+        if replace_token(name, &self.right, &self.left).is_some() { return None; }
+        replace_token(name, &self.left, &self.right)
+    }
+
+    /// The name on the other side, or `None` if `name` is on neither side.
+    pub fn mirror(&self, name: &str) -> Option<String> {
+        self.to_right(name).or_else(|| replace_token(name, &self.right, &self.left))
+    }
+
+    /// Extras with every node name they mention moved to the other side.
+    ///
+    /// Numbers are kept: the bones of a mirrored rig have mirrored local frames,
+    /// so limits and offsets in the space of a joint mean the same on both sides.
+    pub fn mirror_value(&self, value: &Value) -> Value {
+        match value {
+            Value::String(name) => Value::String(self.mirror(name).unwrap_or_else(|| name.clone())),
+            Value::Array(values) => Value::Array(values.iter().map(|value| self.mirror_value(value)).collect()),
+            Value::Object(fields) => Value::Object(fields
+                .iter()
+                .map(|(key, value)| (key.clone(), self.mirror_value(value)))
+                .collect()),
+            value => value.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Symmetry;
+    use serde_json::json;
+
+    #[test]
+    fn mirrors_whole_tokens() {
+        let symmetry = Symmetry::default();
+        assert_eq!(symmetry.mirror("Handle-f.L"), Some("Handle-f.R".into()));
+        assert_eq!(symmetry.mirror("DEF-thigh.R.001"), Some("DEF-thigh.L.001".into()));
+        assert_eq!(symmetry.mirror("L"), Some("R".into()));
+        assert_eq!(symmetry.mirror("Lid"), None);
+        assert_eq!(symmetry.to_right("Pole-r.R"), None);
+    }
+
+    #[test]
+    fn mirrors_names_in_extras() {
+        let symmetry = Symmetry::default();
+        let extras = json!({ "chain": { "target": "Handle-f.L", "length": 4 }, "constrain": { "Hinge": { "limit": [0.0, 2.0] } } });
+        let expected = json!({ "chain": { "target": "Handle-f.R", "length": 4 }, "constrain": { "Hinge": { "limit": [0.0, 2.0] } } });
+        assert_eq!(symmetry.mirror_value(&extras), expected);
+    }
+}
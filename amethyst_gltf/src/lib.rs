@@ -16,8 +16,9 @@ use amethyst_assets::{
     ProgressCounter,
 };
 use amethyst_core::{
-    ecs::prelude::{Entity, Read, ReadExpect, Write, WriteStorage},
-    math::{convert, Point3, Vector3},
+    ecs::prelude::{Component, DenseVecStorage, Entity, Read, ReadExpect, Write, WriteStorage},
+    Hidden,
+    math::{convert, Matrix4, Point3, Vector3},
     Named,
     transform::Transform,
 };
@@ -31,9 +32,13 @@ use amethyst_rendy::{
     visibility::BoundingSphere,
 };
 
-pub use crate::format::GltfSceneFormat;
+pub use crate::{
+    export::{GltfClip, GltfExporter},
+    format::{GltfSceneFormat, read_node_extras, Sidecar, Symmetry},
+};
 
 mod error;
+mod export;
 mod format;
 
 /// Builds a `GltfSceneLoaderSystem`.
@@ -74,6 +79,12 @@ pub struct GltfPrefab<T> {
     pub extras: Option<T>,
     pub(crate) materials: Option<GltfMaterialSet>,
     pub(crate) material_id: Option<usize>,
+    /// Prefab indices of the meshes of each level of detail, on the node of the lowest level
+    pub(crate) lod_levels: Option<Vec<Vec<usize>>>,
+    /// Meshes of the levels of detail past the first start hidden
+    pub(crate) hidden: bool,
+    /// Where the mesh was loaded from, placed with the mesh
+    pub(crate) mesh_source: Option<GltfMeshSource>,
 }
 
 impl<T> GltfPrefab<T> {
@@ -144,6 +155,22 @@ impl GltfNodeExtent {
         }
     }
 
+    /// Returns the extent enclosing this one after transforming it by `matrix`,
+    /// e.g. from the space of a node into the space of its parent.
+    pub fn transformed(&self, matrix: &Matrix4<f32>) -> GltfNodeExtent {
+        let mut extent = GltfNodeExtent::default();
+        for corner in 0..8 {
+            let point = Point3::new(
+                if corner & 1 == 0 { self.start.x } else { self.end.x },
+                if corner & 2 == 0 { self.start.y } else { self.end.y },
+                if corner & 4 == 0 { self.start.z } else { self.end.z },
+            );
+            let corner: [f32; 3] = matrix.transform_point(&point).coords.into();
+            extent.extend_range(&(corner..corner));
+        }
+        extent
+    }
+
     /// Returns the centroid of this extent
     pub fn centroid(&self) -> Point3<f32> {
         (self.start + self.end.coords) / 2.
@@ -183,6 +210,59 @@ impl From<Range<[f32; 3]>> for GltfNodeExtent {
     }
 }
 
+/// Index of the Gltf material an entity was loaded with.
+///
+/// Entities with the same index in the same file share their `Handle<Material>`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct GltfMaterialId(pub usize);
+
+impl Component for GltfMaterialId {
+    type Storage = DenseVecStorage<Self>;
+}
+
+/// The root entity of the scene instance an entity was loaded into.
+///
+/// Top level nodes of a scene are not parented to the root, so this is what ties an instance together.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct GltfSceneRoot(pub Entity);
+
+impl Component for GltfSceneRoot {
+    type Storage = DenseVecStorage<Self>;
+}
+
+/// The levels of detail of a node exported as `<name>_LOD0`, `<name>_LOD1`, ... siblings,
+/// placed on the node of the lowest level.
+///
+/// Each level lists its mesh entities. Only the `current` level is shown; the meshes of the others are `Hidden`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GltfLodGroup {
+    /// The mesh entities of each level, from the most detailed
+    pub levels: Vec<Vec<Entity>>,
+    /// The level shown
+    pub current: usize,
+}
+
+impl Component for GltfLodGroup {
+    type Storage = DenseVecStorage<Self>;
+}
+
+/// The primitive of a file a mesh was loaded from, placed on all `Entity`s with graphics primitives.
+///
+/// Meshes only live on the GPU once loaded, so exported scenes refer to them by their source.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GltfMeshSource {
+    /// Asset path of the scene file
+    pub scene: String,
+    /// Index of the mesh in the file
+    pub mesh: usize,
+    /// Index of the primitive in the mesh
+    pub primitive: usize,
+}
+
+impl Component for GltfMeshSource {
+    type Storage = DenseVecStorage<Self>;
+}
+
 /// Used during gltf loading to contain the materials used from scenes in the file
 #[derive(Debug, Derivative)]
 #[derivative(Default(bound = ""))]
@@ -217,6 +297,15 @@ pub struct GltfSceneOptions {
     pub load_lights: bool,
     /// Flip the v coordinate for all texture coordinates
     pub flip_v_coord: bool,
+    #[derivative(Default(value = "true"))]
+    /// Merge node extras from a `<model>.extras.ron` sidecar next to the Gltf file, if present
+    pub load_extras_sidecar: bool,
+    /// Patches merged over the extras of the named nodes, after the sidecar.
+    /// Use this to load instances of the same file with different settings.
+    pub extras_overrides: HashMap<String, serde_json::Value>,
+    /// Mirror the extras of nodes on the left side onto their counterparts on the right that have none,
+    /// following this naming convention.
+    pub symmetry: Option<Symmetry>,
     /// Load the given scene index, if not supplied will either load the default scene (if set),
     /// or the first scene (only if there is only one scene, otherwise an `Error` will be returned).
     pub scene_index: Option<usize>,
@@ -238,6 +327,11 @@ impl<'a, T> PrefabData<'a> for GltfPrefab<T>
         Read<'a, AssetStorage<Mesh>>,
         ReadExpect<'a, Loader>,
         Write<'a, GltfMaterialSet>,
+        WriteStorage<'a, GltfMaterialId>,
+        WriteStorage<'a, GltfSceneRoot>,
+        WriteStorage<'a, GltfLodGroup>,
+        WriteStorage<'a, Hidden>,
+        WriteStorage<'a, GltfMeshSource>,
     );
     type Result = ();
 
@@ -262,7 +356,15 @@ impl<'a, T> PrefabData<'a> for GltfPrefab<T>
             _,
             _,
             _,
+            material_ids,
+            scene_roots,
+            lod_groups,
+            hiddens,
+            mesh_sources,
         ) = system_data;
+        if let Some(&root) = entities.first() {
+            scene_roots.insert(entity, GltfSceneRoot(root))?;
+        }
         if let Some(transform) = &self.transform {
             transform.add_to_entity(entity, transforms, entities, children)?;
         }
@@ -278,6 +380,9 @@ impl<'a, T> PrefabData<'a> for GltfPrefab<T>
         if let Some(material) = &self.material {
             material.add_to_entity(entity, materials, entities, children)?;
         }
+        if let Some(material_id) = self.material_id {
+            material_ids.insert(entity, GltfMaterialId(material_id))?;
+        }
         if let Some(animatable) = &self.animatable {
             animatable.add_to_entity(entity, animatables, entities, children)?;
         }
@@ -293,6 +398,19 @@ impl<'a, T> PrefabData<'a> for GltfPrefab<T>
         if let Some(extent) = &self.extent {
             bound.insert(entity, extent.clone().into())?;
         }
+        if let Some(levels) = &self.lod_levels {
+            let levels = levels
+                .iter()
+                .map(|meshes| meshes.iter().map(|&index| entities[index]).collect())
+                .collect();
+            lod_groups.insert(entity, GltfLodGroup { levels, current: 0 })?;
+        }
+        if self.hidden {
+            hiddens.insert(entity, Hidden)?;
+        }
+        if let Some(source) = &self.mesh_source {
+            mesh_sources.insert(entity, source.clone())?;
+        }
         Ok(())
     }
 
@@ -301,7 +419,7 @@ impl<'a, T> PrefabData<'a> for GltfPrefab<T>
         progress: &mut ProgressCounter,
         system_data: &mut Self::SystemData,
     ) -> Result<bool, Error> {
-        let (_, _, _, materials, animatables, _, _, _, _, _, meshes_storage, loader, mat_set) =
+        let (_, _, _, materials, animatables, _, _, extras, _, _, meshes_storage, loader, mat_set, _, _, _, _, _) =
             system_data;
 
         let mut ret = false;
@@ -325,6 +443,9 @@ impl<'a, T> PrefabData<'a> for GltfPrefab<T>
         if let Some(animatable) = &mut self.animatable {
             ret |= animatable.load_sub_assets(progress, animatables)?;
         }
+        if let Some(custom) = &mut self.extras {
+            ret |= custom.load_sub_assets(progress, extras)?;
+        }
         Ok(ret)
     }
 }
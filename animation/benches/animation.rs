@@ -0,0 +1,295 @@
+use std::sync::Arc;
+
+use amethyst::{
+    assets::PrefabData,
+    core::{
+        ArcThreadPool,
+        bundle::SystemBundle,
+        math::Point3,
+        Time,
+        transform::{Parent, Transform, TransformSystemDesc},
+    },
+    ecs::{prelude::*, rayon::ThreadPoolBuilder},
+    prelude::SystemDesc,
+    renderer::debug_drawing::DebugLines,
+};
+use criterion::{BenchmarkId, Criterion, criterion_group, criterion_main};
+
+use ceramic_animation::{
+    extras::{EntityRef, RedirectField},
+    systems::{
+        animal::{Config, LimbCalibrationSystem, LocomotionSystem, Quadruped, QuadrupedPrefab},
+        cache::GlobalCacheSystem,
+        kinematics::{Chain, ChainPrefab, KinematicsBundle},
+        player::Player,
+    },
+    utils::{determinism::Determinism, match_shape},
+};
+
+const COUNTS: [usize; 4] = [1, 8, 32, 128];
+
+/// Bodies when comparing the parallel and the serial locomotion.
+const JOIN_COUNTS: [usize; 4] = [1, 16, 64, 256];
+
+/// Entities in the world when comparing storages, most of them without the stored component.
+const STORED_AMONG: usize = 1024;
+
+fn transform_at(x: f32, y: f32, z: f32) -> Transform {
+    let mut transform = Transform::default();
+    transform.set_translation_xyz(x, y, z);
+    transform
+}
+
+fn new_world() -> World {
+    let mut world = World::new();
+    let mut time = Time::default();
+    time.set_delta_seconds(1.0 / 60.0);
+    world.insert(time);
+    world.insert(DebugLines::new());
+    world.insert::<ArcThreadPool>(Arc::new(ThreadPoolBuilder::new().build().unwrap()));
+    world.register::<Transform>();
+    world.register::<Parent>();
+    world
+}
+
+/// Spawn a minimal quadruped rig at `x` and return its body entity.
+fn spawn_quadruped(world: &mut World, x: f32) -> Entity {
+    let body = world
+        .create_entity()
+        .with(transform_at(x, 0.0, 0.0))
+        .with(Player::new(2.0, 1.571, 8.0, [0.5, 4.0], 1.0))
+        .build();
+    let root = world
+        .create_entity()
+        .with(transform_at(0.0, 1.0, 0.0))
+        .with(Parent::new(body))
+        .build();
+
+    let mut entities = vec![body, root];
+    let mut field = |entity: Entity| {
+        entities.push(entity);
+        EntityRef(RedirectField::Target(entities.len() - 1))
+    };
+
+    let (mut feet, mut anchors, mut roots, mut origins, mut homes) = (vec![], vec![], vec![], vec![], vec![]);
+    for &(side, front) in &[(1.0, 1.0), (-1.0, 1.0), (1.0, -1.0), (-1.0, -1.0)] {
+        let (x, z) = (0.3 * side, 0.5 * front);
+        let origin = world.create_entity().with(transform_at(x, 0.0, z)).with(Parent::new(root)).build();
+        let anchor = world.create_entity().with(transform_at(x, -0.2, z)).with(Parent::new(root)).build();
+        let limb_root = world.create_entity().with(transform_at(x, 0.0, z)).with(Parent::new(root)).build();
+        let home = world.create_entity().with(transform_at(x, 0.0, z)).with(Parent::new(body)).build();
+        let foot = world.create_entity().with(transform_at(x, 0.0, z)).build();
+
+        origins.push(field(origin));
+        anchors.push(field(anchor));
+        roots.push(field(limb_root));
+        homes.push(field(home));
+        feet.push(field(foot));
+    }
+    let root = EntityRef(RedirectField::Target(1));
+
+    let prefab = QuadrupedPrefab {
+        feet,
+        anchors,
+        roots,
+        origins,
+        homes,
+        root,
+        home_offsets: vec![],
+        reaches: vec![],
+        phase_offsets: None,
+        config: Config {
+            max_angular_velocity: 12.57,
+            max_duty_factor: 0.8,
+            step_limit: [0.8, 1.2],
+            flight_time: 0.4,
+            flight_factor: 0.2,
+            stance_height: 0.0,
+            bounce_factor: 0.1,
+            ..Default::default()
+        },
+    };
+    prefab
+        .add_to_entity(body, &mut world.write_storage::<Quadruped>(), &entities, &[])
+        .unwrap();
+    body
+}
+
+/// Spawn a three joint chain whose end follows a free target, returning the target.
+fn spawn_chain(world: &mut World, x: f32) -> Entity {
+    let target = world.create_entity().with(transform_at(x + 0.5, 1.0, 0.5)).build();
+    let base = world.create_entity().with(transform_at(x, 0.0, 0.0)).build();
+    let middle = world.create_entity().with(transform_at(0.0, 1.0, 0.0)).with(Parent::new(base)).build();
+    let end = world.create_entity().with(transform_at(0.0, 1.0, 0.0)).with(Parent::new(middle)).build();
+
+    let entities = [target];
+    let prefab = ChainPrefab { target: EntityRef(RedirectField::Target(0)), length: 3 };
+    prefab
+        .add_to_entity(end, &mut world.write_storage::<Chain>(), &entities, &[])
+        .unwrap();
+    target
+}
+
+/// A world of `count` quadrupeds, and a frame of their transforms, global cache, calibration and locomotion.
+fn walking(count: usize, determinism: Determinism) -> (World, impl FnMut(&mut World)) {
+    let mut world = new_world();
+    world.insert(determinism);
+    let mut transform = TransformSystemDesc::default().build(&mut world);
+    let mut cache = GlobalCacheSystem::default();
+    let mut calibration = LimbCalibrationSystem::default();
+    let mut locomotion = LocomotionSystem::<Quadruped>::default();
+    System::setup(&mut cache, &mut world);
+    System::setup(&mut calibration, &mut world);
+    System::setup(&mut locomotion, &mut world);
+    for i in 0..count {
+        spawn_quadruped(&mut world, i as f32 * 3.0);
+    }
+    transform.run_now(&world);
+    cache.run_now(&world);
+
+    let frame = move |world: &mut World| {
+        transform.run_now(world);
+        cache.run_now(world);
+        calibration.run_now(world);
+        locomotion.run_now(world);
+        world.maintain();
+    };
+    (world, frame)
+}
+
+fn locomotion(c: &mut Criterion) {
+    let mut group = c.benchmark_group("locomotion");
+    for &count in COUNTS.iter() {
+        let (mut world, mut frame) = walking(count, Determinism::default());
+        group.bench_with_input(BenchmarkId::from_parameter(count), &count, |b, _| {
+            b.iter(|| frame(&mut world))
+        });
+    }
+    group.finish();
+}
+
+/// Compares processing the bodies in parallel with processing them one at a time, as under `Determinism`.
+fn locomotion_join(c: &mut Criterion) {
+    let mut group = c.benchmark_group("locomotion_join");
+    for &count in JOIN_COUNTS.iter() {
+        for &(name, determinism) in &[
+            ("par_join", Determinism::default()),
+            ("join", Determinism::new(1.0 / 60.0)),
+        ] {
+            let (mut world, mut frame) = walking(count, determinism);
+            group.bench_with_input(BenchmarkId::new(name, count), &count, |b, _| {
+                b.iter(|| frame(&mut world))
+            });
+        }
+    }
+    group.finish();
+}
+
+fn kinematics(c: &mut Criterion) {
+    let mut group = c.benchmark_group("kinematics");
+    for &count in COUNTS.iter() {
+        let mut world = new_world();
+        let mut builder = DispatcherBuilder::new();
+        KinematicsBundle::new(2, 0.01)
+            .build(&mut world, &mut builder)
+            .unwrap();
+        let mut dispatcher = builder.build();
+        dispatcher.setup(&mut world);
+
+        let targets = (0..count)
+            .map(|i| spawn_chain(&mut world, i as f32 * 3.0))
+            .collect::<Vec<_>>();
+
+        let mut frame = 0;
+        group.bench_with_input(BenchmarkId::from_parameter(count), &count, |b, _| {
+            b.iter(|| {
+                // Keep the targets moving so change detection doesn't skip the chains.
+                frame += 1;
+                let offset = (frame as f32 * 0.1).sin() * 0.5;
+                {
+                    let mut transforms = world.write_storage::<Transform>();
+                    for target in targets.iter() {
+                        if let Some(transform) = transforms.get_mut(*target) {
+                            transform.translation_mut().z = offset;
+                        }
+                    }
+                }
+                dispatcher.dispatch(&world);
+                world.maintain();
+            })
+        });
+    }
+    group.finish();
+}
+
+macro_rules! stored {
+    ($name:ident, $storage:ident) => {
+        /// A component about the size of a `Quadruped`, kept in a `$storage`.
+        #[derive(Clone, Default)]
+        struct $name([[f32; 16]; 4]);
+
+        impl Component for $name {
+            type Storage = $storage<Self>;
+        }
+    };
+}
+
+stored!(InVec, VecStorage);
+stored!(InDenseVec, DenseVecStorage);
+stored!(InHashMap, HashMapStorage);
+
+/// Joins `count` components of `T` out of `STORED_AMONG` entities with their transforms.
+fn join_stored<T>(c: &mut Criterion, name: &str)
+    where T: Component + Default,
+          T::Storage: Default {
+    let mut group = c.benchmark_group(format!("storage/{}", name));
+    for &count in COUNTS.iter() {
+        let mut world = new_world();
+        world.register::<T>();
+        for i in 0..STORED_AMONG {
+            let builder = world.create_entity().with(transform_at(i as f32, 0.0, 0.0));
+            let builder = if i % (STORED_AMONG / count) == 0 { builder.with(T::default()) } else { builder };
+            builder.build();
+        }
+
+        group.bench_with_input(BenchmarkId::from_parameter(count), &count, |b, _| {
+            b.iter(|| {
+                let stored = world.read_storage::<T>();
+                let transforms = world.read_storage::<Transform>();
+                (&stored, &transforms)
+                    .join()
+                    .map(|(_, transform)| transform.translation().x)
+                    .sum::<f32>()
+            })
+        });
+    }
+    group.finish();
+}
+
+/// Compares the storages for components a few entities of the world have, like the bodies and the trackers.
+fn storage(c: &mut Criterion) {
+    join_stored::<InVec>(c, "vec");
+    join_stored::<InDenseVec>(c, "dense_vec");
+    join_stored::<InHashMap>(c, "hash_map");
+}
+
+fn shape_matching(c: &mut Criterion) {
+    let origins = [
+        Point3::new(0.3, 1.0, 0.5),
+        Point3::new(-0.3, 1.0, 0.5),
+        Point3::new(0.3, 1.0, -0.5),
+        Point3::new(-0.3, 1.0, -0.5),
+    ];
+    let targets = [
+        Point3::new(0.35, 1.1, 0.45),
+        Point3::new(-0.25, 0.9, 0.55),
+        Point3::new(0.3, 1.2, -0.5),
+        Point3::new(-0.3, 1.0, -0.45),
+    ];
+    c.bench_function("match_shape", |b| {
+        b.iter(|| match_shape(&origins, &targets, 0.01, 10))
+    });
+}
+
+criterion_group!(benches, locomotion, locomotion_join, kinematics, storage, shape_matching);
+criterion_main!(benches);
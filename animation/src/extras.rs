@@ -0,0 +1,187 @@
+use amethyst::{
+    assets::{PrefabData, ProgressCounter},
+    derive::PrefabData,
+    ecs::prelude::*,
+    error::Error,
+};
+use serde::{Deserialize, Serialize};
+
+use ceramic_derive::Redirect;
+use redirect::{Redirect, Resolve};
+
+use crate::systems::{
+    behavior::BehaviorPrefab,
+    blend::AnimationBlendPrefab,
+    carry::{Carryable, CarrierPrefab},
+    effects::FootprintConfig,
+    flock::Flock,
+    helper::Helper,
+    appendage::AppendagePrefab,
+    avoidance::Obstacle,
+    animal::{BipedPrefab, BreathingPrefab, GaitBakery, GazePrefab, HerdSync, InterestPoint, LocomotorPrefab, MotionMatchingPrefab, PosturesPrefab, QuadrupedPrefab, SpinePrefab, TailPrefab, TrackerPrefab},
+    kinematics::{ChainPrefab, ConstrainPrefab},
+    modifier::MovementModifier,
+    particle::{ParticlePrefab, SpringPrefab},
+    path::PathFollowerPrefab,
+    physics::{TerrainPrefab, WorldUpPrefab},
+    platform::Platform,
+    socket::SocketsPrefab,
+    water::WaterPrefab,
+    player::Player,
+};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum RedirectField {
+    Origin(String),
+    Target(usize),
+}
+
+impl Redirect<String, usize> for RedirectField {
+    fn redirect<F>(self, map: &F) -> Self
+        where F: Fn(String) -> usize {
+        match self {
+            RedirectField::Origin(origin) => RedirectField::Target(map(origin)),
+            RedirectField::Target(_) => self,
+        }
+    }
+}
+
+impl RedirectField {
+    pub fn into_entity(self, entities: &[Entity]) -> Entity {
+        let index = match self {
+            RedirectField::Origin(_) => panic!("Redirect field unsolved"),
+            RedirectField::Target(target) => target,
+        };
+        entities[index]
+    }
+}
+
+impl Resolve<Entity> for RedirectField {
+    type Output = Entity;
+
+    fn resolve(&self, entities: &[Entity]) -> Entity {
+        self.clone().into_entity(entities)
+    }
+}
+
+/// A reference from a prefab to another node of the scene, resolved to its entity on instantiation.
+///
+/// Prefabs made of these can derive `Resolve` to build their component in one call:
+/// ```ignore
+/// #[derive(Clone, Serialize, Deserialize, Redirect, Resolve)]
+/// #[resolve(Chain)]
+/// pub struct ChainPrefab {
+///     pub target: EntityRef,
+///     #[redirect(skip)]
+///     pub length: usize,
+/// }
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct EntityRef(pub RedirectField);
+
+impl Redirect<String, usize> for EntityRef {
+    fn redirect<F>(self, map: &F) -> Self
+        where F: Fn(String) -> usize {
+        EntityRef(self.0.redirect(map))
+    }
+}
+
+impl Resolve<Entity> for EntityRef {
+    type Output = Entity;
+
+    fn resolve(&self, entities: &[Entity]) -> Entity {
+        self.0.resolve(entities)
+    }
+}
+
+/// The animation components that can be attached to a node through glTF extras.
+///
+/// Flatten this into the extras type of the application. The components are grouped into flattened sub-structs,
+/// since a `PrefabData` derive fetches one system data per field and those only go up to 26.
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PrefabData, Redirect)]
+#[serde(default)]
+pub struct AnimationExtras {
+    #[serde(flatten)]
+    locomotion: LocomotionExtras,
+    #[serde(flatten)]
+    behavior: BehaviorExtras,
+    #[serde(flatten)]
+    rig: RigExtras,
+    #[serde(flatten)]
+    #[redirect(skip)]
+    world: WorldExtras,
+}
+
+/// Bodies, their gaits and what animates them while they move.
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PrefabData, Redirect)]
+#[serde(default)]
+pub struct LocomotionExtras {
+    #[redirect(skip)]
+    player: Option<Player>,
+    quadruped: Option<QuadrupedPrefab>,
+    biped: Option<BipedPrefab>,
+    locomotor: Option<LocomotorPrefab>,
+    #[redirect(skip)]
+    motion_matching: Option<MotionMatchingPrefab>,
+    #[redirect(skip)]
+    gait_bakery: Option<GaitBakery>,
+    #[redirect(skip)]
+    herd_sync: Option<HerdSync>,
+    #[redirect(skip)]
+    postures: Option<PosturesPrefab>,
+    animation_blend: Option<AnimationBlendPrefab>,
+    breathing: Option<BreathingPrefab>,
+    spine: Option<SpinePrefab>,
+    tail: Option<TailPrefab>,
+}
+
+/// What steers creatures and what they look at or avoid.
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PrefabData, Redirect)]
+#[serde(default)]
+pub struct BehaviorExtras {
+    #[redirect(skip)]
+    behavior: Option<BehaviorPrefab>,
+    #[redirect(skip)]
+    flock: Option<Flock>,
+    path: Option<PathFollowerPrefab>,
+    tracker: Option<TrackerPrefab>,
+    #[redirect(skip)]
+    gaze: Option<GazePrefab>,
+    #[redirect(skip)]
+    interest: Option<InterestPoint>,
+    #[redirect(skip)]
+    movement_modifier: Option<MovementModifier>,
+    #[redirect(skip)]
+    obstacle: Option<Obstacle>,
+}
+
+/// Kinematic chains, simulated particles and the nodes attached to the rig.
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PrefabData, Redirect)]
+#[serde(default)]
+pub struct RigExtras {
+    chain: Option<ChainPrefab>,
+    constrain: Option<ConstrainPrefab>,
+    #[redirect(skip)]
+    particle: Option<ParticlePrefab>,
+    spring: Option<SpringPrefab>,
+    appendage: Option<AppendagePrefab>,
+    carrier: Option<CarrierPrefab>,
+    #[redirect(skip)]
+    carryable: Option<Carryable>,
+    sockets: Option<SocketsPrefab>,
+    #[redirect(skip)]
+    helper: Option<Helper>,
+}
+
+/// The ground, the water and the effects left on them.
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PrefabData)]
+#[serde(default)]
+pub struct WorldExtras {
+    terrain: Option<TerrainPrefab>,
+    world_up: Option<WorldUpPrefab>,
+    water: Option<WaterPrefab>,
+    footprints: Option<FootprintConfig>,
+    platform: Option<Platform>,
+}
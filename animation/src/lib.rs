@@ -0,0 +1,13 @@
+//! Procedural animation for Amethyst: inverse kinematics, quadruped locomotion and particle springs,
+//! configured through glTF extras.
+
+#![feature(tau_constant, option_zip)]
+
+pub use crate::{
+    extras::{AnimationExtras, EntityRef, RedirectField},
+    systems::{animal::LocomotionBundle, kinematics::KinematicsBundle},
+};
+
+pub mod extras;
+pub mod systems;
+pub mod utils;
@@ -0,0 +1,311 @@
+use std::f32::EPSILON;
+
+use amethyst::{
+    core::{math::{Matrix4, Point3, Vector3}, Time, transform::{Parent, Transform}},
+    ecs::{Component, prelude::*, SystemData},
+    shrev::{EventChannel, ReaderId},
+};
+use easer::functions::{Cubic, Easing};
+use log::warn;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    systems::{cache::GlobalCache, physics::{Ground, WorldUp}},
+    utils::{determinism::Determinism, warnings::Warnings},
+};
+
+use super::{Legged, Quadruped, State};
+
+/// Timing and extents of a pounce, for a quadruped of scale 1.
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PounceConfig {
+    pub crouch: f32,
+    pub leap: f32,
+    pub reach: f32,
+    pub recover: f32,
+    /// How far the body sinks while crouching.
+    pub crouch_depth: f32,
+    /// Peak height of the body above its stance during the leap.
+    pub leap_height: f32,
+    /// Farthest the body travels towards the target.
+    pub max_lunge: f32,
+    /// How far the front feet reach beyond the body.
+    pub extension: f32,
+}
+
+impl Default for PounceConfig {
+    fn default() -> Self {
+        PounceConfig {
+            crouch: 0.35,
+            leap: 0.3,
+            reach: 0.15,
+            recover: 0.4,
+            crouch_depth: 0.08,
+            leap_height: 0.25,
+            max_lunge: 1.5,
+            extension: 0.3,
+        }
+    }
+}
+
+/// Starts an action of a quadruped; ignored while it is busy with another one.
+#[derive(Debug, Copy, Clone)]
+pub enum ActionEvent {
+    /// Leap towards `target`, reaching for it with the front feet.
+    Pounce { quadruped: Entity, target: Entity },
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Phase {
+    Crouch,
+    Leap,
+    Reach,
+    Recover,
+}
+
+/// Where the pounce goes, fixed when the leap starts.
+#[derive(Debug, Copy, Clone)]
+struct Trajectory {
+    feet: [Point3<f32>; 4],
+    direction: Vector3<f32>,
+    lunge: f32,
+    /// Distance the quadruped has been moved along `direction` so far.
+    moved: f32,
+}
+
+/// A running action, overriding the feet and the body of the quadruped until it is finished.
+#[derive(Debug, Copy, Clone, Component)]
+#[storage(HashMapStorage)]
+pub struct Action {
+    target: Entity,
+    phase: Phase,
+    elapsed: f32,
+    trajectory: Option<Trajectory>,
+}
+
+impl Action {
+    pub fn target(&self) -> Entity {
+        self.target
+    }
+
+    pub fn phase(&self) -> Phase {
+        self.phase
+    }
+}
+
+/// Runs `Action`s after locomotion, then hands the limbs back to it in stance.
+#[derive(Default)]
+pub struct ActionSystem {
+    reader_id: Option<ReaderId<ActionEvent>>,
+}
+
+impl ActionSystem {
+    /// Advances the phase of `action`, returning the eased progress within the current phase,
+    /// or `None` when the action is finished.
+    fn advance(action: &mut Action, config: &PounceConfig, delta_seconds: f32) -> Option<f32> {
+        action.elapsed += delta_seconds;
+        loop {
+            let (duration, next) = match action.phase {
+                Phase::Crouch => (config.crouch, Some(Phase::Leap)),
+                Phase::Leap => (config.leap, Some(Phase::Reach)),
+                Phase::Reach => (config.reach, Some(Phase::Recover)),
+                Phase::Recover => (config.recover, None),
+            };
+            if action.elapsed < duration {
+                return Some(Cubic::ease_in_out(action.elapsed, 0.0, 1.0, duration));
+            }
+            action.elapsed -= duration;
+            action.phase = next?;
+        }
+    }
+
+    fn trajectory(
+        entity: Entity,
+        quadruped: &Quadruped,
+        target: Entity,
+        config: &PounceConfig,
+        up: &WorldUp,
+        cache: &GlobalCache,
+        transforms: &WriteStorage<'_, Transform>,
+    ) -> Option<Trajectory> {
+        let mut feet = [Point3::origin(); 4];
+        for (foot, limb) in feet.iter_mut().zip(quadruped.limbs.iter()) {
+            *foot = cache.global_position(limb.foot)?;
+        }
+
+        let ref position = cache.global_position(entity)?;
+        let ref target = transforms.get(target)?.global_matrix().transform_point(&Point3::origin());
+        let offset = up.planar(&(target - position));
+        let distance = offset.norm();
+        let direction = offset.try_normalize(EPSILON).unwrap_or_else(Vector3::zeros);
+        let lunge = (distance - config.extension).min(config.max_lunge).max(0.0);
+
+        Some(Trajectory { feet, direction, lunge, moved: 0.0 })
+    }
+
+    fn update(
+        entity: Entity,
+        quadruped: &Quadruped,
+        action: &mut Action,
+        progress: f32,
+        config: &PounceConfig,
+        transforms: &mut WriteStorage<'_, Transform>,
+        parents: &ReadStorage<'_, Parent>,
+        cache: &GlobalCache,
+        ground: &Ground<'_>,
+    ) -> Option<()> {
+        if action.phase != Phase::Crouch && action.trajectory.is_none() {
+            action.trajectory = Some(Self::trajectory(entity, quadruped, action.target, config, ground.up(), cache, transforms)?);
+        }
+
+        // Height of the body relative to its stance.
+        let arc = 4.0 * progress * (1.0 - progress);
+        let height = match action.phase {
+            Phase::Crouch => -config.crouch_depth * progress,
+            Phase::Leap => -config.crouch_depth * (1.0 - progress) + config.leap_height * arc,
+            Phase::Reach | Phase::Recover => 0.0,
+        };
+        let inverse = match parents.get(quadruped.root) {
+            Some(parent) => transforms.get(parent.entity)?.global_matrix().try_inverse()?,
+            None => Matrix4::identity(),
+        };
+        transforms
+            .get_mut(quadruped.root)?
+            .append_translation(inverse.transform_vector(&(ground.up().up() * height)));
+
+        let trajectory = match action.trajectory.as_mut() {
+            Some(trajectory) => trajectory,
+            None => return Some(()),
+        };
+
+        let travelled = match action.phase {
+            Phase::Crouch => 0.0,
+            Phase::Leap => trajectory.lunge * progress,
+            Phase::Reach | Phase::Recover => trajectory.lunge,
+        };
+        transforms
+            .get_mut(entity)?
+            .append_translation(trajectory.direction.scale(travelled - trajectory.moved));
+        trajectory.moved = travelled;
+
+        let extension = match action.phase {
+            Phase::Crouch | Phase::Leap => 0.0,
+            Phase::Reach => config.extension * progress,
+            Phase::Recover => config.extension * (1.0 - progress),
+        };
+        for (index, (limb, start)) in quadruped.limbs.iter().zip(trajectory.feet.iter()).enumerate() {
+            let front = index < 2;
+            let lift = match action.phase {
+                Phase::Leap => config.leap_height * arc,
+                Phase::Reach if front => config.leap_height * 0.5 * (1.0 - progress),
+                _ => 0.0,
+            };
+            let reach = if front { travelled + extension } else { travelled };
+            let ref foot = start + trajectory.direction.scale(reach);
+            let foot = ground.up().with_height(foot, ground.height_at(foot) + limb.config.stance_height + lift);
+            transforms.get_mut(limb.foot)?.set_translation(foot.coords);
+        }
+        Some(())
+    }
+}
+
+impl<'a> System<'a> for ActionSystem {
+    type SystemData = (
+        Entities<'a>,
+        WriteStorage<'a, Action>,
+        WriteStorage<'a, Quadruped>,
+        WriteStorage<'a, Transform>,
+        ReadStorage<'a, Parent>,
+        Read<'a, GlobalCache>,
+        Ground<'a>,
+        Read<'a, PounceConfig>,
+        Read<'a, EventChannel<ActionEvent>>,
+        Read<'a, Time>,
+        Read<'a, Determinism>,
+        Write<'a, Warnings>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            entities,
+            mut actions,
+            mut quadrupeds,
+            mut transforms,
+            parents,
+            cache,
+            ground,
+            config,
+            events,
+            time,
+            determinism,
+            mut warnings,
+        ) = data;
+
+        let reader_id = self.reader_id
+            .as_mut()
+            .expect("`ActionSystem::setup` was not called before `ActionSystem::run`");
+
+        for event in events.read(reader_id) {
+            match *event {
+                ActionEvent::Pounce { quadruped, target } => {
+                    if actions.contains(quadruped) || !quadrupeds.contains(quadruped) { continue; }
+                    let action = Action { target, phase: Phase::Crouch, elapsed: 0.0, trajectory: None };
+                    if let Err(error) = actions.insert(quadruped, action) {
+                        warn!("Failed to start pounce of {:?}: {}", quadruped, error);
+                    }
+                }
+            }
+        }
+
+        let delta_seconds = determinism.delta_seconds(time.delta_seconds());
+        let mut finished = vec![];
+        for (entity, action, quadruped) in (&*entities, &mut actions, &mut quadrupeds).join() {
+            let scale = quadruped.scale().unwrap_or(1.0);
+            let config = PounceConfig {
+                crouch_depth: config.crouch_depth * scale,
+                leap_height: config.leap_height * scale,
+                max_lunge: config.max_lunge * scale,
+                extension: config.extension * scale,
+                ..*config
+            };
+
+            match Self::advance(action, &config, delta_seconds) {
+                Some(progress) => {
+                    let updated = Self::update(
+                        entity,
+                        quadruped,
+                        action,
+                        progress,
+                        &config,
+                        &mut transforms,
+                        &parents,
+                        &cache,
+                        &ground,
+                    );
+                    if updated.is_none() {
+                        warnings.skip("action", entity);
+                        finished.push(entity);
+                    }
+                }
+                None => finished.push(entity),
+            }
+        }
+
+        // Hand the feet back to locomotion where the action left them.
+        for entity in finished {
+            actions.remove(entity);
+            if let Some(quadruped) = quadrupeds.get_mut(entity) {
+                for limb in quadruped.limbs.iter_mut() {
+                    limb.state = State::Stance;
+                    limb.transition = false;
+                }
+            }
+        }
+    }
+
+    fn setup(&mut self, world: &mut World) {
+        Self::SystemData::setup(world);
+        self.reader_id = Some(world.fetch_mut::<EventChannel<ActionEvent>>().register_reader());
+    }
+}
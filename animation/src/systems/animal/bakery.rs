@@ -0,0 +1,305 @@
+//! Warm-starts the locomotion of quadrupeds from an authored walk animation.
+//!
+//! The `GaitBakerySystem` plays the animation of the model offline for a few cycles, finds when each foot
+//! touches down and lifts off and how far it travels on the ground, and writes the duty factor, step limits
+//! and flight time into the config of the quadruped, so they need not be tuned by hand for each model.
+
+use std::collections::HashMap;
+
+use amethyst::{
+    animation::{Animation, AnimationHierarchy, AnimationSampling, AnimationSet, Sampler, SamplerPrimitive},
+    assets::{AssetStorage, PrefabData},
+    core::{math::{Matrix4, Point3, Vector3}, transform::{Parent, Transform}},
+    derive::{PrefabData, SystemDesc},
+    ecs::{Component, prelude::*},
+    error::{Error, format_err},
+};
+use itertools::Itertools;
+use log::warn;
+use serde::{Deserialize, Serialize};
+
+use crate::systems::kinematics::Chain;
+
+use super::{Config, Legged, Quadruped};
+
+/// Duty factors measured outside this range are clamped, as the oscillators need both phases.
+const DUTY_FACTOR_RANGE: [f32; 2] = [0.05, 0.95];
+
+fn default_cycles() -> usize {
+    3
+}
+
+fn default_sample_rate() -> f32 {
+    60.0
+}
+
+fn default_contact_threshold() -> f32 {
+    0.1
+}
+
+fn default_max_step_ratio() -> f32 {
+    1.5
+}
+
+/// Derives the step timing of a quadruped from a walk animation of its model,
+/// replacing the duty factor, step limits and flight time of its config once the animation is loaded.
+#[derive(Debug, Copy, Clone, Serialize, Deserialize, PrefabData, Component)]
+#[prefab(Component)]
+#[storage(HashMapStorage)]
+pub struct GaitBakery {
+    /// Index of the walk animation in the glTF file.
+    pub animation: usize,
+    /// Times the animation is played through while sampling.
+    #[serde(default = "default_cycles")]
+    pub cycles: usize,
+    /// Samples per second.
+    #[serde(default = "default_sample_rate")]
+    pub sample_rate: f32,
+    /// Height above its lowest point under which a foot is on the ground, as a fraction of how high it lifts.
+    #[serde(default = "default_contact_threshold")]
+    pub contact_threshold: f32,
+    /// Longest step as a multiple of the step in the animation.
+    #[serde(default = "default_max_step_ratio")]
+    pub max_step_ratio: f32,
+}
+
+/// Step timing measured from an animation, in seconds and in the units of the animated model.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct BakedGait {
+    /// Time from one touchdown of a foot to the next.
+    pub period: f32,
+    /// Fraction of the period a foot is on the ground.
+    pub duty_factor: f32,
+    /// Distance a foot travels relative to the body while on the ground.
+    pub step_length: f32,
+}
+
+impl BakedGait {
+    pub fn flight_time(&self) -> f32 {
+        self.period * (1.0 - self.duty_factor)
+    }
+}
+
+impl GaitBakery {
+    /// Measures the gait from the tracks of the feet relative to the body, one track per foot sampled at `sample_rate`.
+    ///
+    /// Feet that never lift or don't land twice are left out; it fails if no foot is left.
+    pub fn measure(&self, tracks: &[Vec<Vector3<f32>>]) -> Result<BakedGait, Error> {
+        let (mut periods, mut duty_factors, mut steps) = (vec![], vec![], vec![]);
+        for track in tracks {
+            let (low, high) = track
+                .iter()
+                .fold((f32::INFINITY, f32::NEG_INFINITY), |(low, high), point| (low.min(point.y), high.max(point.y)));
+            if !(high - low > f32::EPSILON) { continue; }
+
+            let threshold = low + (high - low) * self.contact_threshold;
+            let contacts = track.iter().map(|point| point.y <= threshold).collect_vec();
+            let touchdowns = (1..track.len())
+                .filter(|&index| contacts[index] && !contacts[index - 1])
+                .collect_vec();
+            let (first, last) = match touchdowns.as_slice() {
+                [first, .., last] => (*first, *last),
+                _ => continue,
+            };
+
+            let cycles = (touchdowns.len() - 1) as f32;
+            periods.push((last - first) as f32 / cycles / self.sample_rate);
+            let grounded = contacts[first..last].iter().filter(|contact| **contact).count();
+            duty_factors.push(grounded as f32 / (last - first) as f32);
+
+            // Stances still running when the sampling ends are left out.
+            for &start in &touchdowns {
+                if let Some(end) = (start..track.len()).find(|&index| !contacts[index]) {
+                    let delta = track[end - 1] - track[start];
+                    steps.push(Vector3::new(delta.x, 0.0, delta.z).norm());
+                }
+            }
+        }
+        if periods.is_empty() || steps.is_empty() {
+            return Err(format_err!("No foot of the animation lifts and lands twice; sample more cycles"));
+        }
+
+        let mean = |values: &[f32]| values.iter().sum::<f32>() / values.len() as f32;
+        let [min_duty_factor, max_duty_factor] = DUTY_FACTOR_RANGE;
+        Ok(BakedGait {
+            period: mean(&periods),
+            duty_factor: mean(&duty_factors).max(min_duty_factor).min(max_duty_factor),
+            step_length: mean(&steps),
+        })
+    }
+
+    /// `config` with the duty factor, step limits and flight time of `gait`, measured on a body of `scale`.
+    ///
+    /// The animation walks at the shortest step, so the oscillators reach their highest angular velocity at its speed.
+    pub fn configure(&self, gait: &BakedGait, config: &Config, scale: f32) -> Config {
+        let step = gait.step_length / scale;
+        Config {
+            max_duty_factor: gait.duty_factor,
+            step_limit: [step, step * self.max_step_ratio.max(1.0)],
+            flight_time: gait.flight_time() / scale.sqrt(),
+            ..*config
+        }
+    }
+
+    /// Plays `animation` over the scene of `hierarchy` and returns the tracks of `feet` relative to `root`,
+    /// or `None` while a sampler is still loading.
+    fn sample(
+        &self,
+        feet: &[Entity],
+        root: Entity,
+        animation: &Animation<Transform>,
+        hierarchy: &AnimationHierarchy<Transform>,
+        samplers: &AssetStorage<Sampler<SamplerPrimitive<f32>>>,
+        transforms: &ReadStorage<'_, Transform>,
+        parents: &ReadStorage<'_, Parent>,
+    ) -> Option<Vec<Vec<Vector3<f32>>>> {
+        let channels = animation.nodes
+            .iter()
+            .filter_map(|(node, channel, handle)| hierarchy.nodes.get(node).map(|entity| (*entity, channel, handle)))
+            .map(|(entity, channel, handle)| samplers.get(handle).map(|sampler| (entity, channel, sampler)))
+            .collect::<Option<Vec<_>>>()?;
+        let duration = channels
+            .iter()
+            .filter_map(|(_, _, sampler)| sampler.input.last().copied())
+            .fold(0.0, f32::max);
+
+        let count = (duration * self.cycles as f32 * self.sample_rate).ceil() as usize;
+        let mut tracks = vec![Vec::with_capacity(count); feet.len()];
+        let mut locals = HashMap::new();
+        for index in 0..count {
+            let time = (index as f32 / self.sample_rate) % duration;
+            for (entity, channel, sampler) in &channels {
+                let sample = sampler.function.interpolate(time, &sampler.input, &sampler.output, true);
+                locals
+                    .entry(*entity)
+                    .or_insert_with(|| transforms.get(*entity).cloned().unwrap_or_default())
+                    .apply_sample(channel, &sample, &());
+            }
+
+            let origin = global_matrix(root, &locals, transforms, parents).transform_point(&Point3::origin());
+            for (track, &foot) in tracks.iter_mut().zip(feet.iter()) {
+                let position = global_matrix(foot, &locals, transforms, parents).transform_point(&Point3::origin());
+                track.push(position - origin);
+            }
+        }
+        Some(tracks)
+    }
+}
+
+/// Global matrix of `entity` with the local transforms in `locals` taking the place of the current ones.
+fn global_matrix(
+    entity: Entity,
+    locals: &HashMap<Entity, Transform>,
+    transforms: &ReadStorage<'_, Transform>,
+    parents: &ReadStorage<'_, Parent>,
+) -> Matrix4<f32> {
+    let mut matrix = Matrix4::identity();
+    let mut current = Some(entity);
+    while let Some(entity) = current {
+        if let Some(local) = locals.get(&entity).or_else(|| transforms.get(entity)) {
+            matrix = local.matrix() * matrix;
+        }
+        current = parents.get(entity).map(|parent| parent.entity);
+    }
+    matrix
+}
+
+/// Bakes the `GaitBakery` of each quadruped into its config once the animation is loaded, then removes it.
+///
+/// The animation is taken from the animation set of the nearest ancestor of the body that has one,
+/// usually the root of its glTF scene. Feet are followed through the bones whose chains reach for them.
+#[derive(Default, SystemDesc)]
+pub struct GaitBakerySystem;
+
+impl GaitBakerySystem {
+    /// The nearest ancestor of `entity` with an animation set, and the set.
+    fn scene<'s>(
+        entity: Entity,
+        parents: &ReadStorage<'_, Parent>,
+        sets: &'s ReadStorage<'_, AnimationSet<usize, Transform>>,
+    ) -> Option<(Entity, &'s AnimationSet<usize, Transform>)> {
+        let mut current = Some(entity);
+        while let Some(entity) = current {
+            if let Some(set) = sets.get(entity) { return Some((entity, set)); }
+            current = parents.get(entity).map(|parent| parent.entity);
+        }
+        None
+    }
+}
+
+impl<'a> System<'a> for GaitBakerySystem {
+    type SystemData = (
+        Entities<'a>,
+        WriteStorage<'a, GaitBakery>,
+        WriteStorage<'a, Quadruped>,
+        ReadStorage<'a, Chain>,
+        ReadStorage<'a, AnimationSet<usize, Transform>>,
+        ReadStorage<'a, AnimationHierarchy<Transform>>,
+        Read<'a, AssetStorage<Animation<Transform>>>,
+        Read<'a, AssetStorage<Sampler<SamplerPrimitive<f32>>>>,
+        ReadStorage<'a, Transform>,
+        ReadStorage<'a, Parent>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            entities,
+            mut bakeries,
+            mut quadrupeds,
+            chains,
+            sets,
+            hierarchies,
+            animations,
+            samplers,
+            transforms,
+            parents,
+        ) = data;
+
+        let bones = (&*entities, &chains)
+            .join()
+            .map(|(bone, chain)| (chain.target(), bone))
+            .collect::<HashMap<_, _>>();
+
+        let mut baked = vec![];
+        for (entity, bakery, quadruped) in (&*entities, &bakeries, &mut quadrupeds).join() {
+            let found = Self::scene(entity, &parents, &sets).and_then(|(scene, set)| {
+                let hierarchy = hierarchies.get(scene)?;
+                Some((hierarchy, set.get(&bakery.animation)?))
+            });
+            let (hierarchy, handle) = match found {
+                Some(found) => found,
+                None => {
+                    warn!("{:?} has no animation {} to bake its gait from", entity, bakery.animation);
+                    baked.push(entity);
+                    continue;
+                }
+            };
+
+            // Wait for the animation to load.
+            let feet = quadruped.feet().iter().map(|foot| *bones.get(foot).unwrap_or(foot)).collect_vec();
+            let tracks = match animations
+                .get(handle)
+                .and_then(|animation| bakery.sample(&feet, quadruped.root(), animation, hierarchy, &samplers, &transforms, &parents)) {
+                Some(tracks) => tracks,
+                None => continue,
+            };
+            baked.push(entity);
+
+            match bakery.measure(&tracks) {
+                Ok(gait) => {
+                    // Measured in world units, so the same as the scale the quadruped calibrates to.
+                    let scale = quadruped.scale().unwrap_or_else(|| {
+                        let matrix = global_matrix(entity, &HashMap::new(), &transforms, &parents);
+                        (0..3).map(|index| matrix.column(index).xyz().norm()).sum::<f32>() / 3.0
+                    });
+                    let config = bakery.configure(&gait, &quadruped.config(), scale);
+                    quadruped.set_config(config);
+                }
+                Err(error) => warn!("Failed to bake the gait of {:?}: {}", entity, error),
+            }
+        }
+        for entity in baked {
+            bakeries.remove(entity);
+        }
+    }
+}
@@ -0,0 +1,155 @@
+use std::{cmp::Ordering, collections::HashMap, marker::PhantomData};
+
+use amethyst::{
+    core::{math::{Matrix4, Vector2, Vector3}, Time, transform::{Parent, Transform}},
+    ecs::prelude::*,
+};
+
+use crate::{
+    systems::{cache::GlobalCache, player::Player},
+    utils::{determinism::Determinism, warnings::Warnings},
+};
+
+use super::Legged;
+
+/// The convex hull of `points` in counterclockwise order.
+fn convex_hull(mut points: Vec<Vector2<f32>>) -> Vec<Vector2<f32>> {
+    if points.len() < 3 { return points; }
+
+    let compare = |a: f32, b: f32| a.partial_cmp(&b).unwrap_or(Ordering::Equal);
+    points.sort_by(|a, b| compare(a.x, b.x).then(compare(a.y, b.y)));
+
+    // Andrew's monotone chain: the lower hull left to right, then the upper hull back.
+    let mut hull: Vec<Vector2<f32>> = vec![];
+    let extend = |hull: &mut Vec<Vector2<f32>>, points: &mut dyn Iterator<Item = &Vector2<f32>>| {
+        let start = hull.len();
+        for point in points {
+            while hull.len() >= start + 2 {
+                let (a, b) = (hull[hull.len() - 2], hull[hull.len() - 1]);
+                if (b - a).perp(&(point - a)) > 0.0 { break; }
+                hull.pop();
+            }
+            hull.push(*point);
+        }
+        hull.pop();
+    };
+    extend(&mut hull, &mut points.iter());
+    extend(&mut hull, &mut points.iter().rev());
+    hull
+}
+
+/// The point of the convex `polygon` closest to `point`; `point` itself if it lies inside.
+fn closest_in_polygon(polygon: &[Vector2<f32>], point: &Vector2<f32>) -> Option<Vector2<f32>> {
+    let closest_on_edge = |a: &Vector2<f32>, b: &Vector2<f32>| {
+        let edge = b - a;
+        let length = edge.norm_squared();
+        let factor = if length > f32::EPSILON { ((point - a).dot(&edge) / length).max(0.0).min(1.0) } else { 0.0 };
+        a + edge * factor
+    };
+
+    match polygon {
+        [] => None,
+        [a] => Some(*a),
+        [a, b] => Some(closest_on_edge(a, b)),
+        _ => {
+            let edges = polygon.iter().zip(polygon.iter().cycle().skip(1));
+            let inside = edges.clone().all(|(a, b)| (b - a).perp(&(point - a)) >= 0.0);
+            if inside { return Some(*point); }
+            edges
+                .map(|(a, b)| closest_on_edge(a, b))
+                .min_by(|a, b| {
+                    let distance = |closest: &Vector2<f32>| (closest - point).norm_squared();
+                    distance(a).partial_cmp(&distance(b)).unwrap_or(Ordering::Equal)
+                })
+        }
+    }
+}
+
+/// Shifts the root of `T` so that, seen from above, it stays over the feet on the ground.
+///
+/// The support polygon is the convex hull of the feet in stance; with two feet it is the segment between them.
+/// While the root is outside, it eases toward the nearest point of the polygon at `balance_stiffness`,
+/// by at most `max_balance_shift`, so slow walks don't look like the body hangs beside its feet.
+/// Must run after the bounce system, which places the root every frame.
+pub struct BalanceSystem<T> {
+    shifts: HashMap<Entity, Vector3<f32>>,
+    marker: PhantomData<T>,
+}
+
+impl<T> Default for BalanceSystem<T> {
+    fn default() -> Self {
+        BalanceSystem { shifts: HashMap::new(), marker: PhantomData }
+    }
+}
+
+impl<T: Legged> BalanceSystem<T> {
+    fn update(
+        body: &T,
+        shift: &mut Vector3<f32>,
+        delta_seconds: f32,
+        transforms: &mut WriteStorage<'_, Transform>,
+        parents: &ReadStorage<'_, Parent>,
+        cache: &GlobalCache,
+    ) -> Option<()> {
+        let config = body.limbs().first()?.config;
+        if config.balance_stiffness <= 0.0 { return Some(()); }
+
+        let root = body.root();
+        let center = cache.global_position(root)?;
+        let stance = body
+            .limbs()
+            .iter()
+            .filter(|limb| limb.is_stance())
+            .map(|limb| cache.global_position(limb.foot).map(|foot| foot.coords.xz()))
+            .collect::<Option<Vec<_>>>()?;
+
+        let ref projected = center.coords.xz();
+        let target = closest_in_polygon(&convex_hull(stance), projected)
+            .map_or_else(Vector2::zeros, |closest| closest - projected);
+        let target = Vector3::new(target.x, 0.0, target.y);
+        let target = match target.try_normalize(f32::EPSILON) {
+            Some(direction) if target.norm() > config.max_balance_shift => direction * config.max_balance_shift,
+            _ => target,
+        };
+
+        let decay = 1.0 - (-config.balance_stiffness * delta_seconds).exp();
+        *shift += (target - *shift) * decay;
+
+        let inverse = match parents.get(root) {
+            Some(parent) => transforms.get(parent.entity)?.global_matrix().try_inverse()?,
+            None => Matrix4::identity(),
+        };
+        transforms
+            .get_mut(root)?
+            .append_translation(inverse.transform_vector(shift));
+        Some(())
+    }
+}
+
+impl<'a, T: Legged> System<'a> for BalanceSystem<T> {
+    type SystemData = (
+        Entities<'a>,
+        ReadStorage<'a, T>,
+        ReadStorage<'a, Player>,
+        WriteStorage<'a, Transform>,
+        ReadStorage<'a, Parent>,
+        Read<'a, GlobalCache>,
+        Read<'a, Time>,
+        Read<'a, Determinism>,
+        Write<'a, Warnings>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (entities, bodies, players, mut transforms, parents, cache, time, determinism, mut warnings) = data;
+
+        self.shifts.retain(|&entity, _| entities.is_alive(entity));
+
+        let delta_seconds = determinism.delta_seconds(time.delta_seconds());
+        for (entity, body, _) in (&*entities, &bodies, &players).join() {
+            let shift = self.shifts.entry(entity).or_insert_with(Vector3::zeros);
+            if Self::update(body, shift, delta_seconds, &mut transforms, &parents, &cache).is_none() {
+                warnings.skip("balance", entity);
+            }
+        }
+    }
+}
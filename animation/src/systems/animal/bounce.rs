@@ -0,0 +1,154 @@
+use std::{f32::EPSILON, marker::PhantomData};
+
+use amethyst::{
+    core::{math::{Point3, Unit, UnitQuaternion, Vector3}, Transform},
+    ecs::prelude::*,
+    renderer::debug_drawing::DebugLines,
+};
+use easer::functions::{Easing, Linear};
+use num_traits::Zero;
+
+use crate::{
+    systems::{cache::GlobalCache, physics::Ground, player::Player},
+    utils::{match_shape, warnings::Warnings},
+};
+
+use super::{Legged, limb_velocity, State, Stumble, Swimming};
+
+/// Gravity the lean into turns balances, the same for bodies of any scale as their config follows dynamic similarity.
+const GRAVITY: f32 = 9.8;
+
+/// Moves the root of `T` so that its anchors keep their height along the `WorldUp` above the feet, bouncing with the steps
+/// and dipping while the body recovers from a `Stumble`, and rolls it into turns.
+pub struct BounceSystem<T> {
+    /// Scratch buffers of the anchors and origins of a body, reused across bodies and frames.
+    anchors: Vec<Point3<f32>>,
+    origins: Vec<Point3<f32>>,
+    marker: PhantomData<T>,
+}
+
+impl<T> Default for BounceSystem<T> {
+    fn default() -> Self {
+        BounceSystem { anchors: vec![], origins: vec![], marker: PhantomData }
+    }
+}
+
+impl<T: Legged> BounceSystem<T> {
+    /// Rotation rolling the root of a body moving as `player` toward the center of its turn, by the angle at which
+    /// gravity balances the centripetal acceleration `v * ω`, at most `max_lean`. In the frame of the body.
+    fn lean(player: &Player, max_lean: f32) -> UnitQuaternion<f32> {
+        if max_lean <= 0.0 { return UnitQuaternion::identity(); }
+
+        let acceleration = player.spinning().scaled_axis().cross(&player.velocity());
+        let ref lateral = acceleration - Vector3::y().scale(acceleration.y);
+        match Unit::try_new(Vector3::y().cross(lateral), EPSILON) {
+            Some(axis) => UnitQuaternion::from_axis_angle(&axis, (lateral.norm() / GRAVITY).atan().min(max_lean)),
+            None => UnitQuaternion::identity(),
+        }
+    }
+
+    /// Fills `anchors` and `origins` with the points of each limb.
+    fn calculate_points(
+        entity: Entity,
+        body: &mut T,
+        player: &Player,
+        stumble: Option<&Stumble>,
+        cache: &GlobalCache,
+        ground: &Ground<'_>,
+        anchors: &mut Vec<Point3<f32>>,
+        origins: &mut Vec<Point3<f32>>,
+    ) -> Option<()> {
+        anchors.clear();
+        origins.clear();
+        let up = ground.up();
+
+        for limb in body.limbs_mut().iter_mut() {
+            let origin = cache.global_position(limb.origin)?;
+            let mut anchor = origin.clone();
+
+            let stance_height = ground.height_at(&origin) + limb.config.stance_height;
+            let length = up.height(&anchor) - stance_height;
+            let max_step_radius = limb.config.step_limit[1] / 2.0;
+            let baseline = (length * length - max_step_radius * max_step_radius).sqrt();
+
+            let velocity = limb_velocity(cache, entity, limb, player)?;
+            let speed = velocity.norm();
+            let [_, max_speed] = player.speed_limit();
+            let height = Linear::ease_in_out(speed, length, baseline - length, max_speed);
+            anchor = up.with_height(&anchor, stance_height + height);
+            if let Some(stumble) = stumble {
+                anchor -= up.up() * stumble.dip(&limb.config);
+            }
+
+            let speed = limb.angular_velocity * limb.radius;
+            match limb.state {
+                State::Stance | State::Airborne | State::Paddling => {}
+                State::Flight { time, .. } => {
+                    let flight_time = limb.flight_time();
+                    let height = limb.config.bounce_factor * flight_time * speed;
+                    let current = {
+                        let factor = time / flight_time;
+                        let ref center = up.up() * height;
+                        let ref origin = Vector3::zero();
+                        let ref first = origin.lerp(center, factor);
+                        let ref second = center.lerp(origin, factor);
+                        first.lerp(second, factor)
+                    };
+                    anchor += current;
+                }
+            }
+
+            anchors.push(anchor);
+            origins.push(origin);
+        }
+
+        Some(())
+    }
+}
+
+impl<'a, T: Legged> System<'a> for BounceSystem<T> {
+    type SystemData = (
+        Entities<'a>,
+        WriteStorage<'a, Transform>,
+        WriteStorage<'a, T>,
+        ReadStorage<'a, Player>,
+        ReadStorage<'a, Swimming>,
+        ReadStorage<'a, Stumble>,
+        Read<'a, GlobalCache>,
+        Ground<'a>,
+        Write<'a, DebugLines>,
+        Write<'a, Warnings>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            entities,
+            mut transforms,
+            mut bodies,
+            players,
+            swimmings,
+            stumbles,
+            cache,
+            ground,
+            _debug_lines,
+            mut warnings,
+        ) = data;
+        // Swimming bodies float instead.
+        for (entity, body, player, stumble, _) in (&*entities, &mut bodies, &players, stumbles.maybe(), !&swimmings).join() {
+            let BounceSystem { anchors, origins, .. } = self;
+            let bounced = Self::calculate_points(entity, body, player, stumble, &cache, &ground, anchors, origins)
+                .and_then(|_| {
+                    let (translation, rotation) = match_shape(origins, anchors, 0.01, 10);
+                    let max_lean = body.limbs().first()?.config.max_lean;
+                    transforms
+                        .get_mut(body.root())?
+                        .set_translation(translation)
+                        .set_rotation(Self::lean(player, max_lean) * rotation);
+                    Some(())
+                });
+            if bounced.is_none() {
+                warnings.skip("bounce", entity);
+            }
+        }
+    }
+}
\ No newline at end of file
@@ -0,0 +1,170 @@
+use std::f32::consts::TAU;
+
+use amethyst::{
+    assets::PrefabData,
+    core::{math::Vector3, Time, Transform},
+    derive::SystemDesc,
+    ecs::{Component, prelude::*},
+    error::Error,
+};
+use serde::{Deserialize, Serialize};
+
+use ceramic_derive::{Redirect, Resolve};
+use redirect::{Redirect, Resolve};
+
+use crate::{
+    extras::EntityRef,
+    systems::player::Player,
+    utils::{determinism::Determinism, warnings::Warnings},
+};
+
+/// Periodic scale and translation offsets on chest and belly joints.
+///
+/// Breathing speeds up with the exertion of the player, so the animal pants after running
+/// and calms down over `recovery` seconds.
+#[derive(Debug, Clone, Component)]
+#[storage(HashMapStorage)]
+pub struct Breathing {
+    joints: Vec<Entity>,
+    player: Option<Entity>,
+    rate: f32,
+    amplitude: f32,
+    offset: [f32; 3],
+    panting: f32,
+    recovery: f32,
+    phase: f32,
+    exertion: f32,
+    /// Local translations and scales of the joints, captured on the first update.
+    rest: Vec<(Vector3<f32>, Vector3<f32>)>,
+}
+
+impl Breathing {
+    pub fn joints(&self) -> &[Entity] {
+        &self.joints
+    }
+
+    pub fn player(&self) -> Option<Entity> {
+        self.player
+    }
+}
+
+fn default_panting() -> f32 {
+    3.0
+}
+
+fn default_recovery() -> f32 {
+    4.0
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Redirect, Resolve)]
+#[resolve(Breathing)]
+#[resolve(init(phase: 0.0, exertion: 0.0, rest: vec![]))]
+pub struct BreathingPrefab {
+    pub joints: Vec<EntityRef>,
+    /// The player whose speed drives the breathing rate.
+    #[serde(default)]
+    pub player: Option<EntityRef>,
+    /// Breaths per second at rest.
+    #[redirect(skip)]
+    pub rate: f32,
+    /// Relative scale added to the joints at the peak of a breath.
+    #[redirect(skip)]
+    pub amplitude: f32,
+    /// Translation added to the joints at the peak of a breath.
+    #[redirect(skip)]
+    #[serde(default)]
+    pub offset: [f32; 3],
+    /// Factor of the rate at full exertion.
+    #[redirect(skip)]
+    #[serde(default = "default_panting")]
+    pub panting: f32,
+    /// Seconds to calm down from full exertion.
+    #[redirect(skip)]
+    #[serde(default = "default_recovery")]
+    pub recovery: f32,
+}
+
+impl<'a> PrefabData<'a> for BreathingPrefab {
+    type SystemData = WriteStorage<'a, Breathing>;
+    type Result = ();
+
+    fn add_to_entity(
+        &self,
+        entity: Entity,
+        data: &mut Self::SystemData,
+        entities: &[Entity],
+        _children: &[Entity],
+    ) -> Result<Self::Result, Error> {
+        data.insert(entity, self.resolve(entities)).map(|_| ()).map_err(Into::into)
+    }
+}
+
+#[derive(Default, SystemDesc)]
+pub struct BreathingSystem;
+
+impl BreathingSystem {
+    fn update(breathing: &mut Breathing, transforms: &mut WriteStorage<'_, Transform>, exertion: f32, delta_seconds: f32) -> Option<()> {
+        if breathing.rest.is_empty() {
+            breathing.rest = breathing.joints
+                .iter()
+                .map(|&joint| transforms
+                    .get(joint)
+                    .map(|transform| (*transform.translation(), *transform.scale())))
+                .collect::<Option<_>>()?;
+        }
+
+        // Exertion rises at once with speed, but only decays over the recovery time.
+        let decay = if breathing.recovery > 0.0 { delta_seconds / breathing.recovery } else { 1.0 };
+        breathing.exertion = exertion.max(breathing.exertion - decay).max(0.0);
+
+        let rate = breathing.rate * (1.0 + (breathing.panting - 1.0) * breathing.exertion);
+        breathing.phase = (breathing.phase + rate * delta_seconds * TAU) % TAU;
+
+        let breath = 0.5 - 0.5 * breathing.phase.cos();
+        let ref offset = Vector3::from(breathing.offset).scale(breath);
+        let scale = 1.0 + breathing.amplitude * breath;
+        for (&joint, (translation, rest_scale)) in breathing.joints.iter().zip(breathing.rest.iter()) {
+            let transform = transforms.get_mut(joint)?;
+            transform.set_translation(translation + offset);
+            transform.set_scale(rest_scale.scale(scale));
+        }
+        Some(())
+    }
+}
+
+impl<'a> System<'a> for BreathingSystem {
+    type SystemData = (
+        Entities<'a>,
+        WriteStorage<'a, Breathing>,
+        WriteStorage<'a, Transform>,
+        ReadStorage<'a, Player>,
+        Read<'a, Time>,
+        Read<'a, Determinism>,
+        Write<'a, Warnings>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            entities,
+            mut breathings,
+            mut transforms,
+            players,
+            time,
+            determinism,
+            mut warnings,
+        ) = data;
+
+        let delta_seconds = determinism.delta_seconds(time.delta_seconds());
+        for (entity, breathing) in (&*entities, &mut breathings).join() {
+            let exertion = breathing.player
+                .and_then(|player| players.get(player))
+                .map_or(0.0, |player| {
+                    let [_, max] = player.speed_limit();
+                    if max > 0.0 { (player.velocity().norm() / max).min(1.0) } else { 0.0 }
+                });
+            if Self::update(breathing, &mut transforms, exertion, delta_seconds).is_none() {
+                warnings.skip("breathing", entity);
+            }
+        }
+    }
+}
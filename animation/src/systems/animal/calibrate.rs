@@ -0,0 +1,150 @@
+use std::collections::HashSet;
+
+use amethyst::{
+    core::{math::{Matrix4, Point3}, transform::{Parent, Transform}},
+    ecs::{prelude::*, SystemData},
+    shrev::{EventChannel, ReaderId},
+};
+use log::warn;
+
+use crate::{systems::{cache::GlobalCache, physics::{Ground, WorldUp}}, utils::warnings::Warnings};
+
+use super::{Biped, Legged, Limb, Locomotor, Quadruped, State};
+
+/// Recomputes the home and origin points of the given quadrupeds from their current pose,
+/// or of all of them if `targets` is `None`.
+///
+/// Send this after editing the rig, e.g. changing the stance width, or teleporting the quadruped.
+#[derive(Debug, Clone, Default)]
+pub struct RecalibrateLimbs {
+    pub targets: Option<Vec<Entity>>,
+}
+
+/// Measures the scale, limbs and stance of every legged body once before its first stride, and handles `RecalibrateLimbs`.
+///
+/// Homes are moved under the feet and origins above them, both across the `WorldUp` keeping their height,
+/// and the limbs are measured again for their reach.
+/// Limbs in flight are put back into stance.
+#[derive(Default)]
+pub struct LimbCalibrationSystem {
+    reader_id: Option<ReaderId<RecalibrateLimbs>>,
+    /// Bodies already warned about failing to calibrate; they are tried again every frame.
+    failed: HashSet<Entity>,
+}
+
+impl LimbCalibrationSystem {
+    /// Calibrates the bodies of `T` that aren't yet.
+    fn calibrate<T: Legged>(
+        &mut self,
+        entities: &Entities<'_>,
+        bodies: &mut WriteStorage<'_, T>,
+        cache: &GlobalCache,
+        ground: &Ground<'_>,
+    ) {
+        for (entity, body) in (&**entities, &mut *bodies).join() {
+            if body.calibrated() { continue; }
+            if body.calibrate(entity, cache, ground).is_some() {
+                self.failed.remove(&entity);
+            } else if self.failed.insert(entity) {
+                warn!("{:?} could not be calibrated from its rig", entity);
+            }
+        }
+    }
+
+    /// Moves `entity` across the `WorldUp` to be above or below `global`, keeping its height.
+    fn place(
+        entity: Entity,
+        global: &Point3<f32>,
+        up: &WorldUp,
+        cache: &GlobalCache,
+        transforms: &mut WriteStorage<'_, Transform>,
+        parents: &ReadStorage<'_, Parent>,
+    ) -> Option<()> {
+        let inverse = match parents.get(entity) {
+            Some(parent) => transforms.get(parent.entity)?.global_matrix().try_inverse()?,
+            None => Matrix4::identity(),
+        };
+        let ref global = up.with_height(global, up.height(&cache.global_position(entity)?));
+        let local = inverse.transform_point(global);
+        transforms.get_mut(entity)?.set_translation(local.coords);
+        Some(())
+    }
+
+    fn recalibrate(
+        limb: &mut Limb,
+        up: &WorldUp,
+        cache: &GlobalCache,
+        transforms: &mut WriteStorage<'_, Transform>,
+        parents: &ReadStorage<'_, Parent>,
+    ) -> Option<()> {
+        let foot = cache.global_position(limb.foot)?;
+        let anchor = cache.global_position(limb.anchor)?;
+
+        Self::place(limb.home, &foot, up, cache, transforms, parents)?;
+        Self::place(limb.origin, &foot, up, cache, transforms, parents)?;
+
+        limb.length = Some((foot - anchor).norm());
+        limb.state = State::Stance;
+        limb.transition = false;
+        Some(())
+    }
+}
+
+impl<'a> System<'a> for LimbCalibrationSystem {
+    type SystemData = (
+        Entities<'a>,
+        WriteStorage<'a, Quadruped>,
+        WriteStorage<'a, Biped>,
+        WriteStorage<'a, Locomotor>,
+        WriteStorage<'a, Transform>,
+        ReadStorage<'a, Parent>,
+        Read<'a, GlobalCache>,
+        Ground<'a>,
+        Read<'a, EventChannel<RecalibrateLimbs>>,
+        Write<'a, Warnings>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            entities,
+            mut quadrupeds,
+            mut bipeds,
+            mut locomotors,
+            mut transforms,
+            parents,
+            cache,
+            ground,
+            events,
+            mut warnings,
+        ) = data;
+
+        self.failed.retain(|&entity| entities.is_alive(entity));
+        self.calibrate(&entities, &mut quadrupeds, &cache, &ground);
+        self.calibrate(&entities, &mut bipeds, &cache, &ground);
+        self.calibrate(&entities, &mut locomotors, &cache, &ground);
+
+        let reader_id = self.reader_id
+            .as_mut()
+            .expect("`LimbCalibrationSystem::setup` was not called before `LimbCalibrationSystem::run`");
+
+        for RecalibrateLimbs { targets } in events.read(reader_id) {
+            for (entity, quadruped) in (&*entities, &mut quadrupeds).join() {
+                let selected = targets
+                    .as_ref()
+                    .map_or(true, |targets| targets.contains(&entity));
+                if !selected { continue; }
+
+                for limb in quadruped.limbs.iter_mut() {
+                    if Self::recalibrate(limb, ground.up(), &cache, &mut transforms, &parents).is_none() {
+                        warnings.skip("limb_calibration", entity);
+                    }
+                }
+            }
+        }
+    }
+
+    fn setup(&mut self, world: &mut World) {
+        Self::SystemData::setup(world);
+        self.reader_id = Some(world.fetch_mut::<EventChannel<RecalibrateLimbs>>().register_reader());
+    }
+}
@@ -0,0 +1,195 @@
+use std::{collections::HashMap, marker::PhantomData};
+
+use amethyst::{
+    core::{math::{Complex, Point3, UnitQuaternion, Vector3}, Time, Transform},
+    ecs::{Component, prelude::*},
+};
+
+use crate::{
+    systems::{cache::GlobalCache, kinematics::{Chain, Dormant}, physics::Ground, player::Player},
+    utils::{determinism::Determinism, warnings::Warnings},
+};
+
+use super::{FarMode, Legged, limb_velocity, LocomotionLod, State};
+
+/// Marks a legged body beyond the `far_distance` of the `LocomotionLod`, animated cheaply by the `DistantSystem`
+/// instead of the locomotion, the oscillators and the IK.
+#[derive(Debug, Default, Copy, Clone, Component)]
+#[storage(NullStorage)]
+pub struct Distant;
+
+/// Switches bodies of `T` between full and cheap locomotion by their distance to the focus of the `LocomotionLod`,
+/// and swings the legs of distant ones.
+///
+/// Distant bodies have the chains reaching for their feet marked `Dormant`. With `FarMode::Sinusoid`, the oscillator
+/// of each limb keeps turning at the speed of the body, uncoupled, and the anchor swings about its local x axis
+/// with it. Coming back, the anchors are restored and the feet put down at their homes.
+pub struct DistantSystem<T> {
+    /// Local rotations of the anchors of distant bodies, from when they went distant.
+    rest: HashMap<Entity, UnitQuaternion<f32>>,
+    marker: PhantomData<T>,
+}
+
+impl<T> Default for DistantSystem<T> {
+    fn default() -> Self {
+        DistantSystem { rest: HashMap::new(), marker: PhantomData }
+    }
+}
+
+impl<T: Legged> DistantSystem<T> {
+    /// Whether the body at `position` is distant, given whether it was.
+    fn is_distant(lod: &LocomotionLod, position: Option<Point3<f32>>, was_distant: bool) -> bool {
+        let distance = match (lod.focus, position) {
+            (Some(ref focus), Some(ref position)) => (position - focus).norm(),
+            _ => return false,
+        };
+        if was_distant {
+            distance > lod.far_distance - lod.hysteresis
+        } else {
+            distance > lod.far_distance
+        }
+    }
+
+    fn enter(&mut self, body: &T, transforms: &WriteStorage<'_, Transform>) -> Option<()> {
+        for limb in body.limbs() {
+            let rotation = *transforms.get(limb.anchor)?.rotation();
+            self.rest.insert(limb.anchor, rotation);
+        }
+        Some(())
+    }
+
+    fn leave(
+        &mut self,
+        body: &mut T,
+        cache: &GlobalCache,
+        ground: &Ground<'_>,
+        transforms: &mut WriteStorage<'_, Transform>,
+    ) -> Option<()> {
+        for limb in body.limbs_mut() {
+            if let Some(rotation) = self.rest.remove(&limb.anchor) {
+                transforms.get_mut(limb.anchor)?.set_rotation(rotation);
+            }
+            // The body moved on without stepping, so put the feet down where they would stand.
+            let ref home = cache.global_position(limb.home)?;
+            let foot = ground.up().with_height(home, ground.height_at(home) + limb.config.stance_height);
+            transforms
+                .get_mut(limb.foot)?
+                .set_translation(foot.coords);
+            limb.state = State::Stance;
+            limb.platform = None;
+        }
+        Some(())
+    }
+
+    fn swing(
+        &self,
+        entity: Entity,
+        body: &mut T,
+        player: &Player,
+        delta_seconds: f32,
+        cache: &GlobalCache,
+        transforms: &mut WriteStorage<'_, Transform>,
+    ) -> Option<()> {
+        for limb in body.limbs_mut() {
+            let speed = limb_velocity(cache, entity, limb, player)?.norm();
+            limb.match_speed(speed);
+
+            let ref mut signal = limb.signal;
+            let duty_factor = limb.duty_factor;
+            let omega = if signal.im < 0.0 {
+                limb.angular_velocity / duty_factor / 2.0
+            } else {
+                limb.angular_velocity / (1.0 - duty_factor) / 2.0
+            };
+            *signal = *signal * Complex::from_polar(&1.0, &(omega * delta_seconds));
+
+            let length = limb.length.unwrap_or(1.0);
+            let amplitude = (limb.step_radius() / length).atan();
+            let angle = -amplitude * limb.signal.re;
+            let rest = *self.rest.get(&limb.anchor)?;
+            transforms
+                .get_mut(limb.anchor)?
+                .set_rotation(rest * UnitQuaternion::from_axis_angle(&Vector3::x_axis(), angle));
+        }
+        Some(())
+    }
+}
+
+impl<'a, T: Legged> System<'a> for DistantSystem<T> {
+    type SystemData = (
+        Entities<'a>,
+        WriteStorage<'a, T>,
+        ReadStorage<'a, Player>,
+        WriteStorage<'a, Distant>,
+        ReadStorage<'a, Chain>,
+        WriteStorage<'a, Dormant>,
+        WriteStorage<'a, Transform>,
+        Read<'a, GlobalCache>,
+        Ground<'a>,
+        Read<'a, LocomotionLod>,
+        Read<'a, Time>,
+        Read<'a, Determinism>,
+        Write<'a, Warnings>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            entities,
+            mut bodies,
+            players,
+            mut distants,
+            chains,
+            mut dormants,
+            mut transforms,
+            cache,
+            ground,
+            lod,
+            time,
+            determinism,
+            mut warnings,
+        ) = data;
+
+        self.rest.retain(|&entity, _| entities.is_alive(entity));
+        let chains = (&*entities, &chains)
+            .join()
+            .map(|(entity, chain)| (chain.target(), entity))
+            .collect::<HashMap<_, _>>();
+
+        let delta_seconds = determinism.delta_seconds(time.delta_seconds());
+        for (entity, body, player) in (&*entities, &mut bodies, &players).join() {
+            let was_distant = distants.contains(entity);
+            let distant = Self::is_distant(&lod, cache.global_position(entity), was_distant);
+
+            let switched = match (was_distant, distant) {
+                (false, true) => {
+                    let entered = self.enter(body, &transforms);
+                    distants.insert(entity, Distant).ok();
+                    entered
+                }
+                (true, false) => {
+                    distants.remove(entity);
+                    self.leave(body, &cache, &ground, &mut transforms)
+                }
+                _ => Some(()),
+            };
+            if switched.is_none() {
+                warnings.skip("distant", entity);
+            }
+            if was_distant != distant {
+                for chain in body.limbs().iter().filter_map(|limb| chains.get(&limb.foot)) {
+                    if distant {
+                        dormants.insert(*chain, Dormant).ok();
+                    } else {
+                        dormants.remove(*chain);
+                    }
+                }
+            }
+
+            if distant && lod.far_mode == FarMode::Sinusoid {
+                if self.swing(entity, body, player, delta_seconds, &cache, &mut transforms).is_none() {
+                    warnings.skip("distant", entity);
+                }
+            }
+        }
+    }
+}
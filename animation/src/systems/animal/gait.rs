@@ -0,0 +1,136 @@
+use std::f32::consts::{FRAC_PI_2, FRAC_PI_4, PI};
+
+use amethyst::{
+    core::Time,
+    derive::SystemDesc,
+    ecs::prelude::*,
+};
+use interpolation::Lerp;
+use serde::{Deserialize, Serialize};
+
+use crate::utils::determinism::Determinism;
+
+use super::Quadruped;
+
+const WALK_PHASES: [[f32; 4]; 4] = [
+    [0.0, PI, 0.0, FRAC_PI_2],
+    [-PI, 0.0, FRAC_PI_2, 0.0],
+    [0.0, -FRAC_PI_2, 0.0, PI],
+    [-FRAC_PI_2, 0.0, -PI, 0.0],
+];
+const TROT_PHASES: [[f32; 4]; 4] = [
+    [0.0, PI, 0.0, PI],
+    [-PI, 0.0, PI, 0.0],
+    [0.0, -PI, 0.0, PI],
+    [-PI, 0.0, -PI, 0.0],
+];
+const GALLOP_PHASES: [[f32; 4]; 4] = [
+    [0.0, FRAC_PI_2, 0.0, -3.0 * FRAC_PI_4],
+    [-FRAC_PI_2, 0.0, 3.0 * FRAC_PI_4, 0.0],
+    [0.0, -3.0 * FRAC_PI_4, 0.0, 0.0],
+    [3.0 * FRAC_PI_4, 0.0, 0.0, 0.0],
+];
+
+/// A gait of a quadruped, given by the phase offsets between the oscillators of its limbs.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub enum Gait {
+    /// The diagonal walk.
+    Walk,
+    Trot,
+    Gallop,
+}
+
+impl Gait {
+    /// The gait after this one, with `None` for the gait chosen by speed.
+    pub fn next(gait: Option<Self>) -> Option<Self> {
+        match gait {
+            None => Some(Gait::Walk),
+            Some(Gait::Walk) => Some(Gait::Trot),
+            Some(Gait::Trot) => Some(Gait::Gallop),
+            Some(Gait::Gallop) => None,
+        }
+    }
+
+    /// The gait that dominates the blend of `phase_at` at `duty_factor`.
+    pub fn at(duty_factor: f32) -> Self {
+        match duty_factor {
+            factor if factor > 0.75 => Gait::Walk,
+            factor if factor > 0.3 => Gait::Trot,
+            _ => Gait::Gallop,
+        }
+    }
+
+    pub(super) fn phase(self, i: usize, j: usize) -> f32 {
+        match self {
+            Gait::Walk => WALK_PHASES[i][j],
+            Gait::Trot => TROT_PHASES[i][j],
+            Gait::Gallop => GALLOP_PHASES[i][j],
+        }
+    }
+
+    /// The phase offset when the gait follows the speed, blending from a gallop over a trot
+    /// into a diagonal walk as the duty factor rises.
+    pub(super) fn phase_at(i: usize, j: usize, duty_factor: f32) -> f32 {
+        match duty_factor {
+            factor if factor > 0.5 => {
+                let trot = TROT_PHASES[i][j];
+                let ref walk = WALK_PHASES[i][j];
+                let ref factor = (duty_factor - 0.5) / 0.5;
+                trot.lerp(walk, factor)
+            }
+            factor if factor > 0.3 => {
+                let gallop = GALLOP_PHASES[i][j];
+                let ref trot = TROT_PHASES[i][j];
+                let ref factor = duty_factor / 0.5;
+                gallop.lerp(trot, factor)
+            }
+            _ => GALLOP_PHASES[i][j],
+        }
+    }
+}
+
+/// The phase offsets a quadruped had when its gait was changed, faded out over the transition.
+#[derive(Debug, Copy, Clone)]
+pub(super) struct Crossfade {
+    pub from: [[f32; 4]; 4],
+    /// Progress of the transition in `[0, 1]`.
+    pub progress: f32,
+}
+
+/// Seconds to crossfade the phase offsets when the gait of a quadruped is changed.
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+pub struct GaitTransition {
+    pub duration: f32,
+}
+
+impl Default for GaitTransition {
+    fn default() -> Self {
+        GaitTransition { duration: 0.6 }
+    }
+}
+
+/// Advances the crossfades started by `Quadruped::set_gait`.
+#[derive(Default, SystemDesc)]
+pub struct GaitTransitionSystem;
+
+impl<'a> System<'a> for GaitTransitionSystem {
+    type SystemData = (
+        WriteStorage<'a, Quadruped>,
+        Read<'a, GaitTransition>,
+        Read<'a, Time>,
+        Read<'a, Determinism>,
+    );
+
+    fn run(&mut self, (mut quadrupeds, transition, time, determinism): Self::SystemData) {
+        let delta_seconds = determinism.delta_seconds(time.delta_seconds());
+        let step = if transition.duration > 0.0 { delta_seconds / transition.duration } else { 1.0 };
+        for quadruped in (&mut quadrupeds).join() {
+            if let Some(crossfade) = quadruped.crossfade.as_mut() {
+                crossfade.progress += step;
+                if crossfade.progress >= 1.0 {
+                    quadruped.crossfade = None;
+                }
+            }
+        }
+    }
+}
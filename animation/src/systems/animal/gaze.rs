@@ -0,0 +1,217 @@
+use std::{cmp::Ordering, f32::EPSILON};
+
+use amethyst::{
+    assets::PrefabData,
+    core::{
+        math::{Matrix4, Point3, UnitQuaternion, Vector3},
+        Time,
+        transform::{Parent, Transform},
+    },
+    derive::SystemDesc,
+    ecs::{Component, prelude::*},
+    error::Error,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    systems::spatial::{Indexed, SpatialIndex},
+    utils::{determinism::Determinism, transform::TransformTrait, warnings::Warnings},
+};
+
+/// Something worth glancing at.
+#[derive(Debug, Copy, Clone, Serialize, Deserialize, Component)]
+#[storage(HashMapStorage)]
+pub struct InterestPoint {
+    /// Points of higher priority win over nearer ones.
+    #[serde(default)]
+    pub priority: i32,
+    /// Only draws attention within this distance.
+    pub radius: f32,
+}
+
+impl<'a> PrefabData<'a> for InterestPoint {
+    type SystemData = (WriteStorage<'a, InterestPoint>, WriteStorage<'a, Indexed>);
+    type Result = ();
+
+    fn add_to_entity(
+        &self,
+        entity: Entity,
+        (interests, indexed): &mut Self::SystemData,
+        _entities: &[Entity],
+        _children: &[Entity],
+    ) -> Result<Self::Result, Error> {
+        interests.insert(entity, *self)?;
+        indexed.insert(entity, Indexed)?;
+        Ok(())
+    }
+}
+
+/// Turns a joint toward nearby interest points, returning to its rest pose when there are none.
+///
+/// Layer it on the neck, head and eyes with increasing speeds and limits for a natural glance.
+#[derive(Debug, Copy, Clone, Component)]
+#[storage(HashMapStorage)]
+pub struct Gaze {
+    radius: f32,
+    limit: f32,
+    speed: f32,
+    glance: f32,
+    rest: Option<UnitQuaternion<f32>>,
+    /// The point currently looked at, blending toward the selected interest.
+    focus: Option<Point3<f32>>,
+    /// Influence of the focus over the rest pose.
+    weight: f32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GazePrefab {
+    /// Interest points are searched within this distance.
+    pub radius: f32,
+    /// Maximum deviation from the rest pose (degrees).
+    pub limit: f32,
+    /// Stiffness of the joint following the gaze.
+    pub speed: f32,
+    /// Stiffness of the gaze switching between targets.
+    pub glance: f32,
+}
+
+impl<'a> PrefabData<'a> for GazePrefab {
+    type SystemData = WriteStorage<'a, Gaze>;
+    type Result = ();
+
+    fn add_to_entity(
+        &self,
+        entity: Entity,
+        data: &mut Self::SystemData,
+        _entities: &[Entity],
+        _children: &[Entity],
+    ) -> Result<Self::Result, Error> {
+        let component = Gaze {
+            radius: self.radius,
+            limit: self.limit.to_radians(),
+            speed: self.speed,
+            glance: self.glance,
+            rest: None,
+            focus: None,
+            weight: 0.0,
+        };
+        data.insert(entity, component).map(|_| ()).map_err(Into::into)
+    }
+}
+
+#[derive(Default, SystemDesc)]
+pub struct GazeSystem;
+
+impl GazeSystem {
+    fn select_target(
+        entity: Entity,
+        position: Point3<f32>,
+        gaze: &Gaze,
+        index: &SpatialIndex,
+        interests: &ReadStorage<'_, InterestPoint>,
+    ) -> Option<Point3<f32>> {
+        index
+            .query(position, gaze.radius)
+            .filter(|(other, _)| *other != entity)
+            .filter_map(|(other, point)| {
+                let interest = interests.get(other)?;
+                let distance = (point - position).norm();
+                if distance <= interest.radius { Some((interest.priority, distance, point)) } else { None }
+            })
+            .max_by(|(a, a_distance, _), (b, b_distance, _)| a
+                .cmp(b)
+                .then(b_distance.partial_cmp(a_distance).unwrap_or(Ordering::Equal)))
+            .map(|(_, _, point)| point)
+    }
+
+    fn process_gaze(
+        entity: Entity,
+        gaze: &mut Gaze,
+        delta_seconds: f32,
+        index: &SpatialIndex,
+        interests: &ReadStorage<'_, InterestPoint>,
+        parents: &ReadStorage<'_, Parent>,
+        transforms: &mut WriteStorage<'_, Transform>,
+    ) -> Option<()> {
+        let current = transforms.get(entity)?.rotation().clone();
+        let rest = *gaze.rest.get_or_insert(current);
+        let position = transforms.get(entity)?.global_position();
+
+        let blend = 1.0 - (-gaze.glance * delta_seconds).exp();
+        match Self::select_target(entity, position, gaze, index, interests) {
+            Some(target) => {
+                let focus = gaze.focus.map_or(target, |focus| focus + (target - focus).scale(blend));
+                gaze.focus.replace(focus);
+                gaze.weight += (1.0 - gaze.weight) * blend;
+            }
+            None => gaze.weight -= gaze.weight * blend,
+        }
+
+        let parent_view = parents
+            .get(entity)
+            .and_then(|parent| transforms.get(parent.entity))
+            .map(|transform| transform.global_view_matrix())
+            .unwrap_or_else(Matrix4::identity);
+
+        // Joints point along their y axis; turn it toward the focus, clamped by the joint limit.
+        let look = gaze.focus
+            .and_then(|focus| {
+                let ref direction = parent_view.transform_vector(&(focus - position));
+                let ref forward = rest * Vector3::y();
+                let (axis, angle) = UnitQuaternion::rotation_between(forward, direction)?.axis_angle()?;
+                Some(UnitQuaternion::from_axis_angle(&axis, angle.min(gaze.limit)) * rest)
+            })
+            .unwrap_or(rest);
+        let goal = rest.try_slerp(&look, gaze.weight, EPSILON).unwrap_or(look);
+
+        let interpolation = 1.0 - (-gaze.speed * delta_seconds).exp();
+        if let Some(rotation) = current.try_slerp(&goal, interpolation, EPSILON) {
+            transforms.get_mut(entity)?.set_rotation(rotation);
+        }
+        Some(())
+    }
+}
+
+impl<'a> System<'a> for GazeSystem {
+    type SystemData = (
+        Entities<'a>,
+        ReadStorage<'a, Parent>,
+        WriteStorage<'a, Transform>,
+        WriteStorage<'a, Gaze>,
+        ReadStorage<'a, InterestPoint>,
+        Read<'a, SpatialIndex>,
+        Read<'a, Time>,
+        Read<'a, Determinism>,
+        Write<'a, Warnings>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            entities,
+            parents,
+            mut transforms,
+            mut gazes,
+            interests,
+            index,
+            time,
+            determinism,
+            mut warnings,
+        ) = data;
+
+        let delta_seconds = determinism.delta_seconds(time.delta_seconds());
+        for (entity, gaze) in (&*entities, &mut gazes).join() {
+            let processed = Self::process_gaze(
+                entity,
+                gaze,
+                delta_seconds,
+                &index,
+                &interests,
+                &parents,
+                &mut transforms,
+            );
+            if processed.is_none() {
+                warnings.skip("gaze", entity);
+            }
+        }
+    }
+}
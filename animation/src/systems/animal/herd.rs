@@ -0,0 +1,149 @@
+use std::{collections::HashMap, f32::consts::PI};
+
+use amethyst::{
+    assets::PrefabData,
+    core::{math::Complex, Time},
+    ecs::{Component, prelude::*},
+    error::Error,
+};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    systems::{cache::GlobalCache, spatial::{Indexed, SpatialIndex}},
+    utils::{determinism::Determinism, random::{Random, Seed}, warnings::Warnings},
+};
+
+use super::Quadruped;
+
+fn default_radius() -> f32 {
+    6.0
+}
+
+/// How the gait of a quadruped relates to those of the others in its herd, e.g.
+/// `"herd_sync": { "desync": 3.14, "coupling": 0.2, "radius": 6.0 }` next to its `"quadruped"`.
+///
+/// Animals spawned together start in the same phase and step in unison; a `desync` spreads their starting phases,
+/// and a weak `coupling` lets neighbors drift into step with each other over time.
+#[derive(Debug, Copy, Clone, Serialize, Deserialize, Component)]
+#[storage(HashMapStorage)]
+pub struct HerdSync {
+    /// Largest shift in radians of the phase of all limbs at spawn, drawn at random for each animal.
+    #[serde(default)]
+    pub desync: f32,
+    /// Radians per second the phase is pulled toward the mean phase of the neighbors; zero leaves it independent.
+    #[serde(default)]
+    pub coupling: f32,
+    /// Quadrupeds within this distance are neighbors.
+    #[serde(default = "default_radius")]
+    pub radius: f32,
+    #[serde(skip)]
+    desynced: bool,
+}
+
+impl<'a> PrefabData<'a> for HerdSync {
+    type SystemData = (WriteStorage<'a, HerdSync>, WriteStorage<'a, Indexed>);
+    type Result = ();
+
+    fn add_to_entity(
+        &self,
+        entity: Entity,
+        (syncs, indexed): &mut Self::SystemData,
+        _entities: &[Entity],
+        _children: &[Entity],
+    ) -> Result<Self::Result, Error> {
+        syncs.insert(entity, *self)?;
+        indexed.insert(entity, Indexed)?;
+        Ok(())
+    }
+}
+
+/// Rotates every limb of `quadruped` by `angle`, keeping the phase offsets of its gait.
+fn shift_phases(quadruped: &mut Quadruped, angle: f32) {
+    let rotation = Complex::from_polar(&1.0, &angle);
+    let mut phases = quadruped.phases();
+    for phase in phases.iter_mut() {
+        *phase = *phase * rotation;
+    }
+    quadruped.set_phases(phases);
+}
+
+/// Desynchronizes the phases of quadrupeds with a `HerdSync` once at spawn, and couples them to their neighbors.
+///
+/// Phases are compared by the oscillator of the first limb, and every limb of a body is shifted by the same angle,
+/// so the gait itself is left to the coupling of the body's own limbs.
+#[derive(Default)]
+pub struct HerdSyncSystem;
+
+impl<'a> System<'a> for HerdSyncSystem {
+    type SystemData = (
+        Entities<'a>,
+        WriteStorage<'a, HerdSync>,
+        WriteStorage<'a, Quadruped>,
+        WriteStorage<'a, Seed>,
+        Write<'a, Random>,
+        Read<'a, SpatialIndex>,
+        Read<'a, GlobalCache>,
+        Read<'a, Time>,
+        Read<'a, Determinism>,
+        Write<'a, Warnings>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            entities,
+            mut syncs,
+            mut quadrupeds,
+            mut seeds,
+            mut random,
+            index,
+            cache,
+            time,
+            determinism,
+            mut warnings,
+        ) = data;
+
+        for (entity, sync, quadruped) in (&*entities, &mut syncs, &mut quadrupeds).join() {
+            if sync.desynced { continue; }
+            if Seed::assign(entity, &mut random, &mut seeds).is_err() {
+                warnings.skip("herd_sync", entity);
+                continue;
+            }
+            let rng = seeds.get_mut(entity).expect("Unreachable: the seed was just assigned");
+            let desync = sync.desync.max(0.0).min(PI);
+            if desync > 0.0 {
+                shift_phases(quadruped, rng.gen_range(-desync, desync));
+            }
+            sync.desynced = true;
+        }
+
+        let delta_seconds = determinism.delta_seconds(time.delta_seconds());
+        let phases = (&*entities, &syncs, &quadrupeds)
+            .join()
+            .map(|(entity, _, quadruped)| (entity, quadruped.phases()[0]))
+            .collect::<HashMap<_, _>>();
+
+        for (entity, sync, quadruped) in (&*entities, &syncs, &mut quadrupeds).join() {
+            if sync.coupling <= 0.0 { continue; }
+            let position = match cache.global_position(entity) {
+                Some(position) => position,
+                None => {
+                    warnings.skip("herd_sync", entity);
+                    continue;
+                }
+            };
+
+            let mean = index
+                .query(position, sync.radius)
+                .filter(|(neighbor, _)| *neighbor != entity)
+                .filter_map(|(neighbor, _)| phases.get(&neighbor))
+                .fold(Complex::new(0.0, 0.0), |sum, phase| sum + phase.unscale(phase.norm().max(f32::EPSILON)));
+            if mean.norm() <= f32::EPSILON { continue; }
+
+            // Kuramoto coupling: turn toward the mean phase, faster the further off it is.
+            let own = quadruped.phases()[0];
+            let offset = (mean * own.conj()).arg();
+            shift_phases(quadruped, sync.coupling * offset.sin() * delta_seconds);
+        }
+    }
+}
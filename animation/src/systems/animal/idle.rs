@@ -0,0 +1,186 @@
+use std::{collections::HashMap, f32::consts::TAU, marker::PhantomData};
+
+use amethyst::{
+    core::{math::{Matrix4, Vector3}, Time, transform::{Parent, Transform}},
+    ecs::prelude::*,
+};
+use rand::Rng;
+
+use crate::{
+    systems::{cache::GlobalCache, player::Player},
+    utils::{determinism::Determinism, random::{Random, Seed}, warnings::Warnings},
+};
+
+use super::{Legged, State};
+
+/// Seconds to blend the weight shift in when the body stops, and out when it moves again.
+const IDLE_BLEND: f32 = 0.5;
+
+/// Fraction of the stepping threshold below which a limb counts as standing still.
+const IDLE_FACTOR: f32 = 0.1;
+
+/// The fidgeting of one standing body.
+#[derive(Debug, Copy, Clone)]
+struct Idle {
+    elapsed: f32,
+    /// Phases of the sine waves summed into the sway, drawn from the seed of the body.
+    phases: [f32; 4],
+    /// How far the body has settled into idling, in `[0, 1]`.
+    weight: f32,
+    /// Seconds until the next fidget step.
+    countdown: f32,
+}
+
+impl Idle {
+    fn new(rng: &mut Seed) -> Self {
+        let mut phase = || rng.gen_range(0.0, TAU);
+        Idle {
+            elapsed: 0.0,
+            phases: [phase(), phase(), phase(), phase()],
+            weight: 0.0,
+            countdown: 0.0,
+        }
+    }
+
+    /// Sideways and forward sway in `[-1, 1]`, from sines of unrelated frequencies so it never visibly repeats.
+    fn sway(&self, frequency: f32) -> Vector3<f32> {
+        let [a, b, c, d] = self.phases;
+        let wave = |rate: f32, phase: f32| (TAU * frequency * rate * self.elapsed + phase).sin();
+        let sideways = 0.6 * wave(1.0, a) + 0.4 * wave(2.3, b);
+        let forward = 0.7 * wave(0.7, c) + 0.3 * wave(1.9, d);
+        Vector3::new(sideways, 0.0, 0.5 * forward)
+    }
+}
+
+/// Keeps standing bodies of `T` from looking frozen.
+///
+/// While the body stands still its root sways with a smooth noise, shifting the weight between
+/// the limbs, and every few seconds the limb carrying the least weight lifts and plants its foot
+/// at its home again. The amounts come from the `idle_*` fields of the gait `Config`.
+pub struct IdleSystem<T> {
+    idles: HashMap<Entity, Idle>,
+    marker: PhantomData<T>,
+}
+
+impl<T> Default for IdleSystem<T> {
+    fn default() -> Self {
+        IdleSystem { idles: HashMap::new(), marker: PhantomData }
+    }
+}
+
+impl<T: Legged> IdleSystem<T> {
+    fn update(
+        entity: Entity,
+        body: &mut T,
+        idle: &mut Idle,
+        rng: &mut Seed,
+        delta_seconds: f32,
+        transforms: &mut WriteStorage<'_, Transform>,
+        parents: &ReadStorage<'_, Parent>,
+        cache: &GlobalCache,
+    ) -> Option<()> {
+        let config = body.limbs().first()?.config;
+        let standing = body.limbs().iter().all(|limb| {
+            limb.angular_velocity < IDLE_FACTOR * limb.threshold && !limb.held && match limb.state {
+                State::Stance => true,
+                _ => false,
+            }
+        });
+
+        let blend = delta_seconds / IDLE_BLEND;
+        idle.weight = if standing { idle.weight + blend } else { idle.weight - blend }.max(0.0).min(1.0);
+        if idle.weight == 0.0 {
+            idle.elapsed = 0.0;
+            idle.countdown = 0.0;
+            return Some(());
+        }
+        idle.elapsed += delta_seconds;
+
+        let sway = idle.sway(config.idle_frequency);
+        let shift = cache
+            .global_matrix(entity)?
+            .transform_vector(&sway.scale(config.idle_shift * idle.weight));
+
+        let root = body.root();
+        let inverse = match parents.get(root) {
+            Some(parent) => transforms.get(parent.entity)?.global_matrix().try_inverse()?,
+            None => Matrix4::identity(),
+        };
+        transforms
+            .get_mut(root)?
+            .append_translation(inverse.transform_vector(&shift));
+
+        let [min_interval, max_interval] = config.idle_interval;
+        if !standing || max_interval <= 0.0 {
+            return Some(());
+        }
+        idle.countdown -= delta_seconds;
+        if idle.countdown > 0.0 {
+            return Some(());
+        }
+        idle.countdown = if max_interval > min_interval { rng.gen_range(min_interval, max_interval) } else { max_interval };
+
+        // The limb farthest against the sway carries the least weight, so it is free to step.
+        let ref center = cache.global_position(entity)?;
+        let mut unloaded = None;
+        for (index, limb) in body.limbs().iter().enumerate() {
+            let load = (cache.global_position(limb.home)? - center).dot(&shift);
+            if unloaded.map_or(true, |(_, least)| load < least) {
+                unloaded = Some((index, load));
+            }
+        }
+        let (index, _) = unloaded?;
+        let limb = &mut body.limbs_mut()[index];
+        let stance = cache.global_position(limb.foot)?;
+        limb.state = State::Flight { stance, time: 0.0 };
+        Some(())
+    }
+}
+
+impl<'a, T: Legged> System<'a> for IdleSystem<T> {
+    type SystemData = (
+        Entities<'a>,
+        WriteStorage<'a, T>,
+        ReadStorage<'a, Player>,
+        WriteStorage<'a, Transform>,
+        ReadStorage<'a, Parent>,
+        WriteStorage<'a, Seed>,
+        Write<'a, Random>,
+        Read<'a, GlobalCache>,
+        Read<'a, Time>,
+        Read<'a, Determinism>,
+        Write<'a, Warnings>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            entities,
+            mut bodies,
+            players,
+            mut transforms,
+            parents,
+            mut seeds,
+            mut random,
+            cache,
+            time,
+            determinism,
+            mut warnings,
+        ) = data;
+
+        self.idles.retain(|&entity, _| entities.is_alive(entity));
+
+        let delta_seconds = determinism.delta_seconds(time.delta_seconds());
+        for (entity, body, _) in (&*entities, &mut bodies, &players).join() {
+            if Seed::assign(entity, &mut random, &mut seeds).is_err() {
+                warnings.skip("idle", entity);
+                continue;
+            }
+            let rng = seeds.get_mut(entity).expect("Unreachable: the seed was just assigned");
+            let idle = self.idles.entry(entity).or_insert_with(|| Idle::new(rng));
+            let updated = Self::update(entity, body, idle, rng, delta_seconds, &mut transforms, &parents, &cache);
+            if updated.is_none() {
+                warnings.skip("idle", entity);
+            }
+        }
+    }
+}
@@ -0,0 +1,227 @@
+use amethyst::{
+    core::{math::Point3, Time, Transform},
+    ecs::{Component, prelude::*},
+    input::{InputHandler, StringBindings},
+};
+use easer::functions::{Cubic, Easing};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    systems::{
+        cache::GlobalCache,
+        physics::{Ground, Physics, PhysicsData},
+        player::{Player, Steering},
+    },
+    utils::{determinism::Determinism, warnings::Warnings},
+};
+
+use super::{Legged, Quadruped, State, Swimming};
+
+/// Launch and flight of a jump, for a quadruped of scale 1.
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct JumpConfig {
+    /// Upward speed at launch.
+    pub speed: f32,
+    /// Mass of the body while it is simulated.
+    pub mass: f32,
+    pub gravity: f32,
+    /// How far the feet are drawn from their homes to their anchors in the air, in `[0, 1]`.
+    pub tuck: f32,
+    /// Seconds to tuck the feet after launch.
+    pub tuck_time: f32,
+}
+
+impl Default for JumpConfig {
+    fn default() -> Self {
+        JumpConfig {
+            speed: 3.0,
+            mass: 4.0,
+            gravity: 9.8,
+            tuck: 0.6,
+            tuck_time: 0.15,
+        }
+    }
+}
+
+/// A quadruped in the air, its body handed to the physics until it lands.
+#[derive(Debug, Copy, Clone, Component)]
+#[storage(HashMapStorage)]
+pub struct Airborne {
+    /// Height of the body above the ground at launch, restored on landing.
+    rest_height: f32,
+    elapsed: f32,
+}
+
+impl Airborne {
+    pub fn elapsed(&self) -> f32 {
+        self.elapsed
+    }
+}
+
+/// Makes the quadrupeds under input control jump on the "jump" action.
+///
+/// The body becomes a physics particle launched upward with the velocity of its player; the
+/// oscillators are suspended and the feet tucked toward the anchors until a raycast finds the
+/// ground below the body again, which puts all limbs back into stance.
+#[derive(Default)]
+pub struct JumpSystem {
+    /// Whether the action was down last frame, so holding it jumps only once.
+    was_down: bool,
+}
+
+impl JumpSystem {
+    fn launch(
+        entity: Entity,
+        quadruped: &mut Quadruped,
+        player: &Player,
+        config: &JumpConfig,
+        transforms: &WriteStorage<'_, Transform>,
+        ground: &Ground<'_>,
+        physics: &mut PhysicsData<'_>,
+    ) -> Option<Airborne> {
+        let transform = transforms.get(entity)?;
+        let ref position = Point3::from(*transform.translation());
+        let up = ground.up();
+        let rest_height = up.height(position) - ground.height_at(position);
+
+        let velocity = transform.rotation() * player.velocity() + up.up() * config.speed;
+        physics.create_particle(entity, config.mass).ok()?;
+        physics.apply_impulse(entity, &velocity.scale(config.mass));
+
+        for limb in quadruped.limbs.iter_mut() {
+            limb.state = State::Airborne;
+            limb.transition = false;
+        }
+        Some(Airborne { rest_height, elapsed: 0.0 })
+    }
+
+    /// Tucks the feet of a quadruped in the air, returning whether it landed.
+    fn fly(
+        entity: Entity,
+        quadruped: &Quadruped,
+        airborne: &mut Airborne,
+        config: &JumpConfig,
+        delta_seconds: f32,
+        transforms: &mut WriteStorage<'_, Transform>,
+        cache: &GlobalCache,
+        ground: &Ground<'_>,
+        physics: &mut PhysicsData<'_>,
+    ) -> Option<bool> {
+        airborne.elapsed += delta_seconds;
+        let up = ground.up();
+        physics.apply_force(entity, &(-up.up() * config.gravity * config.mass));
+
+        let tuck = config.tuck * Cubic::ease_out(airborne.elapsed.min(config.tuck_time), 0.0, 1.0, config.tuck_time);
+        for limb in quadruped.limbs.iter() {
+            let ref home = cache.global_position(limb.home)?;
+            let ref anchor = cache.global_position(limb.anchor)?;
+            let foot = home + (anchor - home).scale(tuck);
+            transforms.get_mut(limb.foot)?.set_translation(foot.coords);
+        }
+
+        let falling = physics.velocity(entity).map_or(true, |velocity| velocity.dot(&up.up()) <= 0.0);
+        let ref position = Point3::from(*transforms.get(entity)?.translation());
+        let landed = falling && physics
+            .raycast(position, &-up.up(), airborne.rest_height)
+            .is_some();
+        if landed {
+            let landing = up.with_height(position, ground.height_at(position) + airborne.rest_height);
+            physics.remove_particle(entity);
+            transforms.get_mut(entity)?.set_translation(landing.coords);
+        }
+        Some(landed)
+    }
+}
+
+impl<'a> System<'a> for JumpSystem {
+    type SystemData = (
+        Entities<'a>,
+        WriteStorage<'a, Quadruped>,
+        WriteStorage<'a, Airborne>,
+        ReadStorage<'a, Swimming>,
+        ReadStorage<'a, Player>,
+        ReadStorage<'a, Steering>,
+        WriteStorage<'a, Transform>,
+        Read<'a, GlobalCache>,
+        Ground<'a>,
+        PhysicsData<'a>,
+        Read<'a, JumpConfig>,
+        Read<'a, InputHandler<StringBindings>>,
+        Read<'a, Time>,
+        Read<'a, Determinism>,
+        Write<'a, Warnings>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            entities,
+            mut quadrupeds,
+            mut airbornes,
+            swimmings,
+            players,
+            steerings,
+            mut transforms,
+            cache,
+            ground,
+            mut physics,
+            config,
+            input,
+            time,
+            determinism,
+            mut warnings,
+        ) = data;
+
+        let down = input.action_is_down("jump").unwrap_or(false);
+        let pressed = down && !self.was_down;
+        self.was_down = down;
+
+        if pressed {
+            let launches = (&*entities, &mut quadrupeds, &players, !&steerings, !&airbornes, !&swimmings)
+                .join()
+                .map(|(entity, quadruped, player, _, _, _)| {
+                    let scale = quadruped.scale().unwrap_or(1.0);
+                    let config = JumpConfig { speed: config.speed * scale.sqrt(), ..*config };
+                    (entity, Self::launch(entity, quadruped, player, &config, &transforms, &ground, &mut physics))
+                })
+                .collect::<Vec<_>>();
+            for (entity, airborne) in launches {
+                let launched = airborne.and_then(|airborne| airbornes.insert(entity, airborne).ok());
+                if launched.is_none() {
+                    warnings.skip("jump", entity);
+                }
+            }
+        }
+
+        let delta_seconds = determinism.delta_seconds(time.delta_seconds());
+        let mut landed = vec![];
+        for (entity, quadruped, airborne) in (&*entities, &quadrupeds, &mut airbornes).join() {
+            let flown = Self::fly(
+                entity,
+                quadruped,
+                airborne,
+                &config,
+                delta_seconds,
+                &mut transforms,
+                &cache,
+                &ground,
+                &mut physics,
+            );
+            match flown {
+                Some(true) => landed.push(entity),
+                Some(false) => {}
+                None => warnings.skip("jump", entity),
+            }
+        }
+
+        // Land in stance where the feet were tucked; locomotion steps them out from there.
+        for entity in landed {
+            airbornes.remove(entity);
+            if let Some(quadruped) = quadrupeds.get_mut(entity) {
+                for limb in quadruped.limbs.iter_mut() {
+                    limb.state = State::Stance;
+                }
+            }
+        }
+    }
+}
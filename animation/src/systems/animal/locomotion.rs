@@ -0,0 +1,769 @@
+use std::{
+    collections::HashMap,
+    f32::{consts::FRAC_PI_2, EPSILON},
+    marker::PhantomData,
+};
+
+use amethyst::{
+    core::{math::{Complex, Point3, Unit, UnitQuaternion, Vector3}, Time, Transform},
+    ecs::{prelude::*, storage::DistinctStorage},
+    renderer::{debug_drawing::DebugLines, palette::Srgba},
+    shrev::EventChannel,
+};
+use easer::functions::{Cubic, Easing, Sine};
+use interpolation::Lerp;
+use itertools::Itertools;
+use num_traits::Zero;
+
+use crate::{
+    systems::{animal::Limb, cache::GlobalCache, physics::{Ground, StepTime, WorldUp}, player::Player},
+    utils::{determinism::Determinism, warnings::Warnings},
+};
+
+use super::{Distant, Legged, limb_velocity, LocomotionEvent, State, Stride, Stumble, Swimming};
+
+/// Points sampled along a step beyond where it first meets the ground, to find the top of the obstacle.
+const OBSTACLE_SAMPLES: usize = 8;
+
+/// How far the ground rises along the `WorldUp` above the straight path from `from` to `to`,
+/// or zero if nothing is in the way.
+fn obstacle_height(ground: &Ground<'_>, from: &Point3<f32>, to: &Point3<f32>) -> f32 {
+    let ref delta = to - from;
+    let length = delta.norm();
+    let direction = match delta.try_normalize(EPSILON) {
+        Some(direction) => direction,
+        None => return 0.0,
+    };
+    let hit = match ground.raycast(from, &direction, length) {
+        Some(hit) => hit,
+        None => return 0.0,
+    };
+
+    (0..=OBSTACLE_SAMPLES)
+        .map(|i| {
+            let distance = hit.distance + (length - hit.distance) * i as f32 / OBSTACLE_SAMPLES as f32;
+            let ref point = from + direction.scale(distance);
+            ground.height_at(point) - ground.up().height(point)
+        })
+        .fold(0.0, f32::max)
+}
+
+/// Writes to the foot transform that are deferred until all limbs are processed.
+#[derive(Debug, Copy, Clone)]
+enum FootUpdate {
+    Flight {
+        translation: Vector3<f32>,
+        rotation: UnitQuaternion<f32>,
+    },
+    Land {
+        translation: Vector3<f32>,
+        /// Set when the foot aligns to the ground it lands on.
+        rotation: Option<UnitQuaternion<f32>>,
+    },
+}
+
+/// Everything a limb needs to draw for debugging, gathered while processing in parallel.
+#[derive(Debug, Copy, Clone)]
+struct LimbDebug {
+    home: Point3<f32>,
+    foot: Point3<f32>,
+    next: Option<Point3<f32>>,
+    /// The step target was pulled in to the reach of the limb.
+    clamped: bool,
+    direction: Vector3<f32>,
+    step_radius: f32,
+    duty_factor: f32,
+}
+
+#[derive(Debug, Copy, Clone)]
+struct LimbOutput {
+    foot: Entity,
+    update: Option<FootUpdate>,
+    footstep: Option<FootstepEvent>,
+    event: Option<LocomotionEvent>,
+    /// The ground rose higher than the stumble height into the swing of the foot.
+    stumbled: bool,
+    debug: LimbDebug,
+}
+
+/// Basis of the ground plane across the `WorldUp` following the planar velocity of a limb,
+/// falling back to the facing of the body when the limb is not moving.
+#[derive(Debug, Copy, Clone)]
+struct VelocityFrame {
+    forward: Vector3<f32>,
+    left: Vector3<f32>,
+}
+
+impl VelocityFrame {
+    fn new(velocity: &Vector3<f32>, facing: &Vector3<f32>, up: &WorldUp) -> Self {
+        let planar = |vector: &Vector3<f32>| up.planar(vector).try_normalize(EPSILON);
+        let forward = planar(velocity)
+            .or_else(|| planar(facing))
+            .unwrap_or_else(|| up.rotation() * Vector3::z());
+        let left = up.up().cross(&forward);
+        VelocityFrame { forward, left }
+    }
+
+    /// The vector `[along, left]` in global space.
+    fn transform(&self, [along, left]: [f32; 2]) -> Vector3<f32> {
+        self.forward * along + self.left * left
+    }
+
+    /// The axis feet roll about while swinging along the frame; the sideways axis of a body moving forward.
+    fn swing_axis(&self) -> Vector3<f32> {
+        self.left
+    }
+}
+
+/// A foot landed on the ground.
+#[derive(Debug, Copy, Clone)]
+pub struct FootstepEvent {
+    /// The legged body the foot belongs to.
+    pub body: Entity,
+    pub foot: Entity,
+    /// Point of contact on the ground.
+    pub point: Point3<f32>,
+    pub normal: Vector3<f32>,
+    /// Speed of the foot along its stride when it landed.
+    pub speed: f32,
+    /// How hard the foot hit the ground; see `LocomotionEvent::FootDown`.
+    pub impact: f32,
+}
+
+/// How bodies beyond the `far_distance` of the `LocomotionLod` move their legs.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum FarMode {
+    /// Swing the upper bone of each limb back and forth with its oscillator, without coupling or IK.
+    Sinusoid,
+    /// Keep the legs as they were.
+    Freeze,
+}
+
+/// Level of detail of the locomotion: bodies far from the focus step only every few frames,
+/// catching up on the time they skipped, and bodies farther still stop stepping altogether.
+#[derive(Debug, Copy, Clone)]
+pub struct LocomotionLod {
+    /// Bodies within this distance of the focus update every frame.
+    pub distance: f32,
+    /// Frames between updates of bodies farther away.
+    pub interval: u32,
+    /// Bodies farther than this skip the locomotion, the oscillators and the IK of their limbs, moving as `far_mode` says.
+    pub far_distance: f32,
+    /// Distant bodies come back to full detail only within `far_distance - hysteresis`, so they don't pop at the edge.
+    pub hysteresis: f32,
+    pub far_mode: FarMode,
+    /// Usually the active camera, set by the application; every body updates each frame without one.
+    pub focus: Option<Point3<f32>>,
+}
+
+impl Default for LocomotionLod {
+    fn default() -> Self {
+        LocomotionLod {
+            distance: 20.0,
+            interval: 4,
+            far_distance: 60.0,
+            hysteresis: 5.0,
+            far_mode: FarMode::Sinusoid,
+            focus: None,
+        }
+    }
+}
+
+/// Pulls `target` toward `anchor` across the `WorldUp` until it is within `reach`, keeping its height.
+/// Returns whether it had to, so the leg would otherwise overstretch.
+fn clamp_reach(up: &WorldUp, anchor: &Point3<f32>, target: &mut Point3<f32>, reach: f32) -> bool {
+    let delta = *target - anchor;
+    if delta.norm() <= reach { return false; }
+
+    let height = up.height(target) - up.height(anchor);
+    let planar = (reach * reach - height * height).max(0.0).sqrt();
+    let direction = up.planar(&delta).try_normalize(EPSILON).unwrap_or(Vector3::zero());
+    *target = up.with_height(&(anchor + direction * planar), up.height(target));
+    true
+}
+
+/// Momentum of a foot landing at `vertical_speed` while `standing` other feet carry the body:
+/// the speed times the share of the body mass the foot takes on, the tuned rig weighing one.
+fn impact(vertical_speed: f32, scale: f32, standing: usize) -> f32 {
+    let mass = scale.powi(3);
+    vertical_speed.max(0.0) * mass / (standing + 1) as f32
+}
+
+/// Steps the limbs of `T` between stance and flight, following the motion of its `Player`.
+pub struct LocomotionSystem<T> {
+    /// Seconds skipped by bodies outside the detail distance since their last update.
+    skipped: HashMap<Entity, f32>,
+    frame: u32,
+    marker: PhantomData<T>,
+}
+
+impl<T> Default for LocomotionSystem<T> {
+    fn default() -> Self {
+        LocomotionSystem { skipped: HashMap::new(), frame: 0, marker: PhantomData }
+    }
+}
+
+impl<T> LocomotionSystem<T> {
+    /// Seconds to step the body at `position` this frame, or `None` if it skips the frame.
+    fn delta_seconds(&mut self, entity: Entity, position: Option<Point3<f32>>, lod: &LocomotionLod, delta_seconds: f32) -> Option<f32> {
+        let far = match (lod.focus, position) {
+            (Some(ref focus), Some(ref position)) => (position - focus).norm() > lod.distance,
+            _ => false,
+        };
+        let skipped = self.skipped.remove(&entity).unwrap_or_default() + delta_seconds;
+        // Spread the updates of far bodies over the interval.
+        if far && (self.frame + entity.id()) % lod.interval.max(1) != 0 {
+            self.skipped.insert(entity, skipped);
+            None
+        } else {
+            Some(skipped)
+        }
+    }
+
+    fn process_limb(
+        entity: Entity,
+        index: usize,
+        limb: &mut Limb,
+        standing: usize,
+        player: &Player,
+        delta_seconds: f32,
+        transforms: &WriteStorage<'_, Transform>,
+        cache: &GlobalCache,
+        ground: &Ground<'_>,
+    ) -> Option<LimbOutput> {
+        let ref home = cache.global_position(limb.home)?;
+        let ref foot = cache.global_position(limb.foot)?;
+        let ref root = cache.global_position(limb.root)?;
+        let delta = foot - home;
+        let up = ground.up();
+
+        let velocity = limb_velocity(cache, entity, limb, player)?;
+        let speed = velocity.norm();
+        limb.match_speed(speed);
+
+        let step_radius = limb.step_radius();
+        let flight_time = limb.flight_time();
+
+        let mut debug = {
+            let home = up.with_height(home, ground.height_at(home) + limb.config.stance_height);
+
+            let signal = limb.signal;
+            let ref direction = Vector3::new(0.0, signal.im, -signal.re).scale(step_radius);
+            let direction = cache
+                .global_matrix(limb.foot)?
+                .transform_vector(direction);
+
+            LimbDebug {
+                home,
+                foot: foot.clone(),
+                next: None,
+                clamped: false,
+                direction,
+                step_radius,
+                duty_factor: limb.duty_factor,
+            }
+        };
+        let mut update = None;
+        let mut footstep = None;
+        let mut event = None;
+        let mut stumbled = false;
+
+        limb.state = match &limb.state {
+            State::Airborne => State::Airborne,
+            State::Paddling => State::Paddling,
+            State::Stance => {
+                let condition = {
+                    if limb.angular_velocity > limb.threshold && !limb.held {
+                        let transition = limb.transition;
+                        limb.transition = false;
+                        transition
+                    } else {
+                        delta.norm() > step_radius
+                    }
+                };
+                if condition {
+                    event = Some(LocomotionEvent::FootUp {
+                        body: entity,
+                        limb: index,
+                        foot: limb.foot,
+                        position: foot.clone(),
+                        speed: limb.angular_velocity * limb.radius,
+                    });
+                    let stance = foot.clone();
+                    State::Flight { stance, time: 0.0 }
+                } else {
+                    State::Stance
+                }
+            }
+            State::Flight { stance, time } => {
+                let time = *time;
+
+                let direction = velocity.try_normalize(EPSILON).unwrap_or(Vector3::zero());
+                let facing = cache
+                    .global_matrix(entity)?
+                    .transform_vector(&Vector3::z());
+                let frame = VelocityFrame::new(&velocity, &facing, up);
+
+                // The target is placed in the velocity frame, so steps lead the motion in any planar direction.
+                let mut next = home.clone();
+                if limb.angular_velocity > limb.threshold {
+                    let scale = limb.config.scale.unwrap_or(1.0);
+                    next += frame.transform(limb.home_offset) * scale;
+                    // A body riding a platform is carried along with the home while the foot is in the air.
+                    let carried = limb.platform
+                        .and_then(|platform| ground.platform(platform))
+                        .map_or_else(Vector3::zeros, |platform| platform.velocity_at(home));
+                    next += (velocity + carried) * (flight_time - time) + direction * step_radius;
+                }
+                let stance_height = limb.config.stance_height;
+                let stand = |next: &Point3<f32>| {
+                    let height = ground.height_below(&up.with_height(next, up.height(root)));
+                    up.with_height(next, height + stance_height)
+                };
+                next = stand(&next);
+                if let Some(max_reach) = limb.max_reach() {
+                    let ref anchor = cache.global_position(limb.anchor)?;
+                    if clamp_reach(up, anchor, &mut next, max_reach) {
+                        next = stand(&next);
+                        debug.clamped = true;
+                    }
+                }
+                debug.next = Some(next.clone());
+
+                if time < flight_time {
+                    // Raise the swing over ground rising into the path, so the foot steps over low obstacles.
+                    let step_length = step_radius * 2.0;
+                    let mut height = limb.config.flight_factor * step_length;
+                    let obstacle = obstacle_height(ground, stance, &next);
+                    let stumble_height = limb.config.stumble_height;
+                    stumbled = stumble_height > 0.0 && obstacle > stumble_height;
+                    if obstacle > 0.0 {
+                        // The swing peaks half way to its control point.
+                        let ref config = limb.config;
+                        height = height.max(2.0 * (obstacle + config.stance_height + config.obstacle_clearance));
+                    }
+
+                    let ref stance = stance.coords;
+                    let ref next = next.coords;
+
+                    let direction = {
+                        let ref delta = root - foot;
+                        let direction = delta - direction.scale(direction.dot(delta));
+                        direction.try_normalize(EPSILON).unwrap_or(Vector3::zero())
+                    };
+                    let factor = Sine::ease_in(time, 0.0, 1.0, flight_time);
+
+                    let translation = {
+                        let ref center = next.lerp(stance, 0.2) + direction * height;
+                        let ref first = stance.lerp(center, factor);
+                        let ref second = center.lerp(next, factor);
+                        first.lerp(second, factor)
+                    };
+
+                    let ref factor = Cubic::ease_in_out(time, 0.0, 1.0, flight_time);
+                    let angle = {
+                        let max_step_length = limb.config.step_limit[1];
+                        let ref center = FRAC_PI_2 * step_length / max_step_length;
+                        let ref first = 0.0.lerp(center, factor);
+                        let ref second = center.lerp(&0.0, factor);
+                        first.lerp(second, factor)
+                    };
+
+                    // The foot rolls about the axis across its motion, not the sideways axis of the body.
+                    let body = transforms.get(entity)?.rotation().clone();
+                    let ref axis = Unit::new_normalize(frame.swing_axis());
+                    let rotation = UnitQuaternion::from_axis_angle(axis, angle) * body;
+
+                    update = Some(FootUpdate::Flight { translation, rotation });
+                    State::Flight { stance: stance.xyz().into(), time: delta_seconds + time }
+                } else {
+                    let ref origin = next + up.up().scale(step_radius);
+                    let ref down = -up.up();
+                    let (point, normal, platform) = match ground.raycast(origin, down, step_radius * 2.0 + limb.config.stance_height) {
+                        Some(hit) => (hit.point, hit.normal, hit.body),
+                        None => (up.with_height(&next, ground.height_at(&next)), ground.normal_at(&next), None),
+                    };
+                    limb.platform = platform;
+
+                    let rotation = if limb.config.align_to_normal {
+                        let body = transforms.get(entity)?.rotation().clone();
+                        let tilt = UnitQuaternion::rotation_between(&up.up(), &normal)
+                            .and_then(|tilt| tilt.axis_angle())
+                            .map_or_else(UnitQuaternion::identity, |(axis, angle)| {
+                                UnitQuaternion::from_axis_angle(&axis, angle.min(limb.config.max_tilt))
+                            });
+                        Some(tilt * body)
+                    } else {
+                        None
+                    };
+
+                    let vertical_speed = if delta_seconds > 0.0 {
+                        (up.height(foot) - up.height(&next)) / delta_seconds
+                    } else {
+                        0.0
+                    };
+                    let impact = impact(vertical_speed, limb.config.scale.unwrap_or(1.0), standing);
+
+                    update = Some(FootUpdate::Land { translation: next.coords, rotation });
+                    footstep = Some(FootstepEvent {
+                        body: entity,
+                        foot: limb.foot,
+                        point,
+                        normal,
+                        speed: limb.angular_velocity * limb.radius,
+                        impact,
+                    });
+                    event = Some(LocomotionEvent::FootDown {
+                        body: entity,
+                        limb: index,
+                        foot: limb.foot,
+                        position: point,
+                        speed: limb.angular_velocity * limb.radius,
+                        impact,
+                    });
+                    State::Stance
+                }
+            }
+        };
+
+        Some(LimbOutput { foot: limb.foot, update, footstep, event, stumbled, debug })
+    }
+
+    fn apply_output(
+        output: LimbOutput,
+        transforms: &mut WriteStorage<'_, Transform>,
+        debug_lines: &mut Write<'_, DebugLines>,
+        footsteps: &mut Write<'_, EventChannel<FootstepEvent>>,
+        events: &mut Write<'_, EventChannel<LocomotionEvent>>,
+    ) -> Option<()> {
+        let LimbOutput { foot, update, footstep, event, debug, .. } = output;
+        if let Some(footstep) = footstep {
+            footsteps.single_write(footstep);
+        }
+        if let Some(event) = event {
+            events.single_write(event);
+        }
+        {
+            let color = Srgba::new(0.0, 1.0, 0.0, debug.duty_factor);
+            debug_lines.draw_rotated_circle(
+                debug.home,
+                debug.step_radius,
+                10,
+                UnitQuaternion::from_euler_angles(FRAC_PI_2, 0.0, 0.0),
+                color,
+            );
+
+            let color = Srgba::new(1.0, 1.0, 0.0, 1.0);
+            debug_lines.draw_sphere(debug.foot, 0.2, 4, 4, color);
+
+            let color = Srgba::new(1.0, 1.0, 1.0, 1.0);
+            debug_lines.draw_direction(debug.home, debug.direction, color);
+
+            if let Some(next) = debug.next {
+                let color = if debug.clamped { Srgba::new(1.0, 0.0, 0.0, 1.0) } else { color };
+                debug_lines.draw_sphere(next, 0.1, 4, 4, color);
+            }
+        }
+
+        match update {
+            None => {}
+            Some(FootUpdate::Flight { translation, rotation }) => {
+                transforms
+                    .get_mut(foot)?
+                    .set_translation(translation)
+                    .set_rotation(rotation);
+            }
+            Some(FootUpdate::Land { translation, rotation }) => {
+                let transform = transforms.get_mut(foot)?;
+                transform.set_translation(translation);
+                if let Some(rotation) = rotation {
+                    transform.set_rotation(rotation);
+                }
+            }
+        }
+        Some(())
+    }
+}
+
+impl<T: Legged> LocomotionSystem<T> {
+    /// Moves the standing feet on a platform along with it, and the body with the first of them.
+    fn ride(entity: Entity, body: &T, ground: &Ground<'_>, transforms: &mut WriteStorage<'_, Transform>) -> Option<()> {
+        let up = ground.up();
+        let mut riding = None;
+        for limb in body.limbs().iter().filter(|limb| limb.is_stance()) {
+            let platform = match limb.platform.and_then(|platform| ground.platform(platform)) {
+                Some(platform) => platform,
+                None => continue,
+            };
+            // Feet are placed in global space, so their translation is their global position.
+            let transform = transforms.get_mut(limb.foot)?;
+            let ref foot = Point3::from(*transform.translation());
+            transform
+                .set_translation(platform.carry(foot).coords)
+                .append_rotation(up.yaw(platform.yaw(up)));
+            riding = riding.or(Some(platform));
+        }
+
+        if let Some(platform) = riding {
+            let transform = transforms.get_mut(entity)?;
+            let ref position = Point3::from(*transform.translation());
+            transform
+                .append_translation(platform.carry(position) - position)
+                .append_rotation(up.yaw(platform.yaw(up)));
+        }
+        Some(())
+    }
+}
+
+impl<'a, T> System<'a> for LocomotionSystem<T>
+    where T: Legged,
+          T::Storage: DistinctStorage {
+    type SystemData = (
+        Entities<'a>,
+        WriteStorage<'a, Transform>,
+        WriteStorage<'a, T>,
+        ReadStorage<'a, Player>,
+        ReadStorage<'a, Swimming>,
+        ReadStorage<'a, Distant>,
+        Read<'a, GlobalCache>,
+        Ground<'a>,
+        Read<'a, Time>,
+        Read<'a, Determinism>,
+        Read<'a, LocomotionLod>,
+        WriteStorage<'a, Stride>,
+        WriteStorage<'a, Stumble>,
+        Write<'a, DebugLines>,
+        Write<'a, EventChannel<FootstepEvent>>,
+        Write<'a, EventChannel<LocomotionEvent>>,
+        Write<'a, Warnings>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            entities,
+            mut transforms,
+            mut bodies,
+            players,
+            swimmings,
+            distants,
+            cache,
+            ground,
+            time,
+            determinism,
+            lod,
+            mut strides,
+            mut stumbles,
+            mut debug_lines,
+            mut footsteps,
+            mut events,
+            mut warnings,
+        ) = data;
+
+        self.frame = self.frame.wrapping_add(1);
+        self.skipped.retain(|&entity, _| entities.is_alive(entity));
+        // Swimming bodies are paddled by the `SwimSystem` instead, and distant ones by the `DistantSystem`.
+        let deltas = (&*entities, &bodies, &players, !&swimmings, !&distants)
+            .join()
+            .filter_map(|(entity, _, _, _, _)| {
+                let position = cache.global_position(entity);
+                let delta_seconds = determinism.delta_seconds(time.delta_seconds());
+                let delta_seconds = self.delta_seconds(entity, position, &lod, delta_seconds)?;
+                Some((entity, delta_seconds))
+            })
+            .collect::<HashMap<_, _>>();
+
+        // Bodies only read transforms while processing, so they can be updated in parallel;
+        // writes to the feet are gathered and applied afterwards.
+        let outputs = {
+            let transforms = &transforms;
+            let deltas = &deltas;
+            let process = |(entity, body, player, stumble): (Entity, &mut T, &Player, Option<&Stumble>)| {
+                let delta_seconds = *deltas.get(&entity)?;
+                for limb in body.limbs_mut() {
+                    limb.stride_factor = stumble.map_or(1.0, |stumble| stumble.stride_factor(&limb.config));
+                }
+                let standing = body.limbs().iter().filter(|limb| limb.is_stance()).count();
+                let outputs = body.limbs_mut()
+                    .iter_mut()
+                    .enumerate()
+                    .map(|(index, limb)| Self::process_limb(
+                        entity,
+                        index,
+                        limb,
+                        standing,
+                        player,
+                        delta_seconds,
+                        transforms,
+                        &cache,
+                        &ground,
+                    ))
+                    .collect_vec();
+                Some((entity, outputs))
+            };
+            let joined = (&*entities, &mut bodies, &players, (&stumbles).maybe());
+            if determinism.enabled {
+                joined.join().filter_map(process).collect::<Vec<_>>()
+            } else {
+                joined.par_join().filter_map(process).collect::<Vec<_>>()
+            }
+        };
+
+        let mut stumbled = Vec::new();
+        for (entity, outputs) in outputs {
+            if outputs.iter().flatten().any(|output| output.stumbled) {
+                stumbled.push(entity);
+            }
+            for output in outputs {
+                let applied = output.and_then(|output| Self::apply_output(
+                    output,
+                    &mut transforms,
+                    &mut debug_lines,
+                    &mut footsteps,
+                    &mut events,
+                ));
+                if applied.is_none() {
+                    warnings.skip("locomotion", entity);
+                }
+            }
+        }
+
+        // Bodies recover over the time they stepped; stumbling again starts the recovery over.
+        let recovered = (&*entities, &bodies, &mut stumbles)
+            .join()
+            .filter_map(|(entity, body, stumble)| {
+                stumble.advance(*deltas.get(&entity)?);
+                let ref config = body.limbs().first()?.config;
+                Some(entity).filter(|_| stumble.recovery(config) >= 1.0)
+            })
+            .collect_vec();
+        for entity in recovered {
+            stumbles.remove(entity);
+        }
+        for entity in stumbled {
+            stumbles.insert(entity, Stumble::default()).ok();
+        }
+
+        // Riding goes on in the frames a body skips, so it doesn't fall behind its platform.
+        for (entity, body, _) in (&*entities, &bodies, &players).join() {
+            if Self::ride(entity, body, &ground, &mut transforms).is_none() {
+                warnings.skip("locomotion", entity);
+            }
+        }
+
+        for (entity, body, player) in (&*entities, &bodies, &players).join() {
+            if !deltas.contains_key(&entity) { continue; }
+            let observed = cache.global_matrix(entity).and_then(|matrix| {
+                let stride = strides.entry(entity).ok()?.or_insert_with(Stride::default);
+                let previous = stride.gait();
+                stride.observe(body.limbs(), body.current_gait(), player, matrix);
+                match (previous, stride.gait()) {
+                    (Some(from), Some(to)) if from != to => {
+                        events.single_write(LocomotionEvent::GaitChanged { body: entity, from, to });
+                    }
+                    _ => {}
+                }
+                Some(())
+            });
+            if observed.is_none() {
+                warnings.skip("locomotion", entity);
+            }
+        }
+    }
+}
+
+/// Length of the steps the oscillators are integrated over. Frames are split into sub-steps no longer than it,
+/// so from 20 to 240 frames per second the couplings act over steps of the same length.
+const MAX_OSCILLATOR_STEP: f32 = 1.0 / 240.0;
+
+/// Most sub-steps per frame, so a long hitch doesn't stall the next one.
+const MAX_OSCILLATOR_SUBSTEPS: usize = 32;
+
+/// Puts `signal` back on the unit circle, keeping its phase if it has one and the phase of `previous` otherwise.
+fn renormalize(signal: Complex<f32>, previous: Complex<f32>) -> Complex<f32> {
+    let finite = signal.re.is_finite() && signal.im.is_finite();
+    let fallback = if finite && signal.norm() > EPSILON { signal } else { previous };
+    let norm = fallback.norm();
+    if norm > EPSILON && norm.is_finite() { fallback.unscale(norm) } else { Complex::new(1.0, 0.0) }
+}
+
+/// Integrates the coupled oscillators that time the steps of `T`, publishing their phases to the `Stride`.
+///
+/// Frames are integrated in sub-steps of at most `MAX_OSCILLATOR_STEP`, and the signals are renormalized after
+/// each one, so they stay on the unit circle and the gait settles the same when the frame rate drops.
+pub struct OscillatorSystem<T> {
+    /// Scratch buffers of the couplings between the limbs of a body, row by row, and of their signals
+    /// before a sub-step, reused across bodies and frames.
+    couplings: Vec<Complex<f32>>,
+    previous: Vec<Complex<f32>>,
+    marker: PhantomData<T>,
+}
+
+impl<T> Default for OscillatorSystem<T> {
+    fn default() -> Self {
+        OscillatorSystem { couplings: vec![], previous: vec![], marker: PhantomData }
+    }
+}
+
+impl<'a, T: Legged> System<'a> for OscillatorSystem<T> {
+    type SystemData = (
+        WriteStorage<'a, T>,
+        WriteStorage<'a, Stride>,
+        ReadStorage<'a, Distant>,
+        Read<'a, StepTime>,
+        Read<'a, Determinism>,
+    );
+
+    fn run(&mut self, (mut bodies, mut strides, distants, time, determinism): Self::SystemData) {
+        let delta_seconds = determinism.step_seconds(time.delta_seconds());
+        if delta_seconds <= 0.0 { return; }
+        let substeps = ((delta_seconds / MAX_OSCILLATOR_STEP).ceil() as usize).max(1).min(MAX_OSCILLATOR_SUBSTEPS);
+        let step = delta_seconds / substeps as f32;
+
+        // Distant bodies advance their oscillators uncoupled in the `DistantSystem`.
+        for (body, stride, _) in (&mut bodies, (&mut strides).maybe(), !&distants).join() {
+            // Suspended while the body is in the air, so it lands in phase.
+            if body.limbs().iter().any(Limb::is_airborne) { continue; }
+
+            // The duty factors only change with the speed, so the couplings hold over the frame.
+            let count = body.limbs().len();
+            if count == 0 { continue; }
+            let OscillatorSystem { couplings, previous, .. } = self;
+            couplings.clear();
+            for i in 0..count {
+                let duty_factor = body.limbs()[i].duty_factor;
+                couplings.extend((0..count).map(|j| body.coupling(i, j, duty_factor)));
+            }
+
+            for _ in 0..substeps {
+                previous.clear();
+                previous.extend(body.limbs().iter().map(|limb| limb.signal));
+                for (limb, couplings) in body.limbs_mut().iter_mut().zip(couplings.chunks(count)) {
+                    let ref mut signal = limb.signal;
+
+                    let angular_velocity = limb.angular_velocity;
+                    let duty_factor = limb.duty_factor;
+                    let omega = if signal.im < 0.0 {
+                        angular_velocity / duty_factor / 2.0
+                    } else {
+                        angular_velocity / (1.0 - duty_factor) / 2.0
+                    };
+
+                    // The rotation is applied exactly; integrated explicitly it would push the signal off the unit circle.
+                    let rotation = Complex::from_polar(&1.0, &(omega * step));
+                    let mut derivative = Complex::new(0.0, 0.0);
+                    for (signal, coupling) in previous.iter().zip(couplings.iter()) {
+                        derivative += signal * coupling;
+                    }
+
+                    let previous = *signal;
+                    *signal = renormalize(previous * rotation + derivative.scale(step), previous);
+                    if signal.im > 0.0 && previous.im < 0.0 { limb.transition = true; }
+                }
+            }
+
+            if let Some(stride) = stride {
+                stride.observe_phases(body.limbs());
+            }
+        }
+    }
+}
@@ -0,0 +1,390 @@
+//! Experimental motion matching for the gait of quadrupeds.
+//!
+//! Instead of letting the coupled oscillators settle into a gait by themselves, the `MotionMatchingSystem`
+//! looks up the recorded frame that best matches the current motion and pulls the oscillators toward its phases.
+//! Sessions are recorded with the `MotionRecorder` and baked into a `MotionDatabase` with `MotionDatabase::bake`.
+
+use std::f32::consts::{PI, TAU};
+
+use amethyst::{
+    assets::{Asset, AssetStorage, Handle, Loader, PrefabData, ProgressCounter, RonFormat},
+    core::{math::{Complex, Matrix4, Vector3}, Time},
+    derive::SystemDesc,
+    ecs::{Component, prelude::*},
+    error::{Error, format_err},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{systems::cache::GlobalCache, utils::{determinism::Determinism, warnings::Warnings}};
+
+use super::{Quadruped, Stride};
+
+/// Frames slower than this are standing still, and never start a match.
+const MIN_SPEED: f32 = 0.01;
+
+/// One sample of a quadruped, in the space of its body.
+#[derive(Debug, Copy, Clone, Default, Serialize, Deserialize)]
+pub struct MotionFrame {
+    pub velocity: [f32; 3],
+    /// Radians per second about the up axis, positive to the left.
+    pub turning: f32,
+    /// Phase of the oscillator of each limb in radians.
+    pub phases: [f32; 4],
+    /// Position of each foot relative to the body.
+    pub feet: [[f32; 3]; 4],
+}
+
+impl MotionFrame {
+    /// Samples a quadruped whose body has the global `matrix`, or `None` if a foot is not cached.
+    fn sample(quadruped: &Quadruped, stride: &Stride, matrix: &Matrix4<f32>, cache: &GlobalCache) -> Option<Self> {
+        let inverse = matrix.try_inverse()?;
+        let mut phases = [0.0; 4];
+        for (phase, signal) in phases.iter_mut().zip(quadruped.phases().iter()) {
+            *phase = signal.arg();
+        }
+        let mut feet = [[0.0; 3]; 4];
+        for (foot, &entity) in feet.iter_mut().zip(quadruped.feet().iter()) {
+            *foot = inverse.transform_point(&cache.global_position(entity)?).coords.into();
+        }
+        Some(MotionFrame {
+            velocity: inverse.transform_vector(&stride.velocity()).into(),
+            turning: stride.turning(),
+            phases,
+            feet,
+        })
+    }
+}
+
+/// Frames of one quadruped sampled at a fixed rate, as taken by the `MotionRecorder`.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct MotionRecording {
+    pub frame_rate: f32,
+    pub frames: Vec<MotionFrame>,
+}
+
+/// Records the quadruped it is started on, sampled by the `MotionRecorderSystem`.
+#[derive(Debug, Default)]
+pub struct MotionRecorder {
+    body: Option<Entity>,
+    recording: MotionRecording,
+    elapsed: f32,
+}
+
+impl MotionRecorder {
+    /// Drop what was recorded so far and record `body` at `frame_rate` frames per second.
+    pub fn start(&mut self, body: Entity, frame_rate: f32) {
+        self.body = Some(body);
+        self.recording = MotionRecording { frame_rate, frames: vec![] };
+        self.elapsed = 0.0;
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.body.is_some()
+    }
+
+    /// Stop recording and take the frames.
+    pub fn stop(&mut self) -> MotionRecording {
+        self.body = None;
+        std::mem::take(&mut self.recording)
+    }
+}
+
+/// Samples the quadruped the `MotionRecorder` is started on.
+#[derive(Default, SystemDesc)]
+pub struct MotionRecorderSystem;
+
+impl<'a> System<'a> for MotionRecorderSystem {
+    type SystemData = (
+        ReadStorage<'a, Quadruped>,
+        ReadStorage<'a, Stride>,
+        Read<'a, GlobalCache>,
+        Write<'a, MotionRecorder>,
+        Read<'a, Time>,
+        Read<'a, Determinism>,
+    );
+
+    fn run(&mut self, (quadrupeds, strides, cache, mut recorder, time, determinism): Self::SystemData) {
+        let body = match recorder.body {
+            Some(body) => body,
+            None => return,
+        };
+        let period = 1.0 / recorder.recording.frame_rate.max(1.0);
+        recorder.elapsed += determinism.delta_seconds(time.delta_seconds());
+        if recorder.elapsed < period { return; }
+
+        let frame = quadrupeds
+            .get(body)
+            .zip(strides.get(body))
+            .zip(cache.global_matrix(body))
+            .and_then(|((quadruped, stride), matrix)| MotionFrame::sample(quadruped, stride, matrix, &cache));
+        // Frames dropped by a slow update repeat the sample, keeping the recording at its rate.
+        while recorder.elapsed >= period {
+            recorder.elapsed -= period;
+            if let Some(frame) = frame {
+                recorder.recording.frames.push(frame);
+            }
+        }
+    }
+}
+
+/// How much each part of a frame counts when matching.
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MatchWeights {
+    /// The velocity and turning held over the horizon, against the recorded ones.
+    pub trajectory: f32,
+    pub phases: f32,
+    pub feet: f32,
+}
+
+impl Default for MatchWeights {
+    fn default() -> Self {
+        MatchWeights { trajectory: 1.0, phases: 0.5, feet: 1.0 }
+    }
+}
+
+/// Recorded frames searched by the `MotionMatchingSystem`, loaded from RON.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MotionDatabase {
+    pub frame_rate: f32,
+    /// Frames of future motion a match is judged on.
+    pub horizon: usize,
+    pub frames: Vec<MotionFrame>,
+    /// Frames a match can start at: moving ones, followed by `horizon` frames of the same recording.
+    pub starts: Vec<usize>,
+}
+
+impl Asset for MotionDatabase {
+    const NAME: &'static str = "ceramic_animation::MotionDatabase";
+    type Data = Self;
+    type HandleStorage = VecStorage<Handle<Self>>;
+}
+
+impl MotionDatabase {
+    /// Bake `recordings` taken at the same frame rate, judging matches on `horizon` seconds of future motion.
+    pub fn bake(recordings: &[MotionRecording], horizon: f32) -> Result<Self, Error> {
+        let frame_rate = recordings
+            .first()
+            .map(|recording| recording.frame_rate)
+            .ok_or_else(|| format_err!("There are no recordings to bake"))?;
+        if recordings.iter().any(|recording| (recording.frame_rate - frame_rate).abs() > f32::EPSILON) {
+            return Err(format_err!("Recordings taken at different frame rates can't be baked together"));
+        }
+        let horizon = ((horizon * frame_rate).round() as usize).max(1);
+
+        let mut frames = vec![];
+        let mut starts = vec![];
+        for recording in recordings {
+            let offset = frames.len();
+            starts.extend(recording.frames
+                .iter()
+                .take(recording.frames.len().saturating_sub(horizon))
+                .enumerate()
+                .filter(|(_, frame)| Vector3::from(frame.velocity).norm() > MIN_SPEED)
+                .map(|(index, _)| offset + index));
+            frames.extend_from_slice(&recording.frames);
+        }
+        if starts.is_empty() {
+            return Err(format_err!("The recordings are shorter than the horizon or never move"));
+        }
+        Ok(MotionDatabase { frame_rate, horizon, frames, starts })
+    }
+
+    /// How far the frames from `start` on are from `query` with its motion held over the horizon.
+    pub fn cost(&self, start: usize, query: &MotionFrame, weights: &MatchWeights) -> f32 {
+        let ref velocity = Vector3::from(query.velocity);
+        let trajectory = self.frames[start..start + self.horizon]
+            .iter()
+            .map(|frame| (Vector3::from(frame.velocity) - velocity).norm_squared() + (frame.turning - query.turning).powi(2))
+            .sum::<f32>() / self.horizon as f32;
+
+        let ref frame = self.frames[start];
+        let phases = frame.phases
+            .iter()
+            .zip(query.phases.iter())
+            .map(|(recorded, current)| 1.0 - (recorded - current).cos())
+            .sum::<f32>();
+        let feet = frame.feet
+            .iter()
+            .zip(query.feet.iter())
+            .map(|(&recorded, &current)| (Vector3::from(recorded) - Vector3::from(current)).norm_squared())
+            .sum::<f32>();
+
+        weights.trajectory * trajectory + weights.phases * phases + weights.feet * feet
+    }
+
+    /// The start that best matches `query`, and its cost.
+    pub fn best_match(&self, query: &MotionFrame, weights: &MatchWeights) -> Option<(usize, f32)> {
+        self.starts
+            .iter()
+            .map(|&start| (start, self.cost(start, query, weights)))
+            .min_by(|(_, a), (_, b)| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal))
+    }
+
+    fn is_start(&self, frame: usize) -> bool {
+        self.starts.binary_search(&frame).is_ok()
+    }
+}
+
+/// Lets the `MotionMatchingSystem` pick the gait of a quadruped from a `MotionDatabase`.
+#[derive(Debug, Clone, Component)]
+#[storage(HashMapStorage)]
+pub struct MotionMatching {
+    database: Handle<MotionDatabase>,
+    weights: MatchWeights,
+    interval: f32,
+    margin: f32,
+    strength: f32,
+    /// The frame being followed, fractional between two frames.
+    cursor: Option<f32>,
+    timer: f32,
+}
+
+fn default_interval() -> f32 {
+    0.2
+}
+
+fn default_margin() -> f32 {
+    0.1
+}
+
+fn default_strength() -> f32 {
+    4.0
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MotionMatchingPrefab {
+    /// Path of the baked database.
+    pub database: String,
+    #[serde(default)]
+    pub weights: MatchWeights,
+    /// Seconds between two searches.
+    #[serde(default = "default_interval")]
+    pub interval: f32,
+    /// Fraction by which a new match must be cheaper than the one followed to jump to it.
+    #[serde(default = "default_margin")]
+    pub margin: f32,
+    /// How fast the oscillators are pulled toward the matched phases, per second.
+    #[serde(default = "default_strength")]
+    pub strength: f32,
+
+    #[serde(skip)]
+    handle: Option<Handle<MotionDatabase>>,
+}
+
+impl<'a> PrefabData<'a> for MotionMatchingPrefab {
+    type SystemData = (
+        ReadExpect<'a, Loader>,
+        Read<'a, AssetStorage<MotionDatabase>>,
+        WriteStorage<'a, MotionMatching>,
+    );
+    type Result = ();
+
+    fn add_to_entity(
+        &self,
+        entity: Entity,
+        (_, _, matchings): &mut Self::SystemData,
+        _entities: &[Entity],
+        _children: &[Entity],
+    ) -> Result<Self::Result, Error> {
+        if let Some(database) = &self.handle {
+            let component = MotionMatching {
+                database: database.clone(),
+                weights: self.weights,
+                interval: self.interval,
+                margin: self.margin,
+                strength: self.strength,
+                cursor: None,
+                timer: 0.0,
+            };
+            matchings.insert(entity, component)?;
+        }
+        Ok(())
+    }
+
+    fn load_sub_assets(
+        &mut self,
+        progress: &mut ProgressCounter,
+        (loader, storage, _): &mut Self::SystemData,
+    ) -> Result<bool, Error> {
+        let handle = loader.load(self.database.as_str(), RonFormat, progress, storage);
+        self.handle.replace(handle);
+        Ok(true)
+    }
+}
+
+/// Pulls the oscillators of quadrupeds with `MotionMatching` toward the phases of the best matching recorded frame.
+///
+/// The match is followed frame by frame and searched again every `interval`, or once it runs out.
+/// The oscillators still place the feet; the match only decides their timing, and so the gait.
+#[derive(Default, SystemDesc)]
+pub struct MotionMatchingSystem;
+
+impl MotionMatchingSystem {
+    fn update(
+        matching: &mut MotionMatching,
+        quadruped: &mut Quadruped,
+        stride: &Stride,
+        matrix: &Matrix4<f32>,
+        cache: &GlobalCache,
+        database: &MotionDatabase,
+        delta_seconds: f32,
+    ) -> Option<()> {
+        let ref query = MotionFrame::sample(quadruped, stride, matrix, cache)?;
+
+        matching.cursor = matching.cursor
+            .map(|cursor| cursor + delta_seconds * database.frame_rate)
+            .filter(|&cursor| database.is_start(cursor as usize));
+        matching.timer -= delta_seconds;
+        if matching.timer <= 0.0 || matching.cursor.is_none() {
+            matching.timer = matching.interval;
+            let (best, cost) = database.best_match(query, &matching.weights)?;
+            let current = matching.cursor.map(|cursor| database.cost(cursor as usize, query, &matching.weights));
+            if current.map_or(true, |current| cost < current * (1.0 - matching.margin)) {
+                matching.cursor = Some(best as f32);
+            }
+        }
+
+        let ref frame = database.frames[matching.cursor? as usize];
+        let factor = 1.0 - (-matching.strength * delta_seconds).exp();
+        let mut phases = quadruped.phases();
+        for (signal, target) in phases.iter_mut().zip(frame.phases.iter()) {
+            let offset = (target - signal.arg() + PI).rem_euclid(TAU) - PI;
+            *signal *= Complex::from_polar(&1.0, &(offset * factor));
+        }
+        quadruped.set_phases(phases);
+        Some(())
+    }
+}
+
+impl<'a> System<'a> for MotionMatchingSystem {
+    type SystemData = (
+        Entities<'a>,
+        WriteStorage<'a, MotionMatching>,
+        WriteStorage<'a, Quadruped>,
+        ReadStorage<'a, Stride>,
+        Read<'a, GlobalCache>,
+        Read<'a, AssetStorage<MotionDatabase>>,
+        Read<'a, Time>,
+        Read<'a, Determinism>,
+        Write<'a, Warnings>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (entities, mut matchings, mut quadrupeds, strides, cache, storage, time, determinism, mut warnings) = data;
+        let delta_seconds = determinism.delta_seconds(time.delta_seconds());
+        for (entity, matching, quadruped, stride) in (&*entities, &mut matchings, &mut quadrupeds, &strides).join() {
+            // Keep to the oscillators until the database is loaded.
+            let database = match storage.get(&matching.database) {
+                Some(database) => database,
+                None => continue,
+            };
+            let updated = cache
+                .global_matrix(entity)
+                .and_then(|matrix| Self::update(matching, quadruped, stride, matrix, &cache, database, delta_seconds));
+            if updated.is_none() {
+                warnings.skip("motion_matching", entity);
+            }
+        }
+    }
+}
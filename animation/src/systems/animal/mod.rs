@@ -0,0 +1,997 @@
+use std::{
+    convert::TryInto,
+    f32::consts::{PI, TAU},
+};
+
+use amethyst::{
+    assets::{PrefabData, Processor},
+    core::bundle::SystemBundle,
+    core::math::{Complex, Point3, Vector3},
+    ecs::{Component, prelude::*},
+    error::{Error, format_err},
+};
+use easer::functions::{Cubic, Easing};
+use interpolation::Lerp;
+use itertools::multizip;
+use log::info;
+use serde::{Deserialize, Serialize};
+
+pub use action::{Action, ActionEvent, ActionSystem, Phase, PounceConfig};
+pub use bakery::{BakedGait, GaitBakery, GaitBakerySystem};
+pub use balance::BalanceSystem;
+pub use bounce::BounceSystem;
+pub use breathing::{Breathing, BreathingPrefab, BreathingSystem};
+pub use calibrate::{LimbCalibrationSystem, RecalibrateLimbs};
+use ceramic_derive::Redirect;
+use gait::Crossfade;
+pub use gait::{Gait, GaitTransition, GaitTransitionSystem};
+pub use gaze::{GazePrefab, GazeSystem, InterestPoint};
+pub use herd::{HerdSync, HerdSyncSystem};
+pub use idle::IdleSystem;
+pub use jump::{Airborne, JumpConfig, JumpSystem};
+pub use distant::{Distant, DistantSystem};
+pub use locomotion::{FarMode, FootstepEvent, LocomotionLod, LocomotionSystem, OscillatorSystem};
+pub use matching::{
+    MatchWeights,
+    MotionDatabase,
+    MotionFrame,
+    MotionMatching,
+    MotionMatchingPrefab,
+    MotionMatchingSystem,
+    MotionRecorder,
+    MotionRecorderSystem,
+    MotionRecording,
+};
+pub use posture::{Pose, Posture, PostureEvent, Postures, PosturesPrefab, PostureSystem};
+pub use preset::{GaitPresetEvent, GaitPresets, GaitPresetSystem};
+use redirect::{Redirect, Resolve};
+pub use spine::{Spine, SpinePrefab, SpineSystem};
+pub use stride::{LocomotionEvent, Stride};
+pub use stumble::Stumble;
+pub use swim::{SwimConfig, Swimming, SwimSystem};
+pub use tail::{TailPrefab, TailSystem};
+pub use track::{Tracker, TrackerEvent, TrackerPrefab, TrackSystem};
+pub use workspace::{LimbWorkspace, WorkspaceSystem};
+
+use crate::extras::EntityRef;
+
+use super::{
+    appendage::AppendageSystem,
+    cache::{GlobalCache, GlobalCacheSystem},
+    carry::CarrySystem,
+    cleanup::{CleanupSystem, DespawnSystem},
+    effects::FootprintSystem,
+    helper::HelperSystem,
+    physics::{Ground, Heightfield},
+    platform::PlatformSystem,
+    player::Player,
+    socket::SocketSystem,
+    spatial::SpatialIndexSystem,
+    validation::ValidationSystem,
+    water::WaterSystem,
+};
+
+pub mod action;
+pub mod bakery;
+pub mod balance;
+pub mod bounce;
+pub mod breathing;
+pub mod calibrate;
+pub mod distant;
+pub mod gait;
+pub mod gaze;
+pub mod herd;
+pub mod idle;
+pub mod jump;
+pub mod locomotion;
+pub mod matching;
+pub mod posture;
+pub mod preset;
+pub mod track;
+pub mod spine;
+pub mod stride;
+pub mod stumble;
+pub mod swim;
+pub mod tail;
+pub mod workspace;
+
+#[derive(Debug, Copy, Clone)]
+enum State {
+    Stance,
+    Flight { stance: Point3<f32>, time: f32 },
+    /// The body is in the air; the `JumpSystem` holds the feet until it lands.
+    Airborne,
+    /// The body swims; the `SwimSystem` paddles the feet with the oscillator until it can stand.
+    Paddling,
+}
+
+#[derive(Debug, Default, Copy, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    pub max_angular_velocity: f32,
+    pub max_duty_factor: f32,
+    pub step_limit: [f32; 2],
+    pub flight_time: f32,
+    pub flight_factor: f32,
+    /// Height of the feet above the ground while in stance.
+    pub stance_height: f32,
+    /// Measure `stance_height` from the feet at rest when the body is first calibrated, replacing the authored one.
+    pub calibrate_stance: bool,
+    pub bounce_factor: f32,
+    /// Largest angle in radians the root leans into turns, never leaning if zero.
+    pub max_lean: f32,
+    /// Height kept between a swinging foot and the ground it steps over,
+    /// when the ground rises above the straight path of the step.
+    pub obstacle_clearance: f32,
+    /// Height the ground may rise above the straight path of a swinging foot before the body stumbles over it,
+    /// never stumbling if zero.
+    pub stumble_height: f32,
+    /// Seconds a stumbling body takes to recover its gait.
+    pub stumble_recovery: f32,
+    /// Fraction the strides are shortened by right after stumbling, easing off over the recovery.
+    pub stumble_shortening: f32,
+    /// Distance the root dips right after stumbling, easing off over the recovery.
+    pub stumble_dip: f32,
+    /// Tilt landing feet to the normal of the ground instead of keeping the rotation of the body.
+    pub align_to_normal: bool,
+    /// Largest tilt in radians of a landed foot away from the rotation of the body.
+    pub max_tilt: f32,
+    /// Distance the root sways to shift the weight while standing still.
+    pub idle_shift: f32,
+    /// Sways per second of the weight shift.
+    pub idle_frequency: f32,
+    /// Range of seconds between fidget steps while standing still, never stepping if zero.
+    pub idle_interval: [f32; 2],
+    /// Largest distance the root shifts to stay over the feet in stance.
+    pub max_balance_shift: f32,
+    /// Rate per second at which the root eases toward its balance shift, never balancing if zero.
+    pub balance_stiffness: f32,
+    /// Body size relative to the rig the other values are tuned for.
+    /// Measured from the global scale of the quadruped when absent.
+    pub scale: Option<f32>,
+}
+
+impl Config {
+    /// Returns the config for a body `scale` times the size of the tuned rig.
+    ///
+    /// Lengths scale linearly; times and angular velocities follow dynamic similarity,
+    /// so a larger body steps slower as it would under the same gravity.
+    pub fn scaled(&self, scale: f32) -> Self {
+        let [min_step, max_step] = self.step_limit;
+        let [min_interval, max_interval] = self.idle_interval;
+        let time_scale = scale.sqrt();
+        Self {
+            max_angular_velocity: self.max_angular_velocity / time_scale,
+            step_limit: [min_step * scale, max_step * scale],
+            flight_time: self.flight_time * time_scale,
+            stance_height: self.stance_height * scale,
+            obstacle_clearance: self.obstacle_clearance * scale,
+            stumble_height: self.stumble_height * scale,
+            stumble_recovery: self.stumble_recovery * time_scale,
+            stumble_dip: self.stumble_dip * scale,
+            idle_shift: self.idle_shift * scale,
+            idle_frequency: self.idle_frequency / time_scale,
+            idle_interval: [min_interval * time_scale, max_interval * time_scale],
+            max_balance_shift: self.max_balance_shift * scale,
+            balance_stiffness: self.balance_stiffness / time_scale,
+            scale: Some(scale),
+            ..*self
+        }
+    }
+
+    /// Interpolates the continuous parameters towards `other`; the scale and flags are taken from `other`.
+    pub fn lerp(&self, other: &Self, factor: f32) -> Self {
+        let lerp = |a: f32, b: f32| a + (b - a) * factor;
+        Self {
+            max_angular_velocity: lerp(self.max_angular_velocity, other.max_angular_velocity),
+            max_duty_factor: lerp(self.max_duty_factor, other.max_duty_factor),
+            step_limit: [
+                lerp(self.step_limit[0], other.step_limit[0]),
+                lerp(self.step_limit[1], other.step_limit[1]),
+            ],
+            flight_time: lerp(self.flight_time, other.flight_time),
+            flight_factor: lerp(self.flight_factor, other.flight_factor),
+            stance_height: lerp(self.stance_height, other.stance_height),
+            calibrate_stance: other.calibrate_stance,
+            bounce_factor: lerp(self.bounce_factor, other.bounce_factor),
+            max_lean: lerp(self.max_lean, other.max_lean),
+            obstacle_clearance: lerp(self.obstacle_clearance, other.obstacle_clearance),
+            stumble_height: lerp(self.stumble_height, other.stumble_height),
+            stumble_recovery: lerp(self.stumble_recovery, other.stumble_recovery),
+            stumble_shortening: lerp(self.stumble_shortening, other.stumble_shortening),
+            stumble_dip: lerp(self.stumble_dip, other.stumble_dip),
+            align_to_normal: other.align_to_normal,
+            max_tilt: lerp(self.max_tilt, other.max_tilt),
+            idle_shift: lerp(self.idle_shift, other.idle_shift),
+            idle_frequency: lerp(self.idle_frequency, other.idle_frequency),
+            idle_interval: [
+                lerp(self.idle_interval[0], other.idle_interval[0]),
+                lerp(self.idle_interval[1], other.idle_interval[1]),
+            ],
+            max_balance_shift: lerp(self.max_balance_shift, other.max_balance_shift),
+            balance_stiffness: lerp(self.balance_stiffness, other.balance_stiffness),
+            scale: other.scale,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct Limb {
+    foot: Entity,
+    anchor: Entity,
+    root: Entity,
+    origin: Entity,
+    home: Entity,
+    /// Shift of the home along and to the left of the planar velocity while stepping,
+    /// so feet can lead into sideways or backward motion.
+    home_offset: [f32; 2],
+    /// Largest distance from the anchor to a step target, in lengths of the limb at rest.
+    reach: f32,
+    /// Distance from the anchor to the foot at rest, measured when the body is first calibrated.
+    length: Option<f32>,
+    /// The platform the foot last landed on, carrying it while it stands.
+    platform: Option<Entity>,
+
+    state: State,
+    radius: f32,
+    angular_velocity: f32,
+    /// The minimum angular velocity whose flight time is greater than `flight_time`.
+    threshold: f32,
+    duty_factor: f32,
+    /// Share of the full stride length the limb steps, less than one while the body recovers from a stumble.
+    stride_factor: f32,
+
+    config: Config,
+
+    signal: Complex<f32>,
+    transition: bool,
+    /// Keeps the oscillator from starting steps, so the foot only steps when it is far from home.
+    held: bool,
+}
+
+impl Limb {
+    fn match_speed(&mut self, speed: f32) {
+        let ref config = self.config;
+        let [min_step, max_step] = self.config.step_limit;
+
+        // Increase angular speed to be maximum, and then increase radius.
+        let min_radius = min_step / config.max_duty_factor / TAU;
+        self.angular_velocity = (speed / min_radius).min(config.max_angular_velocity);
+        self.radius = if self.angular_velocity > 0.0 { speed / self.angular_velocity } else { min_radius };
+
+        // The step length at this situation to ensure the maximum duty factor and the maximum step length.
+        let step_length = (TAU * self.radius * config.max_duty_factor).min(max_step) * self.stride_factor;
+        self.duty_factor = step_length / (TAU * self.radius);
+        self.threshold = TAU * (1.0 - config.max_duty_factor) / config.flight_time;
+    }
+
+    #[inline]
+    fn is_stance(&self) -> bool {
+        match self.state {
+            State::Stance => true,
+            _ => false,
+        }
+    }
+
+    #[inline]
+    fn is_airborne(&self) -> bool {
+        match self.state {
+            State::Airborne => true,
+            _ => false,
+        }
+    }
+
+    /// Largest distance from the anchor to a step target, once the limb is measured.
+    #[inline]
+    fn max_reach(&self) -> Option<f32> {
+        self.length.map(|length| length * self.reach)
+    }
+
+    #[inline]
+    fn step_radius(&self) -> f32 {
+        PI * self.radius * self.duty_factor
+    }
+
+    #[inline]
+    fn flight_time(&self) -> f32 {
+        if self.angular_velocity > self.threshold {
+            TAU * (1.0 - self.duty_factor) / self.angular_velocity
+        } else {
+            self.config.flight_time
+        }
+    }
+}
+
+/// A body walking on limbs driven by coupled oscillators, moved by the locomotion systems.
+pub trait Legged: Component + Send + Sync {
+    fn limbs(&self) -> &[Limb];
+
+    fn limbs_mut(&mut self) -> &mut [Limb];
+
+    /// The node the bounce system moves to follow the limbs.
+    fn root(&self) -> Entity;
+
+    /// The config as authored, before scaling.
+    fn config(&self) -> Config;
+
+    /// The config as authored; limbs only follow changes to it once rescaled.
+    fn config_mut(&mut self) -> &mut Config;
+
+    /// How the oscillator of limb `j` pulls the one of limb `i` at `duty_factor`:
+    /// the coupling weight rotated by the phase offset between them, zero if they are not coupled.
+    fn coupling(&self, i: usize, j: usize, duty_factor: f32) -> Complex<f32>;
+
+    #[inline]
+    fn scale(&self) -> Option<f32> {
+        self.limbs().first().and_then(|limb| limb.config.scale)
+    }
+
+    /// Rescales the config of all limbs from the authored one.
+    fn set_scale(&mut self, scale: f32) {
+        let config = self.config().scaled(scale);
+        for limb in self.limbs_mut() {
+            limb.config = config;
+        }
+    }
+
+    /// The gait the limbs are in, if the body has gaits.
+    fn current_gait(&self) -> Option<Gait> {
+        None
+    }
+
+    /// Whether the scale and the limbs are measured, and the stance if the config asks for it.
+    fn calibrated(&self) -> bool {
+        self.scale().is_some()
+            && self.limbs().iter().all(|limb| limb.length.is_some())
+            && !self.config().calibrate_stance
+    }
+
+    /// Derives the scale from the global transform of the body if the config doesn't give one,
+    /// and measures the limbs that haven't been.
+    fn calibrate(&mut self, entity: Entity, cache: &GlobalCache, ground: &Ground<'_>) -> Option<()> {
+        if self.scale().is_none() {
+            let matrix = cache.global_matrix(entity)?;
+            let scale = (0..3)
+                .map(|i| Vector3::new(matrix[(0, i)], matrix[(1, i)], matrix[(2, i)]).norm())
+                .sum::<f32>() / 3.0;
+            self.set_scale(scale);
+        }
+        for limb in self.limbs_mut().iter_mut().filter(|limb| limb.length.is_none()) {
+            let anchor = cache.global_position(limb.anchor)?;
+            let foot = cache.global_position(limb.foot)?;
+            limb.length = Some((foot - anchor).norm());
+        }
+        if self.config().calibrate_stance {
+            self.calibrate_stance(entity, cache, ground)?;
+        }
+        Some(())
+    }
+
+    /// Measures the stance height as the mean height of the feet at rest, and stores it unscaled into the config.
+    ///
+    /// Each foot is measured down the `WorldUp` to the ground if a raycast finds it within the length of the limb,
+    /// or else above the body, which stands on the ground at rest.
+    fn calibrate_stance(&mut self, entity: Entity, cache: &GlobalCache, ground: &Ground<'_>) -> Option<()> {
+        let up = ground.up();
+        let base = up.height(&cache.global_position(entity)?);
+        let heights = self.limbs()
+            .iter()
+            .map(|limb| {
+                let ref foot = cache.global_position(limb.foot)?;
+                let above = up.height(foot) - base;
+                let max_distance = above.max(0.0) + limb.length?;
+                let height = ground
+                    .raycast(foot, &-up.up(), max_distance)
+                    .map_or(above, |hit| hit.distance);
+                Some(height)
+            })
+            .collect::<Option<Vec<_>>>()?;
+        if heights.is_empty() { return None; }
+
+        let height = heights.iter().sum::<f32>() / heights.len() as f32;
+        let scale = self.scale().unwrap_or(1.0);
+        info!(
+            "{:?} stands at a stance height of {:.3} ({:.3} unscaled), measured from feet at {:.3?}",
+            entity, height, height / scale, heights,
+        );
+
+        let config = self.config_mut();
+        config.stance_height = height / scale;
+        config.calibrate_stance = false;
+        self.set_scale(scale);
+        Some(())
+    }
+
+    /// All entities of the rig referenced by this body.
+    fn entities(&self) -> Vec<Entity> {
+        self.limbs()
+            .iter()
+            .flat_map(|limb| vec![limb.foot, limb.anchor, limb.root, limb.origin, limb.home])
+            .chain(std::iter::once(self.root()))
+            .collect()
+    }
+}
+
+#[derive(Debug, Copy, Clone, Component)]
+#[storage(DenseVecStorage)]
+pub struct Quadruped {
+    limbs: [Limb; 4],
+    root: Entity,
+    /// The config as authored, before scaling.
+    config: Config,
+    /// The gait forced by gameplay, or `None` to follow the speed.
+    gait: Option<Gait>,
+    crossfade: Option<Crossfade>,
+}
+
+impl Quadruped {
+    /// Replaces the authored config, keeping the current scale unless `config` gives its own.
+    pub fn set_config(&mut self, config: Config) {
+        let scale = config.scale.or(self.scale());
+        self.config = config;
+        match scale {
+            Some(scale) => self.set_scale(scale),
+            None => self.limbs.iter_mut().for_each(|limb| limb.config = config),
+        }
+    }
+
+    pub fn feet(&self) -> [Entity; 4] {
+        let [a, b, c, d] = &self.limbs;
+        [a.foot, b.foot, c.foot, d.foot]
+    }
+
+    /// Gait phase of each limb, as the state of its oscillator.
+    pub fn phases(&self) -> [Complex<f32>; 4] {
+        let mut phases = [Complex::new(0.0, 0.0); 4];
+        for (phase, limb) in phases.iter_mut().zip(self.limbs.iter()) {
+            *phase = limb.signal;
+        }
+        phases
+    }
+
+    pub fn set_phases(&mut self, phases: [Complex<f32>; 4]) {
+        for (limb, phase) in self.limbs.iter_mut().zip(phases.iter()) {
+            limb.signal = *phase;
+        }
+    }
+
+    pub fn gait(&self) -> Option<Gait> {
+        self.gait
+    }
+
+    /// Forces `gait` regardless of speed, crossfading into it over the `GaitTransition`.
+    pub fn set_gait(&mut self, gait: Gait) {
+        self.change_gait(Some(gait));
+    }
+
+    /// Lets the speed pick the gait again.
+    pub fn release_gait(&mut self) {
+        self.change_gait(None);
+    }
+
+    fn change_gait(&mut self, gait: Option<Gait>) {
+        if gait == self.gait { return; }
+
+        let mut from = [[0.0; 4]; 4];
+        for (i, row) in from.iter_mut().enumerate() {
+            let duty_factor = self.limbs[i].duty_factor;
+            for (j, phase) in row.iter_mut().enumerate() {
+                *phase = self.phase(i, j, duty_factor);
+            }
+        }
+        self.gait = gait;
+        self.crossfade = Some(Crossfade { from, progress: 0.0 });
+    }
+
+    /// Phase offset between limbs `i` and `j`, blended from the previous gait while crossfading.
+    fn phase(&self, i: usize, j: usize, duty_factor: f32) -> f32 {
+        let target = match self.gait {
+            Some(gait) => gait.phase(i, j),
+            None => Gait::phase_at(i, j, duty_factor),
+        };
+        match self.crossfade {
+            Some(Crossfade { from, progress }) => {
+                let ref factor = Cubic::ease_in_out(progress, 0.0, 1.0, 1.0);
+                from[i][j].lerp(&target, factor)
+            }
+            None => target,
+        }
+    }
+}
+
+impl Legged for Quadruped {
+    #[inline]
+    fn limbs(&self) -> &[Limb] {
+        &self.limbs
+    }
+
+    #[inline]
+    fn limbs_mut(&mut self) -> &mut [Limb] {
+        &mut self.limbs
+    }
+
+    #[inline]
+    fn root(&self) -> Entity {
+        self.root
+    }
+
+    #[inline]
+    fn config(&self) -> Config {
+        self.config
+    }
+
+    #[inline]
+    fn config_mut(&mut self) -> &mut Config {
+        &mut self.config
+    }
+
+    fn current_gait(&self) -> Option<Gait> {
+        Some(self.gait.unwrap_or_else(|| Gait::at(self.limbs[0].duty_factor)))
+    }
+
+    fn coupling(&self, i: usize, j: usize, duty_factor: f32) -> Complex<f32> {
+        const WEIGHTS: [[f32; 4]; 4] = [
+            [0.0, 1.0, 0.0, 1.0],
+            [1.0, 0.0, 1.0, 0.0],
+            [0.0, 1.0, 0.0, 1.0],
+            [1.0, 0.0, 1.0, 0.0],
+        ];
+
+        let ref phi = self.phase(i, j, duty_factor);
+        Complex::from_polar(&WEIGHTS[i][j], phi)
+    }
+}
+
+/// A two-legged body, stepping with its legs in antiphase.
+#[derive(Debug, Copy, Clone, Component)]
+#[storage(DenseVecStorage)]
+pub struct Biped {
+    limbs: [Limb; 2],
+    root: Entity,
+    /// The config as authored, before scaling.
+    config: Config,
+}
+
+impl Legged for Biped {
+    #[inline]
+    fn limbs(&self) -> &[Limb] {
+        &self.limbs
+    }
+
+    #[inline]
+    fn limbs_mut(&mut self) -> &mut [Limb] {
+        &mut self.limbs
+    }
+
+    #[inline]
+    fn root(&self) -> Entity {
+        self.root
+    }
+
+    #[inline]
+    fn config(&self) -> Config {
+        self.config
+    }
+
+    #[inline]
+    fn config_mut(&mut self) -> &mut Config {
+        &mut self.config
+    }
+
+    fn coupling(&self, i: usize, j: usize, _duty_factor: f32) -> Complex<f32> {
+        const WEIGHTS: [[f32; 2]; 2] = [
+            [0.0, 1.0],
+            [1.0, 0.0],
+        ];
+        const PHASES: [[f32; 2]; 2] = [
+            [0.0, PI],
+            [-PI, 0.0],
+        ];
+
+        Complex::from_polar(&WEIGHTS[i][j], &PHASES[i][j])
+    }
+}
+
+/// Coupling between the oscillators of a `Locomotor`, as square matrices over its limbs.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CouplingMatrix {
+    /// How strongly the oscillator of limb `j` pulls the one of limb `i`; zero leaves them uncoupled.
+    pub weights: Vec<Vec<f32>>,
+    /// Phase offsets between the limbs, each table keyed by the duty factor it applies at.
+    /// Sorted by duty factor; tables are interpolated in between.
+    pub phases: Vec<(f32, Vec<Vec<f32>>)>,
+}
+
+impl CouplingMatrix {
+    fn validate(&self, count: usize) -> Result<(), Error> {
+        let square = |matrix: &Vec<Vec<f32>>| matrix.len() == count && matrix.iter().all(|row| row.len() == count);
+        if !square(&self.weights) || !self.phases.iter().all(|(_, phases)| square(phases)) {
+            return Err(format_err!("The coupling matrices of a locomotor with {} limbs must be {}x{}", count, count, count));
+        }
+        if self.phases.is_empty() {
+            return Err(format_err!("A locomotor needs at least one phase table"));
+        }
+        Ok(())
+    }
+
+    fn phase(&self, i: usize, j: usize, duty_factor: f32) -> f32 {
+        let index = self.phases
+            .iter()
+            .position(|(key, _)| *key > duty_factor)
+            .unwrap_or_else(|| self.phases.len());
+        match (index.checked_sub(1).map(|index| &self.phases[index]), self.phases.get(index)) {
+            (Some((from_key, from)), Some((to_key, to))) => {
+                let ref factor = (duty_factor - from_key) / (to_key - from_key);
+                from[i][j].lerp(&to[i][j], factor)
+            }
+            (Some((_, phases)), None) | (None, Some((_, phases))) => phases[i][j],
+            (None, None) => 0.0,
+        }
+    }
+}
+
+/// A body with any number of limbs, coupled as given by its prefab, e.g. a hexapod.
+#[derive(Debug, Clone, Component)]
+#[storage(DenseVecStorage)]
+pub struct Locomotor {
+    limbs: Vec<Limb>,
+    coupling: CouplingMatrix,
+    root: Entity,
+    /// The config as authored, before scaling.
+    config: Config,
+}
+
+impl Legged for Locomotor {
+    #[inline]
+    fn limbs(&self) -> &[Limb] {
+        &self.limbs
+    }
+
+    #[inline]
+    fn limbs_mut(&mut self) -> &mut [Limb] {
+        &mut self.limbs
+    }
+
+    #[inline]
+    fn root(&self) -> Entity {
+        self.root
+    }
+
+    #[inline]
+    fn config(&self) -> Config {
+        self.config
+    }
+
+    #[inline]
+    fn config_mut(&mut self) -> &mut Config {
+        &mut self.config
+    }
+
+    fn coupling(&self, i: usize, j: usize, duty_factor: f32) -> Complex<f32> {
+        let ref weight = self.coupling.weights[i][j];
+        let ref phase = self.coupling.phase(i, j, duty_factor);
+        Complex::from_polar(weight, phase)
+    }
+}
+
+/// Reach of limbs without one in their prefab. Feet stand with bent legs at rest,
+/// so a stretched leg reaches somewhat further than it stands.
+pub const DEFAULT_REACH: f32 = 1.3;
+
+/// Builds `count` limbs from the limb nodes of a prefab, with their oscillators starting at `phase_offsets`,
+/// or spread over half a cycle without.
+fn build_limbs(
+    body: &str,
+    count: usize,
+    nodes: [&Vec<EntityRef>; 5],
+    home_offsets: &[[f32; 2]],
+    reaches: &[f32],
+    phase_offsets: Option<&[f32]>,
+    config: &Config,
+    entities: &[Entity],
+) -> Result<Vec<Limb>, Error> {
+    if nodes.iter().any(|fields| fields.len() != count) {
+        let [feet, anchors, roots, origins, homes] = nodes;
+        return Err(format_err!(
+            "A {} needs {} of each limb node, got {} feet, {} anchors, {} roots, {} origins and {} homes",
+            body, count, feet.len(), anchors.len(), roots.len(), origins.len(), homes.len(),
+        ));
+    }
+    if !home_offsets.is_empty() && home_offsets.len() != count {
+        return Err(format_err!("A {} needs {} home offsets if any, got {}", body, count, home_offsets.len()));
+    }
+    if !reaches.is_empty() && reaches.len() != count {
+        return Err(format_err!("A {} needs {} reaches if any, got {}", body, count, reaches.len()));
+    }
+    match phase_offsets {
+        Some(phase_offsets) if phase_offsets.len() != count => {
+            return Err(format_err!("A {} needs {} phase offsets if any, got {}", body, count, phase_offsets.len()));
+        }
+        _ => {}
+    }
+
+    let home_offsets = (0..count).map(|i| home_offsets.get(i).copied().unwrap_or_default());
+    let reaches = (0..count).map(|i| reaches.get(i).copied().unwrap_or(DEFAULT_REACH));
+    let signals = (0..count)
+        .map(|i| {
+            let ref radius = 1.0;
+            let ref angle = phase_offsets.map_or(PI * i as f32 / count as f32, |phase_offsets| phase_offsets[i]);
+            Complex::from_polar(radius, angle)
+        });
+
+    // Without an explicit scale the limbs keep the authored config until calibrated.
+    let config = match config.scale {
+        Some(scale) => config.scaled(scale),
+        None => *config,
+    };
+    let [feet, anchors, roots, origins, homes] = nodes;
+    let limbs = multizip((feet, anchors, roots, origins, homes, home_offsets, reaches, signals))
+        .map(|fields| {
+            let (
+                foot,
+                anchor,
+                root,
+                origin,
+                home,
+                home_offset,
+                reach,
+                signal,
+            ) = fields;
+
+            Limb {
+                foot: foot.resolve(entities),
+                anchor: anchor.resolve(entities),
+                root: root.resolve(entities),
+                origin: origin.resolve(entities),
+                home: home.resolve(entities),
+                home_offset,
+                reach,
+                length: None,
+                platform: None,
+
+                state: State::Stance,
+                radius: 0.0,
+                angular_velocity: 0.0,
+                threshold: 0.0,
+                duty_factor: 0.0,
+                stride_factor: 1.0,
+
+                config,
+
+                signal,
+                transition: false,
+                held: false,
+            }
+        })
+        .collect();
+    Ok(limbs)
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Redirect)]
+pub struct QuadrupedPrefab {
+    pub feet: Vec<EntityRef>,
+    pub anchors: Vec<EntityRef>,
+    pub roots: Vec<EntityRef>,
+    pub origins: Vec<EntityRef>,
+    pub homes: Vec<EntityRef>,
+    pub root: EntityRef,
+    /// Per-limb shift of the homes in the velocity frame, `[along, left]`; none by default.
+    #[serde(default)]
+    #[redirect(skip)]
+    pub home_offsets: Vec<[f32; 2]>,
+    /// Per-limb largest distance from the anchor to a step target, in lengths of the limb at rest;
+    /// `DEFAULT_REACH` by default.
+    #[serde(default)]
+    #[redirect(skip)]
+    pub reaches: Vec<f32>,
+    /// Per-limb starting phase of the oscillators in radians, e.g. to start a camel in a pace;
+    /// spread over half a cycle by default. The coupling pulls them into the gait from there.
+    #[serde(default)]
+    #[redirect(skip)]
+    pub phase_offsets: Option<Vec<f32>>,
+
+    #[serde(flatten)]
+    #[redirect(skip)]
+    pub config: Config,
+}
+
+impl<'a> PrefabData<'a> for QuadrupedPrefab {
+    type SystemData = WriteStorage<'a, Quadruped>;
+    type Result = ();
+
+    fn add_to_entity(
+        &self,
+        entity: Entity,
+        data: &mut Self::SystemData,
+        entities: &[Entity],
+        _children: &[Entity],
+    ) -> Result<Self::Result, Error> {
+        let nodes = [&self.feet, &self.anchors, &self.roots, &self.origins, &self.homes];
+        let phase_offsets = self.phase_offsets.as_deref();
+        let limbs = build_limbs("quadruped", 4, nodes, &self.home_offsets, &self.reaches, phase_offsets, &self.config, entities)?;
+        let component = Quadruped {
+            limbs: limbs.as_slice().try_into().unwrap(),
+            root: self.root.resolve(entities),
+            config: self.config,
+            gait: None,
+            crossfade: None,
+        };
+        data.insert(entity, component).map(|_| ()).map_err(Into::into)
+    }
+}
+
+/// Same as `QuadrupedPrefab`, with any number of limbs and their coupling.
+#[derive(Debug, Clone, Serialize, Deserialize, Redirect)]
+pub struct LocomotorPrefab {
+    pub feet: Vec<EntityRef>,
+    pub anchors: Vec<EntityRef>,
+    pub roots: Vec<EntityRef>,
+    pub origins: Vec<EntityRef>,
+    pub homes: Vec<EntityRef>,
+    pub root: EntityRef,
+    #[serde(default)]
+    #[redirect(skip)]
+    pub home_offsets: Vec<[f32; 2]>,
+    #[serde(default)]
+    #[redirect(skip)]
+    pub reaches: Vec<f32>,
+    #[redirect(skip)]
+    pub coupling: CouplingMatrix,
+
+    #[serde(flatten)]
+    #[redirect(skip)]
+    pub config: Config,
+}
+
+impl<'a> PrefabData<'a> for LocomotorPrefab {
+    type SystemData = WriteStorage<'a, Locomotor>;
+    type Result = ();
+
+    fn add_to_entity(
+        &self,
+        entity: Entity,
+        data: &mut Self::SystemData,
+        entities: &[Entity],
+        _children: &[Entity],
+    ) -> Result<Self::Result, Error> {
+        let count = self.feet.len();
+        self.coupling.validate(count)?;
+
+        let nodes = [&self.feet, &self.anchors, &self.roots, &self.origins, &self.homes];
+        let component = Locomotor {
+            limbs: build_limbs("locomotor", count, nodes, &self.home_offsets, &self.reaches, None, &self.config, entities)?,
+            coupling: self.coupling.clone(),
+            root: self.root.resolve(entities),
+            config: self.config,
+        };
+        data.insert(entity, component).map(|_| ()).map_err(Into::into)
+    }
+}
+
+/// Same as `QuadrupedPrefab`, with two of each limb node.
+#[derive(Debug, Clone, Serialize, Deserialize, Redirect)]
+pub struct BipedPrefab {
+    pub feet: Vec<EntityRef>,
+    pub anchors: Vec<EntityRef>,
+    pub roots: Vec<EntityRef>,
+    pub origins: Vec<EntityRef>,
+    pub homes: Vec<EntityRef>,
+    pub root: EntityRef,
+    #[serde(default)]
+    #[redirect(skip)]
+    pub home_offsets: Vec<[f32; 2]>,
+    #[serde(default)]
+    #[redirect(skip)]
+    pub reaches: Vec<f32>,
+
+    #[serde(flatten)]
+    #[redirect(skip)]
+    pub config: Config,
+}
+
+impl<'a> PrefabData<'a> for BipedPrefab {
+    type SystemData = WriteStorage<'a, Biped>;
+    type Result = ();
+
+    fn add_to_entity(
+        &self,
+        entity: Entity,
+        data: &mut Self::SystemData,
+        entities: &[Entity],
+        _children: &[Entity],
+    ) -> Result<Self::Result, Error> {
+        let nodes = [&self.feet, &self.anchors, &self.roots, &self.origins, &self.homes];
+        let limbs = build_limbs("biped", 2, nodes, &self.home_offsets, &self.reaches, None, &self.config, entities)?;
+        let component = Biped {
+            limbs: limbs.as_slice().try_into().unwrap(),
+            root: self.root.resolve(entities),
+            config: self.config,
+        };
+        data.insert(entity, component).map(|_| ()).map_err(Into::into)
+    }
+}
+
+#[inline]
+fn limb_velocity(
+    cache: &GlobalCache,
+    entity: Entity,
+    limb: &Limb,
+    player: &Player,
+) -> Option<Vector3<f32>> {
+    let ref home = cache.global_position(limb.home)?;
+    let root = cache.global_position(entity)?;
+
+    let ref radial = home - root;
+    let ref angular = player.spinning().scaled_axis();
+    let ref linear = player.velocity();
+
+    let transform = cache.global_matrix(entity)?;
+    let angular = transform.transform_vector(angular);
+    let linear = transform.transform_vector(linear);
+    Some(linear + angular.cross(radial))
+}
+
+/// Registers the quadruped locomotion systems.
+///
+/// Must be added after the `TransformBundle`. The `OscillatorSystem` runs inside the physics step
+/// and the `PlayerSystem` needs to run before the transform system, so neither is part of this bundle.
+#[derive(Debug, Default)]
+pub struct LocomotionBundle;
+
+impl<'a, 'b> SystemBundle<'a, 'b> for LocomotionBundle {
+    fn build(
+        self,
+        _world: &mut World,
+        builder: &mut DispatcherBuilder<'a, 'b>,
+    ) -> Result<(), Error> {
+        builder.add(DespawnSystem::default(), "despawn", &[]);
+        builder.add(CleanupSystem::default(), "cleanup", &["despawn"]);
+        builder.add(Processor::<Heightfield>::new(), "heightfield_processor", &[]);
+        builder.add(TailSystem::default(), "tail", &[]);
+        builder.add(GlobalCacheSystem::default(), "global_cache", &["transform_system"]);
+        builder.add(WaterSystem::default(), "water", &["global_cache"]);
+        builder.add(SwimSystem::default(), "swim", &["global_cache", "water"]);
+        builder.add(TrackSystem::default(), "track", &["transform_system", "swim"]);
+        builder.add(AppendageSystem::default(), "appendage", &["transform_system"]);
+        builder.add(BreathingSystem::default(), "breathing", &[]);
+        builder.add(CarrySystem::default(), "carry", &[]);
+        builder.add(SocketSystem::default(), "socket", &[]);
+        builder.add(SpatialIndexSystem::default(), "spatial_index", &["transform_system"]);
+        builder.add(GazeSystem::default(), "gaze", &["spatial_index"]);
+        builder.add(GaitPresetSystem::default(), "gait_preset", &[]);
+        builder.add(GaitTransitionSystem::default(), "gait_transition", &[]);
+        builder.add(LimbCalibrationSystem::default(), "limb_calibration", &["global_cache"]);
+        builder.add(GaitBakerySystem::default(), "gait_bakery", &[]);
+        builder.add(BounceSystem::<Quadruped>::default(), "bounce", &["global_cache", "limb_calibration"]);
+        builder.add(BounceSystem::<Biped>::default(), "biped_bounce", &["global_cache", "limb_calibration"]);
+        builder.add(BounceSystem::<Locomotor>::default(), "locomotor_bounce", &["global_cache", "limb_calibration"]);
+        builder.add(WorkspaceSystem::<Quadruped>::default(), "workspace", &["global_cache", "limb_calibration"]);
+        builder.add(WorkspaceSystem::<Biped>::default(), "biped_workspace", &["global_cache", "limb_calibration"]);
+        builder.add(WorkspaceSystem::<Locomotor>::default(), "locomotor_workspace", &["global_cache", "limb_calibration"]);
+        builder.add(PlatformSystem::default(), "platform", &["transform_system"]);
+        builder.add(DistantSystem::<Quadruped>::default(), "distant", &["global_cache", "limb_calibration"]);
+        builder.add(DistantSystem::<Biped>::default(), "biped_distant", &["global_cache", "limb_calibration"]);
+        builder.add(DistantSystem::<Locomotor>::default(), "locomotor_distant", &["global_cache", "limb_calibration"]);
+        builder.add(LocomotionSystem::<Quadruped>::default(), "locomotion", &["global_cache", "platform", "gait_bakery", "distant"]);
+        builder.add(LocomotionSystem::<Biped>::default(), "biped_locomotion", &["global_cache", "platform", "biped_distant"]);
+        builder.add(LocomotionSystem::<Locomotor>::default(), "locomotor_locomotion", &["global_cache", "platform", "locomotor_distant"]);
+        builder.add(Processor::<MotionDatabase>::new(), "motion_database_processor", &[]);
+        builder.add(MotionMatchingSystem::default(), "motion_matching", &["locomotion"]);
+        builder.add(MotionRecorderSystem::default(), "motion_recorder", &["locomotion"]);
+        builder.add(HerdSyncSystem::default(), "herd_sync", &["spatial_index", "locomotion"]);
+        builder.add(ActionSystem::default(), "action", &["locomotion", "bounce"]);
+        builder.add(JumpSystem::default(), "jump", &["locomotion", "bounce"]);
+        builder.add(SpineSystem::default(), "spine", &["locomotion"]);
+        builder.add(IdleSystem::<Quadruped>::default(), "idle", &["locomotion", "bounce"]);
+        builder.add(IdleSystem::<Biped>::default(), "biped_idle", &["biped_locomotion", "biped_bounce"]);
+        builder.add(IdleSystem::<Locomotor>::default(), "locomotor_idle", &["locomotor_locomotion", "locomotor_bounce"]);
+        builder.add(BalanceSystem::<Quadruped>::default(), "balance", &["locomotion", "bounce"]);
+        builder.add(BalanceSystem::<Biped>::default(), "biped_balance", &["biped_locomotion", "biped_bounce"]);
+        builder.add(BalanceSystem::<Locomotor>::default(), "locomotor_balance", &["locomotor_locomotion", "locomotor_bounce"]);
+        builder.add(PostureSystem::default(), "posture", &["locomotion", "bounce"]);
+        builder.add(ValidationSystem::default(), "validation", &[]);
+        builder.add(HelperSystem::default(), "helper", &["validation"]);
+        builder.add(FootprintSystem::default(), "footprints", &["locomotion"]);
+        Ok(())
+    }
+}
@@ -0,0 +1,271 @@
+use amethyst::{
+    assets::PrefabData,
+    core::{math::{Matrix4, Vector3}, Time, transform::{Parent, Transform}},
+    ecs::{Component, prelude::*, SystemData},
+    error::Error,
+    shrev::{EventChannel, ReaderId},
+};
+use easer::functions::{Cubic, Easing};
+use serde::{Deserialize, Serialize};
+
+use crate::{systems::player::Player, utils::{determinism::Determinism, warnings::Warnings}};
+
+use super::{Legged, Quadruped};
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub enum Posture {
+    Stand,
+    Sit,
+    Lie,
+}
+
+impl Default for Posture {
+    fn default() -> Self {
+        Posture::Stand
+    }
+}
+
+impl Posture {
+    /// The posture after this one, cycling back to standing.
+    pub fn next(self) -> Self {
+        match self {
+            Posture::Stand => Posture::Sit,
+            Posture::Sit => Posture::Lie,
+            Posture::Lie => Posture::Stand,
+        }
+    }
+}
+
+/// Offsets from the standing pose of a quadruped.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Pose {
+    /// How far the root sinks.
+    pub lower: f32,
+    /// Rotation of the root about its x axis in radians.
+    pub pitch: f32,
+    /// Offsets of the limb homes in their parent space, in limb order.
+    pub homes: Vec<[f32; 3]>,
+}
+
+impl Pose {
+    fn home(&self, index: usize) -> Vector3<f32> {
+        self.homes
+            .get(index)
+            .map_or_else(Vector3::zeros, |&home| Vector3::from(home))
+    }
+
+    fn lerp(&self, other: &Self, factor: f32) -> Self {
+        let lerp = |a: f32, b: f32| a + (b - a) * factor;
+        let count = self.homes.len().max(other.homes.len());
+        Pose {
+            lower: lerp(self.lower, other.lower),
+            pitch: lerp(self.pitch, other.pitch),
+            homes: (0..count)
+                .map(|index| self.home(index).lerp(&other.home(index), factor).into())
+                .collect(),
+        }
+    }
+}
+
+fn default_transition() -> f32 {
+    0.8
+}
+
+fn default_wake_speed() -> f32 {
+    0.2
+}
+
+/// Sitting and lying poses of a quadruped.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PosturesPrefab {
+    pub sit: Pose,
+    pub lie: Pose,
+    /// Seconds to blend between postures.
+    #[serde(default = "default_transition")]
+    pub transition: f32,
+    /// Player speed above which the quadruped stands up again.
+    #[serde(default = "default_wake_speed")]
+    pub wake_speed: f32,
+}
+
+impl<'a> PrefabData<'a> for PosturesPrefab {
+    type SystemData = WriteStorage<'a, Postures>;
+    type Result = ();
+
+    fn add_to_entity(
+        &self,
+        entity: Entity,
+        data: &mut Self::SystemData,
+        _entities: &[Entity],
+        _children: &[Entity],
+    ) -> Result<Self::Result, Error> {
+        let postures = Postures {
+            prefab: self.clone(),
+            posture: Posture::Stand,
+            from: Pose::default(),
+            current: Pose::default(),
+            elapsed: 0.0,
+            homes: vec![],
+        };
+        data.insert(entity, postures).map(|_| ()).map_err(Into::into)
+    }
+}
+
+/// The posture of a quadruped and the blend into it.
+///
+/// Out of the standing posture the limbs don't step with the gait, only to reach their moved homes.
+#[derive(Debug, Clone, Component)]
+#[storage(HashMapStorage)]
+pub struct Postures {
+    prefab: PosturesPrefab,
+    posture: Posture,
+    from: Pose,
+    current: Pose,
+    elapsed: f32,
+    /// Local translations of the limb homes when standing, captured on the first update.
+    homes: Vec<Vector3<f32>>,
+}
+
+impl Postures {
+    pub fn posture(&self) -> Posture {
+        self.posture
+    }
+
+    pub fn set_posture(&mut self, posture: Posture) {
+        if posture != self.posture {
+            self.posture = posture;
+            self.from = self.current.clone();
+            self.elapsed = 0.0;
+        }
+    }
+
+    fn target(&self) -> Pose {
+        match self.posture {
+            Posture::Stand => Pose::default(),
+            Posture::Sit => self.prefab.sit.clone(),
+            Posture::Lie => self.prefab.lie.clone(),
+        }
+    }
+}
+
+/// Changes the posture of the given quadrupeds, or of all of them if `targets` is `None`.
+#[derive(Debug, Clone)]
+pub struct PostureEvent {
+    pub posture: Posture,
+    pub targets: Option<Vec<Entity>>,
+}
+
+/// Blends quadrupeds between postures, after bounce has placed their root.
+#[derive(Default)]
+pub struct PostureSystem {
+    reader_id: Option<ReaderId<PostureEvent>>,
+}
+
+impl PostureSystem {
+    fn apply(
+        quadruped: &mut Quadruped,
+        postures: &mut Postures,
+        transforms: &mut WriteStorage<'_, Transform>,
+        parents: &ReadStorage<'_, Parent>,
+    ) -> Option<()> {
+        if postures.homes.is_empty() {
+            postures.homes = quadruped.limbs
+                .iter()
+                .map(|limb| transforms.get(limb.home).map(|transform| *transform.translation()))
+                .collect::<Option<_>>()?;
+        }
+
+        let held = postures.posture != Posture::Stand;
+        for (index, (limb, home)) in quadruped.limbs.iter_mut().zip(postures.homes.iter()).enumerate() {
+            limb.held = held;
+            transforms
+                .get_mut(limb.home)?
+                .set_translation(home + postures.current.home(index));
+        }
+
+        let root = quadruped.root;
+        let inverse = match parents.get(root) {
+            Some(parent) => transforms.get(parent.entity)?.global_matrix().try_inverse()?,
+            None => Matrix4::identity(),
+        };
+        let ref lower = Vector3::new(0.0, -postures.current.lower, 0.0);
+        transforms
+            .get_mut(root)?
+            .append_translation(inverse.transform_vector(lower))
+            .append_rotation_x_axis(postures.current.pitch);
+        Some(())
+    }
+}
+
+impl<'a> System<'a> for PostureSystem {
+    type SystemData = (
+        Entities<'a>,
+        WriteStorage<'a, Postures>,
+        WriteStorage<'a, Quadruped>,
+        WriteStorage<'a, Transform>,
+        ReadStorage<'a, Parent>,
+        ReadStorage<'a, Player>,
+        Read<'a, EventChannel<PostureEvent>>,
+        Read<'a, Time>,
+        Read<'a, Determinism>,
+        Write<'a, Warnings>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            entities,
+            mut postures,
+            mut quadrupeds,
+            mut transforms,
+            parents,
+            players,
+            events,
+            time,
+            determinism,
+            mut warnings,
+        ) = data;
+
+        let reader_id = self.reader_id
+            .as_mut()
+            .expect("`PostureSystem::setup` was not called before `PostureSystem::run`");
+
+        for PostureEvent { posture, targets } in events.read(reader_id) {
+            for (entity, postures) in (&*entities, &mut postures).join() {
+                let selected = targets
+                    .as_ref()
+                    .map_or(true, |targets| targets.contains(&entity));
+                if selected {
+                    postures.set_posture(*posture);
+                }
+            }
+        }
+
+        let delta_seconds = determinism.delta_seconds(time.delta_seconds());
+        for (entity, postures, quadruped, player) in (&*entities, &mut postures, &mut quadrupeds, players.maybe()).join() {
+            // Moving again blends back into locomotion.
+            let speed = player.map_or(0.0, |player| player.velocity().norm());
+            if speed > postures.prefab.wake_speed * quadruped.scale().unwrap_or(1.0) {
+                postures.set_posture(Posture::Stand);
+            }
+
+            let duration = postures.prefab.transition;
+            postures.elapsed += delta_seconds;
+            let factor = if postures.elapsed < duration {
+                Cubic::ease_in_out(postures.elapsed, 0.0, 1.0, duration)
+            } else {
+                1.0
+            };
+            postures.current = postures.from.lerp(&postures.target(), factor);
+
+            if Self::apply(quadruped, postures, &mut transforms, &parents).is_none() {
+                warnings.skip("posture", entity);
+            }
+        }
+    }
+
+    fn setup(&mut self, world: &mut World) {
+        Self::SystemData::setup(world);
+        self.reader_id = Some(world.fetch_mut::<EventChannel<PostureEvent>>().register_reader());
+    }
+}
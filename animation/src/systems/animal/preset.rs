@@ -0,0 +1,129 @@
+use std::collections::HashMap;
+
+use amethyst::{
+    core::Time,
+    ecs::{prelude::*, SystemData},
+    shrev::{EventChannel, ReaderId},
+};
+use easer::functions::{Cubic, Easing};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+
+use crate::utils::determinism::Determinism;
+
+use super::{Config, Legged, Quadruped};
+
+fn default_transition() -> f32 {
+    0.5
+}
+
+/// Named gait configs to compare at runtime, in the order they are cycled through.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct GaitPresets {
+    pub presets: Vec<(String, Config)>,
+    /// Seconds to blend from the current config into a selected preset.
+    #[serde(default = "default_transition")]
+    pub transition: f32,
+    /// The preset selected last.
+    #[serde(skip)]
+    pub active: Option<String>,
+}
+
+impl GaitPresets {
+    pub fn get(&self, name: &str) -> Option<&Config> {
+        self.presets
+            .iter()
+            .find(|(preset, _)| preset == name)
+            .map(|(_, config)| config)
+    }
+
+    /// The name of the preset after the active one, wrapping around.
+    pub fn next(&self) -> Option<&str> {
+        let index = self.active
+            .as_ref()
+            .and_then(|active| self.presets.iter().position(|(preset, _)| preset == active))
+            .map_or(0, |index| (index + 1) % self.presets.len());
+        self.presets.get(index).map(|(preset, _)| preset.as_str())
+    }
+}
+
+/// Selects a gait preset for the given quadrupeds, or for all of them if `targets` is `None`.
+#[derive(Debug, Clone)]
+pub struct GaitPresetEvent {
+    pub preset: String,
+    pub targets: Option<Vec<Entity>>,
+}
+
+#[derive(Debug, Copy, Clone)]
+struct Blend {
+    from: Config,
+    to: Config,
+    elapsed: f32,
+}
+
+/// Blends quadrupeds into the presets selected through `GaitPresetEvent`s.
+#[derive(Default)]
+pub struct GaitPresetSystem {
+    reader_id: Option<ReaderId<GaitPresetEvent>>,
+    blends: HashMap<Entity, Blend>,
+}
+
+impl<'a> System<'a> for GaitPresetSystem {
+    type SystemData = (
+        Entities<'a>,
+        WriteStorage<'a, Quadruped>,
+        Write<'a, GaitPresets>,
+        Read<'a, EventChannel<GaitPresetEvent>>,
+        Read<'a, Time>,
+        Read<'a, Determinism>,
+    );
+
+    fn run(&mut self, (entities, mut quadrupeds, mut presets, events, time, determinism): Self::SystemData) {
+        let reader_id = self.reader_id
+            .as_mut()
+            .expect("`GaitPresetSystem::setup` was not called before `GaitPresetSystem::run`");
+
+        for GaitPresetEvent { preset, targets } in events.read(reader_id) {
+            let to = match presets.get(preset) {
+                Some(config) => *config,
+                None => {
+                    warn!("Unknown gait preset '{}'", preset);
+                    continue;
+                }
+            };
+            for (entity, quadruped) in (&*entities, &quadrupeds).join() {
+                let selected = targets
+                    .as_ref()
+                    .map_or(true, |targets| targets.contains(&entity));
+                if selected {
+                    let blend = Blend { from: quadruped.config(), to, elapsed: 0.0 };
+                    self.blends.insert(entity, blend);
+                }
+            }
+            info!("Gait preset: {}", preset);
+            presets.active = Some(preset.clone());
+        }
+
+        let duration = presets.transition;
+        let delta_seconds = determinism.delta_seconds(time.delta_seconds());
+        self.blends.retain(|&entity, blend| {
+            let quadruped = match quadrupeds.get_mut(entity) {
+                Some(quadruped) => quadruped,
+                None => return false,
+            };
+            blend.elapsed += delta_seconds;
+            let factor = if blend.elapsed < duration {
+                Cubic::ease_in_out(blend.elapsed, 0.0, 1.0, duration)
+            } else {
+                1.0
+            };
+            quadruped.set_config(blend.from.lerp(&blend.to, factor));
+            blend.elapsed < duration
+        });
+    }
+
+    fn setup(&mut self, world: &mut World) {
+        Self::SystemData::setup(world);
+        self.reader_id = Some(world.fetch_mut::<EventChannel<GaitPresetEvent>>().register_reader());
+    }
+}
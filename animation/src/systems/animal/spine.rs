@@ -0,0 +1,132 @@
+use amethyst::{
+    assets::PrefabData,
+    core::{math::{Complex, Unit, UnitQuaternion, Vector3}, Transform},
+    derive::SystemDesc,
+    ecs::{Component, prelude::*},
+    error::Error,
+};
+use serde::{Deserialize, Serialize};
+
+use ceramic_derive::{Redirect, Resolve};
+use redirect::{Redirect, Resolve};
+
+use crate::{extras::EntityRef, utils::warnings::Warnings};
+
+use super::Stride;
+
+/// Joints along the back of a legged body, flexing and extending with its first two limbs,
+/// the front limbs of a quadruped.
+///
+/// Must be on the same entity as the body, and follows its `Stride`.
+#[derive(Debug, Clone, Component)]
+#[storage(HashMapStorage)]
+pub struct Spine {
+    joints: Vec<Entity>,
+    amplitude: f32,
+    axis: Vector3<f32>,
+    phase: f32,
+    /// Local rotations of the joints, captured on the first update.
+    rest: Vec<UnitQuaternion<f32>>,
+}
+
+impl Spine {
+    pub fn joints(&self) -> &[Entity] {
+        &self.joints
+    }
+}
+
+fn default_axis() -> [f32; 3] {
+    [1.0, 0.0, 0.0]
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Redirect, Resolve)]
+#[resolve(Spine)]
+#[resolve(init(axis: Vector3::from(self.axis), rest: vec![]))]
+pub struct SpinePrefab {
+    /// From the hips to the shoulders.
+    pub joints: Vec<EntityRef>,
+    /// Bend in radians of the whole spine as the duty factor approaches zero, spread evenly over the joints.
+    #[redirect(skip)]
+    pub amplitude: f32,
+    /// The local axis the joints bend about.
+    #[redirect(skip)]
+    #[serde(default = "default_axis")]
+    #[resolve(skip)]
+    pub axis: [f32; 3],
+    /// Offset in radians from the phase of the front limbs to the most flexed spine.
+    #[redirect(skip)]
+    #[serde(default)]
+    pub phase: f32,
+}
+
+impl<'a> PrefabData<'a> for SpinePrefab {
+    type SystemData = WriteStorage<'a, Spine>;
+    type Result = ();
+
+    fn add_to_entity(
+        &self,
+        entity: Entity,
+        data: &mut Self::SystemData,
+        entities: &[Entity],
+        _children: &[Entity],
+    ) -> Result<Self::Result, Error> {
+        data.insert(entity, self.resolve(entities)).map(|_| ()).map_err(Into::into)
+    }
+}
+
+/// Arches the spine of legged bodies in sync with the oscillators of their front limbs.
+///
+/// Walking gaits keep the duty factor at its maximum and the back still; as the duty factor
+/// drops toward a bound or gallop, the spine flexes while the front feet reach back and extends
+/// while they reach forward.
+#[derive(Default, SystemDesc)]
+pub struct SpineSystem;
+
+impl SpineSystem {
+    fn update(spine: &mut Spine, stride: &Stride, transforms: &mut WriteStorage<'_, Transform>) -> Option<()> {
+        if spine.rest.is_empty() {
+            spine.rest = spine.joints
+                .iter()
+                .map(|&joint| transforms.get(joint).map(|transform| *transform.rotation()))
+                .collect::<Option<_>>()?;
+        }
+
+        let max_duty_factor = stride.max_duty_factor();
+        let scale = if max_duty_factor > 0.0 {
+            (1.0 - stride.duty_factor() / max_duty_factor).max(0.0).min(1.0)
+        } else {
+            0.0
+        };
+
+        let front = match stride.phases() {
+            [left, right, ..] => (Complex::from_polar(&1.0, left) + Complex::from_polar(&1.0, right)).arg(),
+            _ => return None,
+        };
+        let bend = spine.amplitude * scale * (front + spine.phase).cos();
+
+        let ref axis = Unit::try_new(spine.axis, f32::EPSILON)?;
+        let rotation = UnitQuaternion::from_axis_angle(axis, bend / spine.joints.len() as f32);
+        for (&joint, rest) in spine.joints.iter().zip(spine.rest.iter()) {
+            transforms.get_mut(joint)?.set_rotation(rest * rotation);
+        }
+        Some(())
+    }
+}
+
+impl<'a> System<'a> for SpineSystem {
+    type SystemData = (
+        Entities<'a>,
+        WriteStorage<'a, Spine>,
+        ReadStorage<'a, Stride>,
+        WriteStorage<'a, Transform>,
+        Write<'a, Warnings>,
+    );
+
+    fn run(&mut self, (entities, mut spines, strides, mut transforms, mut warnings): Self::SystemData) {
+        for (entity, spine, stride) in (&*entities, &mut spines, &strides).join() {
+            if Self::update(spine, stride, &mut transforms).is_none() {
+                warnings.skip("spine", entity);
+            }
+        }
+    }
+}
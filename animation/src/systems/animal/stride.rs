@@ -0,0 +1,150 @@
+use amethyst::{
+    core::math::{Matrix4, Point3, Vector3},
+    ecs::{Component, prelude::*},
+};
+
+use crate::systems::player::Player;
+
+use super::{Gait, Limb, State};
+
+/// Steps and gait changes of legged bodies, sent by the `LocomotionSystem`.
+#[derive(Debug, Copy, Clone)]
+pub enum LocomotionEvent {
+    /// A foot landed; the `FootstepEvent` sent along also has the normal of the ground.
+    FootDown {
+        body: Entity,
+        /// Index of the limb, in the order the body lists its limbs.
+        limb: usize,
+        foot: Entity,
+        /// Point of contact on the ground.
+        position: Point3<f32>,
+        /// Speed of the foot along its stride.
+        speed: f32,
+        /// Vertical speed of the foot as it landed times the share of the body mass it takes on,
+        /// with the mass growing with the cube of the scale, so heavy landings hit harder than light steps.
+        impact: f32,
+    },
+    /// A foot left the ground to step.
+    FootUp {
+        body: Entity,
+        limb: usize,
+        foot: Entity,
+        /// Where the foot stood.
+        position: Point3<f32>,
+        speed: f32,
+    },
+    /// The gait of a quadruped changed, either forced by gameplay or following its speed.
+    GaitChanged {
+        body: Entity,
+        from: Gait,
+        to: Gait,
+    },
+}
+
+/// The locomotion of a legged body as seen from outside, so other systems can follow its steps
+/// without reading its limbs.
+///
+/// Added next to every `Quadruped`, `Biped` and `Locomotor` with a `Player` by the `LocomotionSystem`,
+/// which keeps the contacts and the motion up to date; the `OscillatorSystem` keeps the phases.
+#[derive(Debug, Clone, Component)]
+#[storage(HashMapStorage)]
+pub struct Stride {
+    phases: Vec<f32>,
+    contacts: Vec<bool>,
+    velocity: Vector3<f32>,
+    turning: f32,
+    speed_limit: [f32; 2],
+    duty_factor: f32,
+    max_duty_factor: f32,
+    gait: Option<Gait>,
+}
+
+impl Default for Stride {
+    fn default() -> Self {
+        Stride {
+            phases: vec![],
+            contacts: vec![],
+            velocity: Vector3::zeros(),
+            turning: 0.0,
+            speed_limit: [0.0, 0.0],
+            duty_factor: 0.0,
+            max_duty_factor: 0.0,
+            gait: None,
+        }
+    }
+}
+
+impl Stride {
+    /// Phase of the oscillator of each limb in radians, negative in stance and positive in flight.
+    pub fn phases(&self) -> &[f32] {
+        &self.phases
+    }
+
+    /// Whether each foot is on the ground.
+    pub fn contacts(&self) -> &[bool] {
+        &self.contacts
+    }
+
+    /// Whether all feet are on the ground.
+    pub fn is_grounded(&self) -> bool {
+        self.contacts.iter().all(|&contact| contact)
+    }
+
+    /// Velocity of the body in global space.
+    pub fn velocity(&self) -> Vector3<f32> {
+        self.velocity
+    }
+
+    pub fn speed(&self) -> f32 {
+        self.velocity.norm()
+    }
+
+    /// Radians per second the body turns about its up axis, positive to the left.
+    pub fn turning(&self) -> f32 {
+        self.turning
+    }
+
+    /// The speed range of the player moving the body.
+    pub fn speed_limit(&self) -> [f32; 2] {
+        self.speed_limit
+    }
+
+    /// Fraction of the cycle the feet spend on the ground at the current speed.
+    pub fn duty_factor(&self) -> f32 {
+        self.duty_factor
+    }
+
+    /// The duty factor of walking gaits, which drops as the body runs faster.
+    pub fn max_duty_factor(&self) -> f32 {
+        self.max_duty_factor
+    }
+
+    /// The gait of a quadruped, forced or the one its speed settles into; `None` for other bodies.
+    pub fn gait(&self) -> Option<Gait> {
+        self.gait
+    }
+
+    /// Takes the contacts of `limbs`, their `gait` and the motion of `player`, with `matrix` the global matrix of the body.
+    pub(super) fn observe(&mut self, limbs: &[Limb], gait: Option<Gait>, player: &Player, matrix: &Matrix4<f32>) {
+        self.gait = gait;
+        self.contacts = limbs
+            .iter()
+            .map(|limb| match limb.state {
+                State::Stance => true,
+                State::Flight { .. } | State::Airborne | State::Paddling => false,
+            })
+            .collect();
+        self.velocity = matrix.transform_vector(&player.velocity());
+        self.turning = player.spinning().scaled_axis().y;
+        self.speed_limit = player.speed_limit();
+        if let Some(limb) = limbs.first() {
+            self.duty_factor = limb.duty_factor;
+            self.max_duty_factor = limb.config.max_duty_factor;
+        }
+    }
+
+    /// Takes the phases of the oscillators of `limbs`.
+    pub(super) fn observe_phases(&mut self, limbs: &[Limb]) {
+        self.phases = limbs.iter().map(|limb| limb.signal.arg()).collect();
+    }
+}
@@ -0,0 +1,46 @@
+use amethyst::ecs::{Component, prelude::*};
+
+use super::Config;
+
+/// Strides are shortened by at most this fraction, so the limbs keep stepping.
+const MAX_SHORTENING: f32 = 0.9;
+
+/// Marks a legged body whose swinging foot caught on ground rising higher than the `stumble_height` of its config.
+///
+/// Added by the `LocomotionSystem`, which shortens the strides of the body until it recovers and then removes it;
+/// the `BounceSystem` dips the root meanwhile. Stumbling again starts the recovery over.
+#[derive(Debug, Default, Copy, Clone, Component)]
+#[storage(HashMapStorage)]
+pub struct Stumble {
+    elapsed: f32,
+}
+
+impl Stumble {
+    /// Seconds since the body stumbled.
+    pub fn elapsed(&self) -> f32 {
+        self.elapsed
+    }
+
+    pub(super) fn advance(&mut self, delta_seconds: f32) {
+        self.elapsed += delta_seconds;
+    }
+
+    /// How far the body has recovered, from 0 right after stumbling to 1 once recovered.
+    pub fn recovery(&self, config: &Config) -> f32 {
+        if config.stumble_recovery > 0.0 {
+            (self.elapsed / config.stumble_recovery).min(1.0)
+        } else {
+            1.0
+        }
+    }
+
+    /// Share of the full stride length the limbs step.
+    pub fn stride_factor(&self, config: &Config) -> f32 {
+        1.0 - config.stumble_shortening.max(0.0).min(MAX_SHORTENING) * (1.0 - self.recovery(config))
+    }
+
+    /// Distance the root dips below where it would bounce.
+    pub fn dip(&self, config: &Config) -> f32 {
+        config.stumble_dip * (1.0 - self.recovery(config))
+    }
+}
@@ -0,0 +1,254 @@
+use std::f32::consts::TAU;
+
+use amethyst::{
+    core::{math::{Matrix4, Point3, Vector3}, Time, transform::{Parent, Transform}},
+    ecs::{Component, prelude::*},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    systems::{cache::GlobalCache, physics::{Ground, WorldUp}, water::Buoyancy},
+    utils::{determinism::Determinism, warnings::Warnings},
+};
+
+use super::{Airborne, Legged, Quadruped, State, Tracker};
+
+/// When quadrupeds swim and how they move in the water, for a quadruped of scale 1.
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SwimConfig {
+    /// Height of a water surface covering the whole world, in addition to the `Water` bodies; none by default.
+    pub level: Option<f32>,
+    /// Depth under the surface the root floats at.
+    pub float_depth: f32,
+    /// Height the root bobs up and down by while floating.
+    pub bob_height: f32,
+    /// Bobs per second.
+    pub bob_frequency: f32,
+    /// Rate per second at which the root eases toward its floating height.
+    pub stiffness: f32,
+    /// Paddle strokes per second.
+    pub paddle_frequency: f32,
+    /// Radius of the circle the feet paddle along.
+    pub paddle_radius: f32,
+    /// How far the paddles are drawn from the homes to the anchors, in `[0, 1]`.
+    pub tuck: f32,
+    /// Height above the surface the head aims at, at least.
+    pub head_clearance: f32,
+}
+
+impl Default for SwimConfig {
+    fn default() -> Self {
+        SwimConfig {
+            level: None,
+            float_depth: 0.1,
+            bob_height: 0.03,
+            bob_frequency: 0.8,
+            stiffness: 4.0,
+            paddle_frequency: 1.5,
+            paddle_radius: 0.15,
+            tuck: 0.4,
+            head_clearance: 0.3,
+        }
+    }
+}
+
+impl SwimConfig {
+    /// Height of the highest water surface above or below `point`, if any.
+    fn level_at(&self, buoyancy: &Buoyancy, point: &Point3<f32>) -> Option<f32> {
+        let surface = buoyancy.surface_at(point).map(|surface| surface.level);
+        match (self.level, surface) {
+            (Some(a), Some(b)) => Some(a.max(b)),
+            (level, surface) => level.or(surface),
+        }
+    }
+}
+
+/// A quadruped swimming, its root floating and its feet paddling until the ground is shallow enough to stand on.
+#[derive(Debug, Copy, Clone, Component)]
+#[storage(HashMapStorage)]
+pub struct Swimming {
+    /// Height of the root above the ground when it went in, which it needs to stand again.
+    stand_height: f32,
+    /// Height of the surface it swims in.
+    level: f32,
+    elapsed: f32,
+}
+
+impl Swimming {
+    pub fn level(&self) -> f32 {
+        self.level
+    }
+}
+
+/// Switches quadrupeds into swimming once their root is under water, and back once they can stand.
+///
+/// While swimming, the locomotion and bounce leave the quadruped alone: the oscillators keep running at
+/// the paddle frequency and move the feet around circles below their anchors, the root floats under the
+/// surface with a slow bob, and trackers on the body aim at least `head_clearance` above the surface.
+/// Must run after the water system and before the track system.
+#[derive(Default)]
+pub struct SwimSystem;
+
+impl SwimSystem {
+    fn enter(quadruped: &mut Quadruped, level: f32, root: &Point3<f32>, ground: &Ground<'_>) -> Swimming {
+        for limb in quadruped.limbs.iter_mut() {
+            limb.state = State::Paddling;
+            limb.transition = false;
+        }
+        let stand_height = ground.up().height(root) - ground.height_at(root);
+        Swimming { stand_height, level, elapsed: 0.0 }
+    }
+
+    fn leave(quadruped: &mut Quadruped) {
+        // Stand where the feet paddled; locomotion steps them out from there.
+        for limb in quadruped.limbs.iter_mut() {
+            limb.state = State::Stance;
+            limb.transition = false;
+        }
+    }
+
+    fn swim(
+        entity: Entity,
+        quadruped: &mut Quadruped,
+        swimming: &mut Swimming,
+        config: &SwimConfig,
+        up: &WorldUp,
+        delta_seconds: f32,
+        transforms: &mut WriteStorage<'_, Transform>,
+        parents: &ReadStorage<'_, Parent>,
+        cache: &GlobalCache,
+    ) -> Option<()> {
+        swimming.elapsed += delta_seconds;
+        let scale = quadruped.scale().unwrap_or(1.0);
+        let time_scale = scale.sqrt();
+
+        let forward = cache.global_matrix(entity)?.transform_vector(&Vector3::z());
+        let forward = up.planar(&forward).try_normalize(f32::EPSILON).unwrap_or(up.rotation() * Vector3::z());
+        let radius = config.paddle_radius * scale;
+        for limb in quadruped.limbs.iter_mut() {
+            // The oscillator keeps the phase offsets of the gait, with the feet down half of each stroke.
+            limb.angular_velocity = TAU * config.paddle_frequency / time_scale;
+            limb.duty_factor = 0.5;
+
+            // Feet push back along the bottom of the circle and come forward over the top.
+            let ref home = cache.global_position(limb.home)?;
+            let ref anchor = cache.global_position(limb.anchor)?;
+            let center = home + (anchor - home).scale(config.tuck);
+            let paddle = center + forward * -limb.signal.re * radius + up.up() * limb.signal.im * radius;
+            transforms.get_mut(limb.foot)?.set_translation(paddle.coords);
+        }
+
+        let root = quadruped.root();
+        let height = up.height(&cache.global_position(root)?);
+        let bob = (TAU * config.bob_frequency / time_scale * swimming.elapsed).sin();
+        let target = swimming.level - (config.float_depth - config.bob_height * bob) * scale;
+        let decay = 1.0 - (-config.stiffness / time_scale * delta_seconds).exp();
+        let inverse = match parents.get(root) {
+            Some(parent) => transforms.get(parent.entity)?.global_matrix().try_inverse()?,
+            None => Matrix4::identity(),
+        };
+        transforms
+            .get_mut(root)?
+            .append_translation(inverse.transform_vector(&(up.up() * (target - height) * decay)));
+        Some(())
+    }
+
+    /// The swimming body `entity` is on, if any.
+    fn swimmer(entity: Entity, parents: &ReadStorage<'_, Parent>, swimmings: &WriteStorage<'_, Swimming>) -> Option<Entity> {
+        let mut current = Some(entity);
+        while let Some(entity) = current {
+            if swimmings.contains(entity) { return Some(entity); }
+            current = parents.get(entity).map(|parent| parent.entity);
+        }
+        None
+    }
+}
+
+impl<'a> System<'a> for SwimSystem {
+    type SystemData = (
+        Entities<'a>,
+        WriteStorage<'a, Quadruped>,
+        WriteStorage<'a, Swimming>,
+        ReadStorage<'a, Airborne>,
+        WriteStorage<'a, Tracker>,
+        WriteStorage<'a, Transform>,
+        ReadStorage<'a, Parent>,
+        Read<'a, GlobalCache>,
+        Ground<'a>,
+        Read<'a, Buoyancy>,
+        Read<'a, SwimConfig>,
+        Read<'a, Time>,
+        Read<'a, Determinism>,
+        Write<'a, Warnings>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            entities,
+            mut quadrupeds,
+            mut swimmings,
+            airbornes,
+            mut trackers,
+            mut transforms,
+            parents,
+            cache,
+            ground,
+            buoyancy,
+            config,
+            time,
+            determinism,
+            mut warnings,
+        ) = data;
+
+        let mut entered = vec![];
+        let mut left = vec![];
+        for (entity, quadruped, _) in (&*entities, &mut quadrupeds, !&airbornes).join() {
+            let root = match cache.global_position(quadruped.root()) {
+                Some(root) => root,
+                None => {
+                    warnings.skip("swim", entity);
+                    continue;
+                }
+            };
+            let level = config.level_at(&buoyancy, &root);
+            match (swimmings.get_mut(entity), level) {
+                (Some(swimming), Some(level)) if ground.height_at(&root) + swimming.stand_height < level => {
+                    swimming.level = level;
+                }
+                (Some(_), _) => {
+                    Self::leave(quadruped);
+                    left.push(entity);
+                }
+                (None, Some(level)) if ground.up().height(&root) < level => {
+                    entered.push((entity, Self::enter(quadruped, level, &root, &ground)));
+                }
+                (None, _) => {}
+            }
+        }
+        for entity in left {
+            swimmings.remove(entity);
+        }
+        for (entity, swimming) in entered {
+            if swimmings.insert(entity, swimming).is_err() {
+                warnings.skip("swim", entity);
+            }
+        }
+
+        let delta_seconds = determinism.delta_seconds(time.delta_seconds());
+        for (entity, quadruped, swimming) in (&*entities, &mut quadrupeds, &mut swimmings).join() {
+            let swum = Self::swim(entity, quadruped, swimming, &config, ground.up(), delta_seconds, &mut transforms, &parents, &cache);
+            if swum.is_none() {
+                warnings.skip("swim", entity);
+            }
+        }
+
+        for (entity, tracker) in (&*entities, &mut trackers).join() {
+            let surface = Self::swimmer(entity, &parents, &swimmings).and_then(|swimmer| {
+                let scale = quadrupeds.get(swimmer).and_then(Legged::scale).unwrap_or(1.0);
+                swimmings.get(swimmer).map(|swimming| swimming.level + config.head_clearance * scale)
+            });
+            tracker.set_surface(surface);
+        }
+    }
+}
@@ -0,0 +1,161 @@
+use amethyst::{
+    assets::PrefabData,
+    core::{math::Vector3, Transform},
+    derive::SystemDesc,
+    ecs::{Component, prelude::*},
+    error::Error,
+};
+use easer::functions::{Easing, Expo};
+use serde::{Deserialize, Serialize};
+
+use ceramic_derive::{Redirect, Resolve};
+use redirect::{Redirect, Resolve};
+
+use crate::{
+    extras::EntityRef,
+    systems::{
+        particle::Spring,
+        physics::{Ground, Physics, PhysicsData, StepTime},
+    },
+    utils::determinism::Determinism,
+};
+
+use super::Stride;
+
+fn default_clearance() -> f32 {
+    0.02
+}
+
+fn default_ground_stiffness() -> f32 {
+    0.3
+}
+
+#[derive(Debug, Copy, Clone, Component)]
+#[storage(DenseVecStorage)]
+pub struct Tail {
+    player: Entity,
+    stiffness: [f32; 2],
+    clearance: f32,
+    ground_stiffness: f32,
+    swing: f32,
+    droop: f32,
+    /// Local translation of the spring target, captured on the first update.
+    rest: Option<Vector3<f32>>,
+}
+
+impl Tail {
+    /// The legged body whose `Stride` the tail follows.
+    pub fn player(&self) -> Entity {
+        self.player
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Redirect, Resolve)]
+#[resolve(Tail)]
+#[resolve(init(rest: None))]
+pub struct TailPrefab {
+    pub player: EntityRef,
+    #[redirect(skip)]
+    pub stiffness: [f32; 2],
+    /// Height above the ground the tail particle is kept at.
+    #[redirect(skip)]
+    #[serde(default = "default_clearance")]
+    pub clearance: f32,
+    /// Fraction of the ground penetration that is corrected per step.
+    #[redirect(skip)]
+    #[serde(default = "default_ground_stiffness")]
+    pub ground_stiffness: f32,
+    /// Sideways shift of the spring target out of a turn, per radian per second the player turns.
+    #[redirect(skip)]
+    #[serde(default)]
+    pub swing: f32,
+    /// Downward shift of the spring target per unit of player speed.
+    #[redirect(skip)]
+    #[serde(default)]
+    pub droop: f32,
+}
+
+impl<'a> PrefabData<'a> for TailPrefab {
+    type SystemData = WriteStorage<'a, Tail>;
+    type Result = ();
+
+    fn add_to_entity(
+        &self,
+        entity: Entity,
+        data: &mut Self::SystemData,
+        entities: &[Entity],
+        _children: &[Entity],
+    ) -> Result<Self::Result, Error> {
+        data.insert(entity, self.resolve(entities)).map(|_| ()).map_err(Into::into)
+    }
+}
+
+/// Stiffens the tail springs with the speed of the body, swings their targets out of turns
+/// and lowers them with speed, and pushes tail particles that sink into the ground back above it.
+#[derive(Default, SystemDesc)]
+pub struct TailSystem;
+
+impl TailSystem {
+    /// Offsets the spring target from its rest along the local x and y axes of its parent.
+    fn move_target(tail: &mut Tail, spring: &Spring, stride: &Stride, transforms: &mut WriteStorage<'_, Transform>) -> Option<()> {
+        if tail.swing == 0.0 && tail.droop == 0.0 { return Some(()); }
+
+        let transform = transforms.get_mut(spring.target())?;
+        let rest = *tail.rest.get_or_insert(*transform.translation());
+
+        // Turning left spins about +y, which swings the tail out to the right, along -x.
+        let ref offset = Vector3::new(-tail.swing * stride.turning(), -tail.droop * stride.speed(), 0.0);
+        transform.set_translation(rest + offset);
+        Some(())
+    }
+
+    /// Applies a soft penalty to the particle of `entity` if it is closer to the ground than `clearance`.
+    fn avoid_ground(entity: Entity, tail: &Tail, physics: &mut PhysicsData<'_>, ground: &Ground<'_>, delta_seconds: f32) -> Option<()> {
+        let ref position = physics.position(entity)?;
+        let up = ground.up();
+        let height = ground.height_at(position) + tail.clearance;
+        let current = up.height(position);
+        if current >= height { return Some(()); }
+
+        let ref normal = ground.normal_at(position);
+        let depth = (height - current) * normal.dot(&up.up());
+        // Also cancel the velocity into the ground, so the penalty doesn't bounce the particle.
+        let into = physics.velocity(entity)?.dot(normal).min(0.0);
+        let ref impulse = normal.scale(depth * tail.ground_stiffness / delta_seconds - into);
+        physics.apply_impulse(entity, impulse);
+        Some(())
+    }
+}
+
+impl<'a> System<'a> for TailSystem {
+    type SystemData = (
+        Entities<'a>,
+        ReadStorage<'a, Stride>,
+        WriteStorage<'a, Tail>,
+        WriteStorage<'a, Spring>,
+        WriteStorage<'a, Transform>,
+        PhysicsData<'a>,
+        Ground<'a>,
+        Read<'a, StepTime>,
+        Read<'a, Determinism>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (entities, strides, mut tails, mut springs, mut transforms, mut physics, ground, time, determinism) = data;
+
+        let delta_seconds = determinism.step_seconds(time.delta_seconds());
+        for (entity, tail, spring) in (&*entities, &mut tails, &mut springs).join() {
+            if let Some(stride) = strides.get(tail.player) {
+                let speed = stride.speed();
+                let [min, max] = stride.speed_limit();
+                let [loose, tight] = tail.stiffness;
+                let stiffness = Expo::ease_in(speed - min, loose, tight - loose, max - min);
+                spring.set_stiffness(stiffness);
+                Self::move_target(tail, spring, stride, &mut transforms);
+            }
+            if delta_seconds > 0.0 {
+                Self::avoid_ground(entity, tail, &mut physics, &ground, delta_seconds);
+            }
+        }
+    }
+}
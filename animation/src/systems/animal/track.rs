@@ -0,0 +1,307 @@
+use std::f32::{consts::FRAC_PI_2, EPSILON};
+
+use amethyst::{
+    assets::PrefabData,
+    core::{math::UnitQuaternion, transform::ParentHierarchy, Time, Transform},
+    ecs::{Component, prelude::*, SystemData},
+    error::Error,
+    shrev::{EventChannel, ReaderId as EventReaderId},
+};
+use serde::{Deserialize, Serialize};
+
+use ceramic_derive::{Redirect, Resolve};
+use redirect::{Redirect, Resolve};
+
+use crate::{
+    extras::EntityRef,
+    systems::physics::WorldUp,
+    utils::{determinism::Determinism, transform::{mark_descendants, TransformTrait}, warnings::Warnings},
+};
+
+#[derive(Debug, Copy, Clone, Component)]
+#[storage(HashMapStorage)]
+pub struct Tracker {
+    target: Entity,
+    limit: Option<f32>,
+    speed: f32,
+    dead_zone: Option<f32>,
+    max_angular_velocity: Option<f32>,
+    rotation: Option<UnitQuaternion<f32>>,
+    /// Whether the joint has caught up with the target and needs no further rotation.
+    settled: bool,
+    retarget: Option<Retarget>,
+    /// Lowest height the target is aimed at, set while the body swims to keep the head above the water.
+    surface: Option<f32>,
+}
+
+/// Crossfade from the previous target of a tracker to its current one.
+#[derive(Debug, Copy, Clone)]
+struct Retarget {
+    previous: Entity,
+    duration: f32,
+    elapsed: f32,
+}
+
+impl Tracker {
+    pub fn target(&self) -> Entity {
+        self.target
+    }
+
+    /// Switch to a new target, blending from the old one over `duration` seconds.
+    pub fn retarget(&mut self, target: Entity, duration: f32) {
+        if target == self.target { return; }
+        if duration > 0.0 {
+            self.retarget.replace(Retarget { previous: self.target, duration, elapsed: 0.0 });
+        } else {
+            self.retarget = None;
+        }
+        self.target = target;
+        self.settled = false;
+    }
+
+    /// Drops the crossfade from the previous target if `dangling` holds for it.
+    pub fn forget(&mut self, dangling: &dyn Fn(Entity) -> bool) {
+        if self.retarget.map_or(false, |retarget| dangling(retarget.previous)) {
+            self.retarget = None;
+            self.settled = false;
+        }
+    }
+
+    /// Aim at least as high as `surface` along the `WorldUp`, or at the target itself if `None`.
+    pub fn set_surface(&mut self, surface: Option<f32>) {
+        if surface == self.surface { return; }
+        self.surface = surface;
+        self.settled = false;
+    }
+}
+
+/// Requests for the `TrackSystem`, sent through an `EventChannel<TrackerEvent>`.
+#[derive(Debug, Copy, Clone)]
+pub enum TrackerEvent {
+    /// Make the tracker on `entity` follow `target`, crossfading over `duration` seconds.
+    Retarget { entity: Entity, target: Entity, duration: f32 },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Redirect, Resolve)]
+#[resolve(Tracker)]
+#[resolve(init(
+    dead_zone: self.dead_zone.map(f32::to_radians),
+    max_angular_velocity: self.max_angular_velocity.map(f32::to_radians),
+    rotation: None,
+    settled: false,
+    retarget: None,
+    surface: None,
+))]
+pub struct TrackerPrefab {
+    pub target: EntityRef,
+    #[redirect(skip)]
+    pub limit: Option<f32>,
+    #[redirect(skip)]
+    pub speed: f32,
+    /// Once settled, the joint ignores target movements within this angle (degrees).
+    #[redirect(skip)]
+    #[serde(default)]
+    #[resolve(skip)]
+    pub dead_zone: Option<f32>,
+    /// Maximum rotation speed of the joint (degrees per second).
+    #[redirect(skip)]
+    #[serde(default)]
+    #[resolve(skip)]
+    pub max_angular_velocity: Option<f32>,
+}
+
+impl<'a> PrefabData<'a> for TrackerPrefab {
+    type SystemData = WriteStorage<'a, Tracker>;
+    type Result = ();
+
+    fn add_to_entity(
+        &self,
+        entity: Entity,
+        data: &mut Self::SystemData,
+        entities: &[Entity],
+        _children: &[Entity],
+    ) -> Result<Self::Result, Error> {
+        data.insert(entity, self.resolve(entities)).map(|_| ()).map_err(Into::into)
+    }
+}
+
+/// Rotations smaller than this angle are considered settled.
+const SETTLE_ANGLE: f32 = 1.0e-4;
+
+/// Trackers are solved for all entities every this many frames, regardless of change detection.
+const FULL_SOLVE_INTERVAL: usize = 60;
+
+/// Rotates trackers toward their targets.
+///
+/// Only trackers whose joint or target transform changed, or which have not yet settled, are processed.
+#[derive(Default)]
+pub struct TrackSystem {
+    reader_id: Option<ReaderId<ComponentEvent>>,
+    event_reader_id: Option<EventReaderId<TrackerEvent>>,
+    dirty: BitSet,
+    frame: usize,
+}
+
+impl TrackSystem {
+    fn look_rotation(
+        entity: Entity,
+        target: Entity,
+        surface: Option<f32>,
+        up: &WorldUp,
+        transforms: &WriteStorage<'_, Transform>,
+    ) -> Option<UnitQuaternion<f32>> {
+        let mut target = transforms.get(target)?.global_position();
+        if let Some(surface) = surface {
+            if up.height(&target) < surface {
+                target = up.with_height(&target, surface);
+            }
+        }
+        let joint = transforms.get(entity)?.global_position();
+        let ref target = target - joint;
+
+        let transform = transforms.get(entity)?.global_view_matrix();
+        let ref target = transform.transform_vector(target);
+        let ref up = transform.transform_vector(&up.up());
+
+        // The hack here is that the direction of joints is y axis, not z axis by default.
+        Some(UnitQuaternion::from_euler_angles(FRAC_PI_2, 0.0, 0.0) * UnitQuaternion::face_towards(target, up))
+    }
+
+    fn process_tracker(
+        entity: Entity,
+        tracker: &mut Tracker,
+        delta_seconds: f32,
+        up: &WorldUp,
+        transforms: &mut WriteStorage<'_, Transform>,
+    ) -> Option<()> {
+        let mut target = Self::look_rotation(entity, tracker.target, tracker.surface, up, transforms)?;
+
+        // Crossfade from the previous target, with a smooth step to avoid popping at either end.
+        if let Some(ref mut retarget) = tracker.retarget {
+            retarget.elapsed += delta_seconds;
+            let t = (retarget.elapsed / retarget.duration).min(1.0);
+            let previous = Self::look_rotation(entity, retarget.previous, tracker.surface, up, transforms);
+            if t >= 1.0 || previous.is_none() {
+                tracker.retarget = None;
+            } else if let Some(previous) = previous {
+                let t = t * t * (3.0 - 2.0 * t);
+                target = previous.try_slerp(&target, t, EPSILON).unwrap_or(target);
+            }
+        }
+
+        let rotation = tracker.rotation.unwrap_or_else(UnitQuaternion::identity);
+        if let Some((axis, angle)) = (rotation.inverse() * target).axis_angle() {
+            if let Some(limit) = tracker.limit {
+                let angle = angle.min(limit);
+                let delta = UnitQuaternion::from_axis_angle(&axis, angle);
+                target = delta * rotation * rotation;
+            }
+        }
+
+        let current = transforms.get(entity)?.rotation().clone();
+        if let Some(dead_zone) = tracker.dead_zone {
+            if tracker.settled && current.angle_to(&target) < dead_zone { return Some(()); }
+        }
+
+        let interpolation = 1.0 - (-tracker.speed * delta_seconds).exp();
+        tracker.settled = true;
+        if let Some(mut rotation) = current.try_slerp(&target, interpolation, EPSILON) {
+            if let Some(max_angular_velocity) = tracker.max_angular_velocity {
+                let max_angle = max_angular_velocity * delta_seconds;
+                let angle = current.angle_to(&rotation);
+                if angle > max_angle {
+                    rotation = current.try_slerp(&rotation, max_angle / angle, EPSILON).unwrap_or(rotation);
+                }
+            }
+
+            if current.angle_to(&rotation) > SETTLE_ANGLE {
+                tracker.settled = false;
+                transforms.get_mut(entity)?.set_rotation(rotation);
+            }
+        }
+
+        Some(())
+    }
+}
+
+impl<'a> System<'a> for TrackSystem {
+    type SystemData = (
+        Entities<'a>,
+        WriteStorage<'a, Transform>,
+        ReadExpect<'a, ParentHierarchy>,
+        WriteStorage<'a, Tracker>,
+        Read<'a, EventChannel<TrackerEvent>>,
+        Read<'a, Time>,
+        Read<'a, Determinism>,
+        Read<'a, WorldUp>,
+        Write<'a, Warnings>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            entities,
+            mut transforms,
+            hierarchy,
+            mut trackers,
+            events,
+            time,
+            determinism,
+            up,
+            mut warnings,
+        ) = data;
+
+        let reader_id = self.reader_id
+            .as_mut()
+            .expect("`TrackSystem::setup` was not called before `TrackSystem::run`");
+        self.dirty.clear();
+        for event in transforms.channel().read(reader_id) {
+            match event {
+                ComponentEvent::Inserted(id) | ComponentEvent::Modified(id) => { self.dirty.add(*id); }
+                ComponentEvent::Removed(_) => {}
+            }
+        }
+        mark_descendants(&mut self.dirty, &hierarchy);
+
+        let event_reader_id = self.event_reader_id
+            .as_mut()
+            .expect("`TrackSystem::setup` was not called before `TrackSystem::run`");
+        for event in events.read(event_reader_id) {
+            match *event {
+                TrackerEvent::Retarget { entity, target, duration } => {
+                    if let Some(tracker) = trackers.get_mut(entity) {
+                        tracker.retarget(target, duration);
+                    }
+                }
+            }
+        }
+
+        for (tracker, transform) in (&mut trackers, &transforms).join() {
+            if tracker.rotation.is_none() {
+                let rotation = transform.rotation();
+                tracker.rotation.replace(rotation.clone());
+            }
+        }
+
+        let full_solve = self.frame % FULL_SOLVE_INTERVAL == 0;
+        self.frame = self.frame.wrapping_add(1);
+
+        let delta_seconds = determinism.delta_seconds(time.delta_seconds());
+        for (entity, tracker) in (&*entities, &mut trackers).join() {
+            let changed = self.dirty.contains(entity.id()) || self.dirty.contains(tracker.target.id());
+            if full_solve || changed || !tracker.settled || tracker.retarget.is_some() {
+                if Self::process_tracker(entity, tracker, delta_seconds, &up, &mut transforms).is_none() {
+                    warnings.skip("track", entity);
+                }
+            }
+        }
+
+        // Discard events caused by our own writes.
+        transforms.channel().read(reader_id).for_each(drop);
+    }
+
+    fn setup(&mut self, world: &mut World) {
+        Self::SystemData::setup(world);
+        self.reader_id = Some(WriteStorage::<Transform>::fetch(world).register_reader());
+        self.event_reader_id = Some(world.fetch_mut::<EventChannel<TrackerEvent>>().register_reader());
+    }
+}
@@ -0,0 +1,98 @@
+use std::{f32::consts::FRAC_PI_2, marker::PhantomData};
+
+use amethyst::{
+    core::math::{Point3, UnitQuaternion},
+    ecs::prelude::*,
+    renderer::{debug_drawing::DebugLines, palette::Srgba},
+};
+
+use crate::{
+    systems::{cache::GlobalCache, physics::{Ground, WorldUp}},
+    utils::warnings::Warnings,
+};
+
+use super::{Legged, Limb};
+
+/// Draws where each limb can put its foot, to help tuning `step_limit`, `stance_height` and `reaches`.
+#[derive(Debug, Default, Copy, Clone)]
+pub struct LimbWorkspace {
+    pub enabled: bool,
+}
+
+/// Radius of the disc where the plane across `up` at `height` cuts the sphere of `reach` around `anchor`,
+/// or `None` if the plane is out of reach.
+fn reach_radius(up: &WorldUp, anchor: &Point3<f32>, height: f32, reach: f32) -> Option<f32> {
+    let depth = up.height(anchor) - height;
+    if depth.abs() > reach { return None; }
+    Some((reach * reach - depth * depth).sqrt())
+}
+
+/// Draws the workspace of the limbs of `T` while the `LimbWorkspace` is enabled.
+///
+/// For each limb, the sphere its anchor reaches within is drawn in blue, and the disc where it meets the ground
+/// at stance height in cyan. Around the home, the shortest and longest step radii bound the green annulus
+/// the feet land in. Everything is drawn from the live config, so it follows presets and tuning as they change.
+pub struct WorkspaceSystem<T> {
+    marker: PhantomData<T>,
+}
+
+impl<T> Default for WorkspaceSystem<T> {
+    fn default() -> Self {
+        WorkspaceSystem { marker: PhantomData }
+    }
+}
+
+impl<T: Legged> WorkspaceSystem<T> {
+    fn draw(limb: &Limb, cache: &GlobalCache, ground: &Ground<'_>, debug_lines: &mut DebugLines) -> Option<()> {
+        let ref anchor = cache.global_position(limb.anchor)?;
+        let ref home = cache.global_position(limb.home)?;
+        let ref config = limb.config;
+        let up = ground.up();
+        let rotation = up.rotation() * UnitQuaternion::from_euler_angles(FRAC_PI_2, 0.0, 0.0);
+
+        let center = up.with_height(home, ground.height_at(home) + config.stance_height);
+        let [min_step, max_step] = config.step_limit;
+        let color = Srgba::new(0.0, 1.0, 0.0, 1.0);
+        debug_lines.draw_rotated_circle(center, min_step / 2.0, 16, rotation, color);
+        debug_lines.draw_rotated_circle(center, max_step / 2.0, 16, rotation, color);
+
+        // Limbs are only measured once calibrated.
+        let reach = limb.max_reach()?;
+        let color = Srgba::new(0.2, 0.4, 1.0, 0.5);
+        debug_lines.draw_sphere(*anchor, reach, 8, 8, color);
+
+        let height = ground.height_at(anchor) + config.stance_height;
+        if let Some(radius) = reach_radius(up, anchor, height, reach) {
+            let center = up.with_height(anchor, height);
+            let color = Srgba::new(0.0, 1.0, 1.0, 1.0);
+            debug_lines.draw_rotated_circle(center, radius, 24, rotation, color);
+            debug_lines.draw_line(*anchor, center, color);
+        }
+        Some(())
+    }
+}
+
+impl<'a, T: Legged> System<'a> for WorkspaceSystem<T> {
+    type SystemData = (
+        Entities<'a>,
+        ReadStorage<'a, T>,
+        Read<'a, GlobalCache>,
+        Ground<'a>,
+        Read<'a, LimbWorkspace>,
+        Write<'a, DebugLines>,
+        Write<'a, Warnings>,
+    );
+
+    fn run(&mut self, (entities, bodies, cache, ground, workspace, mut debug_lines, mut warnings): Self::SystemData) {
+        if !workspace.enabled { return; }
+
+        for (entity, body) in (&*entities, &bodies).join() {
+            for limb in body.limbs() {
+                let drawn = Self::draw(limb, &cache, &ground, &mut debug_lines);
+                if drawn.is_none() && limb.length.is_some() {
+                    warnings.skip("workspace", entity);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,237 @@
+use std::f32::EPSILON;
+
+use amethyst::{
+    assets::PrefabData,
+    core::{
+        math::{Matrix4, Point3, UnitQuaternion, Vector3},
+        Time,
+        transform::{Parent, Transform},
+    },
+    derive::SystemDesc,
+    ecs::{Component, prelude::*},
+    error::Error,
+};
+use serde::{Deserialize, Serialize};
+
+use ceramic_derive::{Redirect, Resolve};
+use redirect::{Redirect, Resolve};
+
+use crate::{
+    extras::EntityRef,
+    utils::{determinism::Determinism, transform::TransformTrait, warnings::Warnings},
+};
+
+const GRAVITY: f32 = 9.81;
+
+/// Global wind that sways appendages, in units per second.
+#[derive(Debug, Default, Copy, Clone, Serialize, Deserialize)]
+pub struct Wind {
+    pub velocity: [f32; 3],
+}
+
+/// Secondary motion of a short chain of nodes, e.g. ears and whiskers.
+///
+/// The nodes are simulated as Verlet particles pulled toward their rest pose,
+/// independent of the physics backend, and kept out of the head sphere.
+#[derive(Debug, Clone, Component)]
+#[storage(HashMapStorage)]
+pub struct Appendage {
+    nodes: Vec<Entity>,
+    head: Option<Entity>,
+    head_radius: f32,
+    stiffness: f32,
+    damp: f32,
+    gravity: f32,
+    wind: f32,
+    /// Local transforms of the nodes as authored, captured on the first update.
+    rest: Vec<Transform>,
+    /// Current and previous global positions of the nodes.
+    particles: Vec<(Point3<f32>, Point3<f32>)>,
+}
+
+impl Appendage {
+    pub fn nodes(&self) -> &[Entity] {
+        &self.nodes
+    }
+
+    pub fn head(&self) -> Option<Entity> {
+        self.head
+    }
+}
+
+fn default_damp() -> f32 {
+    0.1
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Redirect, Resolve)]
+#[resolve(Appendage)]
+#[resolve(init(rest: vec![], particles: vec![]))]
+pub struct AppendagePrefab {
+    /// The chain from its base to its tip, each node a child of the one before.
+    pub nodes: Vec<EntityRef>,
+    /// The node whose origin is the center of the head sphere the chain can't enter.
+    #[serde(default)]
+    pub head: Option<EntityRef>,
+    #[redirect(skip)]
+    #[serde(default)]
+    pub head_radius: f32,
+    /// Fraction of the distance to the rest pose recovered per frame.
+    #[redirect(skip)]
+    pub stiffness: f32,
+    /// Fraction of the velocity lost per frame.
+    #[redirect(skip)]
+    #[serde(default = "default_damp")]
+    pub damp: f32,
+    /// Scale of the gravity acting on the chain.
+    #[redirect(skip)]
+    #[serde(default)]
+    pub gravity: f32,
+    /// Scale of the `Wind` acting on the chain.
+    #[redirect(skip)]
+    #[serde(default)]
+    pub wind: f32,
+}
+
+impl<'a> PrefabData<'a> for AppendagePrefab {
+    type SystemData = WriteStorage<'a, Appendage>;
+    type Result = ();
+
+    fn add_to_entity(
+        &self,
+        entity: Entity,
+        data: &mut Self::SystemData,
+        entities: &[Entity],
+        _children: &[Entity],
+    ) -> Result<Self::Result, Error> {
+        data.insert(entity, self.resolve(entities)).map(|_| ()).map_err(Into::into)
+    }
+}
+
+#[derive(Default, SystemDesc)]
+pub struct AppendageSystem;
+
+impl AppendageSystem {
+    fn update(
+        appendage: &mut Appendage,
+        transforms: &mut WriteStorage<'_, Transform>,
+        parents: &ReadStorage<'_, Parent>,
+        acceleration: &Vector3<f32>,
+        delta_seconds: f32,
+    ) -> Option<()> {
+        let base = *appendage.nodes.first()?;
+        let mut frame = match parents.get(base) {
+            Some(parent) => *transforms.get(parent.entity)?.global_matrix(),
+            None => Matrix4::identity(),
+        };
+
+        if appendage.rest.is_empty() {
+            appendage.rest = appendage.nodes
+                .iter()
+                .map(|&node| transforms.get(node).cloned())
+                .collect::<Option<_>>()?;
+        }
+
+        // The rest pose under the current base, which the particles are pulled toward.
+        let mut goal = frame;
+        let goals = appendage.rest
+            .iter()
+            .map(|rest| {
+                goal *= rest.matrix();
+                goal.transform_point(&Point3::origin())
+            })
+            .collect::<Vec<_>>();
+
+        if appendage.particles.len() != goals.len() {
+            appendage.particles = goals.iter().map(|&goal| (goal, goal)).collect();
+        }
+
+        let head = appendage.head
+            .and_then(|head| transforms.get(head))
+            .map(|transform| transform.global_position());
+        let (stiffness, damp, head_radius) = (appendage.stiffness, appendage.damp, appendage.head_radius);
+        let particles = &mut appendage.particles;
+
+        particles[0] = (goals[0], goals[0]);
+        for index in 1..particles.len() {
+            let (current, previous) = particles[index];
+            let mut next = current
+                + (current - previous).scale(1.0 - damp)
+                + acceleration.scale(delta_seconds * delta_seconds);
+            next += (goals[index] - next).scale(stiffness);
+
+            // Keep the segment lengths of the rest pose.
+            let (parent, _) = particles[index - 1];
+            let length = (goals[index] - goals[index - 1]).norm();
+            let ref offset = next - parent;
+            if let Some(direction) = offset.try_normalize(EPSILON) {
+                next = parent + direction.scale(length);
+            }
+
+            if let Some(ref center) = head {
+                let ref offset = next - center;
+                let distance = offset.norm();
+                if distance < head_radius && distance > EPSILON {
+                    next = center + offset.scale(head_radius / distance);
+                }
+            }
+            particles[index] = (next, current);
+        }
+
+        // Rotate each node so that its child points at the simulated particle.
+        for (index, rest) in appendage.rest.iter().enumerate() {
+            let transform = transforms.get_mut(appendage.nodes[index])?;
+            let mut local = rest.clone();
+            if let Some(child) = appendage.rest.get(index + 1) {
+                let ref inverse = frame.try_inverse()?;
+                let ref posed = rest.matrix().transform_vector(child.translation());
+                let (from, _) = particles[index];
+                let (to, _) = particles[index + 1];
+                let ref simulated = inverse.transform_vector(&(to - from));
+                let rotation = UnitQuaternion::rotation_between(posed, simulated)
+                    .unwrap_or_else(UnitQuaternion::identity);
+                local.set_rotation(rotation * rest.rotation());
+            }
+            frame *= local.matrix();
+            transform.set_rotation(*local.rotation());
+        }
+        Some(())
+    }
+}
+
+impl<'a> System<'a> for AppendageSystem {
+    type SystemData = (
+        Entities<'a>,
+        WriteStorage<'a, Appendage>,
+        WriteStorage<'a, Transform>,
+        ReadStorage<'a, Parent>,
+        Read<'a, Wind>,
+        Read<'a, Time>,
+        Read<'a, Determinism>,
+        Write<'a, Warnings>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            entities,
+            mut appendages,
+            mut transforms,
+            parents,
+            wind,
+            time,
+            determinism,
+            mut warnings,
+        ) = data;
+
+        let delta_seconds = determinism.delta_seconds(time.delta_seconds());
+        if delta_seconds <= 0.0 { return; }
+
+        let ref wind = Vector3::from(wind.velocity);
+        for (entity, appendage) in (&*entities, &mut appendages).join() {
+            let ref acceleration = Vector3::new(0.0, -GRAVITY, 0.0).scale(appendage.gravity)
+                + wind.scale(appendage.wind);
+            if Self::update(appendage, &mut transforms, &parents, acceleration, delta_seconds).is_none() {
+                warnings.skip("appendage", entity);
+            }
+        }
+    }
+}
@@ -0,0 +1,155 @@
+use amethyst::{
+    assets::PrefabData,
+    core::{math::{Matrix4, Point3, Vector3}, Transform},
+    derive::{PrefabData, SystemDesc},
+    ecs::{Component, prelude::*},
+    error::Error,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    systems::{physics::Ground, player::Steering},
+    utils::transform::TransformTrait,
+};
+
+/// A static box creatures steered by a behavior walk around, e.g. a wall or a rock.
+#[derive(Debug, Copy, Clone, Serialize, Deserialize, PrefabData, Component)]
+#[prefab(Component)]
+#[storage(HashMapStorage)]
+pub struct Obstacle {
+    /// Half size of the box along the local axes of the node.
+    pub extent: [f32; 3],
+}
+
+impl Obstacle {
+    /// Distance along the ray to the box with the global `matrix`, if it is hit within `max_distance`.
+    fn raycast(&self, matrix: &Matrix4<f32>, origin: &Point3<f32>, direction: &Vector3<f32>, max_distance: f32) -> Option<f32> {
+        // An affine map keeps distances along the ray, so the slabs can be tested in the space of the box.
+        let inverse = matrix.try_inverse()?;
+        let origin = inverse.transform_point(origin);
+        let direction = inverse.transform_vector(direction);
+
+        let (mut near, mut far) = (0.0, max_distance);
+        for axis in 0..3 {
+            let extent = self.extent[axis];
+            if direction[axis].abs() < f32::EPSILON {
+                if origin[axis].abs() > extent { return None; }
+                continue;
+            }
+            let a = (-extent - origin[axis]) / direction[axis];
+            let b = (extent - origin[axis]) / direction[axis];
+            near = a.min(b).max(near);
+            far = a.max(b).min(far);
+            if near > far { return None; }
+        }
+        Some(near)
+    }
+}
+
+/// How creatures steered by a behavior feel for obstacles ahead: whiskers fanning out from their body,
+/// cast against `Obstacle`s and rising ground.
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Avoidance {
+    pub whiskers: usize,
+    /// Angle in radians between the heading and the outermost whiskers.
+    pub spread: f32,
+    pub length: f32,
+    /// Height above the ground the whiskers are cast at, so only ground rising higher blocks them.
+    pub height: f32,
+    /// Turn added by a whisker touching an obstacle at its root, less the further out it touches.
+    pub strength: f32,
+}
+
+impl Default for Avoidance {
+    fn default() -> Self {
+        Avoidance {
+            whiskers: 3,
+            spread: 0.6,
+            length: 3.0,
+            height: 0.3,
+            strength: 1.0,
+        }
+    }
+}
+
+/// Bends the `Steering` behaviors give to creatures away from obstacles ahead.
+///
+/// Must run after the `BehaviorSystem` and before the `PlayerSystem`.
+#[derive(Default, SystemDesc)]
+pub struct AvoidanceSystem;
+
+impl AvoidanceSystem {
+    /// How close the nearest obstacle is along each whisker, from 0 for none to 1 at the root,
+    /// with the angle of the whisker, positive to the left.
+    fn feel(
+        transform: &Transform,
+        avoidance: &Avoidance,
+        obstacles: &[(Matrix4<f32>, Obstacle)],
+        ground: &Ground<'_>,
+    ) -> Vec<(f32, f32)> {
+        let position = transform.global_position();
+        let heading = transform.global_matrix().transform_vector(&Vector3::z());
+        let up = ground.up();
+        let heading = match up.planar(&heading).try_normalize(f32::EPSILON) {
+            Some(heading) => heading,
+            None => return vec![],
+        };
+        let origin = up.with_height(&position, ground.height_at(&position) + avoidance.height);
+
+        (0..avoidance.whiskers)
+            .map(|index| {
+                let angle = match avoidance.whiskers {
+                    1 => 0.0,
+                    count => avoidance.spread * (2.0 * index as f32 / (count - 1) as f32 - 1.0),
+                };
+                let ref direction = up.yaw(angle) * heading;
+                let distance = obstacles
+                    .iter()
+                    .filter_map(|(matrix, obstacle)| obstacle.raycast(matrix, &origin, direction, avoidance.length))
+                    .chain(ground.raycast(&origin, direction, avoidance.length).map(|hit| hit.distance))
+                    .fold(avoidance.length, f32::min);
+                (angle, 1.0 - distance / avoidance.length)
+            })
+            .collect()
+    }
+}
+
+impl<'a> System<'a> for AvoidanceSystem {
+    type SystemData = (
+        ReadStorage<'a, Transform>,
+        ReadStorage<'a, Obstacle>,
+        WriteStorage<'a, Steering>,
+        Ground<'a>,
+        Read<'a, Avoidance>,
+    );
+
+    fn run(&mut self, (transforms, obstacles, mut steerings, ground, avoidance): Self::SystemData) {
+        let obstacles = (&transforms, &obstacles)
+            .join()
+            .map(|(transform, obstacle)| (*transform.global_matrix(), *obstacle))
+            .collect::<Vec<_>>();
+
+        for (transform, steering) in (&transforms, &mut steerings).join() {
+            if steering.forward <= 0.0 { continue; }
+
+            let whiskers = Self::feel(transform, &avoidance, &obstacles, &ground);
+            let left = whiskers.iter().filter(|(angle, _)| *angle > 0.0).map(|(_, proximity)| proximity).sum::<f32>();
+            let right = whiskers.iter().filter(|(angle, _)| *angle < 0.0).map(|(_, proximity)| proximity).sum::<f32>();
+            // Whiskers on a side push to the other; the one ahead pushes toward the freer side.
+            let turn = whiskers
+                .iter()
+                .map(|&(angle, proximity)| {
+                    let ahead = angle.abs() < f32::EPSILON;
+                    let away = if (angle > 0.0 && !ahead) || (ahead && left > right) { -1.0 } else { 1.0 };
+                    away * proximity * avoidance.strength
+                })
+                .sum::<f32>();
+
+            steering.turn = (steering.turn + turn).max(-1.0).min(1.0);
+            if steering.turn.abs() >= 1.0 {
+                steering.forward = 0.0;
+            }
+        }
+    }
+}
@@ -0,0 +1,220 @@
+use std::{
+    cmp::Ordering,
+    f32::consts::{FRAC_PI_2, PI},
+};
+
+use amethyst::{
+    assets::PrefabData,
+    core::{math::{Point3, Vector3}, Transform},
+    derive::SystemDesc,
+    ecs::{Component, prelude::*},
+    error::Error,
+};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    systems::{
+        player::{Player, Steering},
+        spatial::{Indexed, SpatialIndex},
+    },
+    utils::{random::{Random, Seed}, transform::TransformTrait, warnings::Warnings},
+};
+
+/// How a creature reacts to the player.
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+pub enum Temperament {
+    /// Keeps within `distance` of the player.
+    Companion { distance: f32 },
+    /// Runs away once the player comes within `distance`.
+    Prey { distance: f32 },
+    /// Ignores the player, roaming between random points within `radius` of where it started.
+    Wander { radius: f32 },
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Activity {
+    Graze,
+    Follow(Entity),
+    Flee(Entity),
+    Wander,
+}
+
+/// An AI controller steering the `Player` of a creature instead of the input.
+#[derive(Debug, Copy, Clone, Component)]
+#[storage(HashMapStorage)]
+pub struct Behavior {
+    temperament: Temperament,
+    sight: f32,
+    activity: Activity,
+    /// Where a wandering creature started, and the point it heads for.
+    origin: Option<Point3<f32>>,
+    waypoint: Option<Point3<f32>>,
+}
+
+impl Behavior {
+    pub fn activity(&self) -> Activity {
+        self.activity
+    }
+}
+
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+pub struct BehaviorPrefab {
+    pub temperament: Temperament,
+    /// How far the creature notices the player.
+    pub sight: f32,
+}
+
+impl<'a> PrefabData<'a> for BehaviorPrefab {
+    type SystemData = (
+        WriteStorage<'a, Behavior>,
+        WriteStorage<'a, Steering>,
+        Write<'a, Random>,
+        WriteStorage<'a, Seed>,
+    );
+    type Result = ();
+
+    fn add_to_entity(
+        &self,
+        entity: Entity,
+        (behaviors, steerings, random, seeds): &mut Self::SystemData,
+        _: &[Entity],
+        _: &[Entity],
+    ) -> Result<Self::Result, Error> {
+        let component = Behavior {
+            temperament: self.temperament,
+            sight: self.sight,
+            activity: Activity::Graze,
+            origin: None,
+            waypoint: None,
+        };
+        behaviors.insert(entity, component)?;
+        steerings.insert(entity, Steering::default())?;
+        Seed::assign(entity, random, seeds)?;
+        Ok(())
+    }
+}
+
+/// Distance at which a wandering creature counts its waypoint as reached.
+const WAYPOINT_REACH: f32 = 1.0;
+
+/// Steers creatures with a `Behavior` relative to the players driven by the input.
+///
+/// Must run before the `PlayerSystem`. Input driven players are marked `Indexed` so that
+/// the `SpatialIndex` of the previous frame can be used to find them.
+#[derive(Default, SystemDesc)]
+pub struct BehaviorSystem;
+
+impl BehaviorSystem {
+    fn select_activity(
+        entity: Entity,
+        behavior: &Behavior,
+        position: Point3<f32>,
+        index: &SpatialIndex,
+        players: &ReadStorage<'_, Player>,
+        steerings: &WriteStorage<'_, Steering>,
+    ) -> Activity {
+        let nearest = index
+            .query(position, behavior.sight)
+            .filter(|&(other, _)| other != entity)
+            .filter(|&(other, _)| players.contains(other) && !steerings.contains(other))
+            .map(|(other, point)| (other, (point - position).norm()))
+            .min_by(|(_, a), (_, b)| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+
+        match (behavior.temperament, nearest) {
+            (Temperament::Wander { .. }, _) => Activity::Wander,
+            (Temperament::Companion { distance }, Some((target, length))) if length > distance => {
+                Activity::Follow(target)
+            }
+            (Temperament::Prey { distance }, Some((target, length))) if length < distance => {
+                Activity::Flee(target)
+            }
+            _ => Activity::Graze,
+        }
+    }
+
+    /// The waypoint of a wandering creature at `position`, picking a new one once it is reached.
+    fn wander(behavior: &mut Behavior, position: Point3<f32>, radius: f32, rng: &mut Seed) -> Point3<f32> {
+        let origin = *behavior.origin.get_or_insert(position);
+        match behavior.waypoint {
+            Some(waypoint) if (waypoint - position).xz().norm() > WAYPOINT_REACH => waypoint,
+            _ => {
+                let distance = radius * rng.gen::<f32>().sqrt();
+                let angle = rng.gen_range(-PI, PI);
+                let waypoint = origin + Vector3::new(distance * angle.cos(), 0.0, distance * angle.sin());
+                behavior.waypoint = Some(waypoint);
+                waypoint
+            }
+        }
+    }
+
+    /// Steering towards `target` from the creature at `transform`, or away from it if `away`.
+    fn steer(transform: &Transform, target: Point3<f32>, away: bool) -> Option<Steering> {
+        let matrix = transform.global_matrix().try_inverse()?;
+        let local = matrix.transform_point(&target);
+        let angle = local.x.atan2(local.z);
+        let angle = if away { angle - angle.signum() * PI } else { angle };
+        let turn = (angle / FRAC_PI_2).max(-1.0).min(1.0);
+        Some(Steering {
+            forward: if turn.abs() < 1.0 { 1.0 } else { 0.0 },
+            turn,
+            throttle: if away { 1.0 } else { 0.0 },
+        })
+    }
+}
+
+impl<'a> System<'a> for BehaviorSystem {
+    type SystemData = (
+        Entities<'a>,
+        ReadStorage<'a, Transform>,
+        ReadStorage<'a, Player>,
+        WriteStorage<'a, Behavior>,
+        WriteStorage<'a, Steering>,
+        WriteStorage<'a, Indexed>,
+        WriteStorage<'a, Seed>,
+        Read<'a, SpatialIndex>,
+        Write<'a, Warnings>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            entities,
+            transforms,
+            players,
+            mut behaviors,
+            mut steerings,
+            mut indexed,
+            mut seeds,
+            index,
+            mut warnings,
+        ) = data;
+
+        for (entity, _, _) in (&*entities, &players, !&steerings).join() {
+            if !indexed.contains(entity) && indexed.insert(entity, Indexed).is_err() {
+                warnings.skip("behavior", entity);
+            }
+        }
+
+        for (entity, transform, behavior) in (&*entities, &transforms, &mut behaviors).join() {
+            let position = transform.global_position();
+            behavior.activity = Self::select_activity(entity, behavior, position, &index, &players, &steerings);
+
+            let target = |target: Entity| transforms.get(target).map(TransformTrait::global_position);
+            let steering = match behavior.activity {
+                Activity::Graze => None,
+                Activity::Follow(other) => target(other).and_then(|point| Self::steer(transform, point, false)),
+                Activity::Flee(other) => target(other).and_then(|point| Self::steer(transform, point, true)),
+                Activity::Wander => match (behavior.temperament, seeds.get_mut(entity)) {
+                    (Temperament::Wander { radius }, Some(rng)) => {
+                        let point = Self::wander(behavior, position, radius, rng);
+                        Self::steer(transform, point, false)
+                    }
+                    _ => None,
+                },
+            };
+            if let Some(current) = steerings.get_mut(entity) {
+                *current = steering.unwrap_or_default();
+            }
+        }
+    }
+}
@@ -0,0 +1,206 @@
+//! Layers authored animations under the procedural motion, e.g. an idle playing on the tail and ears
+//! while the legs walk.
+//!
+//! The `AnimationPoseSystem` keeps the pose sampled from the `AnimationSet` of the scene before the procedural
+//! systems write over it, and the `AnimationBlendSystem` blends each masked bone back toward that pose afterwards.
+
+use amethyst::{
+    animation::{AnimationCommand, AnimationControlSet, AnimationSet, EndControl, get_animation_set},
+    assets::PrefabData,
+    core::{math::Vector3, transform::{Parent, Transform}},
+    ecs::{Component, prelude::*},
+    error::{Error, format_err},
+};
+use log::warn;
+use serde::{Deserialize, Serialize};
+
+use ceramic_derive::Redirect;
+use redirect::{Redirect, Resolve};
+
+use crate::{extras::EntityRef, utils::warnings::Warnings};
+
+/// A bone of the mask and how much of its pose comes from the authored animation.
+#[derive(Debug, Clone)]
+pub struct BlendBone {
+    pub node: Entity,
+    /// One keeps the authored pose, zero the procedural one.
+    pub weight: f32,
+    /// The local transform sampled from the animation this frame.
+    authored: Option<Transform>,
+}
+
+/// Blends the bones of a rig between an authored animation and the procedural systems.
+///
+/// Bones outside the mask are left to the procedural systems.
+#[derive(Debug, Clone, Component)]
+#[storage(HashMapStorage)]
+pub struct AnimationBlend {
+    /// Index in the animation set of the animation looped under the procedural motion, if any.
+    animation: Option<usize>,
+    bones: Vec<BlendBone>,
+    started: bool,
+}
+
+impl AnimationBlend {
+    pub fn bones(&self) -> &[BlendBone] {
+        &self.bones
+    }
+
+    /// Sets the weight of the bone `node` in the mask, adding it if it isn't masked yet.
+    pub fn set_weight(&mut self, node: Entity, weight: f32) {
+        let weight = weight.max(0.0).min(1.0);
+        match self.bones.iter_mut().find(|bone| bone.node == node) {
+            Some(bone) => bone.weight = weight,
+            None => self.bones.push(BlendBone { node, weight, authored: None }),
+        }
+    }
+}
+
+fn default_weight() -> f32 {
+    1.0
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Redirect)]
+pub struct BlendBonePrefab {
+    pub node: EntityRef,
+    #[serde(default = "default_weight")]
+    #[redirect(skip)]
+    pub weight: f32,
+}
+
+/// The mask of a rig, set on any node of its scene, e.g.
+/// `"animation_blend": { "animation": 1, "bones": [{ "node": "Tail", "weight": 1.0 }, { "node": "Head", "weight": 0.5 }] }`.
+#[derive(Debug, Clone, Serialize, Deserialize, Redirect)]
+pub struct AnimationBlendPrefab {
+    /// Index of the animation in the glTF file to loop; without one, whatever else plays the animations is blended.
+    #[serde(default)]
+    #[redirect(skip)]
+    pub animation: Option<usize>,
+    pub bones: Vec<BlendBonePrefab>,
+}
+
+impl<'a> PrefabData<'a> for AnimationBlendPrefab {
+    type SystemData = WriteStorage<'a, AnimationBlend>;
+    type Result = ();
+
+    fn add_to_entity(
+        &self,
+        entity: Entity,
+        data: &mut Self::SystemData,
+        entities: &[Entity],
+        _children: &[Entity],
+    ) -> Result<Self::Result, Error> {
+        if let Some(bone) = self.bones.iter().find(|bone| !(0.0..=1.0).contains(&bone.weight)) {
+            return Err(format_err!("Blend weights must be between 0 and 1, got {}", bone.weight));
+        }
+        let bones = self.bones
+            .iter()
+            .map(|bone| BlendBone { node: bone.node.resolve(entities), weight: bone.weight, authored: None })
+            .collect();
+        let component = AnimationBlend { animation: self.animation, bones, started: false };
+        data.insert(entity, component).map(|_| ()).map_err(Into::into)
+    }
+}
+
+/// Linear blend of the translation and scale, and spherical blend of the rotation,
+/// from `procedural` at a `weight` of zero to `authored` at one.
+pub fn blend_transforms(authored: &Transform, procedural: &Transform, weight: f32) -> Transform {
+    let lerp = |from: &Vector3<f32>, to: &Vector3<f32>| from.lerp(to, weight);
+    let ref from = *procedural.rotation();
+    let ref to = *authored.rotation();
+    // Opposite rotations have no unique slerp; take the nearer one then.
+    let rotation = from.try_slerp(to, weight, f32::EPSILON).unwrap_or(if weight < 0.5 { *from } else { *to });
+
+    let mut transform = procedural.clone();
+    transform
+        .set_translation(lerp(procedural.translation(), authored.translation()))
+        .set_rotation(rotation);
+    transform.set_scale(lerp(procedural.scale(), authored.scale()));
+    transform
+}
+
+/// Starts the animations of the blends, and keeps the pose the animation sampled for each masked bone.
+///
+/// Must run after the sampler interpolation and before the procedural systems.
+#[derive(Default)]
+pub struct AnimationPoseSystem;
+
+impl AnimationPoseSystem {
+    /// The nearest ancestor of `entity` with an animation set, and the set.
+    fn scene<'s>(
+        entity: Entity,
+        parents: &ReadStorage<'_, Parent>,
+        sets: &'s ReadStorage<'_, AnimationSet<usize, Transform>>,
+    ) -> Option<(Entity, &'s AnimationSet<usize, Transform>)> {
+        let mut current = Some(entity);
+        while let Some(entity) = current {
+            if let Some(set) = sets.get(entity) { return Some((entity, set)); }
+            current = parents.get(entity).map(|parent| parent.entity);
+        }
+        None
+    }
+}
+
+impl<'a> System<'a> for AnimationPoseSystem {
+    type SystemData = (
+        Entities<'a>,
+        WriteStorage<'a, AnimationBlend>,
+        ReadStorage<'a, AnimationSet<usize, Transform>>,
+        WriteStorage<'a, AnimationControlSet<usize, Transform>>,
+        ReadStorage<'a, Transform>,
+        ReadStorage<'a, Parent>,
+    );
+
+    fn run(&mut self, (entities, mut blends, sets, mut controls, transforms, parents): Self::SystemData) {
+        for (entity, blend) in (&*entities, &mut blends).join() {
+            if let (Some(animation), false) = (blend.animation, blend.started) {
+                let found = Self::scene(entity, &parents, &sets)
+                    .and_then(|(scene, set)| Some((scene, set.get(&animation)?.clone())));
+                match found {
+                    Some((scene, handle)) => {
+                        if let Some(control) = get_animation_set::<usize, Transform>(&mut controls, scene) {
+                            control.add_animation(animation, &handle, EndControl::Loop(None), 1.0, AnimationCommand::Start);
+                        }
+                    }
+                    None => warn!("{:?} has no animation {} to blend", entity, animation),
+                }
+                blend.started = true;
+            }
+
+            for bone in blend.bones.iter_mut() {
+                bone.authored = transforms.get(bone.node).cloned();
+            }
+        }
+    }
+}
+
+/// Blends the masked bones from the procedural pose toward the one kept by the `AnimationPoseSystem`.
+///
+/// Must run after the procedural systems, i.e. the locomotion and the kinematics.
+/// Bones the animation doesn't drive keep their pose of the last frame, so they ease toward the procedural one.
+#[derive(Default)]
+pub struct AnimationBlendSystem;
+
+impl<'a> System<'a> for AnimationBlendSystem {
+    type SystemData = (
+        Entities<'a>,
+        ReadStorage<'a, AnimationBlend>,
+        WriteStorage<'a, Transform>,
+        Write<'a, Warnings>,
+    );
+
+    fn run(&mut self, (entities, blends, mut transforms, mut warnings): Self::SystemData) {
+        for (entity, blend) in (&*entities, &blends).join() {
+            for bone in blend.bones.iter() {
+                let authored = match bone.authored {
+                    Some(ref authored) => authored,
+                    None => continue,
+                };
+                match transforms.get_mut(bone.node) {
+                    Some(transform) => *transform = blend_transforms(authored, transform, bone.weight),
+                    None => warnings.skip("animation_blend", entity),
+                }
+            }
+        }
+    }
+}
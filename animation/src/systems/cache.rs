@@ -0,0 +1,92 @@
+use std::collections::HashMap;
+
+use amethyst::{
+    core::{math::{Matrix4, Point3}, Transform},
+    derive::SystemDesc,
+    ecs::prelude::*,
+};
+
+use super::{
+    animal::{Biped, Legged, Locomotor, Quadruped},
+    helper::Helpers,
+};
+
+/// Global matrices of registered entities, rebuilt once per frame after the transform system.
+///
+/// Entities referenced by quadrupeds, bipeds and locomotors are registered automatically.
+/// Folded helpers are always cached, from the matrix of their parent.
+#[derive(Debug, Default)]
+pub struct GlobalCache {
+    registered: BitSet,
+    matrices: HashMap<Entity, Matrix4<f32>>,
+}
+
+impl GlobalCache {
+    /// Request the global matrix of `entity` to be cached from the next frame on.
+    pub fn register(&mut self, entity: Entity) {
+        self.registered.add(entity.id());
+    }
+
+    #[inline]
+    pub fn is_registered(&self, entity: Entity) -> bool {
+        self.registered.contains(entity.id())
+    }
+
+    #[inline]
+    pub fn global_matrix(&self, entity: Entity) -> Option<&Matrix4<f32>> {
+        self.matrices.get(&entity)
+    }
+
+    #[inline]
+    pub fn global_position(&self, entity: Entity) -> Option<Point3<f32>> {
+        let ref origin = Point3::origin();
+        self.global_matrix(entity)
+            .map(|matrix| matrix.transform_point(origin))
+    }
+}
+
+#[derive(Default, SystemDesc)]
+pub struct GlobalCacheSystem;
+
+impl GlobalCacheSystem {
+    fn register<T: Legged>(entities: &Entities<'_>, bodies: &ReadStorage<'_, T>, cache: &mut GlobalCache) {
+        for (entity, body) in (&**entities, bodies).join() {
+            if !cache.is_registered(entity) {
+                cache.register(entity);
+                body
+                    .entities()
+                    .into_iter()
+                    .for_each(|entity| cache.register(entity));
+            }
+        }
+    }
+}
+
+impl<'a> System<'a> for GlobalCacheSystem {
+    type SystemData = (
+        Entities<'a>,
+        ReadStorage<'a, Transform>,
+        ReadStorage<'a, Quadruped>,
+        ReadStorage<'a, Biped>,
+        ReadStorage<'a, Locomotor>,
+        Read<'a, Helpers>,
+        Write<'a, GlobalCache>,
+    );
+
+    fn run(&mut self, (entities, transforms, quadrupeds, bipeds, locomotors, helpers, mut cache): Self::SystemData) {
+        Self::register(&entities, &quadrupeds, &mut cache);
+        Self::register(&entities, &bipeds, &mut cache);
+        Self::register(&entities, &locomotors, &mut cache);
+
+        let GlobalCache { registered, matrices } = &mut *cache;
+        matrices.clear();
+        for (entity, transform, _) in (&*entities, &transforms, &*registered).join() {
+            matrices.insert(entity, transform.global_matrix().clone());
+        }
+        for helper in helpers.entities() {
+            if let Some(matrix) = helpers.global_matrix(helper, &transforms) {
+                matrices.insert(helper, matrix);
+            }
+        }
+    }
+}
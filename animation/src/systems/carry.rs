@@ -0,0 +1,233 @@
+use std::collections::HashSet;
+
+use amethyst::{
+    assets::PrefabData,
+    core::{
+        math::{UnitQuaternion, Vector3},
+        transform::{Parent, Transform},
+    },
+    derive::PrefabData,
+    ecs::{Component, prelude::*, SystemData},
+    error::Error,
+    shrev::{EventChannel, ReaderId},
+};
+use log::warn;
+use serde::{Deserialize, Serialize};
+
+use ceramic_derive::{Redirect, Resolve};
+use redirect::{Redirect, Resolve};
+
+use crate::{
+    extras::EntityRef,
+    systems::{
+        physics::{Physics, PhysicsData},
+        player::Player,
+    },
+    utils::transform::TransformTrait,
+};
+
+/// A prop that can be picked up by a `Carrier`.
+#[derive(Debug, Copy, Clone, Serialize, Deserialize, PrefabData, Component)]
+#[prefab(Component)]
+#[storage(HashMapStorage)]
+pub struct Carryable {
+    /// Position of the prop in the space of the jaw while carried.
+    #[serde(default)]
+    pub offset: [f32; 3],
+    /// Mass of the particle the prop becomes when dropped; it stays static without one.
+    #[serde(default)]
+    pub mass: Option<f32>,
+}
+
+/// Something that picks up `Carryable`s with its jaw, e.g. a cat.
+#[derive(Debug, Copy, Clone, Component)]
+#[storage(HashMapStorage)]
+pub struct Carrier {
+    jaw: Entity,
+    reach: f32,
+    carried: Option<Entity>,
+}
+
+impl Carrier {
+    pub fn jaw(&self) -> Entity {
+        self.jaw
+    }
+
+    pub fn carried(&self) -> Option<Entity> {
+        self.carried
+    }
+
+    /// Lets go of the carried prop if `dangling` holds for it.
+    pub fn forget(&mut self, dangling: &dyn Fn(Entity) -> bool) {
+        if self.carried.map_or(false, dangling) {
+            self.carried = None;
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Redirect, Resolve)]
+#[resolve(Carrier)]
+#[resolve(init(carried: None))]
+pub struct CarrierPrefab {
+    pub jaw: EntityRef,
+    /// Distance from the jaw within which props are picked up.
+    #[redirect(skip)]
+    pub reach: f32,
+}
+
+impl<'a> PrefabData<'a> for CarrierPrefab {
+    type SystemData = WriteStorage<'a, Carrier>;
+    type Result = ();
+
+    fn add_to_entity(
+        &self,
+        entity: Entity,
+        data: &mut Self::SystemData,
+        entities: &[Entity],
+        _children: &[Entity],
+    ) -> Result<Self::Result, Error> {
+        data.insert(entity, self.resolve(entities)).map(|_| ()).map_err(Into::into)
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+pub enum CarryEvent {
+    /// Pick up the closest prop in reach of the jaw of the carrier.
+    PickUp(Entity),
+    /// Drop what the carrier holds, handing it back to the physics world.
+    Drop(Entity),
+}
+
+/// Parents picked up props to the jaw of their carrier and releases them again.
+///
+/// Carried props are taken out of the physics world; dropped ones get their particle back
+/// with the velocity of the carrier.
+#[derive(Default)]
+pub struct CarrySystem {
+    reader_id: Option<ReaderId<CarryEvent>>,
+}
+
+impl CarrySystem {
+    fn pick_up(
+        carrier: &mut Carrier,
+        entities: &Entities<'_>,
+        carryables: &ReadStorage<'_, Carryable>,
+        transforms: &mut WriteStorage<'_, Transform>,
+        parents: &mut WriteStorage<'_, Parent>,
+        physics: &mut PhysicsData<'_>,
+        carried: &HashSet<Entity>,
+    ) -> Option<()> {
+        let ref jaw = transforms.get(carrier.jaw)?.global_position();
+        let (prop, carryable, _) = (&**entities, carryables, &*transforms)
+            .join()
+            .filter(|(prop, _, _)| !carried.contains(prop))
+            .map(|(prop, carryable, transform)| (prop, carryable, (transform.global_position() - jaw).norm()))
+            .filter(|(_, _, distance)| *distance <= carrier.reach)
+            .min_by(|(_, _, a), (_, _, b)| a.partial_cmp(b).unwrap())?;
+
+        physics.remove_particle(prop);
+        parents.insert(prop, Parent { entity: carrier.jaw }).ok()?;
+        transforms
+            .get_mut(prop)?
+            .set_translation(Vector3::from(carryable.offset))
+            .set_rotation(UnitQuaternion::<f32>::identity());
+        carrier.carried = Some(prop);
+        Some(())
+    }
+
+    fn release(
+        entity: Entity,
+        carrier: &mut Carrier,
+        carryables: &ReadStorage<'_, Carryable>,
+        transforms: &mut WriteStorage<'_, Transform>,
+        parents: &mut WriteStorage<'_, Parent>,
+        physics: &mut PhysicsData<'_>,
+        players: &ReadStorage<'_, Player>,
+    ) -> Option<()> {
+        let prop = carrier.carried.take()?;
+        parents.remove(prop);
+
+        let transform = transforms.get_mut(prop)?;
+        let position = transform.global_position();
+        transform.set_translation(position.coords);
+
+        if let Some(mass) = carryables.get(prop).and_then(|carryable| carryable.mass) {
+            if let Err(error) = physics.create_particle(prop, mass) {
+                warn!("Failed to hand {:?} back to physics: {}", prop, error);
+                return Some(());
+            }
+            let velocity = players
+                .get(entity)
+                .zip(transforms.get(entity))
+                .map_or_else(Vector3::zeros, |(player, transform)| {
+                    transform.global_matrix().transform_vector(&player.velocity())
+                });
+            physics.apply_impulse(prop, &velocity.scale(mass));
+        }
+        Some(())
+    }
+}
+
+impl<'a> System<'a> for CarrySystem {
+    type SystemData = (
+        Entities<'a>,
+        WriteStorage<'a, Carrier>,
+        ReadStorage<'a, Carryable>,
+        WriteStorage<'a, Transform>,
+        WriteStorage<'a, Parent>,
+        ReadStorage<'a, Player>,
+        PhysicsData<'a>,
+        Read<'a, EventChannel<CarryEvent>>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            entities,
+            mut carriers,
+            carryables,
+            mut transforms,
+            mut parents,
+            players,
+            mut physics,
+            events,
+        ) = data;
+
+        let reader_id = self.reader_id
+            .as_mut()
+            .expect("`CarrySystem::setup` was not called before `CarrySystem::run`");
+
+        for event in events.read(reader_id) {
+            let carried = carriers
+                .join()
+                .filter_map(|carrier| carrier.carried)
+                .collect::<HashSet<_>>();
+            match *event {
+                CarryEvent::PickUp(entity) => {
+                    let carrier = match carriers.get_mut(entity) {
+                        Some(carrier) if carrier.carried.is_none() => carrier,
+                        _ => continue,
+                    };
+                    Self::pick_up(
+                        carrier,
+                        &entities,
+                        &carryables,
+                        &mut transforms,
+                        &mut parents,
+                        &mut physics,
+                        &carried,
+                    );
+                }
+                CarryEvent::Drop(entity) => {
+                    if let Some(carrier) = carriers.get_mut(entity) {
+                        Self::release(entity, carrier, &carryables, &mut transforms, &mut parents, &mut physics, &players);
+                    }
+                }
+            }
+        }
+    }
+
+    fn setup(&mut self, world: &mut World) {
+        Self::SystemData::setup(world);
+        self.reader_id = Some(world.fetch_mut::<EventChannel<CarryEvent>>().register_reader());
+    }
+}
@@ -0,0 +1,241 @@
+use std::collections::HashSet;
+
+use amethyst::{
+    core::Transform,
+    ecs::{prelude::*, storage::ComponentEvent, SystemData},
+    shrev::{EventChannel, ReaderId},
+};
+use log::{info, warn};
+
+use super::{
+    animal::{tail::Tail, Action, Biped, Breathing, Legged, Locomotor, Quadruped, Spine, Tracker},
+    appendage::Appendage,
+    carry::Carrier,
+    helper::Helpers,
+    kinematics::{Chain, Direction, Distance, Pole},
+    particle::Spring,
+    physics::{Physics, PhysicsData},
+    socket::Sockets,
+};
+
+/// A component that references other entities, and has to go when they do.
+///
+/// Some references can be dropped instead, like the prop a carrier holds, and the component kept.
+/// Components implementing it belong in the `ReferencingStorages`, which both the `CleanupSystem`
+/// and the `DespawnSystem` go through.
+pub trait References: Component {
+    /// Name of the component in messages.
+    const NAME: &'static str;
+
+    /// Whether any of the entities the component cannot do without satisfies `predicate`.
+    fn references_any(&self, predicate: &dyn Fn(Entity) -> bool) -> bool;
+
+    /// Drops the references the component can do without that satisfy `predicate`.
+    fn release(&mut self, _predicate: &dyn Fn(Entity) -> bool) {}
+}
+
+macro_rules! references {
+    ($component:ty, $name:expr, |$this:ident| $references:expr) => {
+        impl References for $component {
+            const NAME: &'static str = $name;
+
+            fn references_any(&self, predicate: &dyn Fn(Entity) -> bool) -> bool {
+                let $this = self;
+                IntoIterator::into_iter($references).any(|entity| predicate(entity))
+            }
+        }
+    };
+    ($component:ty, $name:expr, |$this:ident| $references:expr, $forget:ident) => {
+        impl References for $component {
+            const NAME: &'static str = $name;
+
+            fn references_any(&self, predicate: &dyn Fn(Entity) -> bool) -> bool {
+                let $this = self;
+                IntoIterator::into_iter($references).any(|entity| predicate(entity))
+            }
+
+            fn release(&mut self, predicate: &dyn Fn(Entity) -> bool) {
+                self.$forget(predicate)
+            }
+        }
+    };
+}
+
+references!(Quadruped, "quadruped", |this| this.entities());
+references!(Biped, "biped", |this| this.entities());
+references!(Locomotor, "locomotor", |this| this.entities());
+references!(Tracker, "tracker", |this| Some(this.target()), forget);
+references!(Chain, "chain", |this| Some(this.target()));
+references!(Pole, "pole", |this| Some(this.target()));
+references!(Direction, "direction", |this| Some(this.target()));
+references!(Distance, "distance", |this| Some(this.target()));
+references!(Spring, "spring", |this| Some(this.target()));
+references!(Tail, "tail", |this| Some(this.player()));
+references!(Appendage, "appendage", |this| this.nodes().iter().copied().chain(this.head()));
+references!(Breathing, "breathing", |this| this.joints().iter().copied().chain(this.player()));
+references!(Spine, "spine", |this| this.joints().iter().copied());
+references!(Action, "action", |this| Some(this.target()));
+references!(Carrier, "carrier", |this| Some(this.jaw()), forget);
+references!(Sockets, "sockets", |_this| None, forget);
+
+/// The storages of every component that `References` other entities.
+#[derive(SystemData)]
+pub struct ReferencingStorages<'a> {
+    quadrupeds: WriteStorage<'a, Quadruped>,
+    bipeds: WriteStorage<'a, Biped>,
+    locomotors: WriteStorage<'a, Locomotor>,
+    trackers: WriteStorage<'a, Tracker>,
+    chains: WriteStorage<'a, Chain>,
+    poles: WriteStorage<'a, Pole>,
+    directions: WriteStorage<'a, Direction>,
+    distances: WriteStorage<'a, Distance>,
+    springs: WriteStorage<'a, Spring>,
+    tails: WriteStorage<'a, Tail>,
+    appendages: WriteStorage<'a, Appendage>,
+    breathings: WriteStorage<'a, Breathing>,
+    spines: WriteStorage<'a, Spine>,
+    actions: WriteStorage<'a, Action>,
+    carriers: WriteStorage<'a, Carrier>,
+    sockets: WriteStorage<'a, Sockets>,
+}
+
+impl ReferencingStorages<'_> {
+    /// Removes the components on an entity or referencing one for which `dangling` holds,
+    /// returning their names and entities. Components that can do without the reference only drop it.
+    ///
+    /// Legged bodies go first, so no limb is left in flight.
+    pub fn remove(&mut self, entities: &Entities<'_>, dangling: &dyn Fn(Entity) -> bool) -> Vec<(&'static str, Entity)> {
+        let mut removed = vec![];
+        remove(entities, &mut self.quadrupeds, dangling, &mut removed);
+        remove(entities, &mut self.bipeds, dangling, &mut removed);
+        remove(entities, &mut self.locomotors, dangling, &mut removed);
+        remove(entities, &mut self.trackers, dangling, &mut removed);
+        remove(entities, &mut self.chains, dangling, &mut removed);
+        remove(entities, &mut self.poles, dangling, &mut removed);
+        remove(entities, &mut self.directions, dangling, &mut removed);
+        remove(entities, &mut self.distances, dangling, &mut removed);
+        remove(entities, &mut self.springs, dangling, &mut removed);
+        remove(entities, &mut self.tails, dangling, &mut removed);
+        remove(entities, &mut self.appendages, dangling, &mut removed);
+        remove(entities, &mut self.breathings, dangling, &mut removed);
+        remove(entities, &mut self.spines, dangling, &mut removed);
+        remove(entities, &mut self.actions, dangling, &mut removed);
+        remove(entities, &mut self.carriers, dangling, &mut removed);
+        remove(entities, &mut self.sockets, dangling, &mut removed);
+        removed
+    }
+}
+
+fn remove<T: References>(
+    entities: &Entities<'_>,
+    storage: &mut WriteStorage<'_, T>,
+    dangling: &dyn Fn(Entity) -> bool,
+    removed: &mut Vec<(&'static str, Entity)>,
+) {
+    let start = removed.len();
+    removed.extend((&**entities, &*storage)
+        .join()
+        .filter(|(entity, component)| dangling(*entity) || component.references_any(dangling))
+        .map(|(entity, _)| (T::NAME, entity)));
+    for &(_, entity) in &removed[start..] {
+        storage.remove(entity);
+    }
+    for component in (&mut *storage).join() {
+        component.release(dangling);
+    }
+}
+
+/// Removes components that reference deleted entities, e.g. after a scene was despawned or reloaded.
+///
+/// Deletions are noticed through the removal of their `Transform`, so this only does work
+/// in frames where transforms were removed.
+#[derive(Default)]
+pub struct CleanupSystem {
+    reader_id: Option<ReaderId<ComponentEvent>>,
+}
+
+impl<'a> System<'a> for CleanupSystem {
+    type SystemData = (
+        Entities<'a>,
+        ReadStorage<'a, Transform>,
+        Read<'a, Helpers>,
+        ReferencingStorages<'a>,
+    );
+
+    fn run(&mut self, (entities, transforms, helpers, mut storages): Self::SystemData) {
+        let reader_id = self.reader_id
+            .as_mut()
+            .expect("`CleanupSystem::setup` was not called before `CleanupSystem::run`");
+        let removed = transforms
+            .channel()
+            .read(reader_id)
+            .filter(|event| matches!(event, ComponentEvent::Removed(_)))
+            .count();
+        if removed == 0 { return; }
+
+        // References to helpers stay valid after their entities were folded into `Helpers`.
+        let dangling = |reference: Entity| !entities.is_alive(reference) && !helpers.contains(reference);
+        for (name, entity) in storages.remove(&entities, &dangling) {
+            warn!("Removed {} of {:?} referencing a deleted entity", name, entity);
+        }
+    }
+
+    fn setup(&mut self, world: &mut World) {
+        Self::SystemData::setup(world);
+        self.reader_id = Some(WriteStorage::<Transform>::fetch(world).register_reader());
+    }
+}
+
+/// Tears down an animal within the frame, e.g. with every node of its scene as `nodes`.
+///
+/// Deleted entities keep their components until the end of the frame, so systems running after the deletion
+/// would still step their limbs and solve their chains; despawning through this stops them right away.
+#[derive(Debug, Clone, Default)]
+pub struct DespawnAnimal {
+    pub nodes: Vec<Entity>,
+}
+
+/// Handles `DespawnAnimal`, before any other system touches the rig.
+///
+/// Legged bodies go first, so no limb is left in flight, then every component on the rig or reaching into it
+/// from elsewhere, e.g. a tracker looking at the animal or a spring hanging from it, then the physics bodies,
+/// and last the entities themselves.
+#[derive(Default)]
+pub struct DespawnSystem {
+    reader_id: Option<ReaderId<DespawnAnimal>>,
+}
+
+impl<'a> System<'a> for DespawnSystem {
+    type SystemData = (
+        Entities<'a>,
+        Read<'a, EventChannel<DespawnAnimal>>,
+        ReferencingStorages<'a>,
+        PhysicsData<'a>,
+    );
+
+    fn run(&mut self, (entities, events, mut storages, mut physics): Self::SystemData) {
+        let reader_id = self.reader_id
+            .as_mut()
+            .expect("`DespawnSystem::setup` was not called before `DespawnSystem::run`");
+
+        for DespawnAnimal { nodes } in events.read(reader_id) {
+            let rig = nodes.iter().copied().filter(|&node| entities.is_alive(node)).collect::<HashSet<_>>();
+            storages.remove(&entities, &|entity| rig.contains(&entity));
+
+            for &node in rig.iter() {
+                physics.remove_particle(node);
+            }
+            for &node in rig.iter() {
+                if let Err(error) = entities.delete(node) {
+                    warn!("Failed to despawn {:?}: {}", node, error);
+                }
+            }
+            info!("Despawned an animal of {} nodes", rig.len());
+        }
+    }
+
+    fn setup(&mut self, world: &mut World) {
+        Self::SystemData::setup(world);
+        self.reader_id = Some(world.fetch_mut::<EventChannel<DespawnAnimal>>().register_reader());
+    }
+}
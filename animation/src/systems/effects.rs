@@ -0,0 +1,295 @@
+use std::f32::consts::TAU;
+
+use amethyst::{
+    assets::{AssetStorage, Handle, Loader, PrefabData},
+    core::{
+        Hidden,
+        math::{Point3, UnitQuaternion, Vector3},
+        Time,
+        Transform,
+    },
+    ecs::{Component, prelude::*, SystemData},
+    error::Error,
+    renderer::{
+        Material,
+        MaterialDefaults,
+        Mesh,
+        palette::{LinSrgba, Srgba},
+        resources::Tint,
+        rendy::{
+            mesh::{Indices, MeshBuilder, Normal, Position, Tangent, TexCoord},
+            texture::palette::load_from_linear_rgba,
+        },
+        Texture,
+        transparent::Transparent,
+    },
+    shrev::{EventChannel, ReaderId},
+};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::utils::{determinism::Determinism, random::Random};
+
+use super::animal::FootstepEvent;
+
+/// How the feet of a body mark the ground.
+///
+/// Set on the node of a legged body through its extras, e.g. `"footprints": { "decal_lifetime": 8.0 }`;
+/// bodies without one use the `FootprintConfig` resource.
+#[derive(Debug, Copy, Clone, Serialize, Deserialize, Component)]
+#[storage(HashMapStorage)]
+#[serde(default)]
+pub struct FootprintConfig {
+    /// Side length of a footprint decal.
+    pub decal_size: f32,
+    /// Growth of the decal size per unit of impact speed, so running feet leave larger prints.
+    pub size_per_speed: f32,
+    pub decal_lifetime: f32,
+    pub dust_size: f32,
+    pub dust_lifetime: f32,
+    /// Dust particles spawned per unit of impact speed.
+    pub dust_per_speed: f32,
+    /// Dust particles spawned per unit of landing impact, so heavy landings kick up more.
+    pub dust_per_impact: f32,
+    pub max_dust: usize,
+}
+
+impl Default for FootprintConfig {
+    fn default() -> Self {
+        FootprintConfig {
+            decal_size: 0.25,
+            size_per_speed: 0.1,
+            decal_lifetime: 4.0,
+            dust_size: 0.05,
+            dust_lifetime: 0.6,
+            dust_per_speed: 2.0,
+            dust_per_impact: 4.0,
+            max_dust: 12,
+        }
+    }
+}
+
+impl<'a> PrefabData<'a> for FootprintConfig {
+    type SystemData = WriteStorage<'a, FootprintConfig>;
+    type Result = ();
+
+    fn add_to_entity(
+        &self,
+        entity: Entity,
+        data: &mut Self::SystemData,
+        _entities: &[Entity],
+        _children: &[Entity],
+    ) -> Result<Self::Result, Error> {
+        data.insert(entity, *self).map(|_| ()).map_err(Into::into)
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+enum Kind {
+    Decal,
+    Dust { velocity: Vector3<f32> },
+}
+
+/// A short-lived visual effect that fades out, and is hidden and kept for reuse at the end of its lifetime.
+#[derive(Debug, Copy, Clone, Component)]
+#[storage(DenseVecStorage)]
+pub struct Effect {
+    kind: Kind,
+    age: f32,
+    lifetime: f32,
+}
+
+#[derive(Debug, Clone)]
+struct EffectAssets {
+    quad: Handle<Mesh>,
+    decal: Handle<Material>,
+    dust: Handle<Material>,
+}
+
+/// Spawns footprint decals and dust bursts for `FootstepEvent`s, and fades them out.
+///
+/// Expired effects are hidden and pooled rather than deleted, and new ones recycle them,
+/// so a running herd doesn't create and delete entities every step.
+#[derive(Default)]
+pub struct FootprintSystem {
+    reader_id: Option<ReaderId<FootstepEvent>>,
+    assets: Option<EffectAssets>,
+    pool: Vec<Entity>,
+}
+
+impl FootprintSystem {
+    fn quad() -> MeshBuilder<'static> {
+        MeshBuilder::new()
+            .with_vertices(vec![
+                Position([-0.5, 0.0, -0.5]),
+                Position([0.5, 0.0, -0.5]),
+                Position([0.5, 0.0, 0.5]),
+                Position([-0.5, 0.0, 0.5]),
+            ])
+            .with_vertices(vec![Normal([0.0, 1.0, 0.0]); 4])
+            .with_vertices(vec![Tangent([1.0, 0.0, 0.0, 1.0]); 4])
+            .with_vertices(vec![
+                TexCoord([0.0, 0.0]),
+                TexCoord([1.0, 0.0]),
+                TexCoord([1.0, 1.0]),
+                TexCoord([0.0, 1.0]),
+            ])
+            .with_indices(Indices::U16(vec![0, 2, 1, 0, 3, 2].into()))
+    }
+
+    fn load_assets(
+        loader: &Loader,
+        mesh_storage: &AssetStorage<Mesh>,
+        texture_storage: &AssetStorage<Texture>,
+        material_storage: &AssetStorage<Material>,
+        material_defaults: &MaterialDefaults,
+    ) -> EffectAssets {
+        let material = |color: LinSrgba| {
+            let albedo = loader.load_from_data(load_from_linear_rgba(color).into(), (), texture_storage);
+            loader.load_from_data(Material { albedo, ..material_defaults.0.clone() }, (), material_storage)
+        };
+        EffectAssets {
+            quad: loader.load_from_data(Self::quad().into(), (), mesh_storage),
+            decal: material(LinSrgba::new(0.05, 0.04, 0.03, 0.6)),
+            dust: material(LinSrgba::new(0.6, 0.55, 0.45, 0.8)),
+        }
+    }
+}
+
+impl<'a> System<'a> for FootprintSystem {
+    type SystemData = (
+        Entities<'a>,
+        WriteStorage<'a, Effect>,
+        WriteStorage<'a, Transform>,
+        WriteStorage<'a, Tint>,
+        WriteStorage<'a, Handle<Mesh>>,
+        WriteStorage<'a, Handle<Material>>,
+        WriteStorage<'a, Transparent>,
+        WriteStorage<'a, Hidden>,
+        ReadExpect<'a, Loader>,
+        Read<'a, AssetStorage<Mesh>>,
+        Read<'a, AssetStorage<Texture>>,
+        Read<'a, AssetStorage<Material>>,
+        ReadExpect<'a, MaterialDefaults>,
+        Read<'a, EventChannel<FootstepEvent>>,
+        ReadStorage<'a, FootprintConfig>,
+        Read<'a, FootprintConfig>,
+        Write<'a, Random>,
+        Read<'a, Time>,
+        Read<'a, Determinism>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            entities,
+            mut effects,
+            mut transforms,
+            mut tints,
+            mut meshes,
+            mut materials,
+            mut transparents,
+            mut hiddens,
+            loader,
+            mesh_storage,
+            texture_storage,
+            material_storage,
+            material_defaults,
+            footsteps,
+            configs,
+            default_config,
+            mut random,
+            time,
+            determinism,
+        ) = data;
+
+        let reader_id = self.reader_id
+            .as_mut()
+            .expect("`FootprintSystem::setup` was not called before `FootprintSystem::run`");
+        let assets = self.assets
+            .get_or_insert_with(|| Self::load_assets(
+                &loader,
+                &mesh_storage,
+                &texture_storage,
+                &material_storage,
+                &material_defaults,
+            ))
+            .clone();
+
+        let delta_seconds = determinism.delta_seconds(time.delta_seconds());
+        let mut expired = vec![];
+        for (entity, effect, transform, tint) in (&*entities, &mut effects, &mut transforms, &mut tints).join() {
+            effect.age += delta_seconds;
+            if effect.age >= effect.lifetime {
+                expired.push(entity);
+                continue;
+            }
+
+            let fade = 1.0 - effect.age / effect.lifetime;
+            tint.0.alpha = fade;
+            if let Kind::Dust { velocity } = effect.kind {
+                transform.prepend_translation(velocity * delta_seconds);
+            }
+        }
+        for entity in expired {
+            effects.remove(entity);
+            let _ = hiddens.insert(entity, Hidden);
+            self.pool.push(entity);
+        }
+
+        let pool = &mut self.pool;
+        for footstep in footsteps.read(reader_id) {
+            let config = configs.get(footstep.body).unwrap_or(&*default_config);
+            let rotation = UnitQuaternion::rotation_between(&Vector3::y(), &footstep.normal)
+                .unwrap_or_else(UnitQuaternion::identity);
+            let ref point = footstep.point + footstep.normal * 0.005;
+
+            let mut spawn = |kind: Kind, lifetime: f32, point: &Point3<f32>, size: f32, material: &Handle<Material>| {
+                let mut transform = Transform::default();
+                transform
+                    .set_translation(point.coords)
+                    .set_rotation(rotation)
+                    .set_scale(Vector3::new(size, size, size));
+                let effect = Effect { kind, age: 0.0, lifetime };
+                let tint = Tint(Srgba::new(1.0, 1.0, 1.0, 1.0));
+
+                // Pooled entities keep their mesh and transparency, only what differs is set again.
+                while let Some(entity) = pool.pop() {
+                    if !entities.is_alive(entity) { continue; }
+                    hiddens.remove(entity);
+                    let recycled = effects.insert(entity, effect)
+                        .and_then(|_| transforms.insert(entity, transform.clone()))
+                        .and_then(|_| tints.insert(entity, tint))
+                        .and_then(|_| materials.insert(entity, material.clone()));
+                    if recycled.is_ok() { return; }
+                }
+                entities
+                    .build_entity()
+                    .with(effect, &mut effects)
+                    .with(transform, &mut transforms)
+                    .with(tint, &mut tints)
+                    .with(assets.quad.clone(), &mut meshes)
+                    .with(material.clone(), &mut materials)
+                    .with(Transparent, &mut transparents)
+                    .build();
+            };
+
+            let size = config.decal_size * (1.0 + config.size_per_speed * footstep.speed);
+            spawn(Kind::Decal, config.decal_lifetime, point, size, &assets.decal);
+
+            let count = footstep.speed * config.dust_per_speed + footstep.impact * config.dust_per_impact;
+            let count = (count as usize).min(config.max_dust);
+            for _ in 0..count {
+                let angle = random.gen_range(0.0, TAU);
+                let ref outward = rotation * Vector3::new(angle.cos(), 0.0, angle.sin());
+                let velocity = (outward + footstep.normal).scale(0.2 * footstep.speed * random.gen_range(0.5, 1.0));
+                let lifetime = config.dust_lifetime * random.gen_range(0.5, 1.0);
+                spawn(Kind::Dust { velocity }, lifetime, point, config.dust_size, &assets.dust);
+            }
+        }
+    }
+
+    fn setup(&mut self, world: &mut World) {
+        Self::SystemData::setup(world);
+        self.reader_id = Some(world.fetch_mut::<EventChannel<FootstepEvent>>().register_reader());
+    }
+}
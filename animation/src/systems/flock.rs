@@ -0,0 +1,225 @@
+use std::{collections::HashMap, f32::consts::FRAC_PI_2};
+
+use amethyst::{
+    assets::PrefabData,
+    core::{math::{Point3, Vector3}, Transform},
+    derive::SystemDesc,
+    ecs::{Component, prelude::*},
+    error::Error,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    systems::{
+        player::{Player, Steering},
+        spatial::{Indexed, SpatialIndex},
+    },
+    utils::transform::TransformTrait,
+};
+
+fn default_radius() -> f32 {
+    8.0
+}
+
+fn default_spacing() -> f32 {
+    2.0
+}
+
+/// How strongly each rule pulls a member of a flock.
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FlockWeights {
+    /// Away from neighbors closer than the spacing.
+    pub separation: f32,
+    /// Along the mean heading of the neighbors.
+    pub alignment: f32,
+    /// Toward the center of the neighbors.
+    pub cohesion: f32,
+    /// Toward the leader of the flock.
+    pub leader: f32,
+}
+
+impl Default for FlockWeights {
+    fn default() -> Self {
+        FlockWeights {
+            separation: 1.5,
+            alignment: 1.0,
+            cohesion: 1.0,
+            leader: 1.0,
+        }
+    }
+}
+
+/// A member of a flock of creatures moving together, e.g. a herd in the background, set on the node with the `Player`:
+/// `"flock": { "id": 0, "radius": 8.0, "spacing": 2.0, "weights": { "separation": 1.5, "leader": 1.0 } }`.
+///
+/// Followers are steered by the `FlockSystem`; the member with `"leader": true` is steered by anything else,
+/// e.g. the input, a behavior or a path, and the others follow it.
+#[derive(Debug, Copy, Clone, Serialize, Deserialize, Component)]
+#[storage(HashMapStorage)]
+pub struct Flock {
+    /// Members only flock with those of the same id.
+    #[serde(default)]
+    pub id: u32,
+    #[serde(default)]
+    pub leader: bool,
+    /// Members of the flock within this distance are neighbors.
+    #[serde(default = "default_radius")]
+    pub radius: f32,
+    /// Distance under which neighbors push each other apart.
+    #[serde(default = "default_spacing")]
+    pub spacing: f32,
+    #[serde(default)]
+    pub weights: FlockWeights,
+}
+
+impl<'a> PrefabData<'a> for Flock {
+    type SystemData = (
+        WriteStorage<'a, Flock>,
+        WriteStorage<'a, Steering>,
+        WriteStorage<'a, Indexed>,
+    );
+    type Result = ();
+
+    fn add_to_entity(
+        &self,
+        entity: Entity,
+        (flocks, steerings, indexed): &mut Self::SystemData,
+        _entities: &[Entity],
+        _children: &[Entity],
+    ) -> Result<Self::Result, Error> {
+        flocks.insert(entity, *self)?;
+        if !self.leader {
+            steerings.insert(entity, Steering::default())?;
+        }
+        indexed.insert(entity, Indexed)?;
+        Ok(())
+    }
+}
+
+/// Where a member of a flock is and how it moves, in world space.
+#[derive(Debug, Copy, Clone)]
+struct Member {
+    id: u32,
+    position: Point3<f32>,
+    velocity: Vector3<f32>,
+    speed: f32,
+}
+
+/// Below this length the pull of the rules is too weak to walk for.
+const MIN_PULL: f32 = 0.1;
+
+/// Steers the followers of each `Flock` by separation, alignment and cohesion with their neighbors,
+/// and toward the leader of their flock, matching its speed.
+///
+/// Must run after the `BehaviorSystem`, so leaders can be steered by behaviors, and before the `AvoidanceSystem`
+/// and the `PlayerSystem`. Neighbors are found in the `SpatialIndex` of the previous frame.
+#[derive(Default, SystemDesc)]
+pub struct FlockSystem;
+
+impl FlockSystem {
+    /// The horizontal direction the rules of `flock` pull `member` to, as long as its strength.
+    fn pull(flock: &Flock, member: &Member, neighbors: &[Member], leader: Option<&Member>) -> Vector3<f32> {
+        let ref weights = flock.weights;
+        let mut pull = Vector3::zeros();
+
+        if !neighbors.is_empty() {
+            let count = neighbors.len() as f32;
+            let separation = neighbors
+                .iter()
+                .filter_map(|neighbor| {
+                    let offset = member.position - neighbor.position;
+                    let distance = offset.norm();
+                    if distance >= flock.spacing { return None; }
+                    let direction = offset.try_normalize(f32::EPSILON)?;
+                    Some(direction * (1.0 - distance / flock.spacing))
+                })
+                .sum::<Vector3<f32>>();
+            let alignment = neighbors
+                .iter()
+                .filter_map(|neighbor| neighbor.velocity.try_normalize(f32::EPSILON))
+                .sum::<Vector3<f32>>() / count;
+            let center = neighbors.iter().map(|neighbor| neighbor.position.coords).sum::<Vector3<f32>>() / count;
+            let cohesion = (center - member.position.coords) / flock.radius;
+
+            pull += separation * weights.separation + alignment * weights.alignment + cohesion * weights.cohesion;
+        }
+        if let Some(leader) = leader {
+            // Full strength from a radius away, easing off closer in.
+            let offset = (leader.position - member.position) / flock.radius;
+            let offset = if offset.norm() > 1.0 { offset.normalize() } else { offset };
+            pull += offset * weights.leader;
+        }
+
+        pull.y = 0.0;
+        pull
+    }
+
+    /// Steering along `pull` from the creature at `transform`, easing its speed toward `speed`.
+    fn steer(transform: &Transform, player: &Player, pull: &Vector3<f32>, speed: Option<f32>) -> Option<Steering> {
+        let strength = pull.norm();
+        if strength < MIN_PULL { return Some(Steering::default()); }
+
+        let matrix = transform.global_matrix().try_inverse()?;
+        let local = matrix.transform_vector(pull);
+        let turn = (local.x.atan2(local.z) / FRAC_PI_2).max(-1.0).min(1.0);
+        let throttle = speed.map_or(0.0, |speed| (speed - player.linear_speed()).max(-1.0).min(1.0));
+        Some(Steering {
+            forward: if turn.abs() < 1.0 { strength.min(1.0) } else { 0.0 },
+            turn,
+            throttle,
+        })
+    }
+}
+
+impl<'a> System<'a> for FlockSystem {
+    type SystemData = (
+        Entities<'a>,
+        ReadStorage<'a, Flock>,
+        ReadStorage<'a, Player>,
+        ReadStorage<'a, Transform>,
+        WriteStorage<'a, Steering>,
+        Read<'a, SpatialIndex>,
+    );
+
+    fn run(&mut self, (entities, flocks, players, transforms, mut steerings, index): Self::SystemData) {
+        let members = (&*entities, &flocks, &players, &transforms)
+            .join()
+            .map(|(entity, flock, player, transform)| {
+                let member = Member {
+                    id: flock.id,
+                    position: transform.global_position(),
+                    velocity: transform.global_matrix().transform_vector(&player.velocity()),
+                    speed: player.linear_speed(),
+                };
+                (entity, member)
+            })
+            .collect::<HashMap<_, _>>();
+        let leaders = (&*entities, &flocks)
+            .join()
+            .filter(|(_, flock)| flock.leader)
+            .map(|(entity, flock)| (flock.id, entity))
+            .collect::<HashMap<_, _>>();
+
+        for (entity, flock, player, transform, steering) in (&*entities, &flocks, &players, &transforms, &mut steerings).join() {
+            if flock.leader { continue; }
+            let member = match members.get(&entity) {
+                Some(member) => member,
+                None => continue,
+            };
+
+            let neighbors = index
+                .query(member.position, flock.radius)
+                .filter(|(neighbor, _)| *neighbor != entity)
+                .filter_map(|(neighbor, _)| members.get(&neighbor))
+                .filter(|neighbor| neighbor.id == flock.id)
+                .copied()
+                .collect::<Vec<_>>();
+            let leader = leaders.get(&flock.id).and_then(|leader| members.get(leader));
+
+            let pull = Self::pull(flock, member, &neighbors, leader);
+            let speed = leader.map(|leader| leader.speed);
+            *steering = Self::steer(transform, player, &pull, speed).unwrap_or_default();
+        }
+    }
+}
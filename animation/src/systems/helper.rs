@@ -0,0 +1,98 @@
+use std::collections::HashMap;
+
+use amethyst::{
+    assets::{PrefabData, ProgressCounter},
+    core::{math::{Matrix4, Point3}, transform::{Parent, Transform}},
+    derive::{PrefabData, SystemDesc},
+    ecs::{Component, prelude::*, storage::GenericReadStorage},
+    error::Error,
+};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+
+/// Marks a rig node that only defines a position for others, e.g. a limb home or a pole target.
+#[derive(Debug, Default, Copy, Clone, Serialize, Deserialize, Component, PrefabData)]
+#[storage(NullStorage)]
+#[prefab(Component)]
+pub struct Helper {}
+
+/// Helper nodes folded into offsets from their parent, standing in for their deleted entities.
+///
+/// Folded helpers are static: nodes moved at runtime, like the homes of limbs that change posture
+/// or get calibrated, should not be marked.
+#[derive(Debug, Default)]
+pub struct Helpers {
+    /// Whether marked helpers are folded at all; off by default.
+    pub enabled: bool,
+    offsets: HashMap<Entity, (Entity, Matrix4<f32>)>,
+}
+
+impl Helpers {
+    pub fn new(enabled: bool) -> Self {
+        Helpers { enabled, offsets: HashMap::new() }
+    }
+
+    pub fn contains(&self, entity: Entity) -> bool {
+        self.offsets.contains_key(&entity)
+    }
+
+    pub fn entities(&self) -> impl Iterator<Item=Entity> + '_ {
+        self.offsets.keys().copied()
+    }
+
+    /// The global matrix of a folded helper, from the current transform of its parent.
+    pub fn global_matrix<S>(&self, entity: Entity, transforms: &S) -> Option<Matrix4<f32>>
+        where S: GenericReadStorage<Component=Transform> {
+        let (parent, local) = self.offsets.get(&entity)?;
+        Some(transforms.get(*parent)?.global_matrix() * local)
+    }
+
+    pub fn global_position<S>(&self, entity: Entity, transforms: &S) -> Option<Point3<f32>>
+        where S: GenericReadStorage<Component=Transform> {
+        self.global_matrix(entity, transforms)
+            .map(|matrix| matrix.transform_point(&Point3::origin()))
+    }
+}
+
+/// Folds the `Helper` leaves of instantiated scenes into `Helpers` and deletes their entities,
+/// shrinking the transform graph.
+#[derive(Default, SystemDesc)]
+pub struct HelperSystem;
+
+impl<'a> System<'a> for HelperSystem {
+    type SystemData = (
+        Entities<'a>,
+        WriteStorage<'a, Helper>,
+        ReadStorage<'a, Transform>,
+        ReadStorage<'a, Parent>,
+        Write<'a, Helpers>,
+    );
+
+    fn run(&mut self, (entities, mut markers, transforms, parents, mut helpers): Self::SystemData) {
+        if !helpers.enabled { return; }
+
+        helpers.offsets.retain(|_, (parent, _)| entities.is_alive(*parent));
+        if markers.is_empty() { return; }
+
+        let mut branches = BitSet::new();
+        for parent in parents.join() {
+            branches.add(parent.entity.id());
+        }
+
+        let mut kept = vec![];
+        for (entity, _, transform, parent) in (&*entities, &markers, &transforms, &parents).join() {
+            if branches.contains(entity.id()) {
+                info!("Kept helper {:?}, it has children", entity);
+                kept.push(entity);
+                continue;
+            }
+            helpers.offsets.insert(entity, (parent.entity, transform.matrix()));
+            if let Err(error) = entities.delete(entity) {
+                warn!("Failed to delete helper {:?}: {}", entity, error);
+            }
+        }
+        for entity in kept {
+            markers.remove(entity);
+        }
+    }
+}
@@ -0,0 +1,846 @@
+use std::{collections::HashMap, f32::EPSILON, ops::Neg};
+
+use amethyst::{
+    assets::{PrefabData, ProgressCounter},
+    core::{
+        ArcThreadPool,
+        bundle::SystemBundle,
+        Time,
+        math::{Point3, UnitQuaternion, Vector3},
+        transform::{Parent, ParentHierarchy, Transform, TransformSystemDesc},
+    },
+    derive::{PrefabData, SystemDesc},
+    ecs::{
+        AccessorCow, BatchAccessor, BatchController, BatchUncheckedWorld, Component, Dispatcher, prelude::*, RunningTime,
+        SystemData,
+    },
+    error::Error,
+};
+use amethyst::prelude::SystemDesc;
+use getset::CopyGetters;
+use itertools::{iterate, Itertools};
+use serde::{Deserialize, Serialize};
+
+use ceramic_derive::{Redirect, Resolve};
+use redirect::{Redirect, Resolve};
+
+use crate::{
+    extras::EntityRef,
+    systems::helper::Helpers,
+    utils::{determinism::Determinism, transform::{mark_descendants, TransformTrait}, warnings::Warnings},
+};
+
+#[derive(Debug, Copy, Clone, Component)]
+#[storage(DenseVecStorage)]
+pub struct Chain {
+    target: Entity,
+    length: usize,
+}
+
+impl Chain {
+    pub fn target(&self) -> Entity {
+        self.target
+    }
+
+    pub fn length(&self) -> usize {
+        self.length
+    }
+}
+
+/// Keeps a chain from being solved, e.g. while its body is too far away to see it.
+#[derive(Debug, Default, Copy, Clone, Component)]
+#[storage(NullStorage)]
+pub struct Dormant;
+
+#[derive(Debug, Clone, Serialize, Deserialize, Redirect, Resolve)]
+#[resolve(Chain)]
+pub struct ChainPrefab {
+    pub target: EntityRef,
+    #[redirect(skip)]
+    pub length: usize,
+}
+
+impl<'a> PrefabData<'a> for ChainPrefab {
+    type SystemData = WriteStorage<'a, Chain>;
+    type Result = ();
+
+    fn add_to_entity(
+        &self,
+        entity: Entity,
+        data: &mut Self::SystemData,
+        entities: &[Entity],
+        _children: &[Entity],
+    ) -> Result<Self::Result, Error> {
+        data.insert(entity, self.resolve(entities)).map(|_| ()).map_err(Into::into)
+    }
+}
+
+fn default_weight() -> f32 { 1.0 }
+
+#[derive(Debug, Copy, Clone, Serialize, Deserialize, PrefabData)]
+#[prefab(Component)]
+pub struct Hinge {
+    #[serde(skip_deserializing, skip_serializing)]
+    axis: Option<Vector3<f32>>,
+    limit: Option<[f32; 2]>,
+    /// Constrains of a joint are applied in ascending priority, so the highest one wins.
+    #[serde(default)]
+    priority: i32,
+    /// Influence when averaged with constrains of equal priority.
+    #[serde(default = "default_weight")]
+    weight: f32,
+}
+
+impl Hinge {
+    /// A hinge about the axis of the initial rotation of its joint, bending within `limit` radians if given.
+    pub fn new(limit: Option<[f32; 2]>) -> Self {
+        Hinge { axis: None, limit, priority: 0, weight: default_weight() }
+    }
+
+    pub fn limit(&self) -> Option<[f32; 2]> {
+        self.limit
+    }
+}
+
+impl Component for Hinge {
+    type Storage = DenseVecStorage<Self>;
+}
+
+#[derive(Debug, Copy, Clone, Component)]
+#[storage(DenseVecStorage)]
+pub struct Pole {
+    target: Entity,
+    priority: i32,
+    weight: f32,
+}
+
+impl Pole {
+    pub fn target(&self) -> Entity {
+        self.target
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Redirect, Resolve)]
+#[resolve(Pole)]
+pub struct PolePrefab {
+    pub target: EntityRef,
+    #[redirect(skip)]
+    #[serde(default)]
+    pub priority: i32,
+    #[redirect(skip)]
+    #[serde(default = "default_weight")]
+    pub weight: f32,
+}
+
+impl<'a> PrefabData<'a> for PolePrefab {
+    type SystemData = WriteStorage<'a, Pole>;
+    type Result = ();
+
+    fn add_to_entity(
+        &self,
+        entity: Entity,
+        data: &mut Self::SystemData,
+        entities: &[Entity],
+        _children: &[Entity],
+    ) -> Result<Self::Result, Error> {
+        data.insert(entity, self.resolve(entities)).map(|_| ()).map_err(Into::into)
+    }
+}
+
+#[derive(Debug, Copy, Clone, Component)]
+#[storage(HashMapStorage)]
+pub struct Direction {
+    target: Entity,
+    rotation: Option<UnitQuaternion<f32>>,
+    priority: Option<i32>,
+    weight: f32,
+}
+
+impl Direction {
+    pub fn target(&self) -> Entity {
+        self.target
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Redirect, Resolve)]
+#[resolve(Direction)]
+#[resolve(init(rotation: None))]
+pub struct DirectionPrefab {
+    pub target: EntityRef,
+    /// Applies the direction with the other constrains of its joint while solving a chain through it;
+    /// without a priority it is applied after all chains are solved.
+    #[redirect(skip)]
+    #[serde(default)]
+    pub priority: Option<i32>,
+    #[redirect(skip)]
+    #[serde(default = "default_weight")]
+    pub weight: f32,
+}
+
+impl<'a> PrefabData<'a> for DirectionPrefab {
+    type SystemData = WriteStorage<'a, Direction>;
+    type Result = ();
+
+    fn add_to_entity(
+        &self,
+        entity: Entity,
+        data: &mut Self::SystemData,
+        entities: &[Entity],
+        _children: &[Entity],
+    ) -> Result<Self::Result, Error> {
+        data.insert(entity, self.resolve(entities)).map(|_| ()).map_err(Into::into)
+    }
+}
+
+#[derive(Debug, Copy, Clone, Component)]
+#[storage(HashMapStorage)]
+pub struct Distance {
+    target: Entity,
+    distance: f32,
+}
+
+impl Distance {
+    pub fn target(&self) -> Entity {
+        self.target
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Redirect, Resolve)]
+#[resolve(Distance)]
+pub struct DistancePrefab {
+    pub target: EntityRef,
+    #[redirect(skip)]
+    #[serde(default)]
+    pub distance: f32,
+}
+
+impl<'a> PrefabData<'a> for DistancePrefab {
+    type SystemData = WriteStorage<'a, Distance>;
+    type Result = ();
+
+    fn add_to_entity(
+        &self,
+        entity: Entity,
+        data: &mut Self::SystemData,
+        entities: &[Entity],
+        _children: &[Entity],
+    ) -> Result<Self::Result, Error> {
+        data.insert(entity, self.resolve(entities)).map(|_| ()).map_err(Into::into)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PrefabData, Redirect)]
+#[serde(deny_unknown_fields)]
+pub enum ConstrainPrefab {
+    #[redirect(skip)]
+    Hinge(Hinge),
+    Pole(PolePrefab),
+    Direction(DirectionPrefab),
+    Distance(DistancePrefab),
+}
+
+#[derive(Default, SystemDesc)]
+pub struct KinematicsSetupSystem;
+
+impl KinematicsSetupSystem {
+    pub fn setup_direction(
+        entity: Entity,
+        transforms: ReadStorage<'_, Transform>,
+        direction: &mut Direction,
+    ) -> Option<()> {
+        if direction.rotation.is_none() {
+            let transform_vector = |ref vector| {
+                let ref global = transforms
+                    .get(direction.target)?
+                    .global_matrix()
+                    .transform_vector(vector);
+                transforms
+                    .get(entity)
+                    .map(|transform| transform.global_view_matrix().transform_vector(global))
+            };
+            let ref dir = transform_vector(Vector3::z())?;
+            let ref up = transform_vector(Vector3::y())?;
+            direction.rotation.replace(UnitQuaternion::face_towards(dir, up));
+        }
+        Some(())
+    }
+}
+
+impl<'a> System<'a> for KinematicsSetupSystem {
+    type SystemData = (
+        Entities<'a>,
+        ReadStorage<'a, Transform>,
+        WriteStorage<'a, Hinge>,
+        WriteStorage<'a, Direction>,
+    );
+
+    fn run(&mut self, (entities, transforms, mut hinges, mut directions): Self::SystemData) {
+        for (transform, hinge) in (&transforms, &mut hinges).join() {
+            if hinge.axis.is_none() {
+                hinge.axis = transform
+                    .rotation()
+                    .axis()
+                    .map(|axis| axis.into_inner());
+            }
+        }
+
+        for (entity, direction) in (&*entities, &mut directions).join() {
+            Self::setup_direction(entity, transforms.clone(), direction);
+        }
+    }
+}
+
+/// A constrain acting on a single joint of a chain.
+#[derive(Debug, Copy, Clone)]
+enum Constrain<'a> {
+    Pole(&'a Pole),
+    Hinge(&'a Hinge),
+    Direction(&'a Direction),
+}
+
+/// Solves chains and direction constrains.
+///
+/// Constrains on the same joint are applied in ascending priority; those of equal priority are either applied
+/// in the order pole, hinge, direction, or averaged by weight if `Config::blend_equal_priority` is set.
+/// Directions without a priority are applied after all chains, to whatever pose the chains left.
+/// Chains are only solved when the target or one of the joints changed,
+/// with a full solve forced every `Config::full_solve_interval` iterations.
+#[derive(Default)]
+pub struct KinematicsSystem {
+    reader_id: Option<ReaderId<ComponentEvent>>,
+    dirty: BitSet,
+    frame: usize,
+    /// Joints whose direction constrain was applied while solving their chain.
+    solved_directions: BitSet,
+}
+
+impl KinematicsSystem {
+    fn collect_entities(
+        parents: ReadStorage<'_, Parent>,
+        entity: Entity,
+        length: usize,
+    ) -> Option<Vec<Entity>> {
+        iterate(Some(entity), |entity| {
+            match entity {
+                None => None,
+                Some(entity) => parents
+                    .get(*entity)
+                    .map(|parent| parent.entity),
+            }
+        })
+            .take(length)
+            .collect()
+    }
+
+    fn solve_inverse_kinematics(
+        entities: Vec<Entity>,
+        chain: &Chain,
+        config: &Config,
+        transforms: &mut WriteStorage<'_, Transform>,
+        hinges: ReadStorage<'_, Hinge>,
+        poles: ReadStorage<'_, Pole>,
+        directions: ReadStorage<'_, Direction>,
+        helpers: &Helpers,
+        solved_directions: &mut BitSet,
+    ) -> Option<()> {
+        let mut end = Point3::<f32>::origin();
+        let ref target = transforms.get(chain.target)?.global_position();
+        let mut target = transforms
+            .get(*entities.first()?)?
+            .global_view_matrix()
+            .transform_point(target);
+
+        if target.coords.norm() < config.eps { return Some(()); }
+
+        for (child, parent) in entities.into_iter().tuple_windows() {
+            end = transforms.get(child)?.matrix().transform_point(&end);
+            target = transforms.get(child)?.matrix().transform_point(&target);
+
+            // Align the end with the target.
+            if let Some(rotation) = UnitQuaternion::rotation_between(&end.coords, &target.coords) {
+                Self::apply_rotation(parent, &rotation, transforms, &mut target)?;
+            }
+
+            // Apply the constrains of the joint in order of priority.
+            let direction = directions
+                .get(parent)
+                .and_then(|direction| Some((direction.priority?, direction.weight, Constrain::Direction(direction))));
+            if direction.is_some() {
+                solved_directions.add(parent.id());
+            }
+            let mut constrains = [
+                poles.get(parent).map(|pole| (pole.priority, pole.weight, Constrain::Pole(pole))),
+                hinges.get(parent).map(|hinge| (hinge.priority, hinge.weight, Constrain::Hinge(hinge))),
+                direction,
+            ];
+            // The sort is stable, so constrains of equal priority stay in the order above.
+            constrains.sort_by_key(|constrain| constrain.map(|(priority, _, _)| priority));
+
+            for (_, group) in &constrains.iter().flatten().group_by(|(priority, _, _)| *priority) {
+                if config.blend_equal_priority {
+                    let mut blended: Option<(UnitQuaternion<f32>, f32)> = None;
+                    for &(_, weight, constrain) in group {
+                        let rotation = match Self::constrain_rotation(constrain, parent, child, &end, transforms, helpers) {
+                            Some(rotation) => rotation,
+                            None => continue,
+                        };
+                        blended = Some(match blended {
+                            None => (rotation, weight),
+                            Some((average, total)) => {
+                                let total = total + weight;
+                                let average = average
+                                    .try_slerp(&rotation, weight / total, EPSILON)
+                                    .unwrap_or(average);
+                                (average, total)
+                            }
+                        });
+                    }
+                    if let Some((ref rotation, _)) = blended {
+                        Self::apply_rotation(parent, rotation, transforms, &mut target)?;
+                    }
+                } else {
+                    for &(_, _, constrain) in group {
+                        if let Some(ref rotation) = Self::constrain_rotation(constrain, parent, child, &end, transforms, helpers) {
+                            Self::apply_rotation(parent, rotation, transforms, &mut target)?;
+                        }
+                    }
+                }
+            }
+        }
+        Some(())
+    }
+
+    /// Rotate the joint locally, keeping the target fixed in the world.
+    fn apply_rotation(
+        entity: Entity,
+        rotation: &UnitQuaternion<f32>,
+        transforms: &mut WriteStorage<'_, Transform>,
+        target: &mut Point3<f32>,
+    ) -> Option<()> {
+        if let Some((axis, angle)) = rotation.axis_angle() {
+            transforms
+                .get_mut(entity)?
+                .append_rotation(axis, angle);
+            *target = UnitQuaternion::from_axis_angle(&axis, -angle)
+                .transform_point(target);
+        }
+        Some(())
+    }
+
+    /// The local rotation of `parent` that satisfies the constrain.
+    fn constrain_rotation(
+        constrain: Constrain<'_>,
+        parent: Entity,
+        child: Entity,
+        end: &Point3<f32>,
+        transforms: &WriteStorage<'_, Transform>,
+        helpers: &Helpers,
+    ) -> Option<UnitQuaternion<f32>> {
+        match constrain {
+            // Align the joint with pole.
+            Constrain::Pole(pole) => {
+                let ref pole = match transforms.get(pole.target) {
+                    Some(transform) => transform.global_position(),
+                    None => helpers.global_position(pole.target, transforms)?,
+                };
+                let ref pole = transforms
+                    .get(parent)?
+                    .global_view_matrix()
+                    .transform_point(pole)
+                    .coords;
+                let direction = transforms
+                    .get(child)?
+                    .translation();
+                let ref axis = end.coords.normalize();
+
+                let ref pole = pole - axis.scale(pole.dot(axis));
+                let ref direction = direction - axis.scale(direction.dot(axis));
+
+                UnitQuaternion::rotation_between(direction, pole)
+            }
+            // Apply hinge constraint.
+            Constrain::Hinge(hinge) => {
+                let ref hinge_axis = hinge.axis?;
+                let rotation = transforms.get(parent)?.rotation().clone();
+                let ref parent_axis = rotation.inverse_transform_vector(hinge_axis);
+                let align = UnitQuaternion::rotation_between(hinge_axis, parent_axis)
+                    .unwrap_or_else(UnitQuaternion::identity);
+
+                // Apply hinge limit.
+                let limit = hinge.limit
+                    .and_then(|[min, max]| {
+                        let (axis, angle) = (rotation * align).axis_angle()?;
+                        let (axis, angle) = if axis.dot(hinge_axis) < 0.0 {
+                            (axis.neg(), angle.neg())
+                        } else {
+                            (axis, angle)
+                        };
+                        let angle = angle.min(max).max(min) - angle;
+                        Some(UnitQuaternion::from_axis_angle(&axis, angle))
+                    })
+                    .unwrap_or_else(UnitQuaternion::identity);
+
+                Some(align * limit)
+            }
+            Constrain::Direction(direction) => Self::direction_rotation(parent, direction, transforms),
+        }
+    }
+
+    fn direction_rotation(
+        entity: Entity,
+        direction: &Direction,
+        transforms: &WriteStorage<'_, Transform>,
+    ) -> Option<UnitQuaternion<f32>> {
+        let ref rotation = direction.rotation?;
+        let target_rotation = {
+            let transform_vector = |ref vector| {
+                let ref global = transforms
+                    .get(direction.target)?
+                    .global_matrix()
+                    .transform_vector(vector);
+                transforms
+                    .get(entity)
+                    .map(|transform| transform.global_view_matrix().transform_vector(global))
+            };
+            let ref dir = transform_vector(Vector3::z())?;
+            let ref up = transform_vector(Vector3::y())?;
+            UnitQuaternion::face_towards(dir, up)
+        };
+        Some(target_rotation * rotation.inverse())
+    }
+
+    fn solve_direction(
+        entity: Entity,
+        direction: &Direction,
+        transforms: &mut WriteStorage<'_, Transform>,
+    ) -> Option<()> {
+        let rotation = Self::direction_rotation(entity, direction, transforms)?;
+        if let Some((axis, angle)) = rotation.axis_angle() {
+            transforms
+                .get_mut(entity)?
+                .append_rotation(axis, angle);
+        }
+        Some(())
+    }
+}
+
+impl<'a> System<'a> for KinematicsSystem {
+    type SystemData = (
+        Entities<'a>,
+        ReadStorage<'a, Parent>,
+        ReadExpect<'a, ParentHierarchy>,
+        WriteStorage<'a, Transform>,
+        ReadStorage<'a, Chain>,
+        ReadStorage<'a, Dormant>,
+        ReadStorage<'a, Hinge>,
+        ReadStorage<'a, Pole>,
+        ReadStorage<'a, Direction>,
+        Read<'a, Helpers>,
+        ReadExpect<'a, Config>,
+        Write<'a, Warnings>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            entities,
+            parents,
+            hierarchy,
+            mut transforms,
+            chains,
+            dormants,
+            hinges,
+            poles,
+            directions,
+            helpers,
+            config,
+            mut warnings,
+        ) = data;
+
+        let reader_id = self.reader_id
+            .as_mut()
+            .expect("`KinematicsSystem::setup` was not called before `KinematicsSystem::run`");
+        self.dirty.clear();
+        for event in transforms.channel().read(reader_id) {
+            match event {
+                ComponentEvent::Inserted(id) | ComponentEvent::Modified(id) => { self.dirty.add(*id); }
+                ComponentEvent::Removed(_) => {}
+            }
+        }
+        mark_descendants(&mut self.dirty, &hierarchy);
+
+        let full_solve = config.full_solve_interval == 0 || self.frame % config.full_solve_interval == 0;
+        self.frame = self.frame.wrapping_add(1);
+        let dirty = &self.dirty;
+        let solved_directions = &mut self.solved_directions;
+        solved_directions.clear();
+
+        // Solve inverse kinematics constrains.
+        for (entity, chain, _) in (&*entities, &chains, !&dormants).join() {
+            let joints = match Self::collect_entities(parents.clone(), entity, chain.length) {
+                Some(joints) => joints,
+                None => {
+                    warnings.skip("kinematics", entity);
+                    continue;
+                }
+            };
+            let changed = full_solve
+                || dirty.contains(chain.target.id())
+                || joints.iter().any(|joint| dirty.contains(joint.id()));
+            if !changed { continue; }
+
+            let solved = Self::solve_inverse_kinematics(
+                joints,
+                chain,
+                &config,
+                &mut transforms,
+                hinges.clone(),
+                poles.clone(),
+                directions.clone(),
+                &helpers,
+                &mut *solved_directions,
+            );
+            if solved.is_none() {
+                warnings.skip("kinematics", entity);
+            }
+        }
+
+        // Solve direction constrains not already solved as part of a chain.
+        for (entity, direction, _) in (&*entities, &directions, !&*solved_directions).join() {
+            if Self::solve_direction(entity, direction, &mut transforms).is_none() {
+                warnings.skip("kinematics", entity);
+            }
+        }
+
+        // Discard events caused by our own writes.
+        transforms.channel().read(reader_id).for_each(drop);
+    }
+
+    fn setup(&mut self, world: &mut World) {
+        Self::SystemData::setup(world);
+        self.reader_id = Some(WriteStorage::<Transform>::fetch(world).register_reader());
+    }
+}
+/// Joints closer than this angle to their interpolated rotation are left alone.
+const SETTLE_ANGLE: f32 = 1.0e-4;
+
+#[derive(Debug, Copy, Clone, CopyGetters)]
+#[get_copy = "pub"]
+pub struct Config {
+    iter: usize,
+    eps: f32,
+    /// Number of solver iterations between forced full solves; `0` solves every chain every iteration.
+    full_solve_interval: usize,
+    /// Solve at this fixed rate (Hz) and interpolate joints in between, instead of solving every frame.
+    rate: Option<f32>,
+    /// Average constrains of equal priority on a joint by weight, instead of applying them in turn.
+    blend_equal_priority: bool,
+}
+
+pub struct KinematicsBatchSystem<'a, 'b> {
+    accessor: BatchAccessor,
+    dispatcher: Dispatcher<'a, 'b>,
+    /// Time elapsed since the last solve, used when running at a fixed rate.
+    elapsed: f32,
+    /// Joint rotations of the solve before the latest that joints are interpolated from.
+    previous: HashMap<Entity, UnitQuaternion<f32>>,
+    /// Joint rotations of the latest solve that joints are interpolated toward.
+    latest: HashMap<Entity, UnitQuaternion<f32>>,
+}
+
+impl KinematicsBatchSystem<'_, '_> {
+    fn joint_rotations(world: &World) -> HashMap<Entity, UnitQuaternion<f32>> {
+        let entities = world.entities();
+        let parents = world.read_storage::<Parent>();
+        let transforms = world.read_storage::<Transform>();
+        let chains = world.read_storage::<Chain>();
+        let directions = world.read_storage::<Direction>();
+
+        let joints = (&*entities, &chains)
+            .join()
+            .filter_map(|(entity, chain)| KinematicsSystem::collect_entities(parents.clone(), entity, chain.length))
+            .flatten()
+            .chain((&*entities, &directions).join().map(|(entity, _)| entity));
+        joints
+            .filter_map(|entity| transforms
+                .get(entity)
+                .map(|transform| (entity, transform.rotation().clone())))
+            .collect()
+    }
+
+    /// Sets the rotations of the joints that are not there yet.
+    ///
+    /// Joints already within `SETTLE_ANGLE` are not touched, so resting chains don't flag their transforms as modified.
+    fn set_rotations<'r>(world: &World, rotations: impl Iterator<Item = (&'r Entity, UnitQuaternion<f32>)>) {
+        let mut transforms = world.write_storage::<Transform>();
+        for (entity, rotation) in rotations {
+            let moved = transforms
+                .get(*entity)
+                .map_or(false, |transform| transform.rotation().angle_to(&rotation) > SETTLE_ANGLE);
+            if moved {
+                if let Some(transform) = transforms.get_mut(*entity) {
+                    transform.set_rotation(rotation);
+                }
+            }
+        }
+    }
+}
+
+impl<'a, 'b> BatchController<'a, 'b> for KinematicsBatchSystem<'a, 'b> {
+    type BatchSystemData = (ReadExpect<'a, Config>, Read<'a, Time>, Read<'a, Determinism>);
+
+    unsafe fn create(accessor: BatchAccessor, dispatcher: Dispatcher<'a, 'b>) -> Self {
+        KinematicsBatchSystem {
+            accessor,
+            dispatcher,
+            elapsed: 0.0,
+            previous: HashMap::new(),
+            latest: HashMap::new(),
+        }
+    }
+}
+
+impl<'a> System<'a> for KinematicsBatchSystem<'_, '_> {
+    type SystemData = BatchUncheckedWorld<'a>;
+
+    fn run(&mut self, data: Self::SystemData) {
+        let world = data.0;
+        let config = *world.fetch::<Config>();
+
+        let rate = match config.rate {
+            Some(rate) => rate,
+            None => {
+                for _ in 0..config.iter {
+                    self.dispatcher.dispatch(world);
+                }
+                return;
+            }
+        };
+
+        let delta_seconds = world.fetch::<Determinism>().delta_seconds(world.fetch::<Time>().delta_seconds());
+        self.elapsed += delta_seconds;
+
+        let step = 1.0 / rate;
+        if self.elapsed >= step {
+            self.elapsed %= step;
+
+            // Solve, remember the solution, and then restore the pose so joints can be interpolated toward it.
+            let pose = Self::joint_rotations(world);
+            for _ in 0..config.iter {
+                self.dispatcher.dispatch(world);
+            }
+            let solution = Self::joint_rotations(world);
+            Self::set_rotations(world, pose.iter().map(|(entity, rotation)| (entity, *rotation)));
+
+            // Joints new to this solve are interpolated from their current pose.
+            std::mem::swap(&mut self.previous, &mut self.latest);
+            self.latest = solution;
+            for (entity, rotation) in pose {
+                self.previous.entry(entity).or_insert(rotation);
+            }
+        }
+
+        // Lag a step behind the solver, so joints reach each solution just as the next one is solved.
+        let t = (self.elapsed / step).min(1.0);
+        let (previous, latest) = (&self.previous, &self.latest);
+        let rotations = latest.iter().map(|(entity, latest)| {
+            let rotation = previous
+                .get(entity)
+                .and_then(|previous| previous.try_slerp(latest, t, EPSILON))
+                .unwrap_or(*latest);
+            (entity, rotation)
+        });
+        Self::set_rotations(world, rotations);
+    }
+
+    fn running_time(&self) -> RunningTime {
+        RunningTime::VeryLong
+    }
+
+    fn accessor<'b>(&'b self) -> AccessorCow<'a, 'b, Self> {
+        AccessorCow::Ref(&self.accessor)
+    }
+
+    fn setup(&mut self, world: &mut World) {
+        self.dispatcher.setup(world);
+    }
+}
+
+unsafe impl Send for KinematicsBatchSystem<'_, '_> {}
+
+pub struct KinematicsBundle {
+    iter: usize,
+    eps: f32,
+    full_solve_interval: usize,
+    rate: Option<f32>,
+    blend_equal_priority: bool,
+    dependencies: Vec<String>,
+}
+
+impl KinematicsBundle {
+    pub fn new(iter: usize, eps: f32) -> Self {
+        KinematicsBundle {
+            iter,
+            eps,
+            full_solve_interval: 120,
+            rate: None,
+            blend_equal_priority: false,
+            dependencies: vec![],
+        }
+    }
+
+    /// Solve after the systems named in `dependencies`.
+    pub fn with_dep(mut self, dependencies: &[&str]) -> Self {
+        self.dependencies = dependencies.iter().map(|name| name.to_string()).collect();
+        self
+    }
+
+    /// Average constrains of equal priority on the same joint by their weights.
+    pub fn with_priority_blending(mut self) -> Self {
+        self.blend_equal_priority = true;
+        self
+    }
+
+    /// Solve at a fixed `rate` (Hz), interpolating joint rotations between the last two solutions on other frames.
+    pub fn with_rate(mut self, rate: f32) -> Self {
+        self.rate = Some(rate);
+        self
+    }
+
+    /// Force every chain to be solved every `interval` solver iterations, even if nothing changed.
+    pub fn with_full_solve_interval(mut self, interval: usize) -> Self {
+        self.full_solve_interval = interval;
+        self
+    }
+}
+
+impl SystemBundle<'static, 'static> for KinematicsBundle {
+    fn build(
+        self,
+        world: &mut World,
+        builder: &mut DispatcherBuilder<'static, 'static>,
+    ) -> Result<(), Error> {
+        world.insert(Config {
+            iter: self.iter,
+            eps: self.eps,
+            full_solve_interval: self.full_solve_interval,
+            rate: self.rate,
+            blend_equal_priority: self.blend_equal_priority,
+        });
+        world.entry::<Determinism>().or_insert_with(Determinism::default);
+
+        let kinematics_builder = DispatcherBuilder::new()
+            .with(TransformSystemDesc::default().build(world), "transform", &[])
+            .with(KinematicsSetupSystem::default(), "setup", &["transform"])
+            .with(KinematicsSystem::default(), "kinematics", &["transform", "setup"])
+            .with_pool((*world.fetch::<ArcThreadPool>()).clone());
+
+        let dependencies = self.dependencies.iter().map(String::as_str).collect::<Vec<_>>();
+        builder.add_batch::<KinematicsBatchSystem<'static, 'static>>(
+            kinematics_builder,
+            "kinematics_batch",
+            &dependencies,
+        );
+
+        Ok(())
+    }
+}
\ No newline at end of file
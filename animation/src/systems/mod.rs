@@ -0,0 +1,22 @@
+pub mod player;
+pub mod animal;
+pub mod appendage;
+pub mod avoidance;
+pub mod behavior;
+pub mod blend;
+pub mod cache;
+pub mod carry;
+pub mod cleanup;
+pub mod effects;
+pub mod flock;
+pub mod helper;
+pub mod kinematics;
+pub mod modifier;
+pub mod particle;
+pub mod path;
+pub mod physics;
+pub mod platform;
+pub mod socket;
+pub mod spatial;
+pub mod validation;
+pub mod water;
@@ -0,0 +1,99 @@
+use std::collections::HashMap;
+
+use amethyst::{
+    assets::PrefabData,
+    core::{math::Point3, Transform},
+    derive::PrefabData,
+    ecs::{Component, prelude::*},
+    error::Error,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{systems::player::Player, utils::transform::TransformTrait};
+
+/// A box shaped trigger volume changing how players inside it move, e.g. mud or ice.
+#[derive(Debug, Copy, Clone, Serialize, Deserialize, PrefabData, Component)]
+#[prefab(Component)]
+#[storage(HashMapStorage)]
+pub struct MovementModifier {
+    /// Half size of the volume along the local axes of the node.
+    pub extent: [f32; 3],
+    /// Caps the upper speed limit of the player.
+    #[serde(default)]
+    pub max_speed: Option<f32>,
+    /// Scales how fast the player responds to steering; below 1 makes it slide.
+    #[serde(default)]
+    pub stiffness_factor: Option<f32>,
+}
+
+impl MovementModifier {
+    fn contains(&self, transform: &Transform, point: &Point3<f32>) -> bool {
+        let local = match transform.global_matrix().try_inverse() {
+            Some(inverse) => inverse.transform_point(point),
+            None => return false,
+        };
+        let [x, y, z] = self.extent;
+        local.x.abs() <= x && local.y.abs() <= y && local.z.abs() <= z
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+struct Unmodified {
+    stiffness: f32,
+    speed_limit: [f32; 2],
+}
+
+/// Applies the `MovementModifier`s a player is inside of, and restores it once it leaves them all.
+///
+/// Must run before the `PlayerSystem`.
+#[derive(Default)]
+pub struct MovementModifierSystem {
+    unmodified: HashMap<Entity, Unmodified>,
+}
+
+impl<'a> System<'a> for MovementModifierSystem {
+    type SystemData = (
+        Entities<'a>,
+        ReadStorage<'a, Transform>,
+        ReadStorage<'a, MovementModifier>,
+        WriteStorage<'a, Player>,
+    );
+
+    fn run(&mut self, (entities, transforms, modifiers, mut players): Self::SystemData) {
+        self.unmodified.retain(|&entity, _| entities.is_alive(entity));
+
+        for (entity, transform, player) in (&*entities, &transforms, &mut players).join() {
+            let position = transform.global_position();
+            let active = (&transforms, &modifiers)
+                .join()
+                .filter(|(transform, modifier)| modifier.contains(transform, &position))
+                .map(|(_, modifier)| modifier)
+                .collect::<Vec<_>>();
+
+            if active.is_empty() {
+                if let Some(unmodified) = self.unmodified.remove(&entity) {
+                    player.set_stiffness(unmodified.stiffness);
+                    player.set_speed_limit(unmodified.speed_limit);
+                }
+                continue;
+            }
+
+            let unmodified = *self.unmodified
+                .entry(entity)
+                .or_insert(Unmodified { stiffness: player.stiffness(), speed_limit: player.speed_limit() });
+
+            let [min, mut max] = unmodified.speed_limit;
+            let mut stiffness = unmodified.stiffness;
+            for modifier in active {
+                if let Some(max_speed) = modifier.max_speed {
+                    max = max.min(max_speed);
+                }
+                if let Some(factor) = modifier.stiffness_factor {
+                    stiffness *= factor;
+                }
+            }
+            player.set_stiffness(stiffness);
+            player.set_speed_limit([min.min(max), max]);
+        }
+    }
+}
@@ -0,0 +1,153 @@
+use amethyst::{
+    assets::PrefabData,
+    core::{math::Point3, Transform},
+    derive::SystemDesc,
+    ecs::{Component, prelude::*},
+    error::Error,
+};
+use getset::Setters;
+use serde::{Deserialize, Serialize};
+
+use ceramic_derive::{Redirect, Resolve};
+use redirect::{Redirect, Resolve};
+
+use crate::{
+    extras::EntityRef,
+    systems::physics::{Physics, PhysicsData, StepTime},
+    utils::{determinism::Determinism, transform::TransformTrait},
+};
+
+#[derive(Debug, Default, Copy, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ParticlePrefab {
+    pub mass: f32,
+}
+
+impl<'a> PrefabData<'a> for ParticlePrefab {
+    type SystemData = PhysicsData<'a>;
+    type Result = ();
+
+    fn add_to_entity(
+        &self,
+        entity: Entity,
+        physics: &mut Self::SystemData,
+        _: &[Entity],
+        _: &[Entity],
+    ) -> Result<Self::Result, Error> {
+        physics.create_particle(entity, self.mass)
+    }
+}
+
+fn default_sleep_velocity() -> f32 {
+    0.01
+}
+
+fn default_sleep_distance() -> f32 {
+    0.001
+}
+
+#[derive(Debug, Copy, Clone, Setters, Component)]
+#[storage(DenseVecStorage)]
+pub struct Spring {
+    target: Entity,
+    #[set = "pub"]
+    stiffness: f32,
+    #[set = "pub"]
+    damp: f32,
+    sleep_velocity: f32,
+    sleep_distance: f32,
+    /// The target position when the spring fell asleep.
+    rest: Option<Point3<f32>>,
+}
+
+impl Spring {
+    pub fn target(&self) -> Entity {
+        self.target
+    }
+
+    pub fn is_sleeping(&self) -> bool {
+        self.rest.is_some()
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Redirect, Resolve)]
+#[resolve(Spring)]
+#[resolve(init(rest: None))]
+pub struct SpringPrefab {
+    pub target: EntityRef,
+    #[redirect(skip)]
+    pub stiffness: f32,
+    #[redirect(skip)]
+    pub damp: f32,
+    /// Speed below which the particle may fall asleep.
+    #[redirect(skip)]
+    #[serde(default = "default_sleep_velocity")]
+    pub sleep_velocity: f32,
+    /// Distance to the target below which the particle may fall asleep,
+    /// and how far the target moves before waking it up again.
+    #[redirect(skip)]
+    #[serde(default = "default_sleep_distance")]
+    pub sleep_distance: f32,
+}
+
+impl<'a> PrefabData<'a> for SpringPrefab {
+    type SystemData = WriteStorage<'a, Spring>;
+    type Result = ();
+
+    fn add_to_entity(
+        &self,
+        entity: Entity,
+        data: &mut Self::SystemData,
+        entities: &[Entity],
+        _children: &[Entity],
+    ) -> Result<Self::Result, Error> {
+        data.insert(entity, self.resolve(entities)).map(|_| ()).map_err(Into::into)
+    }
+}
+
+#[derive(Default, SystemDesc)]
+pub struct ParticleSystem;
+
+impl<'a> System<'a> for ParticleSystem {
+    type SystemData = (
+        Entities<'a>,
+        ReadStorage<'a, Transform>,
+        WriteStorage<'a, Spring>,
+        PhysicsData<'a>,
+        Read<'a, StepTime>,
+        Read<'a, Determinism>,
+    );
+
+    fn run(&mut self, (entities, transforms, mut springs, mut physics, time, determinism): Self::SystemData) {
+        let delta_seconds = determinism.step_seconds(time.delta_seconds());
+        for (entity, spring) in (&*entities, &mut springs).join() {
+            let position = match physics.position(entity) {
+                Some(position) => position,
+                None => continue,
+            };
+            let target = match transforms.get(spring.target) {
+                Some(transform) => transform.global_position(),
+                None => continue,
+            };
+
+            if let Some(rest) = spring.rest {
+                if (target - rest).norm() <= spring.sleep_distance { continue; }
+                spring.rest = None;
+            }
+
+            let ref delta = target - position;
+            let ref impulse = delta.scale(spring.stiffness / delta_seconds);
+            physics.apply_impulse(entity, impulse);
+
+            if let Some(velocity) = physics.velocity(entity) {
+                let ref damp = velocity.scale(-spring.damp);
+                physics.apply_force(entity, damp);
+
+                if velocity.norm() < spring.sleep_velocity && delta.norm() < spring.sleep_distance {
+                    spring.rest = Some(target);
+                    physics.sleep(entity);
+                }
+            }
+        }
+    }
+}
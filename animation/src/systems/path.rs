@@ -0,0 +1,203 @@
+use std::f32::consts::FRAC_PI_2;
+
+use amethyst::{
+    assets::PrefabData,
+    core::{math::Point3, Transform},
+    derive::SystemDesc,
+    ecs::{Component, prelude::*},
+    error::{Error, format_err},
+};
+use serde::{Deserialize, Serialize};
+
+use ceramic_derive::Redirect;
+use redirect::{Redirect, Resolve};
+
+use crate::{
+    extras::EntityRef,
+    systems::player::{Player, Steering},
+    utils::{transform::TransformTrait, warnings::Warnings},
+};
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Waypoint {
+    /// A node of the scene, followed wherever it moves.
+    Node(Entity),
+    Point(Point3<f32>),
+}
+
+/// Walks a creature along its waypoints in place of the input.
+#[derive(Debug, Clone, Component)]
+#[storage(HashMapStorage)]
+pub struct PathFollower {
+    waypoints: Vec<Waypoint>,
+    /// Horizontal distance at which a waypoint counts as reached.
+    reach: f32,
+    /// Start over from the first waypoint after the last.
+    looping: bool,
+    /// Linear speed the creature eases to while walking, or its own if `None`.
+    speed: Option<f32>,
+    current: usize,
+}
+
+impl PathFollower {
+    pub fn waypoints(&self) -> &[Waypoint] {
+        &self.waypoints
+    }
+
+    /// Index of the waypoint headed for, or `None` once the path is walked.
+    pub fn current(&self) -> Option<usize> {
+        Some(self.current).filter(|&current| current < self.waypoints.len())
+    }
+
+    /// Replaces the waypoints and heads for the first.
+    pub fn set_waypoints(&mut self, waypoints: Vec<Waypoint>) {
+        self.waypoints = waypoints;
+        self.current = 0;
+    }
+
+    pub fn restart(&mut self) {
+        self.current = 0;
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Redirect)]
+pub enum WaypointPrefab {
+    Node(EntityRef),
+    #[redirect(skip)]
+    Point([f32; 3]),
+}
+
+fn default_reach() -> f32 {
+    1.0
+}
+
+/// A route for a creature, set on the node with the `Player`, e.g.
+/// `"path": { "waypoints": [{ "Node": "Gate" }, { "Point": [4.0, 0.0, 8.0] }], "looping": true, "speed": 1.5 }`.
+#[derive(Debug, Clone, Serialize, Deserialize, Redirect)]
+pub struct PathFollowerPrefab {
+    pub waypoints: Vec<WaypointPrefab>,
+    #[serde(default = "default_reach")]
+    #[redirect(skip)]
+    pub reach: f32,
+    #[serde(default)]
+    #[redirect(skip)]
+    pub looping: bool,
+    #[serde(default)]
+    #[redirect(skip)]
+    pub speed: Option<f32>,
+}
+
+impl<'a> PrefabData<'a> for PathFollowerPrefab {
+    type SystemData = (WriteStorage<'a, PathFollower>, WriteStorage<'a, Steering>);
+    type Result = ();
+
+    fn add_to_entity(
+        &self,
+        entity: Entity,
+        (followers, steerings): &mut Self::SystemData,
+        entities: &[Entity],
+        _children: &[Entity],
+    ) -> Result<Self::Result, Error> {
+        if self.reach <= 0.0 {
+            return Err(format_err!("A path needs a positive reach, got {}", self.reach));
+        }
+        let waypoints = self.waypoints
+            .iter()
+            .map(|waypoint| match waypoint {
+                WaypointPrefab::Node(node) => Waypoint::Node(node.resolve(entities)),
+                WaypointPrefab::Point([x, y, z]) => Waypoint::Point(Point3::new(*x, *y, *z)),
+            })
+            .collect();
+        let component = PathFollower {
+            waypoints,
+            reach: self.reach,
+            looping: self.looping,
+            speed: self.speed,
+            current: 0,
+        };
+        followers.insert(entity, component)?;
+        steerings.insert(entity, Steering::default())?;
+        Ok(())
+    }
+}
+
+/// Steers creatures with a `PathFollower` toward their current waypoint, moving on to the next once reached.
+///
+/// The `PlayerSystem` turns the steering into the movement and spinning the locomotion reads,
+/// so it must run after this. A walked path leaves the creature standing.
+#[derive(Default, SystemDesc)]
+pub struct PathFollowSystem;
+
+impl PathFollowSystem {
+    fn position(waypoint: &Waypoint, transforms: &ReadStorage<'_, Transform>) -> Option<Point3<f32>> {
+        match waypoint {
+            Waypoint::Node(entity) => transforms.get(*entity).map(TransformTrait::global_position),
+            Waypoint::Point(point) => Some(*point),
+        }
+    }
+
+    /// The waypoint `follower` heads for from `position`, skipping those already reached.
+    fn target(
+        follower: &mut PathFollower,
+        position: &Point3<f32>,
+        transforms: &ReadStorage<'_, Transform>,
+    ) -> Option<Point3<f32>> {
+        // Each waypoint is passed at most once a frame, so a looping path within reach doesn't spin forever.
+        for _ in 0..follower.waypoints.len() {
+            let waypoint = follower.waypoints.get(follower.current)?;
+            let target = Self::position(waypoint, transforms)?;
+            if (target - position).xz().norm() > follower.reach { return Some(target); }
+
+            follower.current += 1;
+            if follower.looping && follower.current >= follower.waypoints.len() {
+                follower.current = 0;
+            }
+        }
+        // All waypoints of a looping path are within reach; keep heading for the current one.
+        let waypoint = follower.waypoints.get(follower.current)?;
+        Self::position(waypoint, transforms)
+    }
+
+    fn steer(transform: &Transform, player: &Player, target: &Point3<f32>, speed: Option<f32>) -> Option<Steering> {
+        let matrix = transform.global_matrix().try_inverse()?;
+        let local = matrix.transform_point(target);
+        let turn = (local.x.atan2(local.z) / FRAC_PI_2).max(-1.0).min(1.0);
+        let throttle = speed.map_or(0.0, |speed| (speed - player.linear_speed()).max(-1.0).min(1.0));
+        Some(Steering {
+            forward: if turn.abs() < 1.0 { 1.0 } else { 0.0 },
+            turn,
+            throttle,
+        })
+    }
+}
+
+impl<'a> System<'a> for PathFollowSystem {
+    type SystemData = (
+        Entities<'a>,
+        ReadStorage<'a, Transform>,
+        ReadStorage<'a, Player>,
+        WriteStorage<'a, PathFollower>,
+        WriteStorage<'a, Steering>,
+        Write<'a, Warnings>,
+    );
+
+    fn run(&mut self, (entities, transforms, players, mut followers, mut steerings, mut warnings): Self::SystemData) {
+        for (entity, player, follower, steering) in (&*entities, &players, &mut followers, &mut steerings).join() {
+            let transform = match transforms.get(entity) {
+                Some(transform) => transform,
+                None => continue,
+            };
+            let position = transform.global_position();
+
+            let target = Self::target(follower, &position, &transforms);
+            if target.is_none() && follower.current().is_some() {
+                // The waypoint is a node that is gone.
+                warnings.skip("path_follow", entity);
+            }
+
+            *steering = target
+                .and_then(|target| Self::steer(transform, player, &target, follower.speed))
+                .unwrap_or_default();
+        }
+    }
+}
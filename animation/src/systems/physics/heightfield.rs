@@ -0,0 +1,198 @@
+use std::f32::EPSILON;
+
+use amethyst::{
+    assets::{Asset, AssetStorage, Format, Handle, Loader, PrefabData, ProgressCounter},
+    core::math::{Point3, Vector3},
+    ecs::prelude::*,
+    error::{Error, format_err},
+};
+use serde::{Deserialize, Serialize};
+
+use super::RayHit;
+
+/// Terrain heights sampled on a regular grid in the xz plane.
+///
+/// Sampling is O(1): heights are interpolated bilinearly between the four surrounding samples.
+/// Outside the grid the border samples extend indefinitely.
+#[derive(Debug, Clone)]
+pub struct Heightfield {
+    /// Number of samples along x and z.
+    resolution: [usize; 2],
+    cell_size: f32,
+    /// Position of the first sample in the xz plane.
+    origin: [f32; 2],
+    heights: Vec<f32>,
+}
+
+impl Asset for Heightfield {
+    const NAME: &'static str = "ceramic_animation::Heightfield";
+    type Data = Self;
+    type HandleStorage = VecStorage<Handle<Self>>;
+}
+
+impl Heightfield {
+    /// Heights are given row by row, x varying fastest.
+    pub fn new(resolution: [usize; 2], cell_size: f32, origin: [f32; 2], heights: Vec<f32>) -> Result<Self, Error> {
+        let [columns, rows] = resolution;
+        if columns < 2 || rows < 2 || heights.len() != columns * rows {
+            return Err(format_err!("Heightfield of {}x{} needs at least 2x2 samples, got {}", columns, rows, heights.len()));
+        }
+        Ok(Heightfield { resolution, cell_size, origin, heights })
+    }
+
+    /// Sample `height(x, z)` on a grid centered at the origin.
+    pub fn generate(resolution: [usize; 2], cell_size: f32, height: impl Fn(f32, f32) -> f32) -> Self {
+        let [columns, rows] = resolution;
+        let origin = [
+            -cell_size * (columns - 1) as f32 / 2.0,
+            -cell_size * (rows - 1) as f32 / 2.0,
+        ];
+        let heights = (0..rows)
+            .flat_map(|j| (0..columns).map(move |i| (i, j)))
+            .map(|(i, j)| height(origin[0] + i as f32 * cell_size, origin[1] + j as f32 * cell_size))
+            .collect();
+        Heightfield { resolution, cell_size, origin, heights }
+    }
+
+    fn sample(&self, i: isize, j: isize) -> f32 {
+        let [columns, rows] = self.resolution;
+        let i = i.max(0).min(columns as isize - 1) as usize;
+        let j = j.max(0).min(rows as isize - 1) as usize;
+        self.heights[j * columns + i]
+    }
+
+    pub fn height(&self, x: f32, z: f32) -> f32 {
+        let u = (x - self.origin[0]) / self.cell_size;
+        let v = (z - self.origin[1]) / self.cell_size;
+        let (i, j) = (u.floor(), v.floor());
+        let (s, t) = (u - i, v - j);
+        let (i, j) = (i as isize, j as isize);
+
+        let top = self.sample(i, j) * (1.0 - s) + self.sample(i + 1, j) * s;
+        let bottom = self.sample(i, j + 1) * (1.0 - s) + self.sample(i + 1, j + 1) * s;
+        top * (1.0 - t) + bottom * t
+    }
+
+    pub fn normal(&self, x: f32, z: f32) -> Vector3<f32> {
+        let delta = self.cell_size;
+        let dx = self.height(x + delta, z) - self.height(x - delta, z);
+        let dz = self.height(x, z + delta) - self.height(x, z - delta);
+        Vector3::new(-dx, 2.0 * delta, -dz).normalize()
+    }
+
+    /// Intersect a ray with the terrain.
+    ///
+    /// Vertical rays are answered with a single sample; others march the ray one cell at a time
+    /// and refine the crossing by bisection.
+    pub fn raycast(&self, origin: &Point3<f32>, direction: &Vector3<f32>, max_distance: f32) -> Option<RayHit> {
+        let above = |distance: f32| {
+            let point = origin + direction.scale(distance);
+            point.y - self.height(point.x, point.z)
+        };
+        let hit = |distance: f32| {
+            let point = origin + direction.scale(distance);
+            RayHit { point, normal: self.normal(point.x, point.z), distance, body: None }
+        };
+
+        if above(0.0) < 0.0 { return None; }
+
+        let horizontal = direction.xz().norm();
+        if horizontal < EPSILON {
+            if direction.y >= 0.0 { return None; }
+            let distance = above(0.0) / -direction.y;
+            return if distance <= max_distance { Some(hit(distance)) } else { None };
+        }
+
+        let step = self.cell_size / horizontal;
+        let mut near = 0.0;
+        while near < max_distance {
+            let far = (near + step).min(max_distance);
+            if above(far) <= 0.0 {
+                let (mut near, mut far) = (near, far);
+                for _ in 0..8 {
+                    let middle = (near + far) / 2.0;
+                    if above(middle) > 0.0 { near = middle; } else { far = middle; }
+                }
+                return Some(hit(far));
+            }
+            near = far;
+        }
+        None
+    }
+}
+
+/// Loads a heightfield from a grayscale image, one sample per pixel.
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+pub struct HeightfieldFormat {
+    pub cell_size: f32,
+    /// Height of a white pixel.
+    pub height_scale: f32,
+}
+
+impl Format<Heightfield> for HeightfieldFormat {
+    fn name(&self) -> &'static str {
+        "Heightfield"
+    }
+
+    fn import_simple(&self, bytes: Vec<u8>) -> Result<Heightfield, Error> {
+        let image = image::load_from_memory(&bytes)?.to_luma();
+        let (width, height) = image.dimensions();
+        let heights = image
+            .pixels()
+            .map(|pixel| pixel.0[0] as f32 / 255.0 * self.height_scale)
+            .collect();
+        let resolution = [width as usize, height as usize];
+        let origin = [
+            -self.cell_size * (width - 1) as f32 / 2.0,
+            -self.cell_size * (height - 1) as f32 / 2.0,
+        ];
+        Heightfield::new(resolution, self.cell_size, origin, heights)
+    }
+}
+
+/// The heightfield the ground is made of; without one the ground is the plane `y = 0`.
+#[derive(Debug, Default, Clone)]
+pub struct Terrain {
+    pub heightfield: Option<Handle<Heightfield>>,
+}
+
+/// A terrain loaded from a grayscale image, centered at the origin.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TerrainPrefab {
+    pub image: String,
+    #[serde(flatten)]
+    pub format: HeightfieldFormat,
+
+    #[serde(skip)]
+    handle: Option<Handle<Heightfield>>,
+}
+
+impl<'a> PrefabData<'a> for TerrainPrefab {
+    type SystemData = (
+        ReadExpect<'a, Loader>,
+        Read<'a, AssetStorage<Heightfield>>,
+        Write<'a, Terrain>,
+    );
+    type Result = ();
+
+    fn add_to_entity(
+        &self,
+        _entity: Entity,
+        (_, _, terrain): &mut Self::SystemData,
+        _entities: &[Entity],
+        _children: &[Entity],
+    ) -> Result<Self::Result, Error> {
+        terrain.heightfield = self.handle.clone();
+        Ok(())
+    }
+
+    fn load_sub_assets(
+        &mut self,
+        progress: &mut ProgressCounter,
+        (loader, storage, _): &mut Self::SystemData,
+    ) -> Result<bool, Error> {
+        let handle = loader.load(self.image.as_str(), self.format, progress, storage);
+        self.handle.replace(handle);
+        Ok(true)
+    }
+}
@@ -0,0 +1,168 @@
+//! Physics touch points of the animation systems.
+//!
+//! With the `nphysics` feature particles are rigid bodies of `amethyst_physics`;
+//! without it they are integrated by the built-in Verlet backend.
+
+use amethyst::{
+    assets::AssetStorage,
+    core::math::{Point3, Vector3},
+    ecs::prelude::*,
+    error::Error,
+};
+
+use crate::systems::platform::Platform;
+
+pub use self::{
+    heightfield::{Heightfield, HeightfieldFormat, Terrain, TerrainPrefab},
+    up::{WorldUp, WorldUpPrefab},
+};
+
+#[cfg(feature = "nphysics")]
+pub use self::nphysics::{NPhysics as PhysicsData, StepTime};
+#[cfg(not(feature = "nphysics"))]
+pub use self::verlet::{StepTime, Verlet as PhysicsData, VerletBody, VerletSystem};
+
+mod heightfield;
+#[cfg(feature = "nphysics")]
+mod nphysics;
+mod up;
+#[cfg(not(feature = "nphysics"))]
+mod verlet;
+
+#[derive(Debug, Copy, Clone)]
+pub struct RayHit {
+    pub point: Point3<f32>,
+    pub normal: Vector3<f32>,
+    pub distance: f32,
+    /// The `Platform` hit, or `None` for the terrain.
+    pub body: Option<Entity>,
+}
+
+/// Operations on simulated particles that a physics backend provides.
+pub trait Physics {
+    /// Make `entity` a simulated particle of the given mass.
+    fn create_particle(&mut self, entity: Entity, mass: f32) -> Result<(), Error>;
+
+    /// Take `entity` out of the simulation, leaving its transform where it is.
+    fn remove_particle(&mut self, entity: Entity);
+
+    fn position(&self, entity: Entity) -> Option<Point3<f32>>;
+
+    fn velocity(&self, entity: Entity) -> Option<Vector3<f32>>;
+
+    fn apply_impulse(&mut self, entity: Entity, impulse: &Vector3<f32>);
+
+    fn apply_force(&mut self, entity: Entity, force: &Vector3<f32>);
+
+    /// Bring the particle to rest until the next impulse wakes it up.
+    fn sleep(&mut self, entity: Entity);
+
+    /// Cast a ray against the `Ground`, returning the closest hit within `max_distance`.
+    ///
+    /// Only the terrain and the `Platform`s are hit, with every backend: the bodies and colliders of nphysics,
+    /// including the particles themselves, are not seen, since `amethyst_physics` exposes no ray queries.
+    fn raycast(&self, origin: &Point3<f32>, direction: &Vector3<f32>, max_distance: f32) -> Option<RayHit>;
+}
+
+/// Read-only queries against the ground, usable from parallel code.
+///
+/// The ground is the `Terrain` heightfield if there is one loaded, or else the plane `y = 0`, both in the frame
+/// of the `WorldUp`. Raycasts also hit the `Platform`s moving over it; heights and normals are of the terrain alone.
+#[derive(SystemData)]
+pub struct Ground<'a> {
+    terrain: Read<'a, Terrain>,
+    up: Read<'a, WorldUp>,
+    heightfields: Read<'a, AssetStorage<Heightfield>>,
+    entities: Entities<'a>,
+    platforms: ReadStorage<'a, Platform>,
+}
+
+impl Ground<'_> {
+    pub fn heightfield(&self) -> Option<&Heightfield> {
+        self.terrain
+            .heightfield
+            .as_ref()
+            .and_then(|handle| self.heightfields.get(handle))
+    }
+
+    pub fn up(&self) -> &WorldUp {
+        &self.up
+    }
+
+    /// Height of the ground below `(x, z)` of the frame of the `WorldUp`, which is the world with the default `+Y`.
+    pub fn height(&self, x: f32, z: f32) -> f32 {
+        self.heightfield().map_or(0.0, |heightfield| heightfield.height(x, z))
+    }
+
+    /// Normal of the ground below `(x, z)` of the frame of the `WorldUp`, in that frame.
+    pub fn normal(&self, x: f32, z: f32) -> Vector3<f32> {
+        self.heightfield().map_or_else(Vector3::y, |heightfield| heightfield.normal(x, z))
+    }
+
+    /// Height along the `WorldUp` of the ground below `point`.
+    pub fn height_at(&self, point: &Point3<f32>) -> f32 {
+        let local = self.up.to_local(point);
+        self.height(local.x, local.z)
+    }
+
+    /// Normal of the ground below `point`, in world space.
+    pub fn normal_at(&self, point: &Point3<f32>) -> Vector3<f32> {
+        let local = self.up.to_local(point);
+        self.up.rotation() * self.normal(local.x, local.z)
+    }
+
+    /// Height along the `WorldUp` of the top of what is below `point`: a platform, or else the terrain.
+    pub fn height_below(&self, point: &Point3<f32>) -> f32 {
+        let height = self.height_at(point);
+        let ref down = -self.up.up();
+        self.platforms
+            .join()
+            .filter_map(|platform| platform.raycast(point, down, self.up.height(point) - height))
+            .map(|hit| self.up.height(&hit.point))
+            .fold(height, f32::max)
+    }
+
+    pub fn platform(&self, entity: Entity) -> Option<&Platform> {
+        self.platforms.get(entity)
+    }
+
+    pub fn raycast(&self, origin: &Point3<f32>, direction: &Vector3<f32>, max_distance: f32) -> Option<RayHit> {
+        let terrain = {
+            // The terrain lies in the frame of the `WorldUp`, the platforms in the world.
+            let ref local_origin = self.up.to_local(origin);
+            let ref local_direction = self.up.rotation().inverse_transform_vector(direction);
+            let hit = match self.heightfield() {
+                Some(heightfield) => heightfield.raycast(local_origin, local_direction, max_distance),
+                None => ground_raycast(local_origin, local_direction, max_distance),
+            };
+            hit.map(|hit| RayHit {
+                point: self.up.to_world(&hit.point),
+                normal: self.up.rotation() * hit.normal,
+                ..hit
+            })
+        };
+        (&*self.entities, &self.platforms)
+            .join()
+            .filter_map(|(entity, platform)| {
+                let hit = platform.raycast(origin, direction, max_distance)?;
+                Some(RayHit { body: Some(entity), ..hit })
+            })
+            .chain(terrain)
+            .min_by(|a, b| a.distance.partial_cmp(&b.distance).unwrap_or(std::cmp::Ordering::Equal))
+    }
+}
+
+/// Intersect a ray with the ground plane `y = 0`.
+pub fn ground_raycast(origin: &Point3<f32>, direction: &Vector3<f32>, max_distance: f32) -> Option<RayHit> {
+    if direction.y.abs() < std::f32::EPSILON { return None; }
+
+    let distance = -origin.y / direction.y;
+    if distance < 0.0 || distance > max_distance { return None; }
+
+    Some(RayHit {
+        point: origin + direction.scale(distance),
+        normal: Vector3::y(),
+        distance,
+        body: None,
+    })
+}
@@ -0,0 +1,77 @@
+use amethyst::{
+    core::math::{Point3, Vector3},
+    ecs::prelude::*,
+    error::Error,
+};
+use amethyst_physics::prelude::*;
+
+pub use amethyst_physics::PhysicsTime as StepTime;
+
+use super::{Ground, Physics, RayHit};
+
+/// Particles backed by `amethyst_physics` rigid bodies.
+#[derive(SystemData)]
+pub struct NPhysics<'a> {
+    world: ReadExpect<'a, PhysicsWorld<f32>>,
+    bodies: WriteStorage<'a, PhysicsHandle<PhysicsRigidBodyTag>>,
+    ground: Ground<'a>,
+}
+
+impl Physics for NPhysics<'_> {
+    fn create_particle(&mut self, entity: Entity, mass: f32) -> Result<(), Error> {
+        let body = {
+            let ref desc = RigidBodyDesc {
+                mode: BodyMode::Dynamic,
+                mass,
+                ..Default::default()
+            };
+            self.world.rigid_body_server().create(desc)
+        };
+        self.bodies.insert(entity, body)?;
+        Ok(())
+    }
+
+    // Dropping the last handle removes the body from the physics world.
+    fn remove_particle(&mut self, entity: Entity) {
+        self.bodies.remove(entity);
+    }
+
+    fn position(&self, entity: Entity) -> Option<Point3<f32>> {
+        self.bodies.get(entity).map(|body| Point3::from(
+            self.world
+                .rigid_body_server()
+                .transform(body.get())
+                .translation
+                .vector
+        ))
+    }
+
+    fn velocity(&self, entity: Entity) -> Option<Vector3<f32>> {
+        self.bodies
+            .get(entity)
+            .map(|body| self.world.rigid_body_server().linear_velocity(body.get()))
+    }
+
+    fn apply_impulse(&mut self, entity: Entity, impulse: &Vector3<f32>) {
+        if let Some(body) = self.bodies.get(entity) {
+            self.world.rigid_body_server().apply_impulse(body.get(), impulse);
+        }
+    }
+
+    fn apply_force(&mut self, entity: Entity, force: &Vector3<f32>) {
+        if let Some(body) = self.bodies.get(entity) {
+            self.world.rigid_body_server().apply_force(body.get(), force);
+        }
+    }
+
+    // Without forces a body at rest is deactivated by nphysics itself, and woken by the next impulse.
+    fn sleep(&mut self, entity: Entity) {
+        if let Some(body) = self.bodies.get(entity) {
+            self.world.rigid_body_server().set_linear_velocity(body.get(), &Vector3::zeros());
+        }
+    }
+
+    fn raycast(&self, origin: &Point3<f32>, direction: &Vector3<f32>, max_distance: f32) -> Option<RayHit> {
+        self.ground.raycast(origin, direction, max_distance)
+    }
+}
@@ -0,0 +1,103 @@
+use std::f32::{consts::PI, EPSILON};
+
+use amethyst::{
+    assets::PrefabData,
+    core::math::{Point3, Unit, UnitQuaternion, Vector3},
+    ecs::prelude::*,
+    error::Error,
+};
+use serde::{Deserialize, Serialize};
+
+/// The direction opposite gravity, `+Y` unless the scene is authored Z-up or with tilted gravity.
+///
+/// The locomotion measures heights along it, and the `Ground` lies across it: the terrain heightfield is sampled
+/// in the frame turned from `+Y` to up, and without one the ground is the plane through the origin across up.
+#[derive(Debug, Copy, Clone)]
+pub struct WorldUp {
+    /// Turns the `+Y` frame the terrain and the tuning are in to the world.
+    rotation: UnitQuaternion<f32>,
+}
+
+impl Default for WorldUp {
+    fn default() -> Self {
+        WorldUp { rotation: UnitQuaternion::identity() }
+    }
+}
+
+impl WorldUp {
+    /// Up along `up`, or `+Y` if it has no length.
+    pub fn new(up: &Vector3<f32>) -> Self {
+        let up = match up.try_normalize(EPSILON) {
+            Some(up) => up,
+            None => return WorldUp::default(),
+        };
+        // Only straight down has no shortest rotation from `+Y`; turn it over about x.
+        let rotation = UnitQuaternion::rotation_between(&Vector3::y(), &up)
+            .unwrap_or_else(|| UnitQuaternion::from_axis_angle(&Vector3::x_axis(), PI));
+        WorldUp { rotation }
+    }
+
+    pub fn up(&self) -> Vector3<f32> {
+        self.rotation * Vector3::y()
+    }
+
+    /// The rotation from the `+Y` frame to the world.
+    pub fn rotation(&self) -> UnitQuaternion<f32> {
+        self.rotation
+    }
+
+    /// A turn by `angle` radians about up.
+    pub fn yaw(&self, angle: f32) -> UnitQuaternion<f32> {
+        UnitQuaternion::from_axis_angle(&Unit::new_unchecked(self.up()), angle)
+    }
+
+    /// Height of `point` above the plane through the origin across up.
+    pub fn height(&self, point: &Point3<f32>) -> f32 {
+        self.to_local(point).y
+    }
+
+    /// `point` moved along up to `height`.
+    pub fn with_height(&self, point: &Point3<f32>, height: f32) -> Point3<f32> {
+        let mut local = self.to_local(point);
+        local.y = height;
+        self.to_world(&local)
+    }
+
+    /// `vector` without its part along up.
+    pub fn planar(&self, vector: &Vector3<f32>) -> Vector3<f32> {
+        let mut local = self.rotation.inverse_transform_vector(vector);
+        local.y = 0.0;
+        self.rotation * local
+    }
+
+    /// `point` in the `+Y` frame.
+    pub fn to_local(&self, point: &Point3<f32>) -> Point3<f32> {
+        self.rotation.inverse_transform_point(point)
+    }
+
+    /// `point` of the `+Y` frame in the world.
+    pub fn to_world(&self, point: &Point3<f32>) -> Point3<f32> {
+        self.rotation * point
+    }
+}
+
+/// Sets the `WorldUp` of the scene, e.g. `"world_up": [0.0, 0.0, 1.0]` for a scene authored Z-up.
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+pub struct WorldUpPrefab(pub [f32; 3]);
+
+impl<'a> PrefabData<'a> for WorldUpPrefab {
+    type SystemData = Write<'a, WorldUp>;
+    type Result = ();
+
+    fn add_to_entity(
+        &self,
+        _entity: Entity,
+        up: &mut Self::SystemData,
+        _entities: &[Entity],
+        _children: &[Entity],
+    ) -> Result<Self::Result, Error> {
+        let [x, y, z] = self.0;
+        **up = WorldUp::new(&Vector3::new(x, y, z));
+        Ok(())
+    }
+}
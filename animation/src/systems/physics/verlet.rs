@@ -0,0 +1,135 @@
+use amethyst::{
+    core::{math::{Point3, Vector3}, Transform},
+    derive::SystemDesc,
+    ecs::{Component, prelude::*},
+    error::Error,
+};
+
+pub use amethyst::core::Time as StepTime;
+
+use crate::utils::determinism::Determinism;
+
+use super::{Ground, Physics, RayHit};
+
+/// A particle integrated with position Verlet.
+#[derive(Debug, Copy, Clone, Component)]
+#[storage(DenseVecStorage)]
+pub struct VerletBody {
+    mass: f32,
+    position: Option<Point3<f32>>,
+    previous: Point3<f32>,
+    velocity: Vector3<f32>,
+    impulse: Vector3<f32>,
+    force: Vector3<f32>,
+    sleeping: bool,
+}
+
+/// Particles integrated by the built-in Verlet backend.
+#[derive(SystemData)]
+pub struct Verlet<'a> {
+    bodies: WriteStorage<'a, VerletBody>,
+    ground: Ground<'a>,
+}
+
+impl Physics for Verlet<'_> {
+    fn create_particle(&mut self, entity: Entity, mass: f32) -> Result<(), Error> {
+        let body = VerletBody {
+            mass,
+            position: None,
+            previous: Point3::origin(),
+            velocity: Vector3::zeros(),
+            impulse: Vector3::zeros(),
+            force: Vector3::zeros(),
+            sleeping: false,
+        };
+        self.bodies.insert(entity, body)?;
+        Ok(())
+    }
+
+    fn remove_particle(&mut self, entity: Entity) {
+        self.bodies.remove(entity);
+    }
+
+    fn position(&self, entity: Entity) -> Option<Point3<f32>> {
+        self.bodies.get(entity)?.position
+    }
+
+    fn velocity(&self, entity: Entity) -> Option<Vector3<f32>> {
+        self.bodies.get(entity).map(|body| body.velocity)
+    }
+
+    fn apply_impulse(&mut self, entity: Entity, impulse: &Vector3<f32>) {
+        if let Some(body) = self.bodies.get_mut(entity) {
+            body.impulse += impulse;
+            body.sleeping = false;
+        }
+    }
+
+    fn apply_force(&mut self, entity: Entity, force: &Vector3<f32>) {
+        if let Some(body) = self.bodies.get_mut(entity) {
+            body.force += force;
+        }
+    }
+
+    fn sleep(&mut self, entity: Entity) {
+        if let Some(body) = self.bodies.get_mut(entity) {
+            if let Some(position) = body.position {
+                body.previous = position;
+            }
+            body.velocity = Vector3::zeros();
+            body.impulse = Vector3::zeros();
+            body.force = Vector3::zeros();
+            body.sleeping = true;
+        }
+    }
+
+    fn raycast(&self, origin: &Point3<f32>, direction: &Vector3<f32>, max_distance: f32) -> Option<RayHit> {
+        self.ground.raycast(origin, direction, max_distance)
+    }
+}
+
+/// Integrates Verlet particles and writes their positions into the transforms.
+#[derive(Default, SystemDesc)]
+pub struct VerletSystem;
+
+impl<'a> System<'a> for VerletSystem {
+    type SystemData = (
+        WriteStorage<'a, VerletBody>,
+        WriteStorage<'a, Transform>,
+        Read<'a, StepTime>,
+        Read<'a, Determinism>,
+    );
+
+    fn run(&mut self, (mut bodies, mut transforms, time, determinism): Self::SystemData) {
+        let delta_seconds = determinism.step_seconds(time.delta_seconds());
+        if delta_seconds <= 0.0 { return; }
+
+        for (body, transform) in (&mut bodies, &mut transforms).join() {
+            if body.sleeping { continue; }
+
+            let position = match body.position {
+                Some(position) => position,
+                None => {
+                    let position = Point3::from(*transform.translation());
+                    body.previous = position;
+                    body.position.replace(position);
+                    continue;
+                }
+            };
+
+            let ref acceleration = body.force / body.mass;
+            let ref velocity_change = body.impulse / body.mass;
+            let displacement = (position - body.previous)
+                + velocity_change * delta_seconds
+                + acceleration * delta_seconds * delta_seconds;
+
+            body.previous = position;
+            body.position.replace(position + displacement);
+            body.velocity = displacement / delta_seconds;
+            body.impulse = Vector3::zeros();
+            body.force = Vector3::zeros();
+
+            transform.set_translation(position.coords + displacement);
+        }
+    }
+}
@@ -0,0 +1,117 @@
+use amethyst::{
+    assets::PrefabData,
+    core::{math::{Matrix4, Point3, Vector3}, Time, Transform},
+    derive::{PrefabData, SystemDesc},
+    ecs::{Component, prelude::*},
+    error::Error,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{systems::physics::{RayHit, WorldUp}, utils::determinism::Determinism};
+
+/// How a platform moved over the last frame.
+#[derive(Debug, Copy, Clone)]
+struct Motion {
+    /// The global matrix at the end of the frame.
+    matrix: Matrix4<f32>,
+    /// Takes points on the platform from where they were at the start of the frame to where they are now.
+    delta: Matrix4<f32>,
+    delta_seconds: f32,
+}
+
+/// A moving box creatures can stand on, e.g. a raft or a lift.
+///
+/// Feet landing on it are carried along while they stand, and so is a body with its feet on it.
+#[derive(Debug, Clone, Serialize, Deserialize, PrefabData, Component)]
+#[prefab(Component)]
+#[storage(HashMapStorage)]
+pub struct Platform {
+    /// Half size of the box along the local axes of the node.
+    pub extent: [f32; 3],
+    #[serde(skip)]
+    motion: Option<Motion>,
+}
+
+impl Platform {
+    /// Where a point on the platform at the start of the frame is now.
+    pub fn carry(&self, point: &Point3<f32>) -> Point3<f32> {
+        self.motion.map_or(*point, |motion| motion.delta.transform_point(point))
+    }
+
+    /// Turn in radians about `up` over the last frame.
+    pub fn yaw(&self, up: &WorldUp) -> f32 {
+        self.motion.map_or(0.0, |motion| {
+            let ref rotation = up.rotation();
+            let heading = motion.delta.transform_vector(&(rotation * Vector3::z()));
+            let heading = rotation.inverse_transform_vector(&heading);
+            heading.x.atan2(heading.z)
+        })
+    }
+
+    /// Velocity of the platform at `point`.
+    pub fn velocity_at(&self, point: &Point3<f32>) -> Vector3<f32> {
+        match self.motion {
+            Some(motion) if motion.delta_seconds > 0.0 => (self.carry(point) - point) / motion.delta_seconds,
+            _ => Vector3::zeros(),
+        }
+    }
+
+    /// The hit of the ray on the outside of the box, if within `max_distance`.
+    pub(crate) fn raycast(&self, origin: &Point3<f32>, direction: &Vector3<f32>, max_distance: f32) -> Option<RayHit> {
+        let matrix = self.motion?.matrix;
+        // An affine map keeps distances along the ray, so the slabs can be tested in the space of the box.
+        let inverse = matrix.try_inverse()?;
+        let local_origin = inverse.transform_point(origin);
+        let local_direction = inverse.transform_vector(direction);
+
+        let (mut near, mut far) = (0.0, max_distance);
+        let mut normal = None;
+        for axis in 0..3 {
+            let extent = self.extent[axis];
+            if local_direction[axis].abs() < f32::EPSILON {
+                if local_origin[axis].abs() > extent { return None; }
+                continue;
+            }
+            let a = (-extent - local_origin[axis]) / local_direction[axis];
+            let b = (extent - local_origin[axis]) / local_direction[axis];
+            if a.min(b) > near {
+                near = a.min(b);
+                let mut face = Vector3::zeros();
+                face[axis] = -local_direction[axis].signum();
+                normal = Some(face);
+            }
+            far = a.max(b).min(far);
+            if near > far { return None; }
+        }
+
+        // Rays starting inside the box don't enter any face.
+        let normal = inverse.transpose().transform_vector(&normal?).try_normalize(f32::EPSILON)?;
+        Some(RayHit { point: origin + direction * near, normal, distance: near, body: None })
+    }
+}
+
+/// Follows the motion of `Platform`s, so what stands on them can move along.
+///
+/// Must run after the transform system and before the locomotion.
+#[derive(Default, SystemDesc)]
+pub struct PlatformSystem;
+
+impl<'a> System<'a> for PlatformSystem {
+    type SystemData = (
+        ReadStorage<'a, Transform>,
+        WriteStorage<'a, Platform>,
+        Read<'a, Time>,
+        Read<'a, Determinism>,
+    );
+
+    fn run(&mut self, (transforms, mut platforms, time, determinism): Self::SystemData) {
+        let delta_seconds = determinism.delta_seconds(time.delta_seconds());
+        for (transform, platform) in (&transforms, &mut platforms).join() {
+            let matrix = *transform.global_matrix();
+            let delta = platform.motion
+                .and_then(|motion| motion.matrix.try_inverse())
+                .map_or_else(Matrix4::identity, |inverse| matrix * inverse);
+            platform.motion = Some(Motion { matrix, delta, delta_seconds });
+        }
+    }
+}
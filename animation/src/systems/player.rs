@@ -0,0 +1,127 @@
+use std::f32::EPSILON;
+
+use amethyst::{
+    assets::PrefabData,
+    core::{
+        math::{UnitQuaternion, Vector3},
+        timing::Time,
+        transform::Transform,
+    },
+    derive::{PrefabData, SystemDesc},
+    ecs::{Component, prelude::*},
+    error::Error,
+    input::{InputHandler, StringBindings},
+};
+use getset::{CopyGetters, Getters, Setters};
+use num_traits::identities::Zero;
+use serde::{Deserialize, Serialize};
+
+use crate::utils::determinism::Determinism;
+
+#[derive(Getters, CopyGetters, Setters, Debug, Copy, Clone, Serialize, Deserialize, PrefabData)]
+#[prefab(Component)]
+#[get_copy = "pub"]
+pub struct Player {
+    #[set = "pub"]
+    linear_speed: f32,
+    angular_speed: f32,
+
+    #[set = "pub"]
+    stiffness: f32,
+    #[set = "pub"]
+    speed_limit: [f32; 2],
+    acceleration: f32,
+
+    #[set = "pub"]
+    #[serde(skip, default = "Vector3::zero")]
+    movement: Vector3<f32>,
+    #[set = "pub"]
+    #[serde(skip, default = "UnitQuaternion::identity")]
+    spinning: UnitQuaternion<f32>,
+}
+
+impl Player {
+    pub fn new(linear_speed: f32, angular_speed: f32, stiffness: f32, speed_limit: [f32; 2], acceleration: f32) -> Self {
+        Player {
+            linear_speed,
+            angular_speed,
+            stiffness,
+            speed_limit,
+            acceleration,
+            movement: Vector3::zero(),
+            spinning: UnitQuaternion::identity(),
+        }
+    }
+
+    pub fn velocity(&self) -> Vector3<f32> {
+        self.movement.scale(self.linear_speed)
+    }
+}
+
+impl Component for Player {
+    type Storage = VecStorage<Self>;
+}
+
+/// Drives a `Player` in place of the input, with the same meaning as the `move_*` axes.
+///
+/// Attach this to let a behavior control the creature.
+#[derive(Debug, Default, Copy, Clone, Component)]
+#[storage(HashMapStorage)]
+pub struct Steering {
+    /// Forward movement in `[-1, 1]`.
+    pub forward: f32,
+    /// Turning in `[-1, 1]`, positive to the left.
+    pub turn: f32,
+    /// Rate of change of the linear speed in `[-1, 1]`.
+    pub throttle: f32,
+}
+
+#[derive(Default, SystemDesc)]
+pub struct PlayerSystem;
+
+impl<'a> System<'a> for PlayerSystem {
+    type SystemData = (
+        WriteStorage<'a, Player>,
+        WriteStorage<'a, Transform>,
+        ReadStorage<'a, Steering>,
+        Read<'a, InputHandler<StringBindings>>,
+        Read<'a, Time>,
+        Read<'a, Determinism>,
+    );
+
+    fn run(&mut self, (mut players, mut transforms, steerings, input, time, determinism): Self::SystemData) {
+        for (player, transform, steering) in (&mut players, &mut transforms, steerings.maybe()).join() {
+            let (forward, turn, throttle) = match steering {
+                Some(steering) => (steering.forward, steering.turn, steering.throttle),
+                None => (
+                    input.axis_value("move_z").unwrap_or(0.0),
+                    input.axis_value("move_x").unwrap_or(0.0),
+                    input.axis_value("move_y").unwrap_or(0.0),
+                ),
+            };
+
+            let movement = Vector3::new(0.0, 0.0, forward)
+                .try_normalize(EPSILON)
+                .unwrap_or(Vector3::zero());
+            let spinning = UnitQuaternion::from_euler_angles(
+                0.0,
+                player.angular_speed * turn,
+                0.0,
+            );
+
+            let delta_seconds = determinism.delta_seconds(time.delta_seconds());
+            let [min, max] = player.speed_limit;
+            player.linear_speed += throttle * delta_seconds * player.acceleration;
+            player.linear_speed = player.linear_speed.min(max).max(min);
+
+            let decay = 1.0 - (-player.stiffness * delta_seconds).exp();
+            player.movement += decay * (movement - player.movement.clone());
+            player.spinning *= (player.spinning.inverse() * spinning).powf(decay);
+
+            transform.append_translation(delta_seconds * player.linear_speed * &player.movement);
+            if let Some((axis, angle)) = player.spinning.axis_angle() {
+                transform.append_rotation(axis, angle * delta_seconds);
+            }
+        }
+    }
+}
\ No newline at end of file
@@ -0,0 +1,189 @@
+use std::collections::HashMap;
+
+use amethyst::{
+    assets::PrefabData,
+    core::transform::{Parent, Transform},
+    ecs::{Component, prelude::*, SystemData},
+    error::Error,
+    shrev::{EventChannel, ReaderId},
+};
+use log::warn;
+use serde::{Deserialize, Serialize};
+
+use ceramic_derive::Redirect;
+use redirect::{Redirect, Resolve};
+
+use crate::{extras::EntityRef, utils::transform::TransformTrait};
+
+/// A named point on a node of the rig that props attach to, e.g. the collar on the neck.
+#[derive(Debug, Clone)]
+pub struct Socket {
+    pub node: Entity,
+    /// Transform of attached props in the space of the node.
+    pub offset: Transform,
+    pub attached: Option<Entity>,
+}
+
+/// The sockets of a creature by name.
+#[derive(Debug, Default, Clone, Component)]
+#[storage(HashMapStorage)]
+pub struct Sockets {
+    sockets: HashMap<String, Socket>,
+}
+
+impl Sockets {
+    pub fn get(&self, name: &str) -> Option<&Socket> {
+        self.sockets.get(name)
+    }
+
+    /// The socket `prop` is attached to, if any.
+    pub fn holding(&self, prop: Entity) -> Option<&str> {
+        self.sockets
+            .iter()
+            .find(|(_, socket)| socket.attached == Some(prop))
+            .map(|(name, _)| name.as_str())
+    }
+
+    /// Drops the sockets on nodes and detaches the props for which `dangling` holds.
+    pub fn forget(&mut self, dangling: &dyn Fn(Entity) -> bool) {
+        self.sockets.retain(|_, socket| !dangling(socket.node));
+        for socket in self.sockets.values_mut() {
+            if socket.attached.map_or(false, dangling) {
+                socket.attached = None;
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Redirect)]
+pub struct SocketPrefab {
+    #[redirect(skip)]
+    pub name: String,
+    /// The node the socket is on, by name.
+    pub node: EntityRef,
+    #[serde(default)]
+    #[redirect(skip)]
+    pub offset: Transform,
+}
+
+/// The sockets of a creature, set on the node of its body, e.g.
+/// `"sockets": [{ "name": "collar", "node": "DEF-neck", "offset": { "translation": [0.0, 0.1, 0.0] } }]`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct SocketsPrefab(pub Vec<SocketPrefab>);
+
+impl Redirect<String, usize> for SocketsPrefab {
+    fn redirect<F>(self, map: &F) -> Self
+        where F: Fn(String) -> usize {
+        SocketsPrefab(self.0.redirect(map))
+    }
+}
+
+impl<'a> PrefabData<'a> for SocketsPrefab {
+    type SystemData = WriteStorage<'a, Sockets>;
+    type Result = ();
+
+    fn add_to_entity(
+        &self,
+        entity: Entity,
+        data: &mut Self::SystemData,
+        entities: &[Entity],
+        _children: &[Entity],
+    ) -> Result<Self::Result, Error> {
+        let sockets = self.0
+            .iter()
+            .map(|socket| {
+                let resolved = Socket {
+                    node: socket.node.resolve(entities),
+                    offset: socket.offset.clone(),
+                    attached: None,
+                };
+                (socket.name.clone(), resolved)
+            })
+            .collect();
+        data.insert(entity, Sockets { sockets }).map(|_| ()).map_err(Into::into)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum SocketEvent {
+    /// Attach `prop` to the socket called `socket` of `body`, detaching whatever it held.
+    Attach { body: Entity, socket: String, prop: Entity },
+    /// Detach `prop` from the socket holding it, leaving it where it is.
+    Detach { body: Entity, prop: Entity },
+}
+
+/// Parents props to the nodes of sockets at their offset, and unparents them again.
+#[derive(Default)]
+pub struct SocketSystem {
+    reader_id: Option<ReaderId<SocketEvent>>,
+}
+
+impl SocketSystem {
+    fn detach(prop: Entity, transforms: &mut WriteStorage<'_, Transform>, parents: &mut WriteStorage<'_, Parent>) -> Option<()> {
+        parents.remove(prop);
+        let transform = transforms.get_mut(prop)?;
+        let position = transform.global_position();
+        transform.set_translation(position.coords);
+        Some(())
+    }
+
+    fn attach(
+        socket: &mut Socket,
+        prop: Entity,
+        transforms: &mut WriteStorage<'_, Transform>,
+        parents: &mut WriteStorage<'_, Parent>,
+    ) -> Option<()> {
+        if let Some(held) = socket.attached.take() {
+            Self::detach(held, transforms, parents);
+        }
+        *transforms.get_mut(prop)? = socket.offset.clone();
+        parents.insert(prop, Parent { entity: socket.node }).ok()?;
+        socket.attached = Some(prop);
+        Some(())
+    }
+}
+
+impl<'a> System<'a> for SocketSystem {
+    type SystemData = (
+        WriteStorage<'a, Sockets>,
+        WriteStorage<'a, Transform>,
+        WriteStorage<'a, Parent>,
+        Read<'a, EventChannel<SocketEvent>>,
+    );
+
+    fn run(&mut self, (mut sockets, mut transforms, mut parents, events): Self::SystemData) {
+        let reader_id = self.reader_id
+            .as_mut()
+            .expect("`SocketSystem::setup` was not called before `SocketSystem::run`");
+
+        for event in events.read(reader_id) {
+            match event {
+                SocketEvent::Attach { body, socket, prop } => {
+                    let socket = match sockets.get_mut(*body).and_then(|sockets| sockets.sockets.get_mut(socket)) {
+                        Some(socket) => socket,
+                        None => {
+                            warn!("{:?} has no socket called {}", body, socket);
+                            continue;
+                        }
+                    };
+                    Self::attach(socket, *prop, &mut transforms, &mut parents);
+                }
+                SocketEvent::Detach { body, prop } => {
+                    let socket = sockets
+                        .get_mut(*body)
+                        .and_then(|sockets| sockets.sockets.values_mut().find(|socket| socket.attached == Some(*prop)));
+                    if let Some(socket) = socket {
+                        socket.attached = None;
+                        Self::detach(*prop, &mut transforms, &mut parents);
+                    }
+                }
+            }
+        }
+    }
+
+    fn setup(&mut self, world: &mut World) {
+        Self::SystemData::setup(world);
+        self.reader_id = Some(world.fetch_mut::<EventChannel<SocketEvent>>().register_reader());
+    }
+}
@@ -0,0 +1,80 @@
+use std::collections::HashMap;
+
+use amethyst::{
+    core::{math::Point3, Transform},
+    derive::SystemDesc,
+    ecs::{Component, prelude::*},
+};
+use itertools::iproduct;
+
+use crate::utils::transform::TransformTrait;
+
+/// Marks an entity to be tracked by the `SpatialIndex`.
+#[derive(Debug, Default, Copy, Clone, Component)]
+#[storage(NullStorage)]
+pub struct Indexed;
+
+/// A uniform grid over the global positions of `Indexed` entities, rebuilt every frame.
+#[derive(Debug)]
+pub struct SpatialIndex {
+    cell_size: f32,
+    cells: HashMap<[i32; 3], Vec<(Entity, Point3<f32>)>>,
+}
+
+impl Default for SpatialIndex {
+    fn default() -> Self {
+        SpatialIndex::new(2.0)
+    }
+}
+
+impl SpatialIndex {
+    pub fn new(cell_size: f32) -> Self {
+        SpatialIndex { cell_size, cells: HashMap::new() }
+    }
+
+    fn cell(&self, point: &Point3<f32>) -> [i32; 3] {
+        let cell = |x: f32| (x / self.cell_size).floor() as i32;
+        [cell(point.x), cell(point.y), cell(point.z)]
+    }
+
+    pub fn clear(&mut self) {
+        self.cells.values_mut().for_each(Vec::clear);
+    }
+
+    pub fn insert(&mut self, entity: Entity, position: Point3<f32>) {
+        let cell = self.cell(&position);
+        self.cells.entry(cell).or_default().push((entity, position));
+    }
+
+    /// All indexed entities within `radius` of `center`, with their global positions.
+    pub fn query(&self, center: Point3<f32>, radius: f32) -> impl Iterator<Item=(Entity, Point3<f32>)> + '_ {
+        let offset = Point3::new(radius, radius, radius).coords;
+        let [x0, y0, z0] = self.cell(&(center - offset));
+        let [x1, y1, z1] = self.cell(&(center + offset));
+        iproduct!(x0..=x1, y0..=y1, z0..=z1)
+            .filter_map(move |(x, y, z)| self.cells.get(&[x, y, z]))
+            .flatten()
+            .copied()
+            .filter(move |(_, position)| (position - center).norm() <= radius)
+    }
+}
+
+/// Rebuilds the `SpatialIndex` after the transform system.
+#[derive(Default, SystemDesc)]
+pub struct SpatialIndexSystem;
+
+impl<'a> System<'a> for SpatialIndexSystem {
+    type SystemData = (
+        Entities<'a>,
+        ReadStorage<'a, Transform>,
+        ReadStorage<'a, Indexed>,
+        Write<'a, SpatialIndex>,
+    );
+
+    fn run(&mut self, (entities, transforms, indexed, mut index): Self::SystemData) {
+        index.clear();
+        for (entity, transform, _) in (&*entities, &transforms, &indexed).join() {
+            index.insert(entity, transform.global_position());
+        }
+    }
+}
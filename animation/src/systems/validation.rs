@@ -0,0 +1,167 @@
+use std::collections::HashSet;
+
+use amethyst::{
+    core::{transform::Parent, Transform},
+    ecs::prelude::*,
+};
+use log::warn;
+
+use super::{
+    animal::{tail::Tail, Legged, Quadruped, Tracker},
+    kinematics::{Chain, Direction, Distance, Hinge, Pole},
+    particle::Spring,
+};
+
+/// Problems found in the animation components of instantiated scenes.
+#[derive(Debug, Default)]
+pub struct ValidationReport {
+    pub errors: Vec<String>,
+}
+
+/// Checks the structural invariants of newly instantiated animation components, once per entity,
+/// and prints the problems of each frame as one list into the `ValidationReport`.
+#[derive(Default)]
+pub struct ValidationSystem {
+    checked: HashSet<Entity>,
+}
+
+struct Validator<'s, 'a> {
+    entities: &'s Entities<'a>,
+    transforms: &'s ReadStorage<'a, Transform>,
+    parents: &'s ReadStorage<'a, Parent>,
+    errors: Vec<String>,
+}
+
+impl Validator<'_, '_> {
+    fn reference(&mut self, entity: Entity, name: &str, reference: Entity) {
+        if !self.entities.is_alive(reference) || !self.transforms.contains(reference) {
+            self.errors.push(format!("{} of {:?} references {:?}, which has no transform", name, entity, reference));
+        }
+    }
+
+    fn chain(&mut self, entity: Entity, chain: &Chain) {
+        self.reference(entity, "chain", chain.target());
+
+        let mut depth = 1;
+        let mut current = entity;
+        while let Some(parent) = self.parents.get(current) {
+            if depth >= chain.length() { break; }
+            current = parent.entity;
+            depth += 1;
+        }
+        if depth < chain.length() {
+            self.errors.push(format!(
+                "chain of {:?} has length {}, but the hierarchy above it is only {} deep",
+                entity, chain.length(), depth,
+            ));
+        }
+    }
+
+    fn hinge(&mut self, entity: Entity, hinge: &Hinge) {
+        if let Some([min, max]) = hinge.limit() {
+            if min > max {
+                self.errors.push(format!("hinge of {:?} has limit ({}, {}) with min above max", entity, min, max));
+            }
+        }
+    }
+
+    fn quadruped(&mut self, entity: Entity, quadruped: &Quadruped) {
+        for reference in quadruped.entities() {
+            self.reference(entity, "quadruped", reference);
+        }
+
+        let config = quadruped.config();
+        let [min_step, max_step] = config.step_limit;
+        if min_step > max_step {
+            self.errors.push(format!("quadruped {:?} has step_limit ({}, {}) with min above max", entity, min_step, max_step));
+        }
+        if !(config.max_duty_factor > 0.0 && config.max_duty_factor < 1.0) {
+            self.errors.push(format!("quadruped {:?} has max_duty_factor {} outside (0, 1)", entity, config.max_duty_factor));
+        }
+        if config.flight_time <= 0.0 {
+            self.errors.push(format!("quadruped {:?} has non-positive flight_time {}", entity, config.flight_time));
+        }
+    }
+}
+
+impl<'a> System<'a> for ValidationSystem {
+    type SystemData = (
+        Entities<'a>,
+        ReadStorage<'a, Transform>,
+        ReadStorage<'a, Parent>,
+        ReadStorage<'a, Chain>,
+        ReadStorage<'a, Hinge>,
+        ReadStorage<'a, Pole>,
+        ReadStorage<'a, Direction>,
+        ReadStorage<'a, Distance>,
+        ReadStorage<'a, Tracker>,
+        ReadStorage<'a, Spring>,
+        ReadStorage<'a, Tail>,
+        ReadStorage<'a, Quadruped>,
+        Write<'a, ValidationReport>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            entities,
+            transforms,
+            parents,
+            chains,
+            hinges,
+            poles,
+            directions,
+            distances,
+            trackers,
+            springs,
+            tails,
+            quadrupeds,
+            mut report,
+        ) = data;
+
+        let mut validator = Validator {
+            entities: &entities,
+            transforms: &transforms,
+            parents: &parents,
+            errors: vec![],
+        };
+        self.checked.retain(|entity| entities.is_alive(*entity));
+
+        let components = (
+            chains.maybe(),
+            hinges.maybe(),
+            poles.maybe(),
+            directions.maybe(),
+            distances.maybe(),
+            trackers.maybe(),
+            springs.maybe(),
+            tails.maybe(),
+            quadrupeds.maybe(),
+        );
+        for (entity, components) in (&*entities, components).join() {
+            if self.checked.contains(&entity) { continue; }
+            let (chain, hinge, pole, direction, distance, tracker, spring, tail, quadruped) = components;
+            let any = chain.is_some() || hinge.is_some() || pole.is_some() || direction.is_some()
+                || distance.is_some() || tracker.is_some() || spring.is_some() || tail.is_some()
+                || quadruped.is_some();
+            if !any { continue; }
+            self.checked.insert(entity);
+
+            if let Some(chain) = chain { validator.chain(entity, chain); }
+            if let Some(hinge) = hinge { validator.hinge(entity, hinge); }
+            if let Some(pole) = pole { validator.reference(entity, "pole", pole.target()); }
+            if let Some(direction) = direction { validator.reference(entity, "direction", direction.target()); }
+            if let Some(distance) = distance { validator.reference(entity, "distance", distance.target()); }
+            if let Some(tracker) = tracker { validator.reference(entity, "tracker", tracker.target()); }
+            if let Some(spring) = spring { validator.reference(entity, "spring", spring.target()); }
+            if let Some(tail) = tail { validator.reference(entity, "tail", tail.player()); }
+            if let Some(quadruped) = quadruped { validator.quadruped(entity, quadruped); }
+        }
+        if !validator.errors.is_empty() {
+            warn!("Scene validation found {} problems:", validator.errors.len());
+            for error in validator.errors.iter() {
+                warn!("  - {}", error);
+            }
+            report.errors.extend(validator.errors);
+        }
+    }
+}
@@ -0,0 +1,252 @@
+use std::collections::HashMap;
+
+use amethyst::{
+    assets::{AssetStorage, Handle, Loader, PrefabData},
+    core::{math::{Point3, Vector3}, Time, Transform},
+    ecs::{Component, prelude::*},
+    error::Error,
+    renderer::{
+        Material,
+        MaterialDefaults,
+        Mesh,
+        palette::LinSrgba,
+        rendy::{
+            mesh::{Indices, MeshBuilder, Normal, Position, Tangent, TexCoord},
+            texture::palette::load_from_linear_rgba,
+        },
+        Texture,
+        transparent::Transparent,
+    },
+    shrev::EventChannel,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::utils::{determinism::Determinism, transform::TransformTrait};
+
+use super::{animal::Quadruped, cache::GlobalCache, physics::WorldUp};
+
+/// A rectangular body of water, centered at its entity.
+#[derive(Debug, Copy, Clone, Component)]
+#[storage(HashMapStorage)]
+pub struct Water {
+    extent: [f32; 2],
+    level: f32,
+    flow: Vector3<f32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WaterPrefab {
+    /// Size of the surface along x and z.
+    pub extent: [f32; 2],
+    /// Height of the surface above the entity, along the `WorldUp`.
+    #[serde(default)]
+    pub level: f32,
+    /// Velocity of the current in world space.
+    #[serde(default)]
+    pub flow: [f32; 3],
+    /// Color of the rendered surface; no surface is rendered if absent.
+    #[serde(default)]
+    pub color: Option<[f32; 4]>,
+}
+
+impl WaterPrefab {
+    fn surface(&self) -> MeshBuilder<'static> {
+        let [x, z] = [self.extent[0] / 2.0, self.extent[1] / 2.0];
+        let y = self.level;
+        MeshBuilder::new()
+            .with_vertices(vec![
+                Position([-x, y, -z]),
+                Position([x, y, -z]),
+                Position([x, y, z]),
+                Position([-x, y, z]),
+            ])
+            .with_vertices(vec![Normal([0.0, 1.0, 0.0]); 4])
+            .with_vertices(vec![Tangent([1.0, 0.0, 0.0, 1.0]); 4])
+            .with_vertices(vec![
+                TexCoord([0.0, 0.0]),
+                TexCoord([1.0, 0.0]),
+                TexCoord([1.0, 1.0]),
+                TexCoord([0.0, 1.0]),
+            ])
+            .with_indices(Indices::U16(vec![0, 2, 1, 0, 3, 2].into()))
+    }
+}
+
+impl<'a> PrefabData<'a> for WaterPrefab {
+    type SystemData = (
+        WriteStorage<'a, Water>,
+        ReadExpect<'a, Loader>,
+        Read<'a, AssetStorage<Mesh>>,
+        Read<'a, AssetStorage<Texture>>,
+        Read<'a, AssetStorage<Material>>,
+        ReadExpect<'a, MaterialDefaults>,
+        WriteStorage<'a, Handle<Mesh>>,
+        WriteStorage<'a, Handle<Material>>,
+        WriteStorage<'a, Transparent>,
+    );
+    type Result = ();
+
+    fn add_to_entity(
+        &self,
+        entity: Entity,
+        data: &mut Self::SystemData,
+        _entities: &[Entity],
+        _children: &[Entity],
+    ) -> Result<Self::Result, Error> {
+        let (
+            waters,
+            loader,
+            mesh_storage,
+            texture_storage,
+            material_storage,
+            material_defaults,
+            meshes,
+            materials,
+            transparents,
+        ) = data;
+
+        let component = Water {
+            extent: self.extent,
+            level: self.level,
+            flow: Vector3::from(self.flow),
+        };
+        waters.insert(entity, component)?;
+
+        if let Some([r, g, b, a]) = self.color {
+            let mesh = loader.load_from_data(self.surface().into(), (), mesh_storage);
+            let albedo = loader.load_from_data(
+                load_from_linear_rgba(LinSrgba::new(r, g, b, a)).into(),
+                (),
+                texture_storage,
+            );
+            let material = loader.load_from_data(
+                Material { albedo, ..material_defaults.0.clone() },
+                (),
+                material_storage,
+            );
+            meshes.insert(entity, mesh)?;
+            materials.insert(entity, material)?;
+            transparents.insert(entity, Transparent)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct WaterSurface {
+    pub center: Point3<f32>,
+    pub extent: [f32; 2],
+    /// Height of the surface along the `WorldUp`.
+    pub level: f32,
+    pub flow: Vector3<f32>,
+}
+
+impl WaterSurface {
+    fn contains(&self, up: &WorldUp, point: &Point3<f32>) -> bool {
+        let [x, z] = self.extent;
+        let delta = up.to_local(point) - up.to_local(&self.center);
+        delta.x.abs() <= x / 2.0 && delta.z.abs() <= z / 2.0
+    }
+}
+
+/// All water surfaces of the world this frame, for anything that floats or swims.
+///
+/// Surfaces lie across the `WorldUp`, aligned to the axes of its frame regardless of the rotation of their entity.
+#[derive(Debug, Default)]
+pub struct Buoyancy {
+    surfaces: Vec<WaterSurface>,
+    up: WorldUp,
+}
+
+impl Buoyancy {
+    pub fn surfaces(&self) -> &[WaterSurface] {
+        &self.surfaces
+    }
+
+    /// The highest surface above or below `point`.
+    pub fn surface_at(&self, point: &Point3<f32>) -> Option<&WaterSurface> {
+        self.surfaces
+            .iter()
+            .filter(|surface| surface.contains(&self.up, point))
+            .max_by(|a, b| a.level.partial_cmp(&b.level).unwrap_or(std::cmp::Ordering::Equal))
+    }
+
+    /// How deep `point` is under water, if it is.
+    pub fn depth(&self, point: &Point3<f32>) -> Option<f32> {
+        self.surface_at(point)
+            .map(|surface| surface.level - self.up.height(point))
+            .filter(|depth| *depth > 0.0)
+    }
+}
+
+/// A foot crossed a water surface.
+#[derive(Debug, Copy, Clone)]
+pub struct SplashEvent {
+    pub foot: Entity,
+    pub point: Point3<f32>,
+    /// Whether the foot went into the water, rather than out of it.
+    pub entering: bool,
+    /// Speed of the foot along the `WorldUp` when crossing.
+    pub speed: f32,
+}
+
+/// Gathers the water surfaces into `Buoyancy` and emits `SplashEvent`s for quadruped feet.
+#[derive(Default)]
+pub struct WaterSystem {
+    previous: HashMap<Entity, (Point3<f32>, bool)>,
+}
+
+impl<'a> System<'a> for WaterSystem {
+    type SystemData = (
+        ReadStorage<'a, Transform>,
+        ReadStorage<'a, Water>,
+        ReadStorage<'a, Quadruped>,
+        Read<'a, GlobalCache>,
+        Read<'a, Time>,
+        Read<'a, Determinism>,
+        Read<'a, WorldUp>,
+        Write<'a, Buoyancy>,
+        Write<'a, EventChannel<SplashEvent>>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (transforms, waters, quadrupeds, cache, time, determinism, up, mut buoyancy, mut splashes) = data;
+
+        buoyancy.up = *up;
+        buoyancy.surfaces.clear();
+        for (transform, water) in (&transforms, &waters).join() {
+            let center = transform.global_position();
+            buoyancy.surfaces.push(WaterSurface {
+                center,
+                extent: water.extent,
+                level: up.height(&center) + water.level,
+                flow: water.flow,
+            });
+        }
+        if buoyancy.surfaces.is_empty() {
+            self.previous.clear();
+            return;
+        }
+
+        let delta_seconds = determinism.delta_seconds(time.delta_seconds()).max(std::f32::EPSILON);
+        for quadruped in quadrupeds.join() {
+            for foot in quadruped.feet().iter() {
+                let point = match cache.global_position(*foot) {
+                    Some(point) => point,
+                    None => continue,
+                };
+                let submerged = buoyancy.depth(&point).is_some();
+                if let Some((previous, was_submerged)) = self.previous.insert(*foot, (point, submerged)) {
+                    if submerged != was_submerged {
+                        splashes.single_write(SplashEvent {
+                            foot: *foot,
+                            point,
+                            entering: submerged,
+                            speed: (up.height(&point) - up.height(&previous)) / delta_seconds,
+                        });
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,55 @@
+use amethyst::{core::Time, ecs::prelude::*};
+
+/// Makes locomotion and inverse kinematics bit-stable across runs, for replays and networked playback.
+///
+/// When enabled, bodies are processed one at a time in entity order, and the systems advance by
+/// exactly one `timestep` per frame instead of the measured frame time, so the same inputs on the
+/// same frames give the same results. Randomness is reproducible through the seeded `Random`.
+///
+/// The physics step banks the frame time to decide how many sub-steps to run, so the `FixedTimeSystem`
+/// must run first in the frame to make that count fixed as well.
+#[derive(Debug, Copy, Clone)]
+pub struct Determinism {
+    pub enabled: bool,
+    /// Seconds the simulation advances per frame when enabled.
+    pub timestep: f32,
+}
+
+impl Default for Determinism {
+    fn default() -> Self {
+        Determinism { enabled: false, timestep: 1.0 / 60.0 }
+    }
+}
+
+impl Determinism {
+    pub fn new(timestep: f32) -> Self {
+        Determinism { enabled: true, timestep }
+    }
+
+    /// The seconds to advance this frame, given the measured frame time.
+    pub fn delta_seconds(&self, measured: f32) -> f32 {
+        if self.enabled { self.timestep } else { measured }
+    }
+
+    /// The seconds to advance in one run of a system reading the `StepTime`, given its delta.
+    ///
+    /// With nphysics that is a sub-step of the physics, which is fixed already; without, it is the frame.
+    pub fn step_seconds(&self, step: f32) -> f32 {
+        if cfg!(feature = "nphysics") { step } else { self.delta_seconds(step) }
+    }
+}
+
+/// Replaces the measured frame time with the `timestep` when determinism is enabled,
+/// so every system reading the `Time` after it, including the physics step, sees the same frames on every run.
+#[derive(Default)]
+pub struct FixedTimeSystem;
+
+impl<'a> System<'a> for FixedTimeSystem {
+    type SystemData = (Write<'a, Time>, Read<'a, Determinism>);
+
+    fn run(&mut self, (mut time, determinism): Self::SystemData) {
+        if determinism.enabled {
+            time.set_delta_seconds(determinism.timestep);
+        }
+    }
+}
@@ -0,0 +1,142 @@
+//! Euler angles for exchanging rotations with tools that do not speak quaternions.
+
+use std::str::FromStr;
+
+use amethyst::{
+    core::math::{UnitQuaternion, Vector3},
+    error::{Error, format_err},
+};
+use serde::{Deserialize, Serialize};
+
+/// The order Euler angles are applied in, about the fixed axes of the parent as in Blender:
+/// `XYZ` rotates about x first and about z last.
+///
+/// Tools that name intrinsic rotations, like Maya's `zyx` for Blender's `XYZ`, list the axes backwards.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RotationOrder {
+    XYZ,
+    XZY,
+    YXZ,
+    YZX,
+    ZXY,
+    ZYX,
+}
+
+impl Default for RotationOrder {
+    fn default() -> Self {
+        RotationOrder::XYZ
+    }
+}
+
+impl RotationOrder {
+    /// Indices of the axes in the order they are applied.
+    fn axes(self) -> [usize; 3] {
+        match self {
+            RotationOrder::XYZ => [0, 1, 2],
+            RotationOrder::XZY => [0, 2, 1],
+            RotationOrder::YXZ => [1, 0, 2],
+            RotationOrder::YZX => [1, 2, 0],
+            RotationOrder::ZXY => [2, 0, 1],
+            RotationOrder::ZYX => [2, 1, 0],
+        }
+    }
+
+    /// Whether the axes follow x, y, z cyclically, which decides the signs of the decomposition.
+    fn is_cyclic(self) -> bool {
+        matches!(self, RotationOrder::XYZ | RotationOrder::YZX | RotationOrder::ZXY)
+    }
+}
+
+impl FromStr for RotationOrder {
+    type Err = Error;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name.to_ascii_uppercase().as_str() {
+            "XYZ" => Ok(RotationOrder::XYZ),
+            "XZY" => Ok(RotationOrder::XZY),
+            "YXZ" => Ok(RotationOrder::YXZ),
+            "YZX" => Ok(RotationOrder::YZX),
+            "ZXY" => Ok(RotationOrder::ZXY),
+            "ZYX" => Ok(RotationOrder::ZYX),
+            _ => Err(format_err!("Unknown rotation order {}", name)),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AngleUnit {
+    Radians,
+    Degrees,
+}
+
+impl Default for AngleUnit {
+    fn default() -> Self {
+        AngleUnit::Radians
+    }
+}
+
+impl AngleUnit {
+    /// `angle` in radians, expressed in this unit.
+    fn express(self, angle: f32) -> f32 {
+        match self {
+            AngleUnit::Radians => angle,
+            AngleUnit::Degrees => angle.to_degrees(),
+        }
+    }
+
+    /// `angle` in this unit, in radians.
+    fn radians(self, angle: f32) -> f32 {
+        match self {
+            AngleUnit::Radians => angle,
+            AngleUnit::Degrees => angle.to_radians(),
+        }
+    }
+}
+
+impl FromStr for AngleUnit {
+    type Err = Error;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "rad" | "radians" => Ok(AngleUnit::Radians),
+            "deg" | "degrees" => Ok(AngleUnit::Degrees),
+            _ => Err(format_err!("Unknown angle unit {}, expected deg or rad", name)),
+        }
+    }
+}
+
+/// Angles about x, y and z, in that component order whatever the rotation `order` is.
+///
+/// At gimbal lock, where the middle axis is turned a quarter, the last angle is put to zero.
+pub fn to_euler(rotation: &UnitQuaternion<f32>, order: RotationOrder, unit: AngleUnit) -> Vector3<f32> {
+    let matrix = rotation.to_rotation_matrix();
+    let m = |row: usize, column: usize| matrix[(row, column)];
+    let [i, j, k] = order.axes();
+    let sign = if order.is_cyclic() { 1.0 } else { -1.0 };
+
+    let sine = (-sign * m(k, i)).max(-1.0).min(1.0);
+    let middle = sine.asin();
+    let (first, last) = if sine.abs() < 1.0 - 1e-5 {
+        ((sign * m(k, j)).atan2(m(k, k)), (sign * m(j, i)).atan2(m(i, i)))
+    } else {
+        ((-sign * m(j, k)).atan2(m(j, j)), 0.0)
+    };
+
+    let mut angles = Vector3::zeros();
+    angles[i] = unit.express(first);
+    angles[j] = unit.express(middle);
+    angles[k] = unit.express(last);
+    angles
+}
+
+/// The rotation made of `angles` about x, y and z, applied in `order`.
+pub fn from_euler(angles: &Vector3<f32>, order: RotationOrder, unit: AngleUnit) -> UnitQuaternion<f32> {
+    let unit_axes = [Vector3::x_axis(), Vector3::y_axis(), Vector3::z_axis()];
+    order
+        .axes()
+        .iter()
+        .fold(UnitQuaternion::identity(), |rotation, &axis| {
+            let angle = unit.radians(angles[axis]);
+            UnitQuaternion::from_axis_angle(&unit_axes[axis], angle) * rotation
+        })
+}
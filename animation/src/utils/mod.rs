@@ -0,0 +1,53 @@
+use amethyst::core::math::{convert, Matrix3, Point3, RealField, UnitQuaternion, Vector3};
+
+pub mod determinism;
+pub mod euler;
+pub mod random;
+pub mod transform;
+pub mod warnings;
+
+/// Calculate the optimal translation and rotation that minimizes distance between two point sets.
+pub fn match_shape<T: RealField>(origins: &[Point3<T>], targets: &[Point3<T>], eps: T, max_iter: usize) -> (Vector3<T>, UnitQuaternion<T>) {
+    let mean = |points: &[Point3<T>]| {
+        let count: T = convert(points.len() as f64);
+        points
+            .iter()
+            .fold(Vector3::zeros(), |sum, point| sum + point.coords)
+            .unscale(count)
+    };
+    let ref origins_mean = mean(origins);
+    let ref targets_mean = mean(targets);
+    let translation = targets_mean - origins_mean;
+
+    let ref covariance = origins
+        .iter()
+        .zip(targets.iter())
+        .fold(Matrix3::zeros(), |sum, (origin, target)| {
+            let origin = origin.coords - origins_mean;
+            let target = target.coords - targets_mean;
+            sum + origin * target.transpose()
+        });
+    let rotation = UnitQuaternion::from_matrix_eps(covariance, eps, max_iter, UnitQuaternion::identity());
+
+    (translation, rotation)
+}
+
+/*
+/// Verlet integration.
+pub fn verlet<T: RealField, F>(
+    position: Point3<T>,
+    velocity: Vector3<T>,
+    field: F,
+    delta_seconds: T,
+) -> (Point3<T>, Vector3<T>)
+    where F: Fn(&Point3<T>) -> Vector3<T> {
+    let half = T::from_str_radix("0.5", 10).ok().expect("Unreachable: convert from 0.5");
+    let acceleration = field(&position);
+    let velocity = velocity + acceleration.scale(delta_seconds * half);
+    let position = position + velocity.scale(delta_seconds);
+
+    let acceleration = field(&position);
+    let velocity = velocity + acceleration.scale(delta_seconds * half);
+    (position, velocity)
+}
+ */
\ No newline at end of file
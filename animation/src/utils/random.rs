@@ -0,0 +1,95 @@
+use amethyst::ecs::{Component, prelude::*};
+use rand::{Error, Rng, RngCore, rngs::StdRng, SeedableRng};
+
+/// The source of all randomness, seeded once so runs can be reproduced.
+///
+/// Systems should draw from the `Seed` of the entity they process rather than from this resource,
+/// so results do not depend on iteration order or on which other entities exist.
+#[derive(Debug, Clone)]
+pub struct Random {
+    seed: u64,
+    rng: StdRng,
+}
+
+impl Default for Random {
+    fn default() -> Self {
+        Random::new(0)
+    }
+}
+
+impl Random {
+    pub fn new(seed: u64) -> Self {
+        Random { seed, rng: StdRng::seed_from_u64(seed) }
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// A new, independent stream for an entity being spawned.
+    pub fn spawn_seed(&mut self) -> Seed {
+        Seed::new(self.rng.gen())
+    }
+}
+
+impl RngCore for Random {
+    fn next_u32(&mut self) -> u32 {
+        self.rng.next_u32()
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.rng.next_u64()
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.rng.fill_bytes(dest)
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), Error> {
+        self.rng.try_fill_bytes(dest)
+    }
+}
+
+/// The random stream of an entity, assigned from `Random` at spawn.
+#[derive(Debug, Clone, Component)]
+#[storage(HashMapStorage)]
+pub struct Seed {
+    seed: u64,
+    rng: StdRng,
+}
+
+impl Seed {
+    pub fn new(seed: u64) -> Self {
+        Seed { seed, rng: StdRng::seed_from_u64(seed) }
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Insert a seed for `entity` unless it already has one.
+    pub fn assign(entity: Entity, random: &mut Random, seeds: &mut WriteStorage<'_, Seed>) -> Result<(), amethyst::error::Error> {
+        if !seeds.contains(entity) {
+            seeds.insert(entity, random.spawn_seed())?;
+        }
+        Ok(())
+    }
+}
+
+impl RngCore for Seed {
+    fn next_u32(&mut self) -> u32 {
+        self.rng.next_u32()
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.rng.next_u64()
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.rng.fill_bytes(dest)
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), Error> {
+        self.rng.try_fill_bytes(dest)
+    }
+}
@@ -0,0 +1,28 @@
+use amethyst::{
+    core::{math::Point3, transform::ParentHierarchy, Transform},
+    ecs::prelude::*,
+};
+
+pub trait TransformTrait {
+    fn global_position(&self) -> Point3<f32>;
+}
+
+impl TransformTrait for Transform {
+    #[inline]
+    fn global_position(&self) -> Point3<f32> {
+        let ref origin = Point3::origin();
+        self.global_matrix().transform_point(origin)
+    }
+}
+/// Adds the descendants of the entities in `dirty` to it, since their global matrices move along.
+pub fn mark_descendants(dirty: &mut BitSet, hierarchy: &ParentHierarchy) {
+    // Parents come before their children, so a single pass reaches every depth.
+    for &entity in hierarchy.all() {
+        let moved = hierarchy
+            .parent(entity)
+            .map_or(false, |parent| dirty.contains(parent.id()));
+        if moved {
+            dirty.add(entity.id());
+        }
+    }
+}
@@ -0,0 +1,32 @@
+use std::collections::HashMap;
+
+use amethyst::ecs::Entity;
+use log::warn;
+
+/// Counts entities that systems skipped because something they reference is missing,
+/// e.g. a component of an entity deleted mid-frame.
+///
+/// Only the first skip of each system is printed; later ones are just counted.
+#[derive(Debug, Default)]
+pub struct Warnings {
+    counts: HashMap<&'static str, usize>,
+}
+
+impl Warnings {
+    /// Record that `system` skipped processing `entity` this frame.
+    pub fn skip(&mut self, system: &'static str, entity: Entity) {
+        let count = self.counts.entry(system).or_default();
+        if *count == 0 {
+            warn!("{}: skipped {:?} with missing components, further skips are only counted", system, entity);
+        }
+        *count += 1;
+    }
+
+    pub fn count(&self, system: &str) -> usize {
+        self.counts.get(system).copied().unwrap_or_default()
+    }
+
+    pub fn counts(&self) -> impl Iterator<Item=(&'static str, usize)> + '_ {
+        self.counts.iter().map(|(system, count)| (*system, *count))
+    }
+}
@@ -0,0 +1,59 @@
+use std::f32::consts::PI;
+
+use amethyst::core::math::Vector3;
+
+use ceramic_animation::systems::animal::{Config, GaitBakery};
+
+const SAMPLE_RATE: f32 = 60.0;
+
+fn bakery() -> GaitBakery {
+    GaitBakery {
+        animation: 0,
+        cycles: 3,
+        sample_rate: SAMPLE_RATE,
+        contact_threshold: 0.1,
+        max_step_ratio: 1.5,
+    }
+}
+
+/// A foot walking in place for `seconds`, one step per `period` with `duty_factor` of it on the ground,
+/// sliding back by `step` while grounded and lifting by 0.2 while in the air.
+fn track(period: f32, duty_factor: f32, step: f32, offset: f32, seconds: f32) -> Vec<Vector3<f32>> {
+    (0..(seconds * SAMPLE_RATE) as usize)
+        .map(|index| {
+            let phase = (index as f32 / SAMPLE_RATE / period + offset).fract();
+            if phase < duty_factor {
+                Vector3::new(0.0, 0.0, step * (0.5 - phase / duty_factor))
+            } else {
+                let swing = (phase - duty_factor) / (1.0 - duty_factor);
+                Vector3::new(0.0, 0.2 * (PI * swing).sin(), step * (swing - 0.5))
+            }
+        })
+        .collect()
+}
+
+#[test]
+fn measures_a_walk() {
+    let tracks = [0.0, 0.5, 0.25, 0.75]
+        .iter()
+        .map(|&offset| track(1.0, 0.75, 0.5, offset, 3.0))
+        .collect::<Vec<_>>();
+    let gait = bakery().measure(&tracks).unwrap();
+    assert!((gait.period - 1.0).abs() < 0.02, "period {}", gait.period);
+    assert!((gait.duty_factor - 0.75).abs() < 0.03, "duty factor {}", gait.duty_factor);
+    assert!((gait.step_length - 0.5).abs() < 0.05, "step length {}", gait.step_length);
+
+    let config = bakery().configure(&gait, &Config::default(), 2.0);
+    assert!((config.step_limit[0] - gait.step_length / 2.0).abs() < 1e-6);
+    assert!((config.step_limit[1] - config.step_limit[0] * 1.5).abs() < 1e-6);
+    assert!((config.flight_time - gait.flight_time() / 2.0f32.sqrt()).abs() < 1e-6);
+}
+
+#[test]
+fn rejects_feet_that_never_step() {
+    let still = vec![vec![Vector3::zeros(); 120]; 4];
+    assert!(bakery().measure(&still).is_err());
+
+    let once = vec![track(1.0, 0.75, 0.5, 0.5, 1.0); 4];
+    assert!(bakery().measure(&once).is_err());
+}
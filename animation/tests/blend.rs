@@ -0,0 +1,33 @@
+use amethyst::core::{math::{UnitQuaternion, Vector3}, Transform};
+
+use ceramic_animation::systems::blend::blend_transforms;
+
+fn transform(x: f32, angle: f32) -> Transform {
+    let mut transform = Transform::default();
+    transform
+        .set_translation_xyz(x, 0.0, 0.0)
+        .set_rotation(UnitQuaternion::from_axis_angle(&Vector3::y_axis(), angle));
+    transform
+}
+
+#[test]
+fn blend_weighs_authored_pose() {
+    let authored = transform(1.0, 1.0);
+    let procedural = transform(0.0, 0.0);
+
+    let blended = blend_transforms(&authored, &procedural, 0.25);
+    assert!((blended.translation().x - 0.25).abs() < 1.0e-5, "translated to {}", blended.translation().x);
+    assert!((blended.rotation().angle() - 0.25).abs() < 1.0e-5, "rotated by {}", blended.rotation().angle());
+}
+
+#[test]
+fn blend_keeps_ends() {
+    let authored = transform(1.0, 1.0);
+    let procedural = transform(-1.0, -0.5);
+
+    let blended = blend_transforms(&authored, &procedural, 0.0);
+    assert_eq!(blended.translation(), procedural.translation());
+    let blended = blend_transforms(&authored, &procedural, 1.0);
+    assert!((blended.translation() - authored.translation()).norm() < 1.0e-5);
+    assert!(blended.rotation().angle_to(authored.rotation()) < 1.0e-4);
+}
@@ -0,0 +1,91 @@
+//! Picking up a prop goes through the physics backend, and only the Verlet one runs without a physics world,
+//! so these tests need `--no-default-features`.
+#![cfg(not(feature = "nphysics"))]
+
+use amethyst::{assets::PrefabData, core::Transform, ecs::prelude::*, shrev::EventChannel};
+
+use ceramic_animation::{
+    extras::{EntityRef, RedirectField},
+    systems::{
+        animal::{Tracker, TrackerPrefab, TrackSystem},
+        carry::{Carryable, Carrier, CarrierPrefab, CarryEvent, CarrySystem},
+        cleanup::CleanupSystem,
+        socket::{SocketEvent, SocketPrefab, Sockets, SocketsPrefab, SocketSystem},
+    },
+};
+
+use test_support::{TestWorld, transform_at};
+
+mod test_support;
+
+const DELTA_SECONDS: f32 = 1.0 / 60.0;
+
+#[test]
+fn cleanup_releases_deleted_props_and_targets() {
+    let mut test = TestWorld::builder()
+        .with_system(CleanupSystem::default(), "cleanup", &[])
+        .with_system(CarrySystem::default(), "carry", &["cleanup", "transform_system"])
+        .with_system(SocketSystem::default(), "socket", &["cleanup"])
+        .with_system(TrackSystem::default(), "track", &["cleanup", "transform_system"])
+        .build();
+
+    let body = test.spawn(transform_at(0.0, 0.0, 0.0), None);
+    let jaw = test.spawn(transform_at(0.0, 0.5, 0.5), Some(body));
+    let neck = test.spawn(transform_at(0.0, 0.5, 0.3), Some(body));
+    let tail = test.spawn(transform_at(0.0, 0.4, -0.5), Some(body));
+    let head = test.spawn(transform_at(0.0, 0.6, 0.4), Some(body));
+    let first_target = test.spawn(transform_at(1.0, 0.5, 1.0), None);
+    let second_target = test.spawn(transform_at(-1.0, 0.5, 1.0), None);
+    let ball = test.spawn(transform_at(0.0, 0.5, 0.6), None);
+    let collar = test.spawn(transform_at(3.0, 0.0, 0.0), None);
+    let bow = test.spawn(transform_at(-3.0, 0.0, 0.0), None);
+
+    let entities = [jaw, neck, tail, first_target];
+    let field = |index: usize| EntityRef(RedirectField::Target(index));
+    CarrierPrefab { jaw: field(0), reach: 0.5 }
+        .add_to_entity(body, &mut test.world.write_storage::<Carrier>(), &entities, &[])
+        .unwrap();
+    let socket = |name: &str, node: usize| SocketPrefab { name: name.into(), node: field(node), offset: Transform::default() };
+    SocketsPrefab(vec![socket("collar", 1), socket("bow", 2)])
+        .add_to_entity(body, &mut test.world.write_storage::<Sockets>(), &entities, &[])
+        .unwrap();
+    let tracker = TrackerPrefab { target: field(3), limit: None, speed: 4.0, dead_zone: None, max_angular_velocity: None };
+    tracker
+        .add_to_entity(head, &mut test.world.write_storage::<Tracker>(), &entities, &[])
+        .unwrap();
+    test.world
+        .write_storage::<Carryable>()
+        .insert(ball, Carryable { offset: [0.0; 3], mass: None })
+        .unwrap();
+
+    test.world.write_resource::<EventChannel<CarryEvent>>().single_write(CarryEvent::PickUp(body));
+    let mut socket_events = test.world.write_resource::<EventChannel<SocketEvent>>();
+    socket_events.single_write(SocketEvent::Attach { body, socket: "collar".into(), prop: collar });
+    socket_events.single_write(SocketEvent::Attach { body, socket: "bow".into(), prop: bow });
+    drop(socket_events);
+    test.world
+        .write_storage::<Tracker>()
+        .get_mut(head)
+        .unwrap()
+        .retarget(second_target, 1.0);
+    test.run(2, DELTA_SECONDS);
+    assert_eq!(test.world.read_storage::<Carrier>().get(body).unwrap().carried(), Some(ball));
+
+    // The carried prop, the target the tracker fades from, a socketed prop and the node of the other socket.
+    test.world.delete_entities(&[ball, first_target, collar, tail]).unwrap();
+    test.world.maintain();
+    test.run(2, DELTA_SECONDS);
+
+    let carriers = test.world.read_storage::<Carrier>();
+    let carrier = carriers.get(body).expect("the carrier went with its prop");
+    assert_eq!(carrier.carried(), None);
+
+    let trackers = test.world.read_storage::<Tracker>();
+    let tracker = trackers.get(head).expect("the tracker went with its previous target");
+    assert_eq!(tracker.target(), second_target);
+
+    let sockets = test.world.read_storage::<Sockets>();
+    let sockets = sockets.get(body).expect("the sockets went with a socketed prop");
+    assert_eq!(sockets.get("collar").unwrap().attached, None);
+    assert!(sockets.get("bow").is_none(), "the socket stayed on a deleted node");
+}
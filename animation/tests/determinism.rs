@@ -0,0 +1,87 @@
+//! The oscillators run in the stand-in physics step of `test_support`, so these tests cover the stepping contract
+//! of the fixed timestep, not the physics stage that the game's schedule builds.
+
+use amethyst::{core::Transform, prelude::WorldExt};
+
+use ceramic_animation::{
+    systems::{
+        animal::{LimbCalibrationSystem, LocomotionSystem, OscillatorSystem, Quadruped},
+        cache::GlobalCacheSystem,
+        player::Player,
+    },
+    utils::determinism::Determinism,
+};
+
+use test_support::{spawn_quadruped, TestWorld};
+
+mod test_support;
+
+const FRAMES: usize = 240;
+const COUNT: usize = 4;
+
+/// Walk a few quadrupeds with the given frame times, with the oscillators in a physics step of `physics_rate`,
+/// returning the foot positions of every frame.
+fn simulate(physics_rate: u32, frame_time: impl Fn(usize) -> f32) -> Vec<[f32; 3]> {
+    let mut test = TestWorld::builder()
+        .with_resource(Determinism::new(1.0 / 60.0))
+        .with_physics_rate(physics_rate)
+        .with_physics_system(OscillatorSystem::<Quadruped>::default(), "oscillator", &[])
+        .with_system(GlobalCacheSystem::default(), "global_cache", &["transform_system"])
+        .with_system(LimbCalibrationSystem::default(), "limb_calibration", &["global_cache"])
+        .with_system(LocomotionSystem::<Quadruped>::default(), "locomotion", &["global_cache", "limb_calibration"])
+        .build();
+
+    let quadrupeds = (0..COUNT)
+        .map(|i| spawn_quadruped(&mut test.world, i as f32 * 3.0))
+        .collect::<Vec<_>>();
+
+    let mut trajectories = vec![];
+    for frame in 0..FRAMES {
+        let timestep = test.world.read_resource::<Determinism>().timestep;
+        {
+            let players = test.world.read_storage::<Player>();
+            let mut transforms = test.world.write_storage::<Transform>();
+            for (body, _) in &quadrupeds {
+                let velocity = players.get(*body).unwrap().velocity();
+                transforms.get_mut(*body).unwrap().append_translation(velocity * timestep);
+            }
+        }
+        test.step(frame_time(frame));
+
+        for (_, feet) in &quadrupeds {
+            for foot in feet {
+                let translation = *test.transform(*foot).translation();
+                trajectories.push([translation.x, translation.y, translation.z]);
+            }
+        }
+    }
+    trajectories
+}
+
+#[test]
+fn foot_trajectories_are_identical_across_runs() {
+    let steady = simulate(60, |_| 1.0 / 60.0);
+    let jittery = simulate(60, |frame| if frame % 3 == 0 { 1.0 / 24.0 } else { 1.0 / 90.0 });
+
+    assert_eq!(steady.len(), FRAMES * COUNT * 4);
+    assert!(steady.iter().any(|[_, _, z]| *z > 1.0), "the feet never stepped");
+    for (frame, (a, b)) in steady.iter().zip(jittery.iter()).enumerate() {
+        assert!(
+            a.iter().zip(b.iter()).all(|(a, b)| a.to_bits() == b.to_bits()),
+            "trajectories diverge at sample {}: {:?} != {:?}",
+            frame, a, b,
+        );
+    }
+}
+
+#[test]
+fn physics_steps_advance_the_gait_by_their_own_length() {
+    // Two steps a frame at 120 Hz must walk like one at 60 Hz, not twice as fast.
+    let single = simulate(60, |frame| if frame % 2 == 0 { 1.0 / 30.0 } else { 1.0 / 120.0 });
+    let double = simulate(120, |frame| if frame % 2 == 0 { 1.0 / 30.0 } else { 1.0 / 120.0 });
+
+    for (frame, (a, b)) in single.iter().zip(double.iter()).enumerate() {
+        let distance = a.iter().zip(b.iter()).map(|(a, b)| (a - b).powi(2)).sum::<f32>().sqrt();
+        assert!(distance < 0.05, "feet drift apart at sample {}: {:?} != {:?}", frame, a, b);
+    }
+}
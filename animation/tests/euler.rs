@@ -0,0 +1,53 @@
+use std::f32::consts::FRAC_PI_2;
+
+use amethyst::core::math::{UnitQuaternion, Vector3};
+
+use ceramic_animation::utils::euler::{AngleUnit, from_euler, RotationOrder, to_euler};
+
+const ORDERS: [RotationOrder; 6] = [
+    RotationOrder::XYZ,
+    RotationOrder::XZY,
+    RotationOrder::YXZ,
+    RotationOrder::YZX,
+    RotationOrder::ZXY,
+    RotationOrder::ZYX,
+];
+
+#[test]
+fn euler_round_trip() {
+    let rotation = UnitQuaternion::from_euler_angles(0.3, -0.7, 1.2);
+    for &order in &ORDERS {
+        for &unit in &[AngleUnit::Radians, AngleUnit::Degrees] {
+            let angles = to_euler(&rotation, order, unit);
+            let back = from_euler(&angles, order, unit);
+            assert!(back.angle_to(&rotation) < 1e-4, "{:?} {:?}: {:?} != {:?}", order, unit, back, rotation);
+        }
+    }
+}
+
+#[test]
+fn euler_round_trip_at_gimbal_lock() {
+    for &order in &ORDERS {
+        let mut angles = Vector3::new(0.4, 0.4, 0.4);
+        let middle = match order {
+            RotationOrder::YXZ | RotationOrder::ZXY => 0,
+            RotationOrder::XYZ | RotationOrder::ZYX => 1,
+            RotationOrder::XZY | RotationOrder::YZX => 2,
+        };
+        angles[middle] = FRAC_PI_2;
+
+        let rotation = from_euler(&angles, order, AngleUnit::Radians);
+        let back = from_euler(&to_euler(&rotation, order, AngleUnit::Radians), order, AngleUnit::Radians);
+        assert!(back.angle_to(&rotation) < 1e-3, "{:?}: {:?} != {:?}", order, back, rotation);
+    }
+}
+
+#[test]
+fn euler_order_applies_first_axis_first() {
+    let angles = Vector3::new(90.0, 0.0, 90.0);
+    let xyz = from_euler(&angles, RotationOrder::XYZ, AngleUnit::Degrees);
+    let zyx = from_euler(&angles, RotationOrder::ZYX, AngleUnit::Degrees);
+    // About x first takes y to z, which the turn about z keeps; about z first takes y to -x, which x keeps.
+    assert!((xyz * Vector3::y() - Vector3::z()).norm() < 1e-5);
+    assert!((zyx * Vector3::y() + Vector3::x()).norm() < 1e-5);
+}
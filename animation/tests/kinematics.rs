@@ -0,0 +1,77 @@
+use std::f32::consts::FRAC_PI_2;
+
+use amethyst::{
+    assets::PrefabData,
+    core::math::{UnitQuaternion, Vector3},
+    ecs::prelude::*,
+};
+
+use ceramic_animation::{
+    extras::{EntityRef, RedirectField},
+    systems::kinematics::{Chain, ChainPrefab, Hinge, KinematicsBundle},
+};
+
+use test_support::{TestWorld, transform_at};
+
+mod test_support;
+
+const TOLERANCE: f32 = 1.0e-3;
+
+/// A joint bent by 0.3 radians about x with a unit bone along z, reaching for a target straight below the joint,
+/// which takes a bend of a quarter turn, solving at `rate` if any. Returns the world and the joint.
+fn bend_towards_target(limit: [f32; 2], rate: Option<f32>) -> (TestWorld, Entity) {
+    let bundle = KinematicsBundle::new(10, 0.001);
+    let bundle = match rate {
+        Some(rate) => bundle.with_rate(rate),
+        None => bundle,
+    };
+    let mut test = TestWorld::builder()
+        .with_bundle(bundle)
+        .unwrap()
+        .build();
+
+    let mut joint = transform_at(0.0, 1.0, 0.0);
+    joint.set_rotation(UnitQuaternion::from_axis_angle(&Vector3::x_axis(), 0.3));
+    let joint = test.spawn(joint, None);
+    let end = test.spawn(transform_at(0.0, 0.0, 1.0), Some(joint));
+    let target = test.spawn(transform_at(0.0, 0.0, 0.0), None);
+
+    test.world.write_storage::<Hinge>().insert(joint, Hinge::new(Some(limit))).unwrap();
+    let chain = ChainPrefab { target: EntityRef(RedirectField::Target(0)), length: 2 };
+    chain
+        .add_to_entity(end, &mut test.world.write_storage::<Chain>(), &[target], &[])
+        .unwrap();
+
+    test.run(4, 1.0 / 60.0);
+    (test, joint)
+}
+
+/// The bend of `joint` about the x axis.
+fn bend(test: &TestWorld, joint: Entity) -> f32 {
+    let (axis, angle) = test.transform(joint).rotation().axis_angle().expect("The joint is not bent");
+    assert!((axis.into_inner() - Vector3::x()).norm() < TOLERANCE, "the joint left its hinge axis: {:?}", axis);
+    angle
+}
+
+#[test]
+fn hinge_reaches_target_within_limit() {
+    let (test, joint) = bend_towards_target([0.0, 2.0], None);
+    let angle = bend(&test, joint);
+    assert!((angle - FRAC_PI_2).abs() < TOLERANCE, "bent by {} instead of reaching the target", angle);
+}
+
+#[test]
+fn hinge_clamps_to_limit() {
+    let (test, joint) = bend_towards_target([0.0, 0.5], None);
+    let angle = bend(&test, joint);
+    assert!((angle - 0.5).abs() < TOLERANCE, "bent by {} past the limit of 0.5", angle);
+}
+
+#[test]
+fn hinge_reaches_target_at_fixed_rate() {
+    // Interpolated between solves, the joint lands on the solution instead of only closing in on it.
+    let (mut test, joint) = bend_towards_target([0.0, 2.0], Some(30.0));
+    test.run(8, 1.0 / 60.0);
+    let angle = bend(&test, joint);
+    assert!((angle - FRAC_PI_2).abs() < TOLERANCE, "bent by {} instead of reaching the target", angle);
+}
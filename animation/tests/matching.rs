@@ -0,0 +1,44 @@
+use std::f32::consts::PI;
+
+use ceramic_animation::systems::animal::{MatchWeights, MotionDatabase, MotionFrame, MotionRecording};
+
+/// A recording walking forward at `speed` with the limbs in a trot, for `count` frames.
+fn recording(speed: f32, count: usize) -> MotionRecording {
+    let frames = (0..count)
+        .map(|index| {
+            let phase = index as f32 * 0.2;
+            MotionFrame {
+                velocity: [0.0, 0.0, speed],
+                phases: [phase, phase + PI, phase + PI, phase],
+                ..Default::default()
+            }
+        })
+        .collect();
+    MotionRecording { frame_rate: 30.0, frames }
+}
+
+#[test]
+fn bake_keeps_starts_within_recordings() {
+    let database = MotionDatabase::bake(&[recording(1.0, 20), recording(3.0, 20)], 0.2).unwrap();
+    assert_eq!(database.horizon, 6);
+    assert_eq!(database.frames.len(), 40);
+    assert_eq!(database.starts.len(), 28);
+    assert!(database.starts.iter().all(|&start| start % 20 + database.horizon < 20));
+}
+
+#[test]
+fn bake_rejects_mixed_frame_rates() {
+    let mut fast = recording(3.0, 20);
+    fast.frame_rate = 60.0;
+    assert!(MotionDatabase::bake(&[recording(1.0, 20), fast], 0.2).is_err());
+    assert!(MotionDatabase::bake(&[], 0.2).is_err());
+    assert!(MotionDatabase::bake(&[recording(0.0, 20)], 0.2).is_err());
+}
+
+#[test]
+fn best_match_follows_speed() {
+    let database = MotionDatabase::bake(&[recording(1.0, 20), recording(3.0, 20)], 0.2).unwrap();
+    let query = MotionFrame { velocity: [0.0, 0.0, 2.8], ..database.frames[25] };
+    let (start, _) = database.best_match(&query, &MatchWeights::default()).unwrap();
+    assert_eq!(start, 25);
+}
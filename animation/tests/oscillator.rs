@@ -0,0 +1,84 @@
+//! The oscillators run in the stand-in physics step of `test_support`, so these tests cover the stepping contract
+//! of the sub-steps, not the physics stage that the game's schedule builds.
+
+use amethyst::{
+    core::{math::Complex, Transform},
+    prelude::WorldExt,
+};
+
+use ceramic_animation::{
+    systems::{
+        animal::{LimbCalibrationSystem, LocomotionSystem, OscillatorSystem, Quadruped},
+        cache::GlobalCacheSystem,
+        player::Player,
+    },
+    utils::determinism::Determinism,
+};
+
+use test_support::{spawn_quadruped, TestWorld};
+
+mod test_support;
+
+const SECONDS: f32 = 6.0;
+
+/// Walk a quadruped for `SECONDS` at `frame_rate`, returning the phases of its limbs at every frame.
+fn walk(frame_rate: f32) -> Vec<[Complex<f32>; 4]> {
+    let timestep = 1.0 / frame_rate;
+    let mut test = TestWorld::builder()
+        .with_resource(Determinism::new(timestep))
+        .with_physics_rate(frame_rate as u32)
+        .with_physics_system(OscillatorSystem::<Quadruped>::default(), "oscillator", &[])
+        .with_system(GlobalCacheSystem::default(), "global_cache", &["transform_system"])
+        .with_system(LimbCalibrationSystem::default(), "limb_calibration", &["global_cache"])
+        .with_system(LocomotionSystem::<Quadruped>::default(), "locomotion", &["global_cache", "limb_calibration"])
+        .build();
+    let (body, _) = spawn_quadruped(&mut test.world, 0.0);
+
+    let mut phases = vec![];
+    for _ in 0..(SECONDS * frame_rate) as usize {
+        {
+            let velocity = test.world.read_storage::<Player>().get(body).unwrap().velocity();
+            let mut transforms = test.world.write_storage::<Transform>();
+            transforms.get_mut(body).unwrap().append_translation(velocity * timestep);
+        }
+        test.step(timestep);
+        phases.push(test.world.read_storage::<Quadruped>().get(body).unwrap().phases());
+    }
+    phases
+}
+
+/// Phase of each limb relative to the first, at the last frame.
+fn offsets(phases: &[[Complex<f32>; 4]]) -> Vec<f32> {
+    let last = phases.last().unwrap();
+    last.iter()
+        .map(|phase| (phase / last[0]).arg())
+        .collect()
+}
+
+#[test]
+fn phases_stay_on_the_limit_cycle_from_20_to_240_fps() {
+    for &frame_rate in &[20.0, 60.0, 240.0] {
+        for (frame, phases) in walk(frame_rate).iter().enumerate() {
+            for phase in phases {
+                assert!(phase.re.is_finite() && phase.im.is_finite(), "phase is not finite at {} fps", frame_rate);
+            }
+            // Give the oscillators a second to settle from their initial phases.
+            if frame as f32 > frame_rate {
+                for phase in phases {
+                    let norm = phase.norm();
+                    assert!(norm > 0.8 && norm < 1.2, "phase left the limit cycle at {} fps, frame {}: {}", frame_rate, frame, norm);
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn gait_offsets_agree_across_frame_rates() {
+    let slow = offsets(&walk(20.0));
+    let fast = offsets(&walk(240.0));
+    for (a, b) in slow.iter().zip(fast.iter()) {
+        let difference = Complex::from_polar(&1.0, a) / Complex::from_polar(&1.0, b);
+        assert!(difference.arg().abs() < 0.35, "offsets differ: {:?} != {:?}", slow, fast);
+    }
+}
@@ -0,0 +1,244 @@
+//! A minimal world for running systems in isolation, shared by the integration tests.
+#![allow(dead_code)]
+
+use std::sync::Arc;
+
+use amethyst::{
+    assets::PrefabData,
+    core::{
+        ArcThreadPool,
+        bundle::SystemBundle,
+        math::Vector3,
+        Time,
+        transform::{Parent, Transform, TransformBundle},
+    },
+    ecs::{prelude::*, rayon::ThreadPoolBuilder},
+    error::Error,
+    renderer::debug_drawing::DebugLines,
+};
+
+#[cfg(feature = "nphysics")]
+use ceramic_animation::systems::physics::StepTime;
+use ceramic_animation::{
+    extras::{EntityRef, RedirectField},
+    systems::{animal::{Config, Quadruped, QuadrupedPrefab}, player::Player},
+    utils::determinism::FixedTimeSystem,
+};
+
+pub fn transform_at(x: f32, y: f32, z: f32) -> Transform {
+    let mut transform = Transform::default();
+    transform.set_translation_xyz(x, y, z);
+    transform
+}
+
+/// Spawn a minimal quadruped rig walking forward at `x`, returning its body and its feet.
+pub fn spawn_quadruped(world: &mut World, x: f32) -> (Entity, Vec<Entity>) {
+    let mut player = Player::new(2.0, 1.571, 8.0, [0.5, 4.0], 1.0);
+    player.set_movement(Vector3::z());
+    let body = world
+        .create_entity()
+        .with(transform_at(x, 0.0, 0.0))
+        .with(player)
+        .build();
+    let root = world
+        .create_entity()
+        .with(transform_at(0.0, 1.0, 0.0))
+        .with(Parent::new(body))
+        .build();
+
+    let mut entities = vec![body, root];
+    let mut field = |entity: Entity| {
+        entities.push(entity);
+        EntityRef(RedirectField::Target(entities.len() - 1))
+    };
+
+    let (mut feet, mut anchors, mut roots, mut origins, mut homes) = (vec![], vec![], vec![], vec![], vec![]);
+    let mut foot_entities = vec![];
+    for &(side, front) in &[(1.0, 1.0), (-1.0, 1.0), (1.0, -1.0), (-1.0, -1.0)] {
+        let (x, z) = (0.3 * side, 0.5 * front);
+        let origin = world.create_entity().with(transform_at(x, 0.0, z)).with(Parent::new(root)).build();
+        let anchor = world.create_entity().with(transform_at(x, -0.2, z)).with(Parent::new(root)).build();
+        let limb_root = world.create_entity().with(transform_at(x, 0.0, z)).with(Parent::new(root)).build();
+        let home = world.create_entity().with(transform_at(x, 0.0, z)).with(Parent::new(body)).build();
+        let foot = world.create_entity().with(transform_at(x, 0.0, z)).build();
+
+        origins.push(field(origin));
+        anchors.push(field(anchor));
+        roots.push(field(limb_root));
+        homes.push(field(home));
+        feet.push(field(foot));
+        foot_entities.push(foot);
+    }
+    let root = EntityRef(RedirectField::Target(1));
+
+    let prefab = QuadrupedPrefab {
+        feet,
+        anchors,
+        roots,
+        origins,
+        homes,
+        root,
+        home_offsets: vec![],
+        reaches: vec![],
+        phase_offsets: None,
+        config: Config {
+            max_angular_velocity: 12.57,
+            max_duty_factor: 0.8,
+            step_limit: [0.8, 1.2],
+            flight_time: 0.4,
+            flight_factor: 0.2,
+            stance_height: 0.0,
+            bounce_factor: 0.1,
+            ..Default::default()
+        },
+    };
+    prefab
+        .add_to_entity(body, &mut world.write_storage::<Quadruped>(), &entities, &[])
+        .unwrap();
+    (body, foot_entities)
+}
+
+/// Collects the systems under test, after a transform system named `"transform_system"`.
+pub struct TestWorldBuilder {
+    world: World,
+    builder: DispatcherBuilder<'static, 'static>,
+    physics: DispatcherBuilder<'static, 'static>,
+    physics_rate: u32,
+}
+
+impl TestWorldBuilder {
+    pub fn new() -> Self {
+        let mut world = World::new();
+        world.insert(Time::default());
+        world.insert(DebugLines::new());
+        world.insert::<ArcThreadPool>(Arc::new(ThreadPoolBuilder::new().num_threads(4).build().unwrap()));
+        world.register::<Transform>();
+        world.register::<Parent>();
+
+        // The bundle adds the parent hierarchy the transform system reads, and names it `"transform_system"`.
+        let mut builder = DispatcherBuilder::new();
+        TransformBundle::new()
+            .build(&mut world, &mut builder)
+            .expect("Failed to build the transform bundle");
+        TestWorldBuilder { world, builder, physics: DispatcherBuilder::new(), physics_rate: 60 }
+    }
+
+    pub fn with_resource<R: Send + Sync + 'static>(mut self, resource: R) -> Self {
+        self.world.insert(resource);
+        self
+    }
+
+    pub fn with_system<S>(mut self, system: S, name: &str, dependencies: &[&str]) -> Self
+        where S: for<'a> System<'a> + Send + 'static {
+        self.builder.add(system, name, dependencies);
+        self
+    }
+
+    /// Runs the system in the stand-in physics step of `PhysicsStep`, before the systems of the frame.
+    ///
+    /// This is not the stage that `PhysicsBundle::with_in_physics` and the game's `Schedules` build, so tests using it
+    /// only cover the stepping contract: how many steps run per frame, and how long each step is.
+    pub fn with_physics_system<S>(mut self, system: S, name: &str, dependencies: &[&str]) -> Self
+        where S: for<'a> System<'a> + Send + 'static {
+        self.physics.add(system, name, dependencies);
+        self
+    }
+
+    /// Physics steps per second; 60 unless set.
+    pub fn with_physics_rate(mut self, rate: u32) -> Self {
+        self.physics_rate = rate;
+        self
+    }
+
+    pub fn with_bundle<B: SystemBundle<'static, 'static>>(mut self, bundle: B) -> Result<Self, Error> {
+        bundle.build(&mut self.world, &mut self.builder)?;
+        Ok(self)
+    }
+
+    /// Builds the dispatcher and sets up the systems, registering their components and resources.
+    pub fn build(self) -> TestWorld {
+        let TestWorldBuilder { mut world, builder, physics, physics_rate } = self;
+        let pool = (*world.fetch::<ArcThreadPool>()).clone();
+        let mut dispatcher = builder.with_pool(pool.clone()).build();
+        dispatcher.setup(&mut world);
+        let mut physics = physics.with_pool(pool).build();
+        physics.setup(&mut world);
+        FixedTimeSystem.setup(&mut world);
+
+        #[cfg(feature = "nphysics")]
+        world.write_resource::<StepTime>().set_frames_per_seconds(physics_rate);
+        let physics = PhysicsStep { dispatcher: physics, step: 1.0 / physics_rate as f32, bank: 0.0 };
+        TestWorld { world, dispatcher, physics }
+    }
+}
+
+/// Stands in for the physics stepper: banks the frame time, and runs the systems once per whole step in the bank.
+///
+/// It is written after the contract of the `amethyst_physics` stepper, not built from it: the real physics stage
+/// and the schedule around it live in the game and are not exercised here, so a mismatch between this and the
+/// stepper of the physics server would go unnoticed by the tests.
+struct PhysicsStep {
+    dispatcher: Dispatcher<'static, 'static>,
+    step: f32,
+    bank: f32,
+}
+
+impl PhysicsStep {
+    fn run(&mut self, world: &World) {
+        // Without nphysics there is no separate step, and its systems run once per frame.
+        if cfg!(not(feature = "nphysics")) {
+            self.dispatcher.dispatch(world);
+            return;
+        }
+
+        self.bank += world.fetch::<Time>().delta_seconds();
+        while self.bank >= self.step {
+            self.dispatcher.dispatch(world);
+            self.bank -= self.step;
+        }
+    }
+}
+
+/// A world with transforms and parents, stepped by a dispatcher of only the systems under test.
+pub struct TestWorld {
+    pub world: World,
+    dispatcher: Dispatcher<'static, 'static>,
+    physics: PhysicsStep,
+}
+
+impl TestWorld {
+    pub fn builder() -> TestWorldBuilder {
+        TestWorldBuilder::new()
+    }
+
+    pub fn spawn(&mut self, transform: Transform, parent: Option<Entity>) -> Entity {
+        let builder = self.world.create_entity().with(transform);
+        match parent {
+            Some(parent) => builder.with(Parent::new(parent)).build(),
+            None => builder.build(),
+        }
+    }
+
+    /// Runs the systems once, `delta_seconds` after the last step, or a fixed step if determinism is enabled.
+    pub fn step(&mut self, delta_seconds: f32) {
+        self.world.write_resource::<Time>().set_delta_seconds(delta_seconds);
+        FixedTimeSystem.run_now(&self.world);
+        self.physics.run(&self.world);
+        self.dispatcher.dispatch(&self.world);
+        self.world.maintain();
+    }
+
+    pub fn run(&mut self, steps: usize, delta_seconds: f32) {
+        for _ in 0..steps {
+            self.step(delta_seconds);
+        }
+    }
+
+    pub fn transform(&self, entity: Entity) -> Transform {
+        self.world
+            .read_storage::<Transform>()
+            .get(entity)
+            .cloned()
+            .expect("The entity has no transform")
+    }
+}
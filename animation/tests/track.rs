@@ -0,0 +1,104 @@
+use amethyst::{assets::PrefabData, core::Transform, ecs::prelude::*};
+
+use ceramic_animation::{
+    extras::{EntityRef, RedirectField},
+    systems::animal::{Tracker, TrackerPrefab, TrackSystem},
+};
+
+use test_support::{TestWorld, transform_at};
+
+mod test_support;
+
+const DELTA_SECONDS: f32 = 1.0 / 60.0;
+
+/// A joint at the origin of a body, tracking a target off to its side. Returns the world, the joint and the body.
+fn track_on_body(speed: f32, max_angular_velocity: Option<f32>) -> (TestWorld, Entity, Entity) {
+    let mut test = TestWorld::builder()
+        .with_system(TrackSystem::default(), "track", &["transform_system"])
+        .build();
+
+    let body = test.spawn(transform_at(0.0, 0.0, 0.0), None);
+    let joint = test.spawn(transform_at(0.0, 0.0, 0.0), Some(body));
+    let target = test.spawn(transform_at(1.0, 0.0, 1.0), None);
+
+    let tracker = TrackerPrefab {
+        target: EntityRef(RedirectField::Target(0)),
+        limit: None,
+        speed,
+        dead_zone: None,
+        max_angular_velocity,
+    };
+    tracker
+        .add_to_entity(joint, &mut test.world.write_storage::<Tracker>(), &[target], &[])
+        .unwrap();
+    (test, joint, body)
+}
+
+/// A joint at the origin tracking a target off to its side. Returns the world and the joint.
+fn track(speed: f32, max_angular_velocity: Option<f32>) -> (TestWorld, Entity) {
+    let (test, joint, _) = track_on_body(speed, max_angular_velocity);
+    (test, joint)
+}
+
+/// The angle the joint turns in each of `steps` steps.
+fn turns(test: &mut TestWorld, joint: Entity, steps: usize) -> Vec<f32> {
+    let mut rotation = *test.transform(joint).rotation();
+    (0..steps)
+        .map(|_| {
+            test.step(DELTA_SECONDS);
+            let next = *test.transform(joint).rotation();
+            let turn = rotation.angle_to(&next);
+            rotation = next;
+            turn
+        })
+        .collect()
+}
+
+#[test]
+fn tracker_slerps_by_speed() {
+    let (slow, fast) = (2.0, 8.0);
+    let (mut slow_test, slow_joint) = track(slow, None);
+    let (mut fast_test, fast_joint) = track(fast, None);
+    let slow_turn = turns(&mut slow_test, slow_joint, 1)[0];
+    let fast_turn = turns(&mut fast_test, fast_joint, 1)[0];
+
+    // Both start from the same rotation toward the same target, so the first turns only differ by the slerp factor.
+    let factor = |speed: f32| 1.0 - (-speed * DELTA_SECONDS).exp();
+    let expected = factor(fast) / factor(slow);
+    assert!(slow_turn > 0.0, "the joint did not turn toward its target");
+    assert!(
+        (fast_turn / slow_turn - expected).abs() < 1.0e-2 * expected,
+        "turned {} and {}, expected a ratio of {}",
+        slow_turn, fast_turn, expected,
+    );
+}
+
+#[test]
+fn tracker_respects_max_angular_velocity() {
+    let max_angular_velocity = 90.0;
+    let (mut test, joint) = track(20.0, Some(max_angular_velocity));
+    let max_turn = max_angular_velocity.to_radians() * DELTA_SECONDS;
+
+    let turns = turns(&mut test, joint, 30);
+    assert!(turns[0] > 0.5 * max_turn, "the joint did not turn toward its target");
+    for (step, turn) in turns.into_iter().enumerate() {
+        assert!(turn <= max_turn + 1.0e-4, "turned {} at step {}, faster than {}", turn, step, max_turn);
+    }
+}
+
+#[test]
+fn tracker_follows_when_only_its_body_moves() {
+    let (mut test, joint, body) = track_on_body(20.0, None);
+    // Settle, ending off the frames where every tracker is processed anyway.
+    test.run(90, DELTA_SECONDS);
+    let settled = turns(&mut test, joint, 1)[0];
+    assert!(settled < 1.0e-3, "the joint did not settle: still turned {}", settled);
+
+    test.world
+        .write_storage::<Transform>()
+        .get_mut(body)
+        .unwrap()
+        .set_translation_xyz(2.0, 0.0, 0.0);
+    let turn = turns(&mut test, joint, 1)[0];
+    assert!(turn > 1.0e-2, "the joint kept its aim after its body moved");
+}
@@ -0,0 +1,44 @@
+//! Lists every file of the asset directory for the `embed-assets` feature, so the models, sounds,
+//! scripts and sidecars that the manifest and species packs reference are all compiled in.
+
+use std::{
+    env,
+    fs,
+    io,
+    path::{Path, PathBuf},
+};
+
+fn collect(dir: &Path, files: &mut Vec<PathBuf>) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            collect(&path, files)?;
+        } else {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+fn main() -> io::Result<()> {
+    let assets = PathBuf::from(env::var("CARGO_MANIFEST_DIR").expect("Cargo sets the manifest directory")).join("assets");
+    println!("cargo:rerun-if-changed={}", assets.display());
+
+    let mut files = Vec::new();
+    if env::var_os("CARGO_FEATURE_EMBED_ASSETS").is_some() {
+        collect(&assets, &mut files)?;
+        files.sort();
+    }
+
+    let entries: String = files
+        .iter()
+        .map(|file| {
+            let path = file.strip_prefix(&assets).expect("Collected below the asset directory");
+            let path = path.to_string_lossy().replace('\\', "/");
+            format!("    ({:?}, include_bytes!({:?}) as &[u8]),\n", path, file.display().to_string())
+        })
+        .collect();
+
+    let out = PathBuf::from(env::var("OUT_DIR").expect("Cargo sets the output directory"));
+    fs::write(out.join("embedded_assets.rs"), format!("&[\n{}]\n", entries))
+}
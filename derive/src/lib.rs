@@ -2,10 +2,17 @@ use proc_macro::TokenStream;
 use syn::{parse_macro_input, DeriveInput};
 
 mod redirect;
+mod resolve;
 
 #[proc_macro_derive(Redirect, attributes(redirect))]
 pub fn redirect_derive(input: TokenStream) -> TokenStream {
     let ast = parse_macro_input!(input as DeriveInput);
     let gen = redirect::impl_redirect(&ast);
     gen.into()
-}
\ No newline at end of file
+}
+#[proc_macro_derive(Resolve, attributes(resolve))]
+pub fn resolve_derive(input: TokenStream) -> TokenStream {
+    let ast = parse_macro_input!(input as DeriveInput);
+    let gen = resolve::impl_resolve(&ast);
+    gen.into()
+}
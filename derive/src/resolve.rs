@@ -0,0 +1,75 @@
+use proc_macro2::TokenStream;
+use proc_macro_roids::FieldExt;
+use quote::quote;
+use syn::{
+    Data, DeriveInput, FieldValue, Fields, Ident, parenthesized, parse::{Parse, ParseStream}, parse_quote, Path,
+    punctuated::Punctuated, Result, Token,
+};
+
+/// The contents of a struct level `#[resolve(...)]` attribute.
+enum Attribute {
+    /// The type the prefab resolves into.
+    Output(Path),
+    /// Fields of the output that are not taken from the prefab.
+    Init(Punctuated<FieldValue, Token![,]>),
+}
+
+impl Parse for Attribute {
+    fn parse(input: ParseStream) -> Result<Self> {
+        if input.peek(Ident) && input.peek2(syn::token::Paren) {
+            let ident: Ident = input.parse()?;
+            if ident != "init" {
+                return Err(syn::Error::new(ident.span(), "expected `init(...)` or the output type"));
+            }
+            let content;
+            parenthesized!(content in input);
+            Ok(Attribute::Init(content.parse_terminated(FieldValue::parse)?))
+        } else {
+            Ok(Attribute::Output(input.parse()?))
+        }
+    }
+}
+
+pub fn impl_resolve(ast: &DeriveInput) -> TokenStream {
+    let namespace = parse_quote!(resolve);
+    let tag = parse_quote!(skip);
+
+    let base = &ast.ident;
+    let fields = match &ast.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => panic!("Resolve derive only supports structs with named fields"),
+        },
+        _ => panic!("Resolve derive only supports structs"),
+    };
+
+    let mut output = None;
+    let mut init = vec![];
+    for attribute in ast.attrs.iter().filter(|attribute| attribute.path.is_ident("resolve")) {
+        match attribute.parse_args::<Attribute>() {
+            Ok(Attribute::Output(path)) => output = Some(path),
+            Ok(Attribute::Init(fields)) => init.extend(fields),
+            Err(error) => return error.to_compile_error(),
+        }
+    }
+    let output = output.expect("Resolve derive requires the output type, e.g. `#[resolve(Component)]`");
+
+    let resolved = fields
+        .iter()
+        .filter(|field| !field.contains_tag(&namespace, &tag))
+        .map(|field| {
+            let name = &field.ident;
+            quote! { #name: Resolve::resolve(&self.#name, entities) }
+        });
+
+    let (impl_generics, ty_generics, where_clause) = ast.generics.split_for_impl();
+    quote! {
+        impl #impl_generics Resolve<Entity> for #base #ty_generics #where_clause {
+            type Output = #output;
+
+            fn resolve(&self, entities: &[Entity]) -> Self::Output {
+                #output { #(#resolved,)* #(#init),* }
+            }
+        }
+    }
+}
@@ -25,4 +25,46 @@ impl<T, U, V, E> Redirect<T, U> for Result<V, E>
         where F: Fn(T) -> U {
         self.map(|v| v.redirect(map))
     }
-}
\ No newline at end of file
+}
+/// Conversion of a redirected prefab into what it stands for, e.g. a component holding
+/// the entities that its fields refer to by index.
+pub trait Resolve<E> {
+    type Output;
+
+    fn resolve(&self, entities: &[E]) -> Self::Output;
+}
+
+impl<E, V> Resolve<E> for Vec<V>
+    where V: Resolve<E> {
+    type Output = Vec<V::Output>;
+
+    fn resolve(&self, entities: &[E]) -> Self::Output {
+        self.iter().map(|v| v.resolve(entities)).collect()
+    }
+}
+
+impl<E, V> Resolve<E> for Option<V>
+    where V: Resolve<E> {
+    type Output = Option<V::Output>;
+
+    fn resolve(&self, entities: &[E]) -> Self::Output {
+        self.as_ref().map(|v| v.resolve(entities))
+    }
+}
+
+/// Plain values resolve to themselves.
+macro_rules! resolve_as_is {
+    ($($ty:ty),*) => {
+        $(
+            impl<E> Resolve<E> for $ty {
+                type Output = $ty;
+
+                fn resolve(&self, _entities: &[E]) -> Self::Output {
+                    self.clone()
+                }
+            }
+        )*
+    };
+}
+
+resolve_as_is!(bool, i32, u32, usize, f32, f64, String, [f32; 2], [f32; 3], [f32; 4]);
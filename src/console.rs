@@ -0,0 +1,77 @@
+//! Commands typed into the in-game console, e.g. `spawn cat 3` or `set gait trot`.
+//!
+//! Subsystems register their commands with the `Console` resource; the debug keys run command lines too.
+
+use std::{collections::BTreeMap, sync::Arc};
+
+use amethyst::{
+    ecs::prelude::*,
+    error::{Error, format_err},
+};
+
+/// Runs a command with the words typed after its name.
+pub type Handler = Arc<dyn Fn(&mut World, &[&str]) -> Result<(), Error> + Send + Sync>;
+
+struct Command {
+    usage: String,
+    handler: Handler,
+}
+
+/// The registered commands by name, and the lines typed into the console.
+#[derive(Default)]
+pub struct Console {
+    commands: BTreeMap<String, Command>,
+    history: Vec<String>,
+}
+
+impl Console {
+    /// A console that only knows `help`.
+    pub fn new() -> Self {
+        let mut console = Console::default();
+        console.register("help", "help", |world, _| {
+            let console = world.read_resource::<Console>();
+            for command in console.commands.values() {
+                println!("  {}", command.usage);
+            }
+            Ok(())
+        });
+        console
+    }
+
+    /// Register the command `name`, replacing an earlier one of the same name.
+    ///
+    /// `usage` is listed by `help`, e.g. `"spawn <species> [count]"`.
+    pub fn register<F>(&mut self, name: &str, usage: &str, handler: F)
+        where F: Fn(&mut World, &[&str]) -> Result<(), Error> + Send + Sync + 'static {
+        let command = Command { usage: usage.into(), handler: Arc::new(handler) };
+        self.commands.insert(name.into(), command);
+    }
+
+    /// The lines typed so far, oldest first.
+    pub fn history(&self) -> &[String] {
+        &self.history
+    }
+
+    pub fn remember(&mut self, line: &str) {
+        self.history.push(line.to_string());
+    }
+
+    /// Run a command line in `world`. Lines that only hold whitespace do nothing.
+    pub fn execute(world: &mut World, line: &str) -> Result<(), Error> {
+        let mut words = line.split_whitespace();
+        let name = match words.next() {
+            Some(name) => name,
+            None => return Ok(()),
+        };
+        let args = words.collect::<Vec<_>>();
+
+        // The handler may need the console itself, so release the resource before running it.
+        let handler = world
+            .read_resource::<Console>()
+            .commands
+            .get(name)
+            .map(|command| command.handler.clone())
+            .ok_or_else(|| format_err!("Unknown command {}, type help for a list", name))?;
+        handler(world, &args)
+    }
+}
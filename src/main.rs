@@ -1,92 +1,115 @@
-#![feature(tau_constant, option_zip)]
-
 use amethyst::{
-    animation::{AnimationBundle, VertexSkinningBundle},
-    controls::ArcBallControlBundle,
-    core::{Transform, TransformBundle},
-    input::{InputBundle, StringBindings},
+    core::frame_limiter::FrameRateLimitStrategy,
     prelude::*,
     renderer::{
         plugins::{RenderDebugLines, RenderPbr3D, RenderSkybox, RenderToWindow},
         RenderingBundle,
         types::DefaultBackend,
     },
-    utils::{application_root_dir, auto_fov::AutoFovSystem},
 };
-use amethyst_nphysics::NPhysicsBackend;
-use amethyst_physics::PhysicsBundle;
+
+use ceramic_animation::{
+    systems::helper::Helpers,
+    utils::{determinism::{Determinism, FixedTimeSystem}, random::Random},
+};
+#[cfg(not(target_arch = "wasm32"))]
+use ceramic_animation::systems::animal::GaitPresets;
 
 use crate::{
-    scene::SceneLoaderSystemDesc,
     state::load::LoadState,
-    systems::{
-        animal::{BounceSystem, LocomotionSystem, OscillatorSystem, TailSystem, TrackSystem},
-        kinematics::KinematicsBundle,
-        particle::ParticleSystem,
-        player::PlayerSystem,
-    },
+    utils::{dispatch::DispatchGraphBundle, extras_diff::{self, ExtrasDiff}},
 };
+#[cfg(not(target_arch = "wasm32"))]
+use crate::systems::{network::ReplicationBundle, watcher::ConfigWatcher};
 
+mod console;
+mod nudge;
+mod platform;
 mod scene;
+mod schedule;
+mod species;
 mod state;
 mod systems;
 mod utils;
 
-fn main() -> amethyst::Result<()> {
-    amethyst::start_logger(Default::default());
+/// Frame rate of deterministic runs, started with `--deterministic`.
+const FIXED_FRAME_RATE: u32 = 60;
 
-    let app_root = application_root_dir()?;
+/// The seed given with `--seed`; deterministic runs default to 0 and others to the time.
+fn seed_from_args(deterministic: bool) -> u64 {
+    let mut args = std::env::args().skip_while(|arg| arg != "--seed");
+    args.next();
+    args.next()
+        .and_then(|seed| seed.parse().ok())
+        .unwrap_or_else(|| if deterministic { 0 } else {
+            std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map(|duration| duration.as_nanos() as u64)
+                .unwrap_or_default()
+        })
+}
 
-    let config_dir = app_root.join("config");
-    let display_config_path = config_dir.join("display.ron");
-    let bindings_path = config_dir.join("bindings.ron");
-    let assets_dir = app_root.join("assets");
+fn main() -> amethyst::Result<()> {
+    amethyst::start_logger(Default::default());
 
-    let animation_bundle = AnimationBundle::<usize, Transform>::new(
-        "animation_control",
-        "sampler_interpolation",
-    ).with_dep(&["gltf_loader"]);
+    if let Some((old, new)) = extras_diff::paths_from_args() {
+        ExtrasDiff::between(old.as_ref(), new.as_ref())?.print();
+        return Ok(());
+    }
 
-    let input_bundle = InputBundle::<StringBindings>::new()
-        .with_bindings_from_file(bindings_path)?;
+    let assets_dir = platform::assets_dir()?;
 
+    // Added first, so every system reading the frame time, including the physics step, runs after it.
     let game_data = GameDataBuilder::default()
+        .with(FixedTimeSystem, "fixed_time", &[])
         .with_bundle(
             RenderingBundle::<DefaultBackend>::new()
-                .with_plugin(RenderToWindow::from_config_path(display_config_path)?)
+                .with_plugin(RenderToWindow::from_config(platform::display_config()?))
                 .with_plugin(RenderPbr3D::default().with_skinning())
                 .with_plugin(RenderDebugLines::default())
                 .with_plugin(RenderSkybox::default()),
-        )?
-        .with_bundle(
-            PhysicsBundle::<f32, NPhysicsBackend>::new()
-                .with_frames_per_seconds(60)
-                .with_in_physics(OscillatorSystem::default(), "oscillator".into(), vec![])
-                .with_post_physics(ParticleSystem::default(), "particle".into(), vec![])
-        )?
-        .with_system_desc(SceneLoaderSystemDesc::default(), "gltf_loader", &[])
-        .with(PlayerSystem::default(), "player", &[])
-        .with_bundle(animation_bundle)?
-        .with_bundle(ArcBallControlBundle::<StringBindings>::new())?
-        .with_bundle(TransformBundle::new().with_dep(&[
-            "animation_control",
-            "sampler_interpolation",
-            "free_rotation",
-        ]))?
-        .with_bundle(VertexSkinningBundle::new().with_dep(&[
-            "transform_system",
-            "animation_control",
-            "sampler_interpolation",
-        ]))?
-        .with_bundle(KinematicsBundle::new(2, 0.01))?
-        .with(TailSystem::default(), "tail", &[])
-        .with(TrackSystem::default(), "track", &["transform_system"])
-        .with(BounceSystem::default(), "bounce", &["transform_system"])
-        .with(LocomotionSystem::default(), "locomotion", &["transform_system"])
-        .with_bundle(input_bundle)?
-        .with(AutoFovSystem::new(), "auto_fov", &["gltf_loader"]);
+        )?;
+    let mut game_data = platform::schedules()?
+        .profile_from_args()?
+        .build(game_data)?;
+
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        if let Some(bundle) = ReplicationBundle::from_args() {
+            game_data = game_data.with_bundle(bundle)?;
+        }
+    }
+    if let Some(bundle) = DispatchGraphBundle::from_args() {
+        game_data = game_data.with_bundle(bundle)?;
+    }
+
+    let determinism = if std::env::args().any(|arg| arg == "--deterministic") {
+        Determinism::new(1.0 / FIXED_FRAME_RATE as f32)
+    } else {
+        Determinism::default()
+    };
+    let seed = seed_from_args(determinism.enabled);
+    println!("Random seed: {}", seed);
 
-    let mut game = Application::new(assets_dir, LoadState::default(), game_data)?;
+    let mut builder = Application::build(assets_dir, LoadState::default())?
+        .with_resource(Random::new(seed))
+        .with_resource(determinism)
+        .with_resource(Helpers::new(std::env::args().any(|arg| arg == "--fold-helpers")))
+        .with_resource(platform::gait_presets()?);
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        let mut watcher = ConfigWatcher::default();
+        watcher.watch::<GaitPresets>("gaits.ron");
+        builder = builder.with_resource(watcher);
+    }
+    if let Some(source) = platform::asset_source() {
+        builder = builder.with_default_source(source);
+    }
+    if determinism.enabled {
+        // Every frame advances by one fixed step, so keep frames at that rate.
+        builder = builder.with_frame_limit(FrameRateLimitStrategy::Yield, FIXED_FRAME_RATE);
+    }
+    let mut game = builder.build(game_data)?;
     game.run();
 
     Ok(())
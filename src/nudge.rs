@@ -0,0 +1,201 @@
+//! Moving rig nodes like poles and homes with the arrow keys, and saving where they ended up.
+//!
+//! `select <node>` picks a node by name; the arrow keys then move it along the x and z axes of its parent,
+//! `PageUp` and `PageDown` along y, by the step or by the fine step while `Shift` is held.
+//! There is no text rendering, so the node and its translation are shown in the window title.
+//! `nudge save` writes the translations of the nudged nodes into the extras sidecars of their models,
+//! which replace those of the file the next time the model is loaded.
+
+use std::{collections::HashSet, fs};
+
+use amethyst::{
+    core::{math::Vector3, Named, Transform},
+    ecs::prelude::*,
+    error::{Error, format_err},
+    input::{InputHandler, StringBindings, VirtualKeyCode},
+    window::Window,
+};
+
+use amethyst_gltf::{GltfSceneRoot, Sidecar};
+
+use crate::{console::Console, platform, scene::SceneManager};
+
+/// The node moved by the arrow keys, and how far each press moves it.
+#[derive(Debug)]
+pub struct Nudge {
+    pub selected: Option<Entity>,
+    pub step: f32,
+    /// The step while `Shift` is held.
+    pub fine_step: f32,
+    /// Nodes moved since they were last saved.
+    nudged: HashSet<Entity>,
+}
+
+impl Default for Nudge {
+    fn default() -> Self {
+        Nudge {
+            selected: None,
+            step: 0.01,
+            fine_step: 0.001,
+            nudged: HashSet::new(),
+        }
+    }
+}
+
+/// The direction the key moves the selected node in, along the axes of its parent.
+fn direction(key: VirtualKeyCode) -> Option<Vector3<f32>> {
+    match key {
+        VirtualKeyCode::Left => Some(-Vector3::x()),
+        VirtualKeyCode::Right => Some(Vector3::x()),
+        VirtualKeyCode::Up => Some(-Vector3::z()),
+        VirtualKeyCode::Down => Some(Vector3::z()),
+        VirtualKeyCode::PageUp => Some(Vector3::y()),
+        VirtualKeyCode::PageDown => Some(-Vector3::y()),
+        _ => None,
+    }
+}
+
+/// Shows the selected node and its translation in the window title, or the usual title without one.
+fn show(world: &World) {
+    let window = match world.try_fetch::<Window>() {
+        Some(window) => window,
+        None => return,
+    };
+    let nudge = world.read_resource::<Nudge>();
+    let (names, transforms) = world.system_data::<(ReadStorage<'_, Named>, ReadStorage<'_, Transform>)>();
+    let readout = nudge.selected.and_then(|entity| {
+        let name = names.get(entity).map_or_else(|| format!("{:?}", entity), |named| named.name.to_string());
+        let translation = transforms.get(entity)?.translation();
+        Some(format!(
+            "{} ({:.3}, {:.3}, {:.3}), step {} / {}",
+            name, translation.x, translation.y, translation.z, nudge.step, nudge.fine_step,
+        ))
+    });
+    match readout {
+        Some(readout) => window.set_title(&readout),
+        None => window.set_title(&platform::display_config().map(|config| config.title).unwrap_or_default()),
+    }
+}
+
+/// Moves the selected node if `key` is one of the nudging keys, returning whether it was.
+pub fn handle_key(world: &mut World, key: VirtualKeyCode) -> bool {
+    let direction = match direction(key) {
+        Some(direction) => direction,
+        None => return false,
+    };
+    {
+        let (mut nudge, mut transforms, input) = world.system_data::<(
+            Write<'_, Nudge>,
+            WriteStorage<'_, Transform>,
+            Read<'_, InputHandler<StringBindings>>,
+        )>();
+        let entity = match nudge.selected {
+            Some(entity) => entity,
+            None => return false,
+        };
+        let fine = input.key_is_down(VirtualKeyCode::LShift) || input.key_is_down(VirtualKeyCode::RShift);
+        let step = if fine { nudge.fine_step } else { nudge.step };
+        match transforms.get_mut(entity) {
+            Some(transform) => {
+                transform.append_translation(direction * step);
+            }
+            None => {
+                nudge.selected = None;
+                return true;
+            }
+        }
+        nudge.nudged.insert(entity);
+    }
+    show(world);
+    true
+}
+
+fn select(world: &mut World, name: Option<&str>) -> Result<(), Error> {
+    let selected = match name {
+        Some(name) => {
+            let (entities, names) = world.system_data::<(Entities<'_>, ReadStorage<'_, Named>)>();
+            let found = (&*entities, &names)
+                .join()
+                .find(|(_, named)| named.name == name)
+                .map(|(entity, _)| entity)
+                .ok_or_else(|| format_err!("There is no node called {}", name))?;
+            Some(found)
+        }
+        None => None,
+    };
+    world.write_resource::<Nudge>().selected = selected;
+    show(world);
+    Ok(())
+}
+
+/// Writes the translations of the nudged nodes into the sidecars of the models they were loaded from.
+fn save(world: &mut World) -> Result<(), Error> {
+    let nudged = world.read_resource::<Nudge>().nudged.clone();
+    let mut sidecars = Vec::<(String, Sidecar)>::new();
+    {
+        let (names, transforms, scene_roots, manager) = world.system_data::<(
+            ReadStorage<'_, Named>,
+            ReadStorage<'_, Transform>,
+            ReadStorage<'_, GltfSceneRoot>,
+            Read<'_, SceneManager>,
+        )>();
+        for &entity in &nudged {
+            let found = (names.get(entity), transforms.get(entity), scene_roots.get(entity));
+            let (name, transform, GltfSceneRoot(root)) = match found {
+                (Some(name), Some(transform), Some(root)) => (name, transform, root),
+                _ => {
+                    eprintln!("{:?} is not a named node of a scene anymore, not saved", entity);
+                    continue;
+                }
+            };
+            let path = manager
+                .path(*root)
+                .map(Sidecar::path)
+                .ok_or_else(|| format_err!("{:?} is not part of a loaded scene", entity))?;
+            let index = match sidecars.iter().position(|(other, _)| *other == path) {
+                Some(index) => index,
+                None => {
+                    let file = platform::assets_dir()?.join(&path);
+                    let sidecar = match fs::read(&file) {
+                        Ok(bytes) => Sidecar::parse(&bytes)?,
+                        Err(_) => Sidecar::default(),
+                    };
+                    sidecars.push((path, sidecar));
+                    sidecars.len() - 1
+                }
+            };
+            let translation = transform.translation();
+            sidecars[index].1.set_translation(&name.name, [translation.x, translation.y, translation.z]);
+        }
+    }
+
+    for (path, sidecar) in sidecars {
+        fs::write(platform::assets_dir()?.join(&path), sidecar.to_ron()?)?;
+        println!("Saved nudged nodes to {}", path);
+    }
+    world.write_resource::<Nudge>().nudged.clear();
+    Ok(())
+}
+
+/// Registers `select [node]` and `nudge <step <step> [fine_step]|save>`.
+pub fn register_commands(console: &mut Console) {
+    console.register("select", "select [node]", |world, args| select(world, args.first().copied()));
+    console.register("nudge", "nudge <step <step> [fine_step]|save>", |world, args| match args {
+        ["step", step] => {
+            world.write_resource::<Nudge>().step = step.parse()?;
+            show(world);
+            Ok(())
+        }
+        ["step", step, fine_step] => {
+            {
+                let mut nudge = world.write_resource::<Nudge>();
+                nudge.step = step.parse()?;
+                nudge.fine_step = fine_step.parse()?;
+            }
+            show(world);
+            Ok(())
+        }
+        ["save"] => save(world),
+        _ => Err(format_err!("Usage: nudge <step <step> [fine_step]|save>")),
+    });
+}
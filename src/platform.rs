@@ -0,0 +1,184 @@
+//! Platform specific access to assets and configuration.
+//!
+//! Native builds read everything from the application directory. Web builds have no file system,
+//! so configuration is compiled in and assets come from a `Source` selected at build time:
+//! files embedded into the binary with the `embed-assets` feature, or a `FetchSource` requesting them
+//! from the server of the page otherwise. With `embed-assets`, native builds compile configuration in too.
+
+use std::{
+    collections::HashMap,
+    path::PathBuf,
+    sync::{Arc, RwLock},
+};
+
+use amethyst::{
+    assets::Source,
+    config::Config,
+    error::{Error, format_err},
+    input::{Bindings, StringBindings},
+    window::DisplayConfig,
+};
+
+#[cfg(target_arch = "wasm32")]
+use wasm_bindgen::JsValue;
+#[cfg(target_arch = "wasm32")]
+use web_sys::XmlHttpRequest;
+
+use ceramic_animation::systems::animal::GaitPresets;
+
+use crate::{scene::Manifest, schedule::Schedules, species::SpeciesPacks};
+
+/// Assets held in memory, keyed by their path relative to the asset directory.
+#[derive(Debug, Default, Clone)]
+pub struct PreloadedSource {
+    files: Arc<RwLock<HashMap<String, Arc<Vec<u8>>>>>,
+}
+
+impl PreloadedSource {
+    /// Make `bytes` available under `path`, e.g. after the host fetched it.
+    pub fn insert(&self, path: impl Into<String>, bytes: Vec<u8>) {
+        self.files
+            .write()
+            .expect("Preloaded asset lock poisoned")
+            .insert(path.into(), Arc::new(bytes));
+    }
+
+    /// Assets compiled into the binary: every file of the asset directory, listed by the build script.
+    #[cfg(feature = "embed-assets")]
+    pub fn embedded() -> Self {
+        const FILES: &[(&str, &[u8])] = include!(concat!(env!("OUT_DIR"), "/embedded_assets.rs"));
+
+        let source = Self::default();
+        for (path, bytes) in FILES {
+            source.insert(*path, bytes.to_vec());
+        }
+        source
+    }
+}
+
+impl Source for PreloadedSource {
+    fn modified(&self, _path: &str) -> Result<u64, Error> {
+        Ok(0)
+    }
+
+    fn load(&self, path: &str) -> Result<Vec<u8>, Error> {
+        self.files
+            .read()
+            .expect("Preloaded asset lock poisoned")
+            .get(path)
+            .map(|bytes| bytes.as_ref().clone())
+            .ok_or_else(|| format_err!("Asset {} is not preloaded", path))
+    }
+}
+
+/// Assets requested from the server of the page, under `base`.
+///
+/// `Source::load` is synchronous, so every file is fetched with a blocking request. Those cannot ask
+/// for binary responses, so the body is read as text in the `x-user-defined` charset, which maps each byte
+/// to one character whose low byte it is.
+#[cfg(target_arch = "wasm32")]
+#[derive(Debug, Clone)]
+pub struct FetchSource {
+    base: String,
+}
+
+#[cfg(target_arch = "wasm32")]
+impl Default for FetchSource {
+    fn default() -> Self {
+        FetchSource { base: "assets".into() }
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+impl Source for FetchSource {
+    fn modified(&self, _path: &str) -> Result<u64, Error> {
+        Ok(0)
+    }
+
+    fn load(&self, path: &str) -> Result<Vec<u8>, Error> {
+        let url = format!("{}/{}", self.base, path);
+        let fail = |error: JsValue| format_err!("Failed to fetch {}: {:?}", url, error);
+
+        let request = XmlHttpRequest::new().map_err(fail)?;
+        request.open_with_async("GET", &url, false).map_err(fail)?;
+        request.override_mime_type("text/plain; charset=x-user-defined").map_err(fail)?;
+        request.send().map_err(fail)?;
+        match request.status().map_err(fail)? {
+            200 => {}
+            status => return Err(format_err!("Failed to fetch {}: status {}", url, status)),
+        }
+
+        let text = request.response_text().map_err(fail)?.unwrap_or_default();
+        Ok(text.chars().map(|c| c as u32 as u8).collect())
+    }
+}
+
+/// The source assets are loaded from by default, if not the asset directory.
+pub fn asset_source() -> Option<impl Source> {
+    #[cfg(feature = "embed-assets")]
+        return Some(PreloadedSource::embedded());
+    #[cfg(all(not(feature = "embed-assets"), target_arch = "wasm32"))]
+        return Some(FetchSource::default());
+    #[cfg(all(not(feature = "embed-assets"), not(target_arch = "wasm32")))]
+        return None::<PreloadedSource>;
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn root_dir() -> Result<PathBuf, Error> {
+    amethyst::utils::application_root_dir().map_err(Into::into)
+}
+
+#[cfg(target_arch = "wasm32")]
+fn root_dir() -> Result<PathBuf, Error> {
+    Ok(PathBuf::new())
+}
+
+pub fn assets_dir() -> Result<PathBuf, Error> {
+    root_dir().map(|root| root.join("assets"))
+}
+
+/// The file the config `name` is read from, or `None` if configs are compiled in.
+#[cfg(not(any(target_arch = "wasm32", feature = "embed-assets")))]
+pub fn config_path(name: &str) -> Option<PathBuf> {
+    root_dir().ok().map(|root| root.join("config").join(name))
+}
+
+#[cfg(any(target_arch = "wasm32", feature = "embed-assets"))]
+pub fn config_path(_name: &str) -> Option<PathBuf> {
+    None
+}
+
+#[cfg(not(any(target_arch = "wasm32", feature = "embed-assets")))]
+fn load_config<T: Config>(name: &str, _bytes: &[u8]) -> Result<T, Error> {
+    let path = root_dir()?.join("config").join(name);
+    T::load(path).map_err(Into::into)
+}
+
+#[cfg(any(target_arch = "wasm32", feature = "embed-assets"))]
+fn load_config<T: Config>(_name: &str, bytes: &[u8]) -> Result<T, Error> {
+    T::load_bytes(bytes).map_err(Into::into)
+}
+
+pub fn display_config() -> Result<DisplayConfig, Error> {
+    load_config("display.ron", include_bytes!("../config/display.ron"))
+}
+
+pub fn bindings() -> Result<Bindings<StringBindings>, Error> {
+    load_config("bindings.ron", include_bytes!("../config/bindings.ron"))
+}
+
+pub fn manifest() -> Result<Manifest, Error> {
+    load_config("manifest.ron", include_bytes!("../config/manifest.ron"))
+}
+
+pub fn gait_presets() -> Result<GaitPresets, Error> {
+    load_config("gaits.ron", include_bytes!("../config/gaits.ron"))
+}
+
+pub fn species_packs() -> Result<SpeciesPacks, Error> {
+    load_config("species.ron", include_bytes!("../config/species.ron"))
+}
+
+pub fn schedules() -> Result<Schedules, Error> {
+    load_config("schedule.ron", include_bytes!("../config/schedule.ron"))
+}
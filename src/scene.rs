@@ -1,71 +1,508 @@
+use std::{collections::HashMap, fmt, fs, path::Path};
+
 use amethyst::{
-    assets::{PrefabData, ProgressCounter},
+    assets::{Handle, PrefabData, PrefabLoader, Progress, ProgressCounter, WeakHandle},
     controls::ControlTagPrefab,
-    derive::PrefabData,
+    core::{
+        math::{UnitQuaternion, Vector3},
+        Named,
+        transform::{Parent, ParentHierarchy, Transform},
+    },
+    derive::{PrefabData, SystemDesc},
     ecs::prelude::*,
-    error::Error,
+    error::{Error, format_err},
+    renderer::{Material, types::Mesh},
+    shrev::EventChannel,
     utils::auto_fov::AutoFov,
 };
 use serde::{Deserialize, Serialize};
 
-use amethyst_gltf::{GltfPrefab, GltfSceneAsset, GltfSceneFormat, GltfSceneLoaderSystemDesc};
+use amethyst_gltf::{
+    GltfPrefab,
+    GltfSceneAsset,
+    GltfSceneFormat,
+    GltfSceneLoaderSystemDesc,
+    GltfSceneOptions,
+    GltfExporter,
+    GltfSceneRoot,
+    Symmetry,
+};
+use ceramic_animation::{
+    AnimationExtras,
+    systems::cleanup::DespawnAnimal,
+    utils::euler::{AngleUnit, RotationOrder, to_euler},
+};
 use ceramic_derive::Redirect;
 use redirect::Redirect;
 
-use crate::systems::{
-    animal::{QuadrupedPrefab, TailPrefab, TrackerPrefab},
-    kinematics::{ChainPrefab, ConstrainPrefab},
-    particle::{ParticlePrefab, SpringPrefab},
-    player::Player,
+use crate::{
+    console::Console,
+    systems::{
+        audio::{AudioEmitterPrefab, FootstepPrefab, Surface, VocalizationPrefab},
+        clip::ClipRecorder,
+        crowd::CrowdConfig,
+        network::Replicated,
+        script::ScriptPrefab,
+        skin_debug::SkinDebugTarget,
+        visibility::Visibility,
+    },
 };
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-#[serde(untagged)]
-pub enum RedirectField {
-    Origin(String),
-    Target(usize),
-}
-
-impl Redirect<String, usize> for RedirectField {
-    fn redirect<F>(self, map: &F) -> Self
-        where F: Fn(String) -> usize {
-        match self {
-            RedirectField::Origin(origin) => RedirectField::Target(map(origin)),
-            RedirectField::Target(_) => self,
-        }
-    }
-}
-
-impl RedirectField {
-    pub fn into_entity(self, entities: &[Entity]) -> Entity {
-        let index = match self {
-            RedirectField::Origin(_) => panic!("Redirect field unsolved"),
-            RedirectField::Target(target) => target,
-        };
-        entities[index]
-    }
-}
-
 #[derive(Debug, Default, Clone, Serialize, Deserialize, PrefabData, Redirect)]
 #[serde(default)]
 pub struct Extras {
+    #[serde(flatten)]
+    animation: AnimationExtras,
+    #[serde(flatten)]
     #[redirect(skip)]
-    player: Option<Player>,
-    quadruped: Option<QuadrupedPrefab>,
-    tracker: Option<TrackerPrefab>,
-    tail: Option<TailPrefab>,
-    chain: Option<ChainPrefab>,
-    constrain: Option<ConstrainPrefab>,
-    #[redirect(skip)]
-    particle: Option<ParticlePrefab>,
-    spring: Option<SpringPrefab>,
+    visibility: Visibility,
     #[redirect(skip)]
     auto_fov: Option<AutoFov>,
     #[redirect(skip)]
     control_tag: Option<ControlTagPrefab>,
+    #[redirect(skip)]
+    audio_emitter: Option<AudioEmitterPrefab>,
+    #[redirect(skip)]
+    vocalization: Option<VocalizationPrefab>,
+    #[redirect(skip)]
+    footsteps: Option<FootstepPrefab>,
+    #[redirect(skip)]
+    surface: Option<Surface>,
+    #[redirect(skip)]
+    script: Option<ScriptPrefab>,
+    #[redirect(skip)]
+    skin_debug: Option<SkinDebugTarget>,
+    #[redirect(skip)]
+    replicated: Option<Replicated>,
 }
 
 pub type ScenePrefab = GltfPrefab<Extras>;
 pub type SceneAsset = GltfSceneAsset<Extras>;
 pub type SceneLoaderSystemDesc = GltfSceneLoaderSystemDesc<Extras>;
-pub type SceneFormat = GltfSceneFormat;
\ No newline at end of file
+pub type SceneFormat = GltfSceneFormat;
+
+/// Partial `Extras` patches keyed by node name, e.g. `{"Cat": {"quadruped": {"stance_height": 0.3}}}`.
+///
+/// They are merged into the node extras while loading the instance, before redirecting,
+/// so patches can reference other nodes by name just like embedded extras.
+///
+/// Merging at load time gives the same components as patching them after spawning would: every set of overrides
+/// is loaded into a prefab of its own, and `SceneManager::load_shared` only reuses a prefab for the same path and
+/// the same overrides, so no instance ever sees the extras of another. The cost is that instances with different
+/// overrides don't share their meshes and materials.
+pub type ExtrasOverrides = HashMap<String, serde_json::Value>;
+
+/// The format for loading one instance of a scene with its own overrides.
+///
+/// Right nodes without extras take the mirrored extras of their left counterparts, with sides named `.L` and `.R`.
+pub fn instance_format(overrides: ExtrasOverrides) -> SceneFormat {
+    GltfSceneFormat(GltfSceneOptions {
+        extras_overrides: overrides,
+        symmetry: Some(Symmetry::default()),
+        ..Default::default()
+    })
+}
+
+/// One instance of a scene to spawn when the game starts.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct SceneInstance {
+    pub path: String,
+    #[serde(default)]
+    pub overrides: ExtrasOverrides,
+    /// Where to put the instance; it keeps the layout of the file if absent.
+    #[serde(default)]
+    pub placement: Option<Placement>,
+}
+
+/// Moves and turns the top-level nodes of a scene instance.
+#[derive(Debug, Default, Copy, Clone, Serialize, Deserialize)]
+pub struct Placement {
+    pub translation: [f32; 3],
+    /// Turn about the up axis in radians.
+    #[serde(default)]
+    pub yaw: f32,
+}
+
+impl Placement {
+    fn apply(&self, transform: &mut Transform) {
+        let ref rotation = UnitQuaternion::from_axis_angle(&Vector3::y_axis(), self.yaw);
+        let translation = rotation * transform.translation() + Vector3::from(self.translation);
+        transform.set_translation(translation);
+        transform.set_rotation(rotation * transform.rotation());
+    }
+}
+
+/// The scenes spawned when the game starts, e.g. the player's cat and a companion.
+///
+/// Overrides are written as maps since they are parsed without knowing the `Extras` type.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Manifest {
+    pub instances: Vec<SceneInstance>,
+    /// Animals spawned by species name from the species packs.
+    #[serde(default)]
+    pub species: Vec<String>,
+    /// Wandering animals spawned over the first frames.
+    #[serde(default)]
+    pub crowd: Option<CrowdConfig>,
+}
+
+impl Default for Manifest {
+    fn default() -> Self {
+        Manifest {
+            instances: vec![SceneInstance { path: "model/cat.glb".into(), ..Default::default() }],
+            species: vec![],
+            crowd: None,
+        }
+    }
+}
+
+/// A scene instance spawned through the `SceneManager`.
+#[derive(Debug, Clone)]
+struct LoadedScene {
+    instance: SceneInstance,
+    prefab: Handle<SceneAsset>,
+    root: Entity,
+}
+
+/// Assets of an unloaded scene, watched until their storages have freed them.
+struct ReleasedScene {
+    path: String,
+    prefab: WeakHandle<SceneAsset>,
+    meshes: Vec<WeakHandle<Mesh>>,
+    materials: Vec<WeakHandle<Material>>,
+    frames: u32,
+}
+
+impl fmt::Debug for ReleasedScene {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Weak handles have no `Debug`; whether they are freed yet is what matters here.
+        f.debug_struct("ReleasedScene")
+            .field("path", &self.path)
+            .field("prefab_dead", &self.prefab.is_dead())
+            .field("meshes", &self.meshes.len())
+            .field("materials", &self.materials.len())
+            .field("frames", &self.frames)
+            .finish()
+    }
+}
+
+impl ReleasedScene {
+    fn is_evicted(&self) -> bool {
+        self.prefab.is_dead()
+            && self.meshes.iter().all(WeakHandle::is_dead)
+            && self.materials.iter().all(WeakHandle::is_dead)
+    }
+}
+
+/// Frames to wait for the assets of an unloaded scene to be freed before reporting them as leaked.
+const EVICTION_FRAMES: u32 = 120;
+
+/// Loads and unloads scene instances at runtime.
+///
+/// Unloading deletes every entity of the instance, which drops the last handles to its prefab,
+/// meshes and materials; the `SceneEvictionSystem` then checks that the asset storages freed them.
+#[derive(Debug, Default)]
+pub struct SceneManager {
+    scenes: Vec<LoadedScene>,
+    released: Vec<ReleasedScene>,
+    /// Placements of instances whose prefab was not instantiated yet, by root.
+    placements: HashMap<Entity, Placement>,
+}
+
+impl SceneManager {
+    /// The root entities of the loaded instances, in the order they were loaded.
+    pub fn roots(&self) -> Vec<Entity> {
+        self.scenes.iter().map(|scene| scene.root).collect()
+    }
+
+    /// The file the instance with the given root was loaded from.
+    pub fn path(&self, root: Entity) -> Option<&str> {
+        self.scenes
+            .iter()
+            .find(|scene| scene.root == root)
+            .map(|scene| scene.instance.path.as_str())
+    }
+
+    /// Spawn an instance of a scene, returning its root entity.
+    pub fn load<P: Progress>(world: &mut World, instance: SceneInstance, progress: P) -> Entity {
+        let prefab = world.exec(|loader: PrefabLoader<'_, ScenePrefab>| {
+            loader.load(instance.path.clone(), instance_format(instance.overrides.clone()), progress)
+        });
+        Self::instantiate(world, instance, prefab)
+    }
+
+    /// Spawn an instance of a scene, reusing the prefab of a loaded instance with the same path and overrides.
+    ///
+    /// Instances of one prefab share its meshes and materials, so they are drawn in batches.
+    pub fn load_shared<P: Progress>(world: &mut World, instance: SceneInstance, progress: P) -> Entity {
+        let prefab = world
+            .read_resource::<SceneManager>()
+            .scenes
+            .iter()
+            .find(|scene| scene.instance.path == instance.path && scene.instance.overrides == instance.overrides)
+            .map(|scene| scene.prefab.clone());
+        match prefab {
+            Some(prefab) => Self::instantiate(world, instance, prefab),
+            None => Self::load(world, instance, progress),
+        }
+    }
+
+    fn instantiate(world: &mut World, instance: SceneInstance, prefab: Handle<SceneAsset>) -> Entity {
+        let root = world.create_entity().with(prefab.clone()).build();
+        let mut manager = world.write_resource::<SceneManager>();
+        if let Some(placement) = instance.placement {
+            manager.placements.insert(root, placement);
+        }
+        manager.scenes.push(LoadedScene { instance, prefab, root });
+        root
+    }
+
+    /// Despawn the instance with the given root and release its assets.
+    pub fn unload(world: &mut World, root: Entity) -> Result<(), Error> {
+        Self::remove(world, root, false)
+    }
+
+    /// Despawn the instance of an animal with the given root like `unload`, but through a `DespawnAnimal`,
+    /// so its rig is torn down in order and nothing steps it in the meantime.
+    pub fn despawn(world: &mut World, root: Entity) -> Result<(), Error> {
+        Self::remove(world, root, true)
+    }
+
+    fn remove(world: &mut World, root: Entity, despawn: bool) -> Result<(), Error> {
+        let scene = {
+            let mut manager = world.write_resource::<SceneManager>();
+            let index = manager.scenes
+                .iter()
+                .position(|scene| scene.root == root)
+                .ok_or_else(|| format_err!("{:?} is not the root of a loaded scene", root))?;
+            manager.placements.remove(&root);
+            manager.scenes.remove(index)
+        };
+        // Other instances still hold the assets of a shared prefab.
+        let shared = world
+            .read_resource::<SceneManager>()
+            .scenes
+            .iter()
+            .any(|other| other.prefab.id() == scene.prefab.id());
+
+        let released = {
+            let (entities, scene_roots, prefabs, meshes, materials) = world.system_data::<(
+                Entities<'_>,
+                ReadStorage<'_, GltfSceneRoot>,
+                ReadStorage<'_, Handle<SceneAsset>>,
+                ReadStorage<'_, Handle<Mesh>>,
+                ReadStorage<'_, Handle<Material>>,
+            )>();
+            let members = (&*entities, &scene_roots)
+                .join()
+                .filter(|(_, GltfSceneRoot(member_root))| *member_root == root)
+                .map(|(entity, _)| entity)
+                .chain(std::iter::once(root))
+                .collect::<Vec<_>>();
+
+            let prefab = prefabs
+                .get(root)
+                .map(Handle::downgrade)
+                .ok_or_else(|| format_err!("Scene {} has no prefab handle", scene.instance.path))?;
+            let released = ReleasedScene {
+                path: scene.instance.path,
+                prefab,
+                meshes: members.iter().filter_map(|&entity| meshes.get(entity)).map(Handle::downgrade).collect(),
+                materials: members.iter().filter_map(|&entity| materials.get(entity)).map(Handle::downgrade).collect(),
+                frames: 0,
+            };
+            if despawn {
+                world.write_resource::<EventChannel<DespawnAnimal>>().single_write(DespawnAnimal { nodes: members });
+            } else {
+                for entity in members {
+                    entities.delete(entity)?;
+                }
+            }
+            released
+        };
+        if !shared {
+            world.write_resource::<SceneManager>().released.push(released);
+        }
+        Ok(())
+    }
+
+    /// Unload every instance and load them again from their files.
+    pub fn reload(world: &mut World) -> Result<(), Error> {
+        let scenes = world.read_resource::<SceneManager>().scenes.clone();
+        for scene in &scenes {
+            Self::unload(world, scene.root)?;
+        }
+        for scene in scenes {
+            Self::load_shared(world, scene.instance, ());
+        }
+        Ok(())
+    }
+
+    /// Print the node tree of every loaded instance, by node name.
+    pub fn dump_hierarchy(world: &World) {
+        let (entities, manager, scene_roots, parents, names, hierarchy) = world.system_data::<(
+            Entities<'_>,
+            Read<'_, SceneManager>,
+            ReadStorage<'_, GltfSceneRoot>,
+            ReadStorage<'_, Parent>,
+            ReadStorage<'_, Named>,
+            ReadExpect<'_, ParentHierarchy>,
+        )>();
+
+        fn print_node(entity: Entity, depth: usize, names: &ReadStorage<'_, Named>, hierarchy: &ParentHierarchy) {
+            match names.get(entity) {
+                Some(named) => println!("{:indent$}{}", "", named.name, indent = depth * 2),
+                None => println!("{:indent$}{:?}", "", entity, indent = depth * 2),
+            }
+            for &child in hierarchy.children(entity) {
+                print_node(child, depth + 1, names, hierarchy);
+            }
+        }
+
+        for scene in &manager.scenes {
+            println!("{} ({:?})", scene.instance.path, scene.root);
+            let top = (&*entities, &scene_roots, !&parents)
+                .join()
+                .filter(|(entity, GltfSceneRoot(root), _)| *root == scene.root && *entity != scene.root);
+            for (entity, _, _) in top {
+                print_node(entity, 1, &names, &hierarchy);
+            }
+        }
+    }
+
+    /// Write the local transforms of the named nodes of every scene to `path`, one node per line
+    /// as `name tx ty tz rx ry rz`, with Euler angles for tools that take them.
+    pub fn export_pose(world: &World, path: &Path, order: RotationOrder, unit: AngleUnit) -> Result<(), Error> {
+        let (manager, names, transforms, hierarchy) = world.system_data::<(
+            Read<'_, SceneManager>,
+            ReadStorage<'_, Named>,
+            ReadStorage<'_, Transform>,
+            ReadExpect<'_, ParentHierarchy>,
+        )>();
+
+        let mut lines = vec![format!("# rotation order {:?}, angles in {:?}", order, unit)];
+        for scene in &manager.scenes {
+            lines.push(format!("# {}", scene.instance.path));
+            // Parents come before their children, the order tools expect to rebuild a pose in.
+            for &entity in hierarchy.all() {
+                if !is_under(entity, scene.root, &hierarchy) { continue; }
+                if let (Some(named), Some(transform)) = (names.get(entity), transforms.get(entity)) {
+                    let translation = transform.translation();
+                    let angles = to_euler(transform.rotation(), order, unit);
+                    lines.push(format!(
+                        "{} {} {} {} {} {} {}",
+                        named.name, translation.x, translation.y, translation.z, angles.x, angles.y, angles.z,
+                    ));
+                }
+            }
+        }
+        lines.push(String::new());
+        fs::write(path, lines.join("\n"))?;
+        println!("Pose written to {}", path.display());
+        Ok(())
+    }
+
+    /// Write every scene to `path` as glTF in its current pose, with the clips baked so far.
+    pub fn export_gltf(world: &World, path: &Path) -> Result<(), Error> {
+        let roots = world.read_resource::<SceneManager>().roots();
+        let clips = world.read_resource::<ClipRecorder>().baked.clone();
+        let count = clips.len();
+        clips
+            .into_iter()
+            .fold(GltfExporter::new(roots), GltfExporter::with_clip)
+            .write(world, path)?;
+        println!("Scene written to {} with {} baked clips", path.display(), count);
+        Ok(())
+    }
+}
+
+/// Whether `root` is an ancestor of `entity`.
+fn is_under(entity: Entity, root: Entity, hierarchy: &ParentHierarchy) -> bool {
+    let mut parent = hierarchy.parent(entity);
+    while let Some(entity) = parent {
+        if entity == root { return true; }
+        parent = hierarchy.parent(entity);
+    }
+    false
+}
+
+/// Registers `reload`, `dump hierarchy`, `export pose` and `export gltf`.
+pub fn register_commands(console: &mut Console) {
+    console.register("reload", "reload", |world, _| SceneManager::reload(world));
+    console.register("dump", "dump hierarchy", |world, args| match args {
+        ["hierarchy"] => {
+            SceneManager::dump_hierarchy(world);
+            Ok(())
+        }
+        _ => Err(format_err!("Usage: dump hierarchy")),
+    });
+    console.register("export", "export <pose <path> [xyz|xzy|yxz|yzx|zxy|zyx] [deg|rad]|gltf <path>>", |world, args| match args {
+        ["pose", path, rest @ ..] if rest.len() <= 2 => {
+            let order = rest.get(0).map_or(Ok(RotationOrder::default()), |order| order.parse())?;
+            let unit = rest.get(1).map_or(Ok(AngleUnit::Degrees), |unit| unit.parse())?;
+            SceneManager::export_pose(world, Path::new(path), order, unit)
+        }
+        ["gltf", path] => SceneManager::export_gltf(world, Path::new(path)),
+        _ => Err(format_err!("Usage: export <pose <path> [order] [deg|rad]|gltf <path>>")),
+    });
+}
+
+/// Moves the nodes of newly instantiated scenes to their `Placement`.
+///
+/// Must run after the `gltf_loader` and before the transform system.
+#[derive(Default, SystemDesc)]
+pub struct ScenePlacementSystem;
+
+impl<'a> System<'a> for ScenePlacementSystem {
+    type SystemData = (
+        Entities<'a>,
+        ReadStorage<'a, GltfSceneRoot>,
+        ReadStorage<'a, Parent>,
+        WriteStorage<'a, Transform>,
+        Write<'a, SceneManager>,
+    );
+
+    fn run(&mut self, (entities, scene_roots, parents, mut transforms, mut manager): Self::SystemData) {
+        if manager.placements.is_empty() { return; }
+
+        let mut placed = vec![];
+        for (entity, GltfSceneRoot(root), transform, _) in (&*entities, &scene_roots, &mut transforms, !&parents).join() {
+            if entity == *root { continue; }
+            if let Some(placement) = manager.placements.get(root) {
+                placement.apply(transform);
+                placed.push(*root);
+            }
+        }
+        for root in placed {
+            manager.placements.remove(&root);
+        }
+    }
+}
+
+/// Reports whether the assets of unloaded scenes were freed.
+#[derive(Default, SystemDesc)]
+pub struct SceneEvictionSystem;
+
+impl<'a> System<'a> for SceneEvictionSystem {
+    type SystemData = Write<'a, SceneManager>;
+
+    fn run(&mut self, mut manager: Self::SystemData) {
+        manager.released.retain(|scene| {
+            if scene.is_evicted() {
+                println!(
+                    "Released {}: prefab, {} meshes and {} materials evicted",
+                    scene.path,
+                    scene.meshes.len(),
+                    scene.materials.len(),
+                );
+                return false;
+            }
+            if scene.frames >= EVICTION_FRAMES {
+                eprintln!("Assets of {} are still referenced {} frames after unloading", scene.path, scene.frames);
+                return false;
+            }
+            true
+        });
+        manager.released.iter_mut().for_each(|scene| scene.frames += 1);
+    }
+}
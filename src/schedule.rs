@@ -0,0 +1,399 @@
+//! The layout of the game loop, read from `config/schedule.ron`.
+//!
+//! A profile lists the system groups in the order they are added to the dispatcher, with the stage each
+//! runs in and the systems it waits for, so e.g. solving IK inside or outside of the physics step is a config change.
+
+use std::collections::HashMap;
+
+use amethyst::{
+    animation::{AnimationBundle, VertexSkinningBundle},
+    assets::Processor,
+    audio::AudioBundle,
+    controls::ArcBallControlBundle,
+    core::{bundle::SystemBundle, Transform, TransformBundle},
+    ecs::System,
+    error::{Error, format_err},
+    GameDataBuilder,
+    input::{InputBundle, StringBindings},
+    utils::auto_fov::AutoFovSystem,
+};
+#[cfg(feature = "nphysics")]
+use amethyst_nphysics::NPhysicsBackend;
+#[cfg(feature = "nphysics")]
+use amethyst_physics::PhysicsBundle;
+use serde::{Deserialize, Serialize};
+
+#[cfg(not(feature = "nphysics"))]
+use ceramic_animation::systems::physics::VerletSystem;
+use ceramic_animation::{
+    KinematicsBundle,
+    LocomotionBundle,
+    systems::{
+        animal::{Biped, Locomotor, OscillatorSystem, Quadruped},
+        avoidance::AvoidanceSystem,
+        behavior::BehaviorSystem,
+        blend::{AnimationBlendSystem, AnimationPoseSystem},
+        flock::FlockSystem,
+        modifier::MovementModifierSystem,
+        particle::ParticleSystem,
+        path::PathFollowSystem,
+        player::PlayerSystem,
+    },
+};
+#[cfg(not(target_arch = "wasm32"))]
+use ceramic_animation::systems::animal::GaitPresets;
+
+use crate::{
+    platform,
+    scene::{SceneEvictionSystem, SceneLoaderSystemDesc, ScenePlacementSystem},
+    systems::{
+        audio::{AudioEmitterSystem, FootstepAudioSystem, ListenerSystem, VocalizationSystem},
+        clip::ClipRecorderSystem,
+        crowd::{CrowdSpawnSystem, LodFocusSystem},
+        culling::FrustumCullingSystem,
+        lod::MeshLodSystem,
+        material::MaterialTweakSystem,
+        script::{Script, ScriptSystem},
+        skin_debug::SkinDebugSystem,
+        visibility::RenderLayerSystem,
+    },
+};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::systems::watcher::{ConfigWatchSystem, ReloadResourceSystem};
+
+/// Seconds between checks of the watched config files for changes.
+#[cfg(not(target_arch = "wasm32"))]
+const CONFIG_POLL_INTERVAL: f32 = 1.0;
+
+/// When the systems of a group run.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Stage {
+    /// Once per frame, in the main dispatcher.
+    Frame,
+    /// Every physics step, before the physics world steps.
+    InPhysics,
+    /// Every physics step, after the physics world stepped.
+    PostPhysics,
+}
+
+impl Default for Stage {
+    fn default() -> Self {
+        Stage::Frame
+    }
+}
+
+/// A named set of systems added together, e.g. `"kinematics"` or `"oscillators"`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Group {
+    pub name: String,
+    /// Only the oscillators, particles and kinematics can run in the physics step.
+    /// Without nphysics there is no separate step, and every group runs per frame in the listed order.
+    #[serde(default)]
+    pub stage: Stage,
+    /// Systems of the same stage the group waits for, in addition to the ones it always depends on.
+    #[serde(default)]
+    pub after: Vec<String>,
+    /// Updates per second of groups that can run at their own rate, i.e. the kinematics once per frame.
+    #[serde(default)]
+    pub rate: Option<f32>,
+}
+
+impl Group {
+    /// The dependencies `own` of a system of this group, followed by those the group waits for.
+    fn dependencies(&self, own: &[&str]) -> Vec<String> {
+        own.iter()
+            .map(|name| name.to_string())
+            .chain(self.after.iter().cloned())
+            .collect()
+    }
+
+    /// Fails if the group sets a rate or dependencies its systems can't take.
+    fn check(&self, rate: bool, after: bool) -> Result<(), Error> {
+        if self.rate.is_some() && !rate {
+            return Err(format_err!("The system group {} has no rate", self.name));
+        }
+        if !self.after.is_empty() && !after {
+            return Err(format_err!("The system group {} can't wait for other systems", self.name));
+        }
+        Ok(())
+    }
+}
+
+fn as_strs(names: &[String]) -> Vec<&str> {
+    names.iter().map(String::as_str).collect()
+}
+
+/// A layout of the game loop.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Profile {
+    /// Physics steps per second.
+    pub physics_rate: u32,
+    /// The groups in the order they are added; `"physics"` is where the physics step goes.
+    pub groups: Vec<Group>,
+}
+
+impl Profile {
+    /// Adds the groups of the profile to `builder`.
+    pub fn build(&self, mut builder: GameDataBuilder<'static, 'static>) -> Result<GameDataBuilder<'static, 'static>, Error> {
+        #[cfg(feature = "nphysics")]
+        let mut physics = Some(self.physics()?);
+
+        for group in &self.groups {
+            builder = match (group.name.as_str(), group.stage) {
+                #[cfg(feature = "nphysics")]
+                ("physics", _) => {
+                    let physics = physics
+                        .take()
+                        .ok_or_else(|| format_err!("The physics group is listed more than once"))?;
+                    builder.with_bundle(physics)?
+                }
+                #[cfg(not(feature = "nphysics"))]
+                ("physics", _) => builder,
+                #[cfg(feature = "nphysics")]
+                (_, Stage::InPhysics) | (_, Stage::PostPhysics) => builder,
+                _ => add_group(builder, group)?,
+            };
+        }
+
+        #[cfg(feature = "nphysics")]
+        {
+            if physics.is_some() && self.groups.iter().any(|group| group.stage != Stage::Frame) {
+                return Err(format_err!("Groups run in the physics step, but the physics group is not listed"));
+            }
+        }
+        Ok(builder)
+    }
+
+    /// The physics step with the groups that run in it.
+    #[cfg(feature = "nphysics")]
+    fn physics(&self) -> Result<PhysicsBundle<'static, 'static, f32, NPhysicsBackend>, Error> {
+        let bundle = PhysicsBundle::<f32, NPhysicsBackend>::new().with_frames_per_seconds(self.physics_rate);
+        let mut stage = PhysicsStage(bundle, Stage::InPhysics);
+        for group in self.groups.iter().filter(|group| group.stage != Stage::Frame) {
+            stage.1 = group.stage;
+            stage = add_simulation(stage, group)?;
+        }
+        Ok(stage.0)
+    }
+}
+
+/// The profiles by name.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Schedules {
+    /// The profile used unless another is picked with `--schedule <name>`.
+    pub default: String,
+    pub profiles: HashMap<String, Profile>,
+}
+
+impl Schedules {
+    /// The profile named with `--schedule`, or the default one.
+    pub fn profile_from_args(&self) -> Result<&Profile, Error> {
+        let mut args = std::env::args().skip_while(|arg| arg != "--schedule");
+        args.next();
+        let name = args.next().unwrap_or_else(|| self.default.clone());
+        self.profiles
+            .get(&name)
+            .ok_or_else(|| format_err!("Unknown schedule profile {}", name))
+    }
+}
+
+/// Where the systems of a group go: the main dispatcher, or a stage of the physics step.
+trait Target: Sized {
+    fn system<S>(self, system: S, name: &str, dependencies: Vec<String>) -> Self
+        where S: for<'c> System<'c> + Send + 'static;
+
+    fn bundle<B>(self, bundle: B) -> Result<Self, Error>
+        where B: SystemBundle<'static, 'static> + Send + 'static;
+}
+
+impl Target for GameDataBuilder<'static, 'static> {
+    fn system<S>(self, system: S, name: &str, dependencies: Vec<String>) -> Self
+        where S: for<'c> System<'c> + Send + 'static {
+        self.with(system, name, &as_strs(&dependencies))
+    }
+
+    fn bundle<B>(self, bundle: B) -> Result<Self, Error>
+        where B: SystemBundle<'static, 'static> + Send + 'static {
+        self.with_bundle(bundle)
+    }
+}
+
+#[cfg(feature = "nphysics")]
+struct PhysicsStage(PhysicsBundle<'static, 'static, f32, NPhysicsBackend>, Stage);
+
+#[cfg(feature = "nphysics")]
+impl Target for PhysicsStage {
+    fn system<S>(self, system: S, name: &str, dependencies: Vec<String>) -> Self
+        where S: for<'c> System<'c> + Send + 'static {
+        let PhysicsStage(bundle, stage) = self;
+        let bundle = match stage {
+            Stage::PostPhysics => bundle.with_post_physics(system, name.into(), dependencies),
+            _ => bundle.with_in_physics(system, name.into(), dependencies),
+        };
+        PhysicsStage(bundle, stage)
+    }
+
+    fn bundle<B>(self, bundle: B) -> Result<Self, Error>
+        where B: SystemBundle<'static, 'static> + Send + 'static {
+        let PhysicsStage(physics, stage) = self;
+        let physics = match stage {
+            Stage::PostPhysics => physics.with_bundle_post_physics(bundle),
+            _ => physics.with_bundle_in_physics(bundle),
+        };
+        Ok(PhysicsStage(physics, stage))
+    }
+}
+
+/// Adds the groups that can also run in the physics step.
+fn add_simulation<T: Target>(target: T, group: &Group) -> Result<T, Error> {
+    match group.name.as_str() {
+        "oscillators" => {
+            group.check(false, true)?;
+            Ok(target
+                .system(OscillatorSystem::<Quadruped>::default(), "oscillator", group.dependencies(&[]))
+                .system(OscillatorSystem::<Biped>::default(), "biped_oscillator", group.dependencies(&[]))
+                .system(OscillatorSystem::<Locomotor>::default(), "locomotor_oscillator", group.dependencies(&[])))
+        }
+        #[cfg(feature = "nphysics")]
+        "particles" => {
+            group.check(false, true)?;
+            Ok(target.system(ParticleSystem::default(), "particle", group.dependencies(&[])))
+        }
+        #[cfg(not(feature = "nphysics"))]
+        "particles" => {
+            group.check(false, true)?;
+            Ok(target
+                .system(ParticleSystem::default(), "particle", group.dependencies(&[]))
+                .system(VerletSystem::default(), "verlet", group.dependencies(&["particle"])))
+        }
+        "kinematics" => {
+            // The fixed rate counts frame time, which doesn't pass inside the physics step.
+            group.check(cfg!(not(feature = "nphysics")) || group.stage == Stage::Frame, true)?;
+            let mut bundle = KinematicsBundle::new(2, 0.01).with_dep(&as_strs(&group.after));
+            if let Some(rate) = group.rate {
+                bundle = bundle.with_rate(rate);
+            }
+            target.bundle(bundle)
+        }
+        "locomotion" => {
+            // The locomotion waits for the transforms and the caches of the frame, which the physics step doesn't have.
+            if group.stage != Stage::Frame {
+                return Err(format_err!("The system group {} only runs once per frame", group.name));
+            }
+            group.check(false, false)?;
+            target.bundle(LocomotionBundle::default())
+        }
+        name => Err(format_err!("Unknown system group {}", name)),
+    }
+}
+
+/// Adds a group to the main dispatcher.
+fn add_group(builder: GameDataBuilder<'static, 'static>, group: &Group) -> Result<GameDataBuilder<'static, 'static>, Error> {
+    let name = group.name.as_str();
+    if let "oscillators" | "particles" | "kinematics" | "locomotion" = name {
+        return add_simulation(builder, group);
+    }
+    if group.stage != Stage::Frame {
+        return Err(format_err!("The system group {} only runs once per frame", name));
+    }
+
+    // The bundles of Amethyst only take static dependencies, so only groups of plain systems wait for others.
+    match name {
+        "scene" => {
+            group.check(false, true)?;
+            let loader = group.dependencies(&[]);
+            Ok(builder
+                .with_system_desc(SceneLoaderSystemDesc::default(), "gltf_loader", &as_strs(&loader))
+                .system(ScenePlacementSystem::default(), "scene_placement", group.dependencies(&["gltf_loader"]))
+                .system(SceneEvictionSystem::default(), "scene_eviction", group.dependencies(&[]))
+                .system(CrowdSpawnSystem::default(), "crowd_spawn", group.dependencies(&[]))
+                .system(ClipRecorderSystem::default(), "clip_recorder", group.dependencies(&["scene_placement"])))
+        }
+        "player" => {
+            group.check(false, true)?;
+            Ok(builder
+                .system(BehaviorSystem::default(), "behavior", group.dependencies(&[]))
+                .system(FlockSystem::default(), "flock", group.dependencies(&["behavior"]))
+                .system(PathFollowSystem::default(), "path_follow", group.dependencies(&[]))
+                .system(AvoidanceSystem::default(), "avoidance", group.dependencies(&["behavior", "flock", "path_follow"]))
+                .system(MovementModifierSystem::default(), "movement_modifier", group.dependencies(&[]))
+                .system(PlayerSystem::default(), "player", group.dependencies(&["avoidance", "movement_modifier"])))
+        }
+        "animation" => {
+            group.check(false, false)?;
+            Ok(builder
+                .with_bundle(AnimationBundle::<usize, Transform>::new(
+                    "animation_control",
+                    "sampler_interpolation",
+                ).with_dep(&["gltf_loader"]))?
+                .with(AnimationPoseSystem::default(), "animation_pose", &["sampler_interpolation"]))
+        }
+        "camera" => {
+            group.check(false, false)?;
+            builder.with_bundle(ArcBallControlBundle::<StringBindings>::new())
+        }
+        "transform" => {
+            group.check(false, false)?;
+            builder.with_bundle(TransformBundle::new().with_dep(&[
+                "animation_control",
+                "sampler_interpolation",
+                "free_rotation",
+                "scene_placement",
+            ]))
+        }
+        "skinning" => {
+            group.check(false, false)?;
+            Ok(builder
+                .with_bundle(VertexSkinningBundle::new().with_dep(&[
+                    "transform_system",
+                    "animation_control",
+                    "sampler_interpolation",
+                ]))?
+                .with(SkinDebugSystem::default(), "skin_debug", &["vertex_skinning_system"]))
+        }
+        "blend" => {
+            group.check(false, true)?;
+            Ok(builder.system(AnimationBlendSystem::default(), "animation_blend", group.dependencies(&["animation_pose"])))
+        }
+        "audio" => {
+            group.check(false, false)?;
+            Ok(builder
+                .with_bundle(AudioBundle::default())?
+                .with(ListenerSystem::default(), "listener", &["transform_system"])
+                .with(AudioEmitterSystem::default(), "audio_emitter", &["transform_system", "listener"])
+                .with(VocalizationSystem::default(), "vocalization", &["player"])
+                .with(FootstepAudioSystem::default(), "footstep_audio", &[]))
+        }
+        "input" => {
+            group.check(false, false)?;
+            builder.with_bundle(InputBundle::<StringBindings>::new().with_bindings(platform::bindings()?))
+        }
+        "scripts" => {
+            group.check(false, true)?;
+            Ok(builder
+                .system(Processor::<Script>::new(), "script_processor", group.dependencies(&[]))
+                .system(ScriptSystem::default(), "script", group.dependencies(&["script_processor", "input_system", "player"])))
+        }
+        "view" => {
+            group.check(false, true)?;
+            Ok(builder
+                .system(AutoFovSystem::new(), "auto_fov", group.dependencies(&["gltf_loader"]))
+                .system(MaterialTweakSystem::default(), "material_tweak", group.dependencies(&["gltf_loader"]))
+                .system(RenderLayerSystem::default(), "render_layer", group.dependencies(&["gltf_loader"]))
+                .system(MeshLodSystem::default(), "mesh_lod", group.dependencies(&["transform_system", "gltf_loader"]))
+                .system(FrustumCullingSystem::default(), "frustum_culling", group.dependencies(&["transform_system", "render_layer", "mesh_lod"]))
+                .system(LodFocusSystem::default(), "lod_focus", group.dependencies(&["transform_system"])))
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        "config_watch" => {
+            group.check(false, true)?;
+            Ok(builder
+                .system(ConfigWatchSystem::new(CONFIG_POLL_INTERVAL), "config_watch", group.dependencies(&[]))
+                .system(ReloadResourceSystem::<GaitPresets>::default(), "gait_presets_reload", group.dependencies(&["config_watch"])))
+        }
+        // Configs are compiled into web builds and never change.
+        #[cfg(target_arch = "wasm32")]
+        "config_watch" => Ok(builder),
+        name => Err(format_err!("Unknown system group {}", name)),
+    }
+}
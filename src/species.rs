@@ -0,0 +1,272 @@
+use std::collections::HashMap;
+
+use amethyst::{
+    assets::Progress,
+    core::Transform,
+    ecs::prelude::*,
+    error::{Error, format_err},
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use amethyst_gltf::{GltfSceneRoot, Symmetry};
+use ceramic_animation::{
+    systems::{
+        animal::{Config, HerdSync},
+        behavior::{BehaviorPrefab, Temperament},
+        player::{Player, Steering},
+    },
+    utils::transform::TransformTrait,
+};
+
+use crate::{
+    console::Console,
+    platform,
+    scene::{ExtrasOverrides, Placement, SceneInstance, SceneManager},
+};
+
+/// Node names of a rig. A `{key}` in a name is replaced by the value of `key` for each limb,
+/// e.g. `"Handle-{limb}"` with `{"limb": "f.L"}` names the node `Handle-f.L`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RigNaming {
+    /// The node moved by the player, which carries the legged body.
+    pub body: String,
+    /// The node bounced along with the steps.
+    pub root: String,
+    /// Placeholder values of each limb, in the order of the gait.
+    /// With a `symmetry`, only the left limbs from front to rear.
+    pub limbs: Vec<HashMap<String, String>>,
+    /// Mirrors the left limbs to the right, swapping the side markers in their values.
+    #[serde(default)]
+    pub symmetry: Option<Symmetry>,
+    pub foot: String,
+    pub anchor: String,
+    pub limb_root: String,
+    pub origin: String,
+    pub home: String,
+    /// The node holding the camera control tag, which is dropped from animals driven by AI.
+    #[serde(default)]
+    pub control: Option<String>,
+}
+
+impl RigNaming {
+    /// The placeholder values of all limbs in the order of the gait, front left first.
+    fn limbs(&self) -> Vec<HashMap<String, String>> {
+        let symmetry = match &self.symmetry {
+            Some(symmetry) => symmetry,
+            None => return self.limbs.clone(),
+        };
+        let mirror = |limb: &HashMap<String, String>| limb
+            .iter()
+            .map(|(key, value)| (key.clone(), symmetry.mirror(value).unwrap_or_else(|| value.clone())))
+            .collect::<HashMap<_, _>>();
+
+        match self.limbs.as_slice() {
+            // Quadruped gaits go around the body: front left, front right, rear right, rear left.
+            [front, rear] => vec![front.clone(), mirror(front), mirror(rear), rear.clone()],
+            limbs => limbs.iter().flat_map(|limb| vec![limb.clone(), mirror(limb)]).collect(),
+        }
+    }
+}
+
+/// Inverse kinematics of each limb, with node names following the `RigNaming`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LimbKinematics {
+    /// The end of the chain reaching for the foot.
+    pub chain: String,
+    pub length: usize,
+    /// A joint and the node it points at.
+    #[serde(default)]
+    pub pole: Option<(String, String)>,
+    /// Joints bending about one axis, with their limits in radians.
+    #[serde(default)]
+    pub hinges: Vec<(String, [f32; 2])>,
+}
+
+/// Everything needed to spawn a wired animal from a model.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Species {
+    pub model: String,
+    pub rig: RigNaming,
+    pub gait: Config,
+    #[serde(default)]
+    pub kinematics: Option<LimbKinematics>,
+    /// How animals of the species fall into step with each other, spawned in unison without.
+    #[serde(default)]
+    pub herd: Option<HerdSync>,
+}
+
+impl Species {
+    /// The extras that wire the rig, as overrides of the node extras of the model.
+    pub fn overrides(&self) -> Result<ExtrasOverrides, Error> {
+        let rig = &self.rig;
+        let limbs = rig.limbs();
+        let names = |pattern: &str| limbs
+            .iter()
+            .map(|limb| expand(pattern, limb))
+            .collect::<Result<Vec<_>, _>>();
+
+        let body = match limbs.len() {
+            4 => "quadruped",
+            2 => "biped",
+            count => return Err(format_err!("Species with {} limbs are not supported, only 2 or 4", count)),
+        };
+        let mut legged = serde_json::to_value(&self.gait)?;
+        if let Value::Object(fields) = &mut legged {
+            fields.insert("feet".into(), json!(names(&rig.foot)?));
+            fields.insert("anchors".into(), json!(names(&rig.anchor)?));
+            fields.insert("roots".into(), json!(names(&rig.limb_root)?));
+            fields.insert("origins".into(), json!(names(&rig.origin)?));
+            fields.insert("homes".into(), json!(names(&rig.home)?));
+            fields.insert("root".into(), json!(rig.root));
+        }
+
+        let mut overrides = ExtrasOverrides::new();
+        let mut extras = json!({ body: legged });
+        if let (Some(herd), Value::Object(fields)) = (&self.herd, &mut extras) {
+            fields.insert("herd_sync".into(), serde_json::to_value(herd)?);
+        }
+        overrides.insert(rig.body.clone(), extras);
+
+        if let Some(kinematics) = &self.kinematics {
+            for limb in &limbs {
+                let foot = expand(&rig.foot, limb)?;
+                let chain = json!({ "chain": { "target": foot, "length": kinematics.length } });
+                overrides.insert(expand(&kinematics.chain, limb)?, chain);
+                if let Some((joint, target)) = &kinematics.pole {
+                    let pole = json!({ "constrain": { "Pole": { "target": expand(target, limb)? } } });
+                    overrides.insert(expand(joint, limb)?, pole);
+                }
+                for (joint, limit) in &kinematics.hinges {
+                    let hinge = json!({ "constrain": { "Hinge": { "limit": limit } } });
+                    overrides.insert(expand(joint, limb)?, hinge);
+                }
+            }
+        }
+        Ok(overrides)
+    }
+
+    pub fn instance(&self) -> Result<SceneInstance, Error> {
+        Ok(SceneInstance { path: self.model.clone(), overrides: self.overrides()?, placement: None })
+    }
+
+    /// An instance steered by `behavior` instead of the input.
+    pub fn with_behavior(&self, behavior: &BehaviorPrefab) -> Result<SceneInstance, Error> {
+        let mut instance = self.instance()?;
+        if let Some(Value::Object(fields)) = instance.overrides.get_mut(&self.rig.body) {
+            fields.insert("behavior".into(), serde_json::to_value(behavior)?);
+        }
+        if let Some(control) = &self.rig.control {
+            instance.overrides.insert(control.clone(), json!({ "control_tag": null }));
+        }
+        Ok(instance)
+    }
+}
+
+/// Replace the `{key}` placeholders of `pattern` with the values of a limb.
+fn expand(pattern: &str, limb: &HashMap<String, String>) -> Result<String, Error> {
+    let name = limb
+        .iter()
+        .fold(pattern.to_string(), |name, (key, value)| name.replace(&format!("{{{}}}", key), value));
+    if name.contains('{') {
+        return Err(format_err!("Rig name {} has a placeholder the limb {:?} doesn't set", pattern, limb));
+    }
+    Ok(name)
+}
+
+/// Species by name, e.g. a cat, a horse and a lizard sharing the same systems.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct SpeciesPacks {
+    pub species: HashMap<String, Species>,
+}
+
+impl SpeciesPacks {
+    pub fn get(&self, name: &str) -> Result<&Species, Error> {
+        self.species
+            .get(name)
+            .ok_or_else(|| format_err!("Unknown species {}", name))
+    }
+
+    /// The scene instance of a fully wired animal of the species called `name`.
+    pub fn instance(&self, name: &str) -> Result<SceneInstance, Error> {
+        self.get(name)?.instance()
+    }
+
+    /// Spawn an animal of the species called `name`, returning the root entity of its scene.
+    pub fn spawn<P: Progress>(&self, world: &mut World, name: &str, progress: P) -> Result<Entity, Error> {
+        let instance = self.instance(name)?;
+        Ok(SceneManager::load(world, instance, progress))
+    }
+}
+
+/// Spacing of the animals spawned together by the `spawn` command.
+const SPAWN_SPACING: f32 = 2.0;
+
+/// Despawns the scene of the animal not under input control nearest to one that is, or of all of them.
+fn despawn(world: &mut World, all: bool) -> Result<(), Error> {
+    let roots = {
+        let (entities, players, steerings, transforms, scene_roots) = world.system_data::<(
+            Entities<'_>,
+            ReadStorage<'_, Player>,
+            ReadStorage<'_, Steering>,
+            ReadStorage<'_, Transform>,
+            ReadStorage<'_, GltfSceneRoot>,
+        )>();
+        let animals = (&*entities, &players, &steerings, &transforms, &scene_roots)
+            .join()
+            .map(|(_, _, _, transform, GltfSceneRoot(root))| (*root, transform.global_position()));
+        if all {
+            animals.map(|(root, _)| root).collect::<Vec<_>>()
+        } else {
+            let ref position = (&players, !&steerings, &transforms)
+                .join()
+                .map(|(_, _, transform)| transform.global_position())
+                .next()
+                .ok_or_else(|| format_err!("There is no animal under input control"))?;
+            animals
+                .map(|(root, point)| (root, (point - position).norm()))
+                .min_by(|(_, a), (_, b)| a.partial_cmp(b).unwrap())
+                .map(|(root, _)| root)
+                .into_iter()
+                .collect()
+        }
+    };
+    if roots.is_empty() {
+        return Err(format_err!("There is no animal to despawn"));
+    }
+    for &root in &roots {
+        SceneManager::despawn(world, root)?;
+    }
+    println!("Despawning {} animals", roots.len());
+    Ok(())
+}
+
+/// Registers `spawn <species> [count]`, which adds wandering animals in a row, read fresh from `config/species.ron`,
+/// and `despawn [all]`, which removes the nearest of the animals driven by AI, or all of them.
+pub fn register_commands(console: &mut Console) {
+    console.register("spawn", "spawn <species> [count]", |world, args| {
+        let (name, count) = match args {
+            [name] => (*name, 1),
+            [name, count] => (*name, count.parse::<usize>()?),
+            _ => return Err(format_err!("Usage: spawn <species> [count]")),
+        };
+        let packs = platform::species_packs()?;
+        let behavior = BehaviorPrefab {
+            temperament: Temperament::Wander { radius: 8.0 },
+            sight: 0.0,
+        };
+        for index in 0..count {
+            let mut instance = packs.get(name)?.with_behavior(&behavior)?;
+            let offset = (index as f32 - (count - 1) as f32 / 2.0) * SPAWN_SPACING;
+            instance.placement = Some(Placement { translation: [offset, 0.0, 0.0], yaw: 0.0 });
+            SceneManager::load_shared(world, instance, ());
+        }
+        println!("Spawned {} {}", count, name);
+        Ok(())
+    });
+    console.register("despawn", "despawn [all]", |world, args| match args {
+        [] => despawn(world, false),
+        ["all"] => despawn(world, true),
+        _ => Err(format_err!("Usage: despawn [all]")),
+    });
+}
@@ -0,0 +1,90 @@
+use amethyst::{
+    input::{ElementState, get_key, is_close_requested, StringBindings, VirtualKeyCode},
+    prelude::*,
+    window::Window,
+    winit::{Event, WindowEvent},
+};
+
+use crate::{console::Console, platform};
+
+/// Reads a command line while the game keeps running, opened and closed with the grave key.
+///
+/// There is no text rendering, so the line being typed is shown in the window title.
+#[derive(Default)]
+pub struct ConsoleState {
+    line: String,
+    /// How many lines back in the history the up key went.
+    recall: usize,
+}
+
+impl ConsoleState {
+    fn show(&self, world: &World) {
+        if let Some(window) = world.try_fetch::<Window>() {
+            window.set_title(&format!("> {}", self.line));
+        }
+    }
+
+    fn recall(&mut self, world: &World, recall: usize) {
+        let console = world.read_resource::<Console>();
+        let history = console.history();
+        self.recall = recall.min(history.len());
+        self.line = match self.recall {
+            0 => String::new(),
+            recall => history[history.len() - recall].clone(),
+        };
+    }
+
+    fn submit(&mut self, world: &mut World) {
+        let line = std::mem::take(&mut self.line);
+        self.recall = 0;
+        if line.trim().is_empty() { return; }
+
+        println!("> {}", line);
+        world.write_resource::<Console>().remember(&line);
+        if let Err(error) = Console::execute(world, &line) {
+            eprintln!("{}", error);
+        }
+    }
+}
+
+impl SimpleState for ConsoleState {
+    fn on_start(&mut self, data: StateData<'_, GameData<'_, '_>>) {
+        println!("Console open, type help for a list of commands");
+        self.show(data.world);
+    }
+
+    fn on_stop(&mut self, data: StateData<'_, GameData<'_, '_>>) {
+        if let Some(window) = data.world.try_fetch::<Window>() {
+            let title = platform::display_config()
+                .map(|config| config.title)
+                .unwrap_or_default();
+            window.set_title(&title);
+        }
+    }
+
+    fn handle_event(
+        &mut self,
+        data: StateData<'_, GameData<'_, '_>>,
+        event: StateEvent<StringBindings>,
+    ) -> SimpleTrans {
+        if let StateEvent::Window(event) = &event {
+            if is_close_requested(event) { return Trans::Quit; }
+            match get_key(event) {
+                Some((VirtualKeyCode::Escape, ElementState::Pressed))
+                | Some((VirtualKeyCode::Grave, ElementState::Pressed)) => { return Trans::Pop; }
+                Some((VirtualKeyCode::Return, ElementState::Pressed)) => self.submit(data.world),
+                Some((VirtualKeyCode::Back, ElementState::Pressed)) => { self.line.pop(); }
+                Some((VirtualKeyCode::Up, ElementState::Pressed)) => self.recall(data.world, self.recall + 1),
+                Some((VirtualKeyCode::Down, ElementState::Pressed)) => self.recall(data.world, self.recall.saturating_sub(1)),
+                _ => {}
+            }
+            if let Event::WindowEvent { event: WindowEvent::ReceivedCharacter(character), .. } = event {
+                if !character.is_control() && *character != '`' {
+                    self.line.push(*character);
+                }
+            }
+            self.show(data.world);
+        }
+        Trans::None
+    }
+}
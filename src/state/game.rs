@@ -1,11 +1,55 @@
 use amethyst::{
-    core::math::{Point3, Vector3},
+    config::Config,
+    core::{math::{Point3, Vector3}, Named, Transform},
+    ecs::prelude::*,
+    error::{Error, format_err},
     input::{ElementState, get_key, is_close_requested, StringBindings, VirtualKeyCode},
     prelude::*,
     renderer::{
         debug_drawing::DebugLinesComponent,
         palette::Srgba,
     },
+    shrev::EventChannel,
+};
+
+use amethyst_gltf::GltfSceneRoot;
+use ceramic_animation::{
+    systems::{
+        animal::{
+            ActionEvent,
+            Gait,
+            GaitPresetEvent,
+            GaitPresets,
+            Legged,
+            LimbWorkspace,
+            MotionDatabase,
+            MotionRecorder,
+            MotionRecording,
+            Posture,
+            PostureEvent,
+            Postures,
+            Quadruped,
+        },
+        carry::{Carrier, CarryEvent},
+        player::Steering,
+        socket::{SocketEvent, Sockets},
+    },
+    utils::transform::TransformTrait,
+};
+
+use crate::{
+    console::Console,
+    nudge::{self, Nudge},
+    scene,
+    species,
+    state::console::ConsoleState,
+    systems::{
+        clip::ClipRecorder,
+        culling::CullingStats,
+        material::{MaterialParameter, MaterialTweak},
+        skin_debug::SkinDebug,
+    },
+    utils::extras_diff,
 };
 
 pub struct GameState;
@@ -41,20 +85,364 @@ impl SimpleState for GameState {
             .create_entity()
             .with(debug_lines_component)
             .build();
+
+        let mut console = Console::new();
+        register_commands(&mut console);
+        scene::register_commands(&mut console);
+        species::register_commands(&mut console);
+        nudge::register_commands(&mut console);
+        extras_diff::register_commands(&mut console);
+        data.world.insert(console);
+        data.world.insert(Nudge::default());
     }
 
     fn handle_event(
         &mut self,
-        _data: StateData<'_, GameData<'_, '_>>,
+        data: StateData<'_, GameData<'_, '_>>,
         event: StateEvent<StringBindings>,
     ) -> SimpleTrans {
         if let StateEvent::Window(event) = &event {
             if is_close_requested(event) { return Trans::Quit; }
             match get_key(&event) {
                 Some((VirtualKeyCode::Escape, ElementState::Pressed)) => { return Trans::Quit; }
+                Some((VirtualKeyCode::Grave, ElementState::Pressed)) => { return Trans::Push(Box::new(ConsoleState::default())); }
+                Some((key, ElementState::Pressed)) if nudge::handle_key(data.world, key) => {}
+                Some((key, ElementState::Pressed)) => {
+                    let line = DEBUG_KEYS
+                        .iter()
+                        .find(|(debug_key, _)| *debug_key == key)
+                        .map(|(_, line)| *line);
+                    if let Some(line) = line {
+                        if let Err(error) = Console::execute(data.world, line) {
+                            eprintln!("{}: {}", line, error);
+                        }
+                    }
+                }
                 _ => {}
             }
         }
         Trans::None
     }
 }
+
+/// Command lines run by the function keys.
+const DEBUG_KEYS: [(VirtualKeyCode, &str); 9] = [
+    (VirtualKeyCode::F2, "bind_pose"),
+    (VirtualKeyCode::F3, "preset"),
+    (VirtualKeyCode::F4, "pounce"),
+    (VirtualKeyCode::F5, "posture"),
+    (VirtualKeyCode::F6, "carry"),
+    (VirtualKeyCode::F7, "culling"),
+    (VirtualKeyCode::F8, "gait"),
+    (VirtualKeyCode::F9, "reload"),
+    (VirtualKeyCode::F10, "workspace"),
+];
+
+/// Registers the gameplay and debug commands.
+fn register_commands(console: &mut Console) {
+    console.register("bind_pose", "bind_pose", |world, _| {
+        let mut debug = world.write_resource::<SkinDebug>();
+        debug.bind_pose = !debug.bind_pose;
+        Ok(())
+    });
+    console.register("preset", "preset [name]", |world, args| preset(world, args.first().copied()));
+    console.register("pounce", "pounce", |world, _| {
+        pounce(world);
+        Ok(())
+    });
+    console.register("posture", "posture [stand|sit|lie]", |world, args| posture(world, args.first().copied()));
+    console.register("carry", "carry", |world, _| {
+        carry(world);
+        Ok(())
+    });
+    console.register("culling", "culling", |world, _| {
+        let stats = *world.read_resource::<CullingStats>();
+        println!(
+            "Culling: {} of {} meshes visible, {} subtrees outside the frustum",
+            stats.visible,
+            stats.meshes,
+            stats.culled_subtrees,
+        );
+        Ok(())
+    });
+    console.register("workspace", "workspace", |world, _| {
+        let mut workspace = world.write_resource::<LimbWorkspace>();
+        workspace.enabled = !workspace.enabled;
+        Ok(())
+    });
+    console.register("tune", "tune <stance_height|step_limit|max_duty_factor|flight_time> <value>...", |world, args| tune(world, args));
+    console.register("gait", "gait [walk|trot|gallop|auto]", |world, args| gait(world, args.first().copied()));
+    console.register(
+        "material",
+        "material <scene> <index> <albedo|emission <r> <g> <b> <a>|metallic_roughness <metallic> <roughness>>",
+        |world, args| material(world, args),
+    );
+    console.register("set", "set <gait|preset|posture> <value>", |world, args| match args {
+        ["gait", value] => gait(world, Some(*value)),
+        ["preset", value] => preset(world, Some(*value)),
+        ["posture", value] => posture(world, Some(*value)),
+        _ => Err(format_err!("Usage: set <gait|preset|posture> <value>")),
+    });
+    console.register("record", "record <start [frame_rate]|stop <path>>", |world, args| match args {
+        ["start"] => record(world, 30.0),
+        ["start", frame_rate] => record(world, frame_rate.parse()?),
+        ["stop", path] => {
+            let recording = world.write_resource::<MotionRecorder>().stop();
+            recording.write(path)?;
+            println!("{} frames written to {}", recording.frames.len(), path);
+            Ok(())
+        }
+        _ => Err(format_err!("Usage: record <start [frame_rate]|stop <path>>")),
+    });
+    console.register("clip", "clip <start <name> [frame_rate]|stop>", |world, args| match args {
+        ["start", name] => clip(world, name, 30.0),
+        ["start", name, frame_rate] => clip(world, name, frame_rate.parse()?),
+        ["stop"] => {
+            let frames = world
+                .write_resource::<ClipRecorder>()
+                .stop()
+                .ok_or_else(|| format_err!("No clip is being recorded"))?;
+            println!("Baked {} frames, written with the next `export gltf`", frames);
+            Ok(())
+        }
+        _ => Err(format_err!("Usage: clip <start <name> [frame_rate]|stop>")),
+    });
+    console.register("attach", "attach <socket> <node>", |world, args| match args {
+        [socket, node] => attach(world, socket, Some(node)),
+        _ => Err(format_err!("Usage: attach <socket> <node>")),
+    });
+    console.register("detach", "detach <socket>", |world, args| match args {
+        [socket] => attach(world, socket, None),
+        _ => Err(format_err!("Usage: detach <socket>")),
+    });
+    console.register("bake", "bake <database> <horizon> <recording>...", |_, args| match args {
+        [database, horizon, recordings @ ..] if !recordings.is_empty() => {
+            let recordings = recordings
+                .iter()
+                .map(MotionRecording::load)
+                .collect::<Result<Vec<_>, _>>()?;
+            let baked = MotionDatabase::bake(&recordings, horizon.parse()?)?;
+            baked.write(database)?;
+            println!("{} of {} frames can start a match, written to {}", baked.starts.len(), baked.frames.len(), database);
+            Ok(())
+        }
+        _ => Err(format_err!("Usage: bake <database> <horizon> <recording>...")),
+    });
+}
+
+/// Starts recording the first quadruped under input control for motion matching.
+fn record(world: &World, frame_rate: f32) -> Result<(), Error> {
+    let (entities, quadrupeds, steerings, mut recorder) = world.system_data::<(
+        Entities<'_>,
+        ReadStorage<'_, Quadruped>,
+        ReadStorage<'_, Steering>,
+        Write<'_, MotionRecorder>,
+    )>();
+    let (body, _, _) = (&*entities, &quadrupeds, !&steerings)
+        .join()
+        .next()
+        .ok_or_else(|| format_err!("There is no quadruped under input control to record"))?;
+    recorder.start(body, frame_rate);
+    println!("Recording {:?} at {} frames per second", body, frame_rate);
+    Ok(())
+}
+
+/// Starts baking the scene of the first quadruped under input control into a clip called `name`.
+fn clip(world: &World, name: &str, frame_rate: f32) -> Result<(), Error> {
+    let (entities, quadrupeds, steerings, scene_roots, mut recorder) = world.system_data::<(
+        Entities<'_>,
+        ReadStorage<'_, Quadruped>,
+        ReadStorage<'_, Steering>,
+        ReadStorage<'_, GltfSceneRoot>,
+        Write<'_, ClipRecorder>,
+    )>();
+    let GltfSceneRoot(root) = (&quadrupeds, !&steerings, &scene_roots)
+        .join()
+        .map(|(_, _, scene_root)| *scene_root)
+        .next()
+        .ok_or_else(|| format_err!("There is no quadruped under input control to record"))?;
+    let nodes = (&*entities, &scene_roots)
+        .join()
+        .filter(|(entity, GltfSceneRoot(member_root))| *member_root == root && *entity != root)
+        .map(|(entity, _)| entity)
+        .chain(std::iter::once(root))
+        .collect::<Vec<_>>();
+    println!("Baking {} nodes into {} at {} frames per second", nodes.len(), name, frame_rate);
+    recorder.start(name, nodes, frame_rate);
+    Ok(())
+}
+
+/// Attaches the node called `node` to the socket called `socket` of the creatures under input control,
+/// or detaches what the socket holds without a node.
+fn attach(world: &World, socket: &str, node: Option<&str>) -> Result<(), Error> {
+    let (entities, sockets, steerings, names, mut events) = world.system_data::<(
+        Entities<'_>,
+        ReadStorage<'_, Sockets>,
+        ReadStorage<'_, Steering>,
+        ReadStorage<'_, Named>,
+        Write<'_, EventChannel<SocketEvent>>,
+    )>();
+    let prop = match node {
+        Some(node) => Some(
+            (&*entities, &names)
+                .join()
+                .find(|(_, name)| name.name == node)
+                .map(|(entity, _)| entity)
+                .ok_or_else(|| format_err!("There is no node called {}", node))?,
+        ),
+        None => None,
+    };
+    for (body, sockets, _) in (&*entities, &sockets, !&steerings).join() {
+        let held = sockets
+            .get(socket)
+            .ok_or_else(|| format_err!("{:?} has no socket called {}", body, socket))?
+            .attached;
+        let event = match (prop, held) {
+            (Some(prop), _) => SocketEvent::Attach { body, socket: socket.to_string(), prop },
+            (None, Some(prop)) => SocketEvent::Detach { body, prop },
+            (None, None) => continue,
+        };
+        events.single_write(event);
+    }
+    Ok(())
+}
+
+/// Blends all quadrupeds into the preset called `name`, or into the next one.
+fn preset(world: &World, name: Option<&str>) -> Result<(), Error> {
+    let preset = match name {
+        Some(name) => name.to_string(),
+        None => world
+            .read_resource::<GaitPresets>()
+            .next()
+            .map(String::from)
+            .ok_or_else(|| format_err!("There are no gait presets"))?,
+    };
+    let event = GaitPresetEvent { preset, targets: None };
+    world.write_resource::<EventChannel<GaitPresetEvent>>().single_write(event);
+    Ok(())
+}
+
+/// Moves all quadrupeds into the posture called `name`, or into the one after the current.
+fn posture(world: &World, name: Option<&str>) -> Result<(), Error> {
+    let posture = match name {
+        Some("stand") => Posture::Stand,
+        Some("sit") => Posture::Sit,
+        Some("lie") => Posture::Lie,
+        Some(name) => return Err(format_err!("Unknown posture {}", name)),
+        None => (&world.read_storage::<Postures>())
+            .join()
+            .next()
+            .map_or(Posture::Stand, |postures| postures.posture())
+            .next(),
+    };
+    let event = PostureEvent { posture, targets: None };
+    world.write_resource::<EventChannel<PostureEvent>>().single_write(event);
+    Ok(())
+}
+
+/// Forces all quadrupeds into the gait called `name`, back to the gait chosen by speed with `auto`,
+/// or into the next gait of each.
+fn gait(world: &World, name: Option<&str>) -> Result<(), Error> {
+    let forced = match name {
+        Some("walk") => Some(Some(Gait::Walk)),
+        Some("trot") => Some(Some(Gait::Trot)),
+        Some("gallop") => Some(Some(Gait::Gallop)),
+        Some("auto") => Some(None),
+        Some(name) => return Err(format_err!("Unknown gait {}", name)),
+        None => None,
+    };
+    let mut quadrupeds = world.write_storage::<Quadruped>();
+    for quadruped in (&mut quadrupeds).join() {
+        match forced.unwrap_or_else(|| Gait::next(quadruped.gait())) {
+            Some(gait) => quadruped.set_gait(gait),
+            None => quadruped.release_gait(),
+        }
+    }
+    Ok(())
+}
+
+/// Sets a field of the config of all quadrupeds, e.g. `tune step_limit 0.8 1.2`, keeping their scale.
+fn tune(world: &World, args: &[&str]) -> Result<(), Error> {
+    let values = args
+        .get(1..)
+        .unwrap_or_default()
+        .iter()
+        .map(|value| value.parse::<f32>())
+        .collect::<Result<Vec<_>, _>>()?;
+    let mut quadrupeds = world.write_storage::<Quadruped>();
+    for quadruped in (&mut quadrupeds).join() {
+        let mut config = quadruped.config();
+        match (args.first().copied(), values.as_slice()) {
+            (Some("stance_height"), &[value]) => config.stance_height = value,
+            (Some("step_limit"), &[min, max]) => config.step_limit = [min, max],
+            (Some("max_duty_factor"), &[value]) => config.max_duty_factor = value,
+            (Some("flight_time"), &[value]) => config.flight_time = value,
+            _ => return Err(format_err!("Usage: tune <stance_height|step_limit|max_duty_factor|flight_time> <value>...")),
+        }
+        quadruped.set_config(config);
+    }
+    Ok(())
+}
+
+/// Overrides a parameter of a material of a scene file, e.g. `material model/cat.glb 0 albedo 1 0.5 0 1`.
+fn material(world: &World, args: &[&str]) -> Result<(), Error> {
+    let usage = || format_err!(
+        "Usage: material <scene> <index> <albedo|emission <r> <g> <b> <a>|metallic_roughness <metallic> <roughness>>"
+    );
+    let (scene, material_id, parameter, values) = match args {
+        [scene, material_id, parameter, values @ ..] => (scene, material_id.parse()?, parameter, values),
+        _ => return Err(usage()),
+    };
+    let values = values
+        .iter()
+        .map(|value| value.parse::<f32>())
+        .collect::<Result<Vec<_>, _>>()?;
+    let parameter = match (*parameter, values.as_slice()) {
+        ("albedo", &[r, g, b, a]) => MaterialParameter::Albedo([r, g, b, a]),
+        ("emission", &[r, g, b, a]) => MaterialParameter::Emission([r, g, b, a]),
+        ("metallic_roughness", &[metallic, roughness]) => MaterialParameter::MetallicRoughness { metallic, roughness },
+        _ => return Err(usage()),
+    };
+    let event = MaterialTweak { scene: scene.to_string(), material_id, parameter };
+    world.write_resource::<EventChannel<MaterialTweak>>().single_write(event);
+    Ok(())
+}
+
+/// Makes the quadrupeds under input control pounce at the nearest other quadruped.
+fn pounce(world: &World) {
+    let (entities, quadrupeds, steerings, transforms, mut actions) = world.system_data::<(
+        Entities<'_>,
+        ReadStorage<'_, Quadruped>,
+        ReadStorage<'_, Steering>,
+        ReadStorage<'_, Transform>,
+        Write<'_, EventChannel<ActionEvent>>,
+    )>();
+    for (quadruped, _, transform, _) in (&*entities, &quadrupeds, &transforms, !&steerings).join() {
+        let ref position = transform.global_position();
+        let nearest = (&*entities, &quadrupeds, &transforms)
+            .join()
+            .filter(|(target, _, _)| *target != quadruped)
+            .map(|(target, _, transform)| (target, (transform.global_position() - position).norm()))
+            .min_by(|(_, a), (_, b)| a.partial_cmp(b).unwrap());
+        if let Some((target, _)) = nearest {
+            actions.single_write(ActionEvent::Pounce { quadruped, target });
+        }
+    }
+}
+
+/// Makes the carriers under input control pick up the prop closest to their jaw, or drop what they hold.
+fn carry(world: &World) {
+    let (entities, carriers, steerings, mut events) = world.system_data::<(
+        Entities<'_>,
+        ReadStorage<'_, Carrier>,
+        ReadStorage<'_, Steering>,
+        Write<'_, EventChannel<CarryEvent>>,
+    )>();
+    for (entity, carrier, _) in (&*entities, &carriers, !&steerings).join() {
+        let event = match carrier.carried() {
+            Some(_) => CarryEvent::Drop(entity),
+            None => CarryEvent::PickUp(entity),
+        };
+        events.single_write(event);
+    }
+}
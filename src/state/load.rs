@@ -1,25 +1,103 @@
 use amethyst::{
-    assets::{Completion, Handle, PrefabLoader, ProgressCounter},
+    assets::{AssetStorage, Completion, Handle, ProgressCounter},
     ecs::prelude::*,
     input::{ElementState, get_key, is_close_requested, StringBindings, VirtualKeyCode},
     prelude::*,
+    renderer::{Material, types::{Mesh, Texture}},
 };
 
+use ceramic_animation::utils::random::Random;
+
 use crate::{
-    scene::{SceneAsset, SceneFormat, ScenePrefab},
+    platform,
+    scene::{Manifest, SceneManager},
     state::game::GameState,
+    systems::{crowd::CrowdSpawner, culling::WarmUp},
 };
 
+/// Frames drawn with culling off once every asset is ready, so nothing is first uploaded in the game.
+const WARM_UP_FRAMES: u32 = 2;
+
+/// Frames to wait for loaded assets to reach their storages before starting the game anyway.
+const MAX_UPLOAD_FRAMES: u32 = 300;
+
+#[derive(Debug, Copy, Clone)]
+enum Stage {
+    Loading,
+    /// Waiting for the processors to put the loaded meshes, materials and textures in their storages.
+    Uploading { frames: u32 },
+    /// Drawing the whole scene for a few frames before switching.
+    WarmingUp { frames: u32 },
+}
+
+impl Default for Stage {
+    fn default() -> Self {
+        Stage::Loading
+    }
+}
+
+/// Whether every mesh and material on an entity, and every texture of those materials, is in its storage.
+fn assets_ready(world: &World) -> bool {
+    let (meshes, materials, mesh_storage, material_storage, texture_storage) = world.system_data::<(
+        ReadStorage<'_, Handle<Mesh>>,
+        ReadStorage<'_, Handle<Material>>,
+        Read<'_, AssetStorage<Mesh>>,
+        Read<'_, AssetStorage<Material>>,
+        Read<'_, AssetStorage<Texture>>,
+    )>();
+
+    let textures_ready = |material: &Material| {
+        [
+            &material.albedo,
+            &material.emission,
+            &material.normal,
+            &material.metallic_roughness,
+            &material.ambient_occlusion,
+            &material.cavity,
+        ]
+            .iter()
+            .all(|texture| texture_storage.get(texture).is_some())
+    };
+    meshes.join().all(|mesh| mesh_storage.get(mesh).is_some())
+        && materials.join().all(|material| material_storage.get(material).map_or(false, textures_ready))
+}
+
+/// Loads the scenes of the manifest, then warms up by drawing all of them once before the game starts,
+/// so the first frames of the game don't hitch on uploads.
 #[derive(Default)]
 pub struct LoadState {
     progress: ProgressCounter,
+    stage: Stage,
 }
 
 impl SimpleState for LoadState {
     fn on_start(&mut self, data: StateData<'_, GameData<'_, '_>>) {
         println!("Loading...");
-        let handle = self.load_scene(data.world, "model/cat.glb".into());
-        data.world.create_entity().with(handle).build();
+        let manifest = platform::manifest().unwrap_or_else(|err| {
+            eprintln!("Failed to load the manifest, spawning the default scene: {}", err);
+            Manifest::default()
+        });
+        for instance in manifest.instances {
+            SceneManager::load(data.world, instance, &mut self.progress);
+        }
+        if !manifest.species.is_empty() || manifest.crowd.is_some() {
+            let packs = platform::species_packs().unwrap_or_else(|err| {
+                eprintln!("Failed to load the species packs: {}", err);
+                Default::default()
+            });
+            for name in &manifest.species {
+                if let Err(err) = packs.spawn(data.world, name, &mut self.progress) {
+                    eprintln!("Failed to spawn a {}: {}", name, err);
+                }
+            }
+            if let Some(crowd) = &manifest.crowd {
+                let spawner = CrowdSpawner::new(crowd, &packs, &mut data.world.write_resource::<Random>());
+                match spawner {
+                    Ok(spawner) => data.world.insert(spawner),
+                    Err(err) => eprintln!("Failed to spawn the crowd: {}", err),
+                }
+            }
+        }
     }
 
     fn handle_event(
@@ -37,25 +115,37 @@ impl SimpleState for LoadState {
         Trans::None
     }
 
-    fn update(&mut self, _data: &mut StateData<'_, GameData<'_, '_>>) -> SimpleTrans {
-        match self.progress.complete() {
-            Completion::Failed => Trans::Quit,
-            Completion::Complete => {
-                println!("Assets loaded");
-                Trans::Switch(Box::new(GameState))
+    fn update(&mut self, data: &mut StateData<'_, GameData<'_, '_>>) -> SimpleTrans {
+        match self.stage {
+            Stage::Loading => match self.progress.complete() {
+                Completion::Failed => return Trans::Quit,
+                Completion::Complete => {
+                    println!("Assets loaded");
+                    self.stage = Stage::Uploading { frames: 0 };
+                }
+                Completion::Loading => {}
+            },
+            Stage::Uploading { frames } => {
+                let ready = assets_ready(data.world);
+                if !ready && frames < MAX_UPLOAD_FRAMES {
+                    self.stage = Stage::Uploading { frames: frames + 1 };
+                } else {
+                    if !ready {
+                        eprintln!("Assets still missing after {} frames, starting anyway", frames);
+                    }
+                    data.world.insert(WarmUp { active: true });
+                    self.stage = Stage::WarmingUp { frames: WARM_UP_FRAMES };
+                }
+            }
+            Stage::WarmingUp { frames } if frames > 0 => {
+                self.stage = Stage::WarmingUp { frames: frames - 1 };
+            }
+            Stage::WarmingUp { .. } => {
+                data.world.insert(WarmUp::default());
+                println!("Warmed up");
+                return Trans::Switch(Box::new(GameState));
             }
-            Completion::Loading => Trans::None,
         }
-    }
-}
-
-impl LoadState {
-    fn load_scene(&mut self, world: &mut World, path: String) -> Handle<SceneAsset> {
-        world.exec(
-            |loader: PrefabLoader<'_, ScenePrefab>| {
-                let handle = loader.load(path, SceneFormat::default(), &mut self.progress);
-                handle
-            },
-        )
+        Trans::None
     }
 }
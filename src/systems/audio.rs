@@ -0,0 +1,719 @@
+use std::{
+    collections::HashMap,
+    io::Cursor,
+    sync::{Arc, atomic::{AtomicU32, Ordering}},
+    time::Duration,
+};
+
+use amethyst::{
+    assets::{AssetStorage, Handle, Loader, PrefabData, ProgressCounter},
+    audio::{AudioEmitter, AudioListener, OggFormat, Source, WavFormat},
+    core::{math::Point3, Time, Transform},
+    derive::SystemDesc,
+    ecs::{Component, prelude::*},
+    error::Error,
+    renderer::{ActiveCamera, Camera},
+    shrev::{EventChannel, ReaderId},
+};
+use rand::{Rng, seq::SliceRandom};
+use rodio::{Decoder, Device, SpatialSink, Source as _};
+use serde::{Deserialize, Serialize};
+
+use ceramic_animation::{
+    systems::{
+        animal::{LocomotionEvent, Stride},
+        water::Buoyancy,
+    },
+    utils::{random::{Random, Seed}, transform::TransformTrait},
+};
+
+fn load_sound(
+    path: &str,
+    loader: &Loader,
+    progress: &mut ProgressCounter,
+    storage: &AssetStorage<Source>,
+) -> Handle<Source> {
+    if path.ends_with(".wav") {
+        loader.load(path, WavFormat, progress, storage)
+    } else {
+        loader.load(path, OggFormat, progress, storage)
+    }
+}
+
+/// A positional sound placed on a node.
+///
+/// Plays through a spatial sink of its own rather than an `AudioEmitter`, so it can be ducked by `AmbientDucking`.
+#[derive(Component)]
+#[storage(HashMapStorage)]
+pub struct Emitter {
+    sound: Handle<Source>,
+    looping: bool,
+    radius: f32,
+    sink: Option<AmbientSink>,
+}
+
+/// How often the low-pass filter of a playing sound picks up the current cutoff.
+const FILTER_PERIOD: Duration = Duration::from_millis(20);
+/// Fraction of the radius of an emitter over which it fades out toward the edge.
+const FADE_WIDTH: f32 = 0.2;
+
+impl Emitter {
+    /// Volume at `distance` from the listener: full inside the radius, fading to silence at its edge.
+    fn attenuation(&self, distance: f32) -> f32 {
+        let width = (FADE_WIDTH * self.radius).max(f32::EPSILON);
+        ((self.radius - distance) / width).max(0.0).min(1.0)
+    }
+}
+
+/// The spatial sink of an emitter, with the cutoff of its low-pass filter shared with the audio thread.
+struct AmbientSink {
+    sink: SpatialSink,
+    cutoff: Arc<AtomicU32>,
+}
+
+impl AmbientSink {
+    fn new(device: &Device, position: Point3<f32>, ears: &Ears) -> Self {
+        let sink = SpatialSink::new(device, position.coords.into(), ears.left.coords.into(), ears.right.coords.into());
+        let cutoff = Arc::new(AtomicU32::new(OPEN_CUTOFF as u32));
+        AmbientSink { sink, cutoff }
+    }
+
+    /// Queues `source` through the low-pass filter; returns false if the sound cannot be decoded.
+    fn append(&self, source: &Source) -> bool {
+        let decoder = match Decoder::new(Cursor::new(source.clone())) {
+            Ok(decoder) => decoder,
+            Err(_) => return false,
+        };
+
+        // Keep the cutoff below the Nyquist frequency of the sound, where the filter is well defined.
+        let limit = decoder.sample_rate() * 9 / 20;
+        let cutoff = self.cutoff.clone();
+        let filtered = decoder
+            .convert_samples::<f32>()
+            .low_pass(cutoff.load(Ordering::Relaxed).min(limit))
+            .periodic_access(FILTER_PERIOD, move |filter| {
+                filter.to_low_pass(cutoff.load(Ordering::Relaxed).min(limit))
+            });
+        self.sink.append(filtered);
+        true
+    }
+
+    fn update(&self, position: Point3<f32>, ears: &Ears, volume: f32, cutoff: f32) {
+        self.sink.set_emitter_position(position.coords.into());
+        self.sink.set_left_ear_position(ears.left.coords.into());
+        self.sink.set_right_ear_position(ears.right.coords.into());
+        self.sink.set_volume(volume);
+        self.cutoff.store(cutoff as u32, Ordering::Relaxed);
+    }
+}
+
+/// Global positions of the ears of the listener.
+struct Ears {
+    left: Point3<f32>,
+    right: Point3<f32>,
+}
+
+impl Ears {
+    fn center(&self) -> Point3<f32> {
+        Point3::from((self.left.coords + self.right.coords) / 2.0)
+    }
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AudioEmitterPrefab {
+    /// Path of the sound asset, either `.ogg` or `.wav`; the node gets no emitter if empty.
+    pub sound: String,
+    #[serde(rename = "loop")]
+    pub looping: bool,
+    /// The emitter plays while the listener is within this distance, fading out toward it.
+    /// It stops beyond, and starts over once the listener comes back.
+    pub radius: f32,
+
+    #[serde(skip)]
+    handle: Option<Handle<Source>>,
+}
+
+impl<'a> PrefabData<'a> for AudioEmitterPrefab {
+    type SystemData = (
+        ReadExpect<'a, Loader>,
+        Read<'a, AssetStorage<Source>>,
+        WriteStorage<'a, Emitter>,
+    );
+    type Result = ();
+
+    fn add_to_entity(
+        &self,
+        entity: Entity,
+        (_, _, emitters): &mut Self::SystemData,
+        _entities: &[Entity],
+        _children: &[Entity],
+    ) -> Result<Self::Result, Error> {
+        if let Some(sound) = &self.handle {
+            let component = Emitter {
+                sound: sound.clone(),
+                looping: self.looping,
+                radius: self.radius,
+                sink: None,
+            };
+            emitters.insert(entity, component)?;
+        }
+        Ok(())
+    }
+
+    fn load_sub_assets(
+        &mut self,
+        progress: &mut ProgressCounter,
+        (loader, storage, _): &mut Self::SystemData,
+    ) -> Result<bool, Error> {
+        // Nodes without a sound get no emitter.
+        if self.sound.is_empty() { return Ok(false); }
+        let handle = load_sound(&self.sound, loader, progress, storage);
+        self.handle.replace(handle);
+        Ok(true)
+    }
+}
+
+/// Starts emitters once their sound is loaded and the listener is within range, and stops them out of range.
+///
+/// Playing sinks follow the listener, fade with distance toward the radius and are ducked by `AmbientDucking`.
+#[derive(Default)]
+pub struct AudioEmitterSystem {
+    device: Option<Device>,
+}
+
+impl<'a> System<'a> for AudioEmitterSystem {
+    type SystemData = (
+        ReadStorage<'a, Transform>,
+        ReadStorage<'a, AudioListener>,
+        WriteStorage<'a, Emitter>,
+        Read<'a, AssetStorage<Source>>,
+        Read<'a, AmbientDucking>,
+    );
+
+    fn run(&mut self, (transforms, listeners, mut emitters, storage, ducking): Self::SystemData) {
+        let device = match &self.device {
+            Some(device) => device,
+            None => return,
+        };
+        let ears = (&transforms, &listeners)
+            .join()
+            .map(|(transform, listener)| {
+                let matrix = transform.global_matrix();
+                Ears {
+                    left: matrix.transform_point(&listener.left_ear),
+                    right: matrix.transform_point(&listener.right_ear),
+                }
+            })
+            .next();
+        let ears = match ears {
+            Some(ears) => ears,
+            None => return,
+        };
+
+        for (transform, emitter) in (&transforms, &mut emitters).join() {
+            let position = transform.global_position();
+            let distance = (ears.center() - position).norm();
+            if distance > emitter.radius {
+                // Dropping the sink stops the sound, so it starts over when the listener comes back.
+                emitter.sink = None;
+                continue;
+            }
+
+            let volume = ducking.volume() * emitter.attenuation(distance);
+            let cutoff = ducking.cutoff();
+            if let Some(sink) = &emitter.sink {
+                sink.update(position, &ears, volume, cutoff);
+                if emitter.looping && sink.sink.empty() {
+                    if let Some(source) = storage.get(&emitter.sound) {
+                        sink.append(source);
+                    }
+                }
+            } else if let Some(source) = storage.get(&emitter.sound) {
+                let sink = AmbientSink::new(device, position, &ears);
+                sink.update(position, &ears, volume, cutoff);
+                if sink.append(source) {
+                    emitter.sink = Some(sink);
+                }
+            }
+        }
+    }
+
+    fn setup(&mut self, world: &mut World) {
+        Self::SystemData::setup(world);
+        self.device = rodio::default_output_device();
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum Mood {
+    Idle,
+    Walk,
+    Sprint,
+}
+
+/// Sounds made by a creature depending on how it moves, following the `Stride` of its body.
+#[derive(Debug, Clone, Component)]
+#[storage(HashMapStorage)]
+pub struct Vocalization {
+    idle: Vec<Handle<Source>>,
+    sprint: Vec<Handle<Source>>,
+    cooldown: [f32; 2],
+    sprint_factor: f32,
+    mood: Mood,
+    timer: f32,
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct VocalizationPrefab {
+    /// Sounds picked at random when standing still.
+    pub idle: Vec<String>,
+    /// Sounds picked at random when running close to the speed limit.
+    pub sprint: Vec<String>,
+    /// Range of seconds to wait between two sounds.
+    pub cooldown: [f32; 2],
+    /// Fraction of the maximum speed above which the creature is sprinting.
+    pub sprint_factor: f32,
+
+    #[serde(skip)]
+    handles: Option<(Vec<Handle<Source>>, Vec<Handle<Source>>)>,
+}
+
+impl<'a> PrefabData<'a> for VocalizationPrefab {
+    type SystemData = (
+        ReadExpect<'a, Loader>,
+        Read<'a, AssetStorage<Source>>,
+        WriteStorage<'a, Vocalization>,
+        WriteStorage<'a, AudioEmitter>,
+        Write<'a, Random>,
+        WriteStorage<'a, Seed>,
+    );
+    type Result = ();
+
+    fn add_to_entity(
+        &self,
+        entity: Entity,
+        (_, _, vocalizations, audio_emitters, random, seeds): &mut Self::SystemData,
+        _entities: &[Entity],
+        _children: &[Entity],
+    ) -> Result<Self::Result, Error> {
+        if let Some((idle, sprint)) = &self.handles {
+            let component = Vocalization {
+                idle: idle.clone(),
+                sprint: sprint.clone(),
+                cooldown: self.cooldown,
+                sprint_factor: self.sprint_factor,
+                mood: Mood::Walk,
+                timer: 0.0,
+            };
+            vocalizations.insert(entity, component)?;
+            Seed::assign(entity, random, seeds)?;
+            if !audio_emitters.contains(entity) {
+                audio_emitters.insert(entity, AudioEmitter::default())?;
+            }
+        }
+        Ok(())
+    }
+
+    fn load_sub_assets(
+        &mut self,
+        progress: &mut ProgressCounter,
+        (loader, storage, _, _, _, _): &mut Self::SystemData,
+    ) -> Result<bool, Error> {
+        let mut load = |paths: &[String]| paths
+            .iter()
+            .map(|path| load_sound(path, loader, progress, storage))
+            .collect::<Vec<_>>();
+        let idle = load(&self.idle);
+        let sprint = load(&self.sprint);
+        self.handles.replace((idle, sprint));
+        Ok(true)
+    }
+}
+
+/// Plays random idle or sprint sounds, waiting a random cooldown in between.
+///
+/// Draws from the `Seed` of each creature, so the sequence is reproducible.
+#[derive(Default, SystemDesc)]
+pub struct VocalizationSystem;
+
+impl<'a> System<'a> for VocalizationSystem {
+    type SystemData = (
+        ReadStorage<'a, Stride>,
+        WriteStorage<'a, Vocalization>,
+        WriteStorage<'a, AudioEmitter>,
+        WriteStorage<'a, Seed>,
+        Read<'a, AssetStorage<Source>>,
+        Read<'a, Time>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (strides, mut vocalizations, mut audio_emitters, mut seeds, storage, time) = data;
+        for (stride, vocalization, audio_emitter, rng) in (&strides, &mut vocalizations, &mut audio_emitters, &mut seeds).join() {
+            let speed = stride.speed();
+            let [_, max_speed] = stride.speed_limit();
+            let mood = if speed < 0.01 {
+                Mood::Idle
+            } else if speed > vocalization.sprint_factor * max_speed {
+                Mood::Sprint
+            } else {
+                Mood::Walk
+            };
+
+            let [min, max] = vocalization.cooldown;
+            if mood != vocalization.mood {
+                // Wait a while before reacting to the new state.
+                vocalization.mood = mood;
+                vocalization.timer = rng.gen_range(min, max.max(min + f32::EPSILON));
+                continue;
+            }
+
+            vocalization.timer -= time.delta_seconds();
+            if vocalization.timer > 0.0 { continue; }
+
+            let sounds = match mood {
+                Mood::Idle => &vocalization.idle,
+                Mood::Sprint => &vocalization.sprint,
+                Mood::Walk => continue,
+            };
+            if let Some(source) = sounds
+                .choose(rng)
+                .and_then(|sound| storage.get(sound)) {
+                let _ = audio_emitter.play(source);
+            }
+            vocalization.timer = rng.gen_range(min, max.max(min + f32::EPSILON));
+        }
+    }
+}
+
+/// A region of ground whose steps sound alike, e.g. `"grass"` or `"gravel"`, centered at its entity.
+///
+/// Regions are treated as axis aligned regardless of the rotation of their entity;
+/// where they overlap, the smallest one wins.
+#[derive(Debug, Clone, Serialize, Deserialize, Component)]
+#[storage(HashMapStorage)]
+pub struct Surface {
+    pub tag: String,
+    /// Size of the region along x and z.
+    pub extent: [f32; 2],
+}
+
+impl Surface {
+    fn contains(&self, center: &Point3<f32>, point: &Point3<f32>) -> bool {
+        let [x, z] = self.extent;
+        (point.x - center.x).abs() <= x / 2.0 && (point.z - center.z).abs() <= z / 2.0
+    }
+}
+
+impl<'a> PrefabData<'a> for Surface {
+    type SystemData = WriteStorage<'a, Surface>;
+    type Result = ();
+
+    fn add_to_entity(
+        &self,
+        entity: Entity,
+        data: &mut Self::SystemData,
+        _entities: &[Entity],
+        _children: &[Entity],
+    ) -> Result<Self::Result, Error> {
+        data.insert(entity, self.clone()).map(|_| ()).map_err(Into::into)
+    }
+}
+
+/// The tag of steps on ground outside every `Surface`.
+pub const DEFAULT_SURFACE: &str = "default";
+/// The tag of steps landing under water, whatever the ground below.
+pub const WATER_SURFACE: &str = "water";
+
+/// Step sounds of a creature by the surface its feet land on.
+#[derive(Debug, Clone, Component)]
+#[storage(HashMapStorage)]
+pub struct Footsteps {
+    sounds: HashMap<String, Vec<Handle<Source>>>,
+    loud_speed: f32,
+    loud_impact: f32,
+    min_volume: f32,
+}
+
+impl Footsteps {
+    /// The sounds of `tag`, or of the default surface if it has none.
+    fn sounds(&self, tag: &str) -> Option<&[Handle<Source>]> {
+        self.sounds
+            .get(tag)
+            .filter(|sounds| !sounds.is_empty())
+            .or_else(|| self.sounds.get(DEFAULT_SURFACE))
+            .map(Vec::as_slice)
+    }
+}
+
+/// Step sounds of a creature, set on the node of its body.
+///
+/// Emitters play every sound at the same volume, so each surface lists its sounds from the softest
+/// to the loudest and the speed or the impact of a foot, whichever is louder, picks among them.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FootstepPrefab {
+    /// Sounds by surface tag; `"default"` for ground outside every surface and `"water"` for steps under water.
+    pub sounds: HashMap<String, Vec<String>>,
+    /// Impact speed at which the loudest sound plays.
+    pub loud_speed: f32,
+    /// Landing impact at which the loudest sound plays, so heavy landings are loud even at low speed.
+    pub loud_impact: f32,
+    /// Fraction of the loudest step below which steps are silent.
+    pub min_volume: f32,
+
+    #[serde(skip)]
+    handles: Option<HashMap<String, Vec<Handle<Source>>>>,
+}
+
+impl Default for FootstepPrefab {
+    fn default() -> Self {
+        FootstepPrefab {
+            sounds: HashMap::new(),
+            loud_speed: 2.0,
+            loud_impact: 1.0,
+            min_volume: 0.1,
+            handles: None,
+        }
+    }
+}
+
+impl<'a> PrefabData<'a> for FootstepPrefab {
+    type SystemData = (
+        ReadExpect<'a, Loader>,
+        Read<'a, AssetStorage<Source>>,
+        WriteStorage<'a, Footsteps>,
+    );
+    type Result = ();
+
+    fn add_to_entity(
+        &self,
+        entity: Entity,
+        (_, _, footsteps): &mut Self::SystemData,
+        _entities: &[Entity],
+        _children: &[Entity],
+    ) -> Result<Self::Result, Error> {
+        if let Some(sounds) = &self.handles {
+            let component = Footsteps {
+                sounds: sounds.clone(),
+                loud_speed: self.loud_speed,
+                loud_impact: self.loud_impact,
+                min_volume: self.min_volume,
+            };
+            footsteps.insert(entity, component)?;
+        }
+        Ok(())
+    }
+
+    fn load_sub_assets(
+        &mut self,
+        progress: &mut ProgressCounter,
+        (loader, storage, _): &mut Self::SystemData,
+    ) -> Result<bool, Error> {
+        let handles = self.sounds
+            .iter()
+            .map(|(tag, paths)| {
+                let sounds = paths
+                    .iter()
+                    .map(|path| load_sound(path, loader, progress, storage))
+                    .collect();
+                (tag.clone(), sounds)
+            })
+            .collect();
+        self.handles.replace(handles);
+        Ok(true)
+    }
+}
+
+/// Plays a step sound at every foot landing on the ground, picked by the surface and how hard the foot lands.
+///
+/// Feet get an `AudioEmitter` of their own the first time they land, so steps are heard where they are.
+#[derive(Default)]
+pub struct FootstepAudioSystem {
+    reader_id: Option<ReaderId<LocomotionEvent>>,
+}
+
+impl FootstepAudioSystem {
+    fn surface<'s>(
+        position: &Point3<f32>,
+        transforms: &ReadStorage<'_, Transform>,
+        surfaces: &'s ReadStorage<'_, Surface>,
+        buoyancy: &Buoyancy,
+    ) -> &'s str {
+        if buoyancy.depth(position).is_some() { return WATER_SURFACE; }
+
+        (transforms, surfaces)
+            .join()
+            .filter(|(transform, surface)| surface.contains(&transform.global_position(), position))
+            .min_by(|(_, a), (_, b)| {
+                let area = |surface: &Surface| surface.extent[0] * surface.extent[1];
+                area(a).partial_cmp(&area(b)).unwrap_or(std::cmp::Ordering::Equal)
+            })
+            .map_or(DEFAULT_SURFACE, |(_, surface)| surface.tag.as_str())
+    }
+
+    fn play(
+        footsteps: &Footsteps,
+        foot: Entity,
+        tag: &str,
+        speed: f32,
+        impact: f32,
+        audio_emitters: &mut WriteStorage<'_, AudioEmitter>,
+        storage: &AssetStorage<Source>,
+    ) -> Option<()> {
+        let speed = speed / footsteps.loud_speed.max(f32::EPSILON);
+        let impact = impact / footsteps.loud_impact.max(f32::EPSILON);
+        let volume = speed.max(impact).min(1.0);
+        if volume < footsteps.min_volume { return Some(()); }
+
+        let sounds = footsteps.sounds(tag)?;
+        let layer = ((volume * sounds.len() as f32) as usize).min(sounds.len() - 1);
+        let source = storage.get(&sounds[layer])?;
+        let audio_emitter = audio_emitters
+            .entry(foot)
+            .ok()?
+            .or_insert_with(AudioEmitter::default);
+        audio_emitter.play(source).ok()
+    }
+}
+
+impl<'a> System<'a> for FootstepAudioSystem {
+    type SystemData = (
+        ReadStorage<'a, Transform>,
+        ReadStorage<'a, Surface>,
+        ReadStorage<'a, Footsteps>,
+        WriteStorage<'a, AudioEmitter>,
+        Read<'a, Buoyancy>,
+        Read<'a, AssetStorage<Source>>,
+        Read<'a, EventChannel<LocomotionEvent>>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (transforms, surfaces, footsteps, mut audio_emitters, buoyancy, storage, events) = data;
+        let reader_id = self.reader_id
+            .as_mut()
+            .expect("`FootstepAudioSystem::setup` was not called before `FootstepAudioSystem::run`");
+
+        for event in events.read(reader_id) {
+            if let LocomotionEvent::FootDown { body, foot, position, speed, impact, .. } = *event {
+                let footsteps = match footsteps.get(body) {
+                    Some(footsteps) => footsteps,
+                    None => continue,
+                };
+                let tag = Self::surface(&position, &transforms, &surfaces, &buoyancy);
+                let _ = Self::play(footsteps, foot, tag, speed, impact, &mut audio_emitters, &storage);
+            }
+        }
+    }
+
+    fn setup(&mut self, world: &mut World) {
+        Self::SystemData::setup(world);
+        self.reader_id = Some(world.fetch_mut::<EventChannel<LocomotionEvent>>().register_reader());
+    }
+}
+
+/// Cutoff of the low-pass filter on ambient sounds while the listener is at rest, above what is heard.
+const OPEN_CUTOFF: f32 = 20_000.0;
+
+/// Ducking of the ambient `Emitter`s while the listener moves fast: they get quieter and muffled by a low-pass filter.
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AmbientDucking {
+    /// Listener speed at which ambient sounds are ducked the most.
+    pub max_speed: f32,
+    /// Volume of ambient sounds at `max_speed`.
+    pub min_volume: f32,
+    /// Cutoff of the low-pass filter in hertz at `max_speed`.
+    pub min_cutoff: f32,
+    /// How fast the ducking follows the speed.
+    pub stiffness: f32,
+    #[serde(skip)]
+    amount: f32,
+}
+
+impl Default for AmbientDucking {
+    fn default() -> Self {
+        AmbientDucking {
+            max_speed: 8.0,
+            min_volume: 0.4,
+            min_cutoff: 800.0,
+            stiffness: 4.0,
+            amount: 0.0,
+        }
+    }
+}
+
+impl AmbientDucking {
+    /// The current volume of ambient sounds.
+    pub fn volume(&self) -> f32 {
+        1.0 + (self.min_volume - 1.0) * self.amount
+    }
+
+    /// The current cutoff of the low-pass filter in hertz, falling geometrically so it sweeps evenly by ear.
+    pub fn cutoff(&self) -> f32 {
+        OPEN_CUTOFF * (self.min_cutoff / OPEN_CUTOFF).powf(self.amount)
+    }
+}
+
+/// Keeps the audio listener on the active camera and sets the `AmbientDucking` by listener speed.
+#[derive(Default)]
+pub struct ListenerSystem {
+    previous: Option<(Entity, Point3<f32>)>,
+}
+
+impl<'a> System<'a> for ListenerSystem {
+    type SystemData = (
+        Entities<'a>,
+        ReadStorage<'a, Transform>,
+        ReadStorage<'a, Camera>,
+        WriteStorage<'a, AudioListener>,
+        Read<'a, ActiveCamera>,
+        Write<'a, AmbientDucking>,
+        Read<'a, Time>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            entities,
+            transforms,
+            cameras,
+            mut listeners,
+            active_camera,
+            mut ducking,
+            time,
+        ) = data;
+
+        let camera = active_camera.entity.or_else(|| {
+            (&*entities, &cameras)
+                .join()
+                .map(|(entity, _)| entity)
+                .next()
+        });
+        let camera = match camera {
+            Some(camera) => camera,
+            None => return,
+        };
+
+        if !listeners.contains(camera) {
+            listeners.clear();
+            let _ = listeners.insert(camera, AudioListener::default());
+        }
+
+        let position = match transforms.get(camera) {
+            Some(transform) => transform.global_position(),
+            None => return,
+        };
+        let delta_seconds = time.delta_seconds();
+        let speed = match self.previous.replace((camera, position)) {
+            Some((previous, last)) if previous == camera && delta_seconds > 0.0 => {
+                (position - last).norm() / delta_seconds
+            }
+            _ => 0.0,
+        };
+
+        let target = (speed / ducking.max_speed).min(1.0);
+        let decay = 1.0 - (-ducking.stiffness * delta_seconds).exp();
+        ducking.amount += decay * (target - ducking.amount);
+    }
+}
@@ -0,0 +1,68 @@
+use amethyst::{
+    core::{Time, Transform},
+    derive::SystemDesc,
+    ecs::prelude::*,
+};
+
+use amethyst_gltf::GltfClip;
+
+/// Bakes the motion of a scene into clips for the glTF export, sampled by the `ClipRecorderSystem`.
+#[derive(Debug, Default)]
+pub struct ClipRecorder {
+    recording: Option<GltfClip>,
+    elapsed: f32,
+    /// Clips stopped so far, written with the next export.
+    pub baked: Vec<GltfClip>,
+}
+
+impl ClipRecorder {
+    /// Drop what was recorded so far and record the local transforms of `nodes` at `frame_rate` frames per second.
+    pub fn start(&mut self, name: &str, nodes: Vec<Entity>, frame_rate: f32) {
+        let tracks = nodes.into_iter().map(|node| (node, vec![])).collect();
+        self.recording = Some(GltfClip { name: name.to_string(), frame_rate, tracks });
+        self.elapsed = 0.0;
+    }
+
+    /// Stop recording and keep the clip for export, returning its number of frames.
+    pub fn stop(&mut self) -> Option<usize> {
+        let clip = self.recording.take()?;
+        let frames = clip.tracks.first().map_or(0, |(_, frames)| frames.len());
+        self.baked.push(clip);
+        Some(frames)
+    }
+}
+
+/// Samples the nodes the `ClipRecorder` is started on.
+#[derive(Default, SystemDesc)]
+pub struct ClipRecorderSystem;
+
+impl<'a> System<'a> for ClipRecorderSystem {
+    type SystemData = (
+        ReadStorage<'a, Transform>,
+        Write<'a, ClipRecorder>,
+        Read<'a, Time>,
+    );
+
+    fn run(&mut self, (transforms, mut recorder, time): Self::SystemData) {
+        let recorder = &mut *recorder;
+        let clip = match recorder.recording.as_mut() {
+            Some(clip) => clip,
+            None => return,
+        };
+        let period = 1.0 / clip.frame_rate.max(1.0);
+        recorder.elapsed += time.delta_seconds();
+
+        // Frames dropped by a slow update repeat the sample, keeping the clip at its rate.
+        while recorder.elapsed >= period {
+            recorder.elapsed -= period;
+            for (node, frames) in clip.tracks.iter_mut() {
+                let frame = transforms
+                    .get(*node)
+                    .cloned()
+                    .or_else(|| frames.last().cloned())
+                    .unwrap_or_default();
+                frames.push(frame);
+            }
+        }
+    }
+}
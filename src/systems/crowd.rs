@@ -0,0 +1,151 @@
+use std::f32::consts::PI;
+
+use amethyst::{
+    core::Transform,
+    derive::SystemDesc,
+    ecs::prelude::*,
+    error::{Error, format_err},
+    renderer::{ActiveCamera, Camera},
+};
+use rand::{Rng, seq::SliceRandom};
+use serde::{Deserialize, Serialize};
+
+use ceramic_animation::{
+    systems::{
+        animal::LocomotionLod,
+        behavior::{BehaviorPrefab, Temperament},
+    },
+    utils::{random::Random, transform::TransformTrait},
+};
+
+use crate::{
+    scene::{Placement, SceneInstance, SceneManager},
+    species::SpeciesPacks,
+};
+
+fn default_wander() -> f32 { 8.0 }
+
+fn default_per_frame() -> usize { 2 }
+
+/// How the members of a crowd are spread out.
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+pub enum Layout {
+    /// Rows of `spacing` apart, as square as the count allows.
+    Grid { spacing: f32 },
+    /// Uniformly within `radius`.
+    Random { radius: f32 },
+}
+
+/// A crowd of wandering animals, e.g. for stress testing.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CrowdConfig {
+    pub count: usize,
+    /// Species picked at random for each member.
+    pub species: Vec<String>,
+    pub layout: Layout,
+    #[serde(default)]
+    pub center: [f32; 3],
+    /// Radius around its spawn point each member wanders in.
+    #[serde(default = "default_wander")]
+    pub wander: f32,
+    /// Members spawned per frame, so spawning does not stall a single frame.
+    #[serde(default = "default_per_frame")]
+    pub per_frame: usize,
+}
+
+impl CrowdConfig {
+    fn position(&self, index: usize, rng: &mut Random) -> [f32; 3] {
+        let [x, y, z] = self.center;
+        match self.layout {
+            Layout::Grid { spacing } => {
+                let columns = (self.count as f32).sqrt().ceil().max(1.0) as usize;
+                let rows = (self.count + columns - 1) / columns;
+                let column = (index % columns) as f32 - (columns - 1) as f32 / 2.0;
+                let row = (index / columns) as f32 - (rows - 1) as f32 / 2.0;
+                [x + column * spacing, y, z + row * spacing]
+            }
+            Layout::Random { radius } => {
+                let distance = radius * rng.gen::<f32>().sqrt();
+                let angle = rng.gen_range(-PI, PI);
+                [x + distance * angle.cos(), y, z + distance * angle.sin()]
+            }
+        }
+    }
+}
+
+/// Crowd members waiting to be spawned by the `CrowdSpawnSystem`.
+#[derive(Debug, Default)]
+pub struct CrowdSpawner {
+    pending: Vec<SceneInstance>,
+    per_frame: usize,
+}
+
+impl CrowdSpawner {
+    /// Queue the members of a crowd, drawing their species and placement from `random`.
+    ///
+    /// Members of the same species share one prefab, and with it their meshes and materials.
+    pub fn new(config: &CrowdConfig, packs: &SpeciesPacks, random: &mut Random) -> Result<Self, Error> {
+        let behavior = BehaviorPrefab {
+            temperament: Temperament::Wander { radius: config.wander },
+            sight: 0.0,
+        };
+        let mut pending = Vec::with_capacity(config.count);
+        for index in 0..config.count {
+            let name = config.species
+                .choose(random)
+                .ok_or_else(|| format_err!("A crowd needs at least one species"))?;
+            let mut instance = packs.get(name)?.with_behavior(&behavior)?;
+            let translation = config.position(index, random);
+            let yaw = random.gen_range(-PI, PI);
+            instance.placement = Some(Placement { translation, yaw });
+            pending.push(instance);
+        }
+        Ok(CrowdSpawner { pending, per_frame: config.per_frame.max(1) })
+    }
+}
+
+/// Spawns a few queued crowd members each frame.
+#[derive(Default, SystemDesc)]
+pub struct CrowdSpawnSystem;
+
+impl<'a> System<'a> for CrowdSpawnSystem {
+    type SystemData = (
+        Write<'a, CrowdSpawner>,
+        Read<'a, LazyUpdate>,
+    );
+
+    fn run(&mut self, (mut spawner, lazy): Self::SystemData) {
+        let count = spawner.per_frame.min(spawner.pending.len());
+        for instance in spawner.pending.drain(..count) {
+            lazy.exec_mut(move |world| {
+                SceneManager::load_shared(world, instance, ());
+            });
+        }
+    }
+}
+
+/// Focuses the `LocomotionLod` on the active camera.
+#[derive(Default, SystemDesc)]
+pub struct LodFocusSystem;
+
+impl<'a> System<'a> for LodFocusSystem {
+    type SystemData = (
+        Entities<'a>,
+        ReadStorage<'a, Camera>,
+        ReadStorage<'a, Transform>,
+        Read<'a, ActiveCamera>,
+        Write<'a, LocomotionLod>,
+    );
+
+    fn run(&mut self, (entities, cameras, transforms, active_camera, mut lod): Self::SystemData) {
+        let camera = active_camera.entity.or_else(|| {
+            (&*entities, &cameras)
+                .join()
+                .map(|(entity, _)| entity)
+                .next()
+        });
+        lod.focus = camera
+            .and_then(|camera| transforms.get(camera))
+            .map(TransformTrait::global_position);
+    }
+}
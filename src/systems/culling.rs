@@ -0,0 +1,176 @@
+use std::cmp::Ordering;
+
+use amethyst::{
+    assets::Handle,
+    core::{
+        Hidden,
+        HiddenPropagate,
+        math::{Matrix4, Point3, Vector4},
+        transform::{Parent, Transform},
+    },
+    ecs::prelude::*,
+    renderer::{
+        ActiveCamera,
+        Camera,
+        skinning::JointTransforms,
+        transparent::Transparent,
+        types::Mesh,
+        visibility::{BoundingSphere, Visibility},
+    },
+};
+
+use ceramic_animation::utils::transform::TransformTrait;
+
+/// Counts of the last culling pass, printed with `F7`.
+#[derive(Debug, Default, Copy, Clone)]
+pub struct CullingStats {
+    /// Meshes that could be drawn.
+    pub meshes: usize,
+    /// Meshes drawn after culling.
+    pub visible: usize,
+    /// Nodes whose bounds are outside the frustum, hiding their whole subtree.
+    pub culled_subtrees: usize,
+}
+
+/// Turns culling off while active, so every mesh that isn't hidden is drawn.
+///
+/// Set by the load state while it warms up, so meshes and textures are uploaded before the game starts.
+#[derive(Debug, Default, Copy, Clone)]
+pub struct WarmUp {
+    pub active: bool,
+}
+
+/// Clip planes of the active camera, facing inwards.
+struct Frustum {
+    planes: Vec<Vector4<f32>>,
+}
+
+impl Frustum {
+    /// The side planes and the plane through the camera from a view projection matrix.
+    ///
+    /// Near and far are left out, as they depend on the depth convention of the projection.
+    fn new(matrix: &Matrix4<f32>) -> Self {
+        let row = |index: usize| matrix.row(index).transpose();
+        let (x, y, w) = (row(0), row(1), row(3));
+        let planes = vec![&w + &x, &w - &x, &w + &y, &w - &y, w.clone()]
+            .into_iter()
+            .map(|plane| plane / plane.xyz().norm())
+            .collect();
+        Frustum { planes }
+    }
+
+    fn contains(&self, center: &Point3<f32>, radius: f32) -> bool {
+        self.planes
+            .iter()
+            .all(|plane| plane.xyz().dot(&center.coords) + plane.w >= -radius)
+    }
+}
+
+/// Culls meshes whose node, or any node above it, has bounds outside the view of the active camera.
+///
+/// The result goes into the `Visibility` resource read by the render passes, which then no longer
+/// check `Hidden` themselves; hidden meshes are left out here. Skinned meshes are never culled,
+/// as their joints move them away from the bounds of their node.
+#[derive(Default)]
+pub struct FrustumCullingSystem {
+    outside: BitSet,
+}
+
+impl FrustumCullingSystem {
+    fn is_culled(&self, entity: Entity, parents: &ReadStorage<'_, Parent>) -> bool {
+        let mut current = Some(entity);
+        while let Some(entity) = current {
+            if self.outside.contains(entity.id()) { return true; }
+            current = parents.get(entity).map(|parent| parent.entity);
+        }
+        false
+    }
+}
+
+impl<'a> System<'a> for FrustumCullingSystem {
+    type SystemData = (
+        Entities<'a>,
+        ReadStorage<'a, Transform>,
+        ReadStorage<'a, Parent>,
+        ReadStorage<'a, BoundingSphere>,
+        ReadStorage<'a, Handle<Mesh>>,
+        ReadStorage<'a, JointTransforms>,
+        ReadStorage<'a, Transparent>,
+        ReadStorage<'a, Hidden>,
+        ReadStorage<'a, HiddenPropagate>,
+        ReadStorage<'a, Camera>,
+        Read<'a, ActiveCamera>,
+        Read<'a, WarmUp>,
+        Write<'a, Visibility>,
+        Write<'a, CullingStats>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            entities,
+            transforms,
+            parents,
+            spheres,
+            meshes,
+            joints,
+            transparents,
+            hiddens,
+            hidden_propagates,
+            cameras,
+            active_camera,
+            warm_up,
+            mut visibility,
+            mut stats,
+        ) = data;
+
+        let camera = active_camera.entity.or_else(|| {
+            (&*entities, &cameras)
+                .join()
+                .map(|(entity, _)| entity)
+                .next()
+        });
+        let view = camera.and_then(|camera| cameras.get(camera).zip(transforms.get(camera)));
+
+        self.outside.clear();
+        match view {
+            Some((camera, transform)) if !warm_up.active => {
+                let ref frustum = Frustum::new(&(camera.as_matrix() * transform.global_view_matrix()));
+                for (entity, sphere, transform) in (&*entities, &spheres, &transforms).join() {
+                    let matrix = transform.global_matrix();
+                    let ref center = matrix.transform_point(&sphere.center);
+                    let scale = (0..3)
+                        .map(|index| matrix.column(index).xyz().norm())
+                        .fold(0.0, f32::max);
+                    if !frustum.contains(center, sphere.radius * scale) {
+                        self.outside.add(entity.id());
+                    }
+                }
+            }
+            _ => {}
+        }
+
+        visibility.visible_unordered.clear();
+        visibility.visible_ordered.clear();
+        let mut transparent = vec![];
+        let mut counted = CullingStats { culled_subtrees: (&self.outside).join().count(), ..Default::default() };
+        for (entity, _, transform, joint, _, _) in (&*entities, &meshes, &transforms, joints.maybe(), !&hiddens, !&hidden_propagates).join() {
+            counted.meshes += 1;
+            if joint.is_none() && self.is_culled(entity, &parents) { continue; }
+            counted.visible += 1;
+            if transparents.contains(entity) {
+                transparent.push((entity, transform.global_position()));
+            } else {
+                visibility.visible_unordered.add(entity.id());
+            }
+        }
+
+        // Transparent meshes are drawn back to front.
+        if let Some((_, transform)) = view {
+            let ref eye = transform.global_position();
+            let distance = |position: &Point3<f32>| (position - eye).norm_squared();
+            transparent.sort_by(|(_, a), (_, b)| distance(b).partial_cmp(&distance(a)).unwrap_or(Ordering::Equal));
+        }
+        visibility.visible_ordered.extend(transparent.into_iter().map(|(entity, _)| entity));
+        *stats = counted;
+    }
+}
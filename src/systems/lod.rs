@@ -0,0 +1,74 @@
+use amethyst::{
+    core::{Hidden, Transform},
+    ecs::prelude::*,
+    renderer::{ActiveCamera, Camera},
+};
+use amethyst_gltf::GltfLodGroup;
+
+use ceramic_animation::utils::transform::TransformTrait;
+
+/// When levels of detail loaded from `_LOD<n>` nodes switch.
+#[derive(Debug, Copy, Clone)]
+pub struct MeshLod {
+    /// Distance from the camera over which each level is shown, in units of the scale of the node.
+    pub distance_per_level: f32,
+}
+
+impl Default for MeshLod {
+    fn default() -> Self {
+        MeshLod { distance_per_level: 25.0 }
+    }
+}
+
+/// Shows the level of each `GltfLodGroup` that fits its distance to the active camera, hiding the others.
+///
+/// Must run before frustum culling, which leaves hidden meshes out.
+#[derive(Default)]
+pub struct MeshLodSystem;
+
+impl<'a> System<'a> for MeshLodSystem {
+    type SystemData = (
+        Entities<'a>,
+        WriteStorage<'a, GltfLodGroup>,
+        WriteStorage<'a, Hidden>,
+        ReadStorage<'a, Transform>,
+        ReadStorage<'a, Camera>,
+        Read<'a, ActiveCamera>,
+        Read<'a, MeshLod>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (entities, mut groups, mut hiddens, transforms, cameras, active_camera, settings) = data;
+
+        let camera = active_camera.entity.or_else(|| {
+            (&*entities, &cameras)
+                .join()
+                .map(|(entity, _)| entity)
+                .next()
+        });
+        let eye = match camera.and_then(|camera| transforms.get(camera)) {
+            Some(transform) => transform.global_position(),
+            None => return,
+        };
+
+        for (group, transform) in (&mut groups, &transforms).join() {
+            if group.levels.is_empty() { continue; }
+
+            let matrix = transform.global_matrix();
+            let scale = (0..3)
+                .map(|index| matrix.column(index).xyz().norm())
+                .fold(0.0, f32::max);
+            let distance = (transform.global_position() - eye).norm() / (settings.distance_per_level * scale).max(f32::EPSILON);
+            let level = (distance as usize).min(group.levels.len() - 1);
+            if level == group.current { continue; }
+
+            for &mesh in &group.levels[group.current] {
+                let _ = hiddens.insert(mesh, Hidden);
+            }
+            for &mesh in &group.levels[level] {
+                hiddens.remove(mesh);
+            }
+            group.current = level;
+        }
+    }
+}
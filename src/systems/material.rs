@@ -0,0 +1,117 @@
+use amethyst::{
+    assets::{AssetStorage, Handle, Loader},
+    ecs::{prelude::*, SystemData},
+    renderer::{
+        Material,
+        palette::LinSrgba,
+        rendy::texture::palette::load_from_linear_rgba,
+        Texture,
+    },
+    shrev::{EventChannel, ReaderId},
+};
+
+use amethyst_gltf::{GltfMaterialId, GltfMeshSource};
+
+/// A material parameter to override. Each one replaces the corresponding texture with a constant.
+#[derive(Debug, Copy, Clone)]
+pub enum MaterialParameter {
+    Albedo([f32; 4]),
+    Emission([f32; 4]),
+    MetallicRoughness { metallic: f32, roughness: f32 },
+}
+
+/// Change a parameter of all entities loaded with the given Gltf material.
+///
+/// Material indices are local to their file, so the tweak names the scene file too.
+/// Send it through an `EventChannel<MaterialTweak>`, e.g. with the `material` console command.
+#[derive(Debug, Clone)]
+pub struct MaterialTweak {
+    /// Asset path of the scene file, e.g. `model/cat.glb`.
+    pub scene: String,
+    /// Index of the material in that file.
+    pub material_id: usize,
+    pub parameter: MaterialParameter,
+}
+
+impl MaterialTweak {
+    fn matches(&self, source: &GltfMeshSource, material_id: &GltfMaterialId) -> bool {
+        source.scene == self.scene && material_id.0 == self.material_id
+    }
+}
+
+/// Applies `MaterialTweak`s by loading a modified copy of the material and swapping it in
+/// on every entity that shares it, leaving other materials untouched.
+#[derive(Default)]
+pub struct MaterialTweakSystem {
+    reader_id: Option<ReaderId<MaterialTweak>>,
+}
+
+impl MaterialTweakSystem {
+    fn tweak(
+        tweak: &MaterialTweak,
+        loader: &Loader,
+        materials: &AssetStorage<Material>,
+        textures: &AssetStorage<Texture>,
+        sources: &ReadStorage<'_, GltfMeshSource>,
+        material_ids: &ReadStorage<'_, GltfMaterialId>,
+        handles: &mut WriteStorage<'_, Handle<Material>>,
+    ) -> Option<()> {
+        let mut material = (sources, material_ids, &*handles)
+            .join()
+            .find(|(source, material_id, _)| tweak.matches(source, material_id))
+            .and_then(|(_, _, handle)| materials.get(handle))?
+            .clone();
+
+        let constant = |[r, g, b, a]: [f32; 4]| loader.load_from_data(
+            load_from_linear_rgba(LinSrgba::new(r, g, b, a)).into(),
+            (),
+            textures,
+        );
+        match tweak.parameter {
+            MaterialParameter::Albedo(color) => material.albedo = constant(color),
+            MaterialParameter::Emission(color) => material.emission = constant(color),
+            // Gltf keeps roughness in the green and metalness in the blue channel.
+            MaterialParameter::MetallicRoughness { metallic, roughness } => {
+                material.metallic_roughness = constant([0.0, roughness, metallic, 1.0]);
+            }
+        }
+
+        let handle = loader.load_from_data(material, (), materials);
+        for (source, material_id, material) in (sources, material_ids, handles).join() {
+            if tweak.matches(source, material_id) {
+                *material = handle.clone();
+            }
+        }
+        Some(())
+    }
+}
+
+impl<'a> System<'a> for MaterialTweakSystem {
+    type SystemData = (
+        ReadExpect<'a, Loader>,
+        Read<'a, AssetStorage<Material>>,
+        Read<'a, AssetStorage<Texture>>,
+        ReadStorage<'a, GltfMeshSource>,
+        ReadStorage<'a, GltfMaterialId>,
+        WriteStorage<'a, Handle<Material>>,
+        Read<'a, EventChannel<MaterialTweak>>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (loader, materials, textures, sources, material_ids, mut handles, tweaks) = data;
+        let reader_id = self.reader_id
+            .as_mut()
+            .expect("`MaterialTweakSystem::setup` was not called before `MaterialTweakSystem::run`");
+
+        for tweak in tweaks.read(reader_id) {
+            if Self::tweak(tweak, &loader, &materials, &textures, &sources, &material_ids, &mut handles).is_none() {
+                eprintln!("No loaded material with index {} in {}", tweak.material_id, tweak.scene);
+            }
+        }
+    }
+
+    fn setup(&mut self, world: &mut World) {
+        Self::SystemData::setup(world);
+        self.reader_id = Some(world.fetch_mut::<EventChannel<MaterialTweak>>().register_reader());
+    }
+}
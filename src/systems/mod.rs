@@ -1,4 +1,12 @@
-pub mod player;
-pub mod animal;
-pub mod kinematics;
-pub mod particle;
\ No newline at end of file
+pub mod audio;
+pub mod clip;
+pub mod crowd;
+pub mod culling;
+pub mod lod;
+pub mod script;
+pub mod network;
+pub mod skin_debug;
+pub mod material;
+pub mod visibility;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod watcher;
@@ -0,0 +1,370 @@
+//! Replication of the controlled creature between two clients over UDP.
+//!
+//! Web builds have no sockets, so there only the `Replicated` marker exists, for scenes to load unchanged.
+
+#[cfg(not(target_arch = "wasm32"))]
+use std::{
+    collections::VecDeque,
+    io::ErrorKind,
+    net::{SocketAddr, UdpSocket},
+};
+
+use amethyst::{
+    assets::PrefabData,
+    derive::PrefabData,
+    ecs::{Component, prelude::*},
+    error::Error,
+};
+#[cfg(not(target_arch = "wasm32"))]
+use amethyst::core::{
+    bundle::SystemBundle,
+    math::{Complex, Quaternion, UnitQuaternion, Vector3},
+    Time,
+    Transform,
+};
+use serde::{Deserialize, Serialize};
+
+#[cfg(not(target_arch = "wasm32"))]
+use ceramic_animation::systems::{animal::Quadruped, player::Player};
+
+/// How long the receiver lags behind the sender, so there are usually two snapshots to interpolate.
+#[cfg(not(target_arch = "wasm32"))]
+const INTERPOLATION_DELAY: f64 = 0.1;
+/// Beyond this, the remote quadruped holds still rather than keep guessing.
+#[cfg(not(target_arch = "wasm32"))]
+const MAX_EXTRAPOLATION: f64 = 0.25;
+#[cfg(not(target_arch = "wasm32"))]
+const BUFFER_SIZE: usize = 32;
+/// Weight of each snapshot in the running estimate of the clock offset, which smooths out the jitter of the network.
+#[cfg(not(target_arch = "wasm32"))]
+const OFFSET_SMOOTHING: f64 = 0.1;
+/// Seconds the offset of a snapshot can be off the estimate before the sender is taken to have restarted its clock.
+#[cfg(not(target_arch = "wasm32"))]
+const OFFSET_RESET: f64 = 0.5;
+
+/// Marks the creature whose state is sent to, or driven from, the peer.
+///
+/// Companions and crowds carry a `Player` too, so the replicated one is picked by this rather than by its `Player`.
+#[derive(Debug, Default, Copy, Clone, Serialize, Deserialize, Component, PrefabData)]
+#[storage(NullStorage)]
+#[prefab(Component)]
+pub struct Replicated {}
+
+/// State of the controlled cat at one point of the sender's clock.
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+struct Snapshot {
+    time: f64,
+    linear_speed: f32,
+    movement: [f32; 3],
+    spinning: [f32; 4],
+    translation: [f32; 3],
+    rotation: [f32; 4],
+    phases: [[f32; 2]; 4],
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Snapshot {
+    fn capture(time: f64, player: &Player, transform: &Transform, quadruped: Option<&Quadruped>) -> Self {
+        let movement = player.movement();
+        let spinning = player.spinning().into_inner().coords;
+        let translation = transform.translation();
+        let rotation = transform.rotation().into_inner().coords;
+        let mut phases = [[0.0; 2]; 4];
+        if let Some(quadruped) = quadruped {
+            for (phase, signal) in phases.iter_mut().zip(quadruped.phases().iter()) {
+                *phase = [signal.re, signal.im];
+            }
+        }
+
+        Snapshot {
+            time,
+            linear_speed: player.linear_speed(),
+            movement: [movement.x, movement.y, movement.z],
+            spinning: [spinning.x, spinning.y, spinning.z, spinning.w],
+            translation: [translation.x, translation.y, translation.z],
+            rotation: [rotation.x, rotation.y, rotation.z, rotation.w],
+            phases,
+        }
+    }
+
+    fn interpolate(&self, other: &Snapshot, t: f32) -> Snapshot {
+        let lerp = |a: f32, b: f32| a + (b - a) * t;
+        let slerp = |a: [f32; 4], b: [f32; 4]| {
+            let a = to_rotation(a);
+            let b = to_rotation(b);
+            let c = a.try_slerp(&b, t, 1.0e-6).unwrap_or(b).into_inner().coords;
+            [c.x, c.y, c.z, c.w]
+        };
+
+        let mut result = *other;
+        result.time = self.time + (other.time - self.time) * t as f64;
+        result.linear_speed = lerp(self.linear_speed, other.linear_speed);
+        for i in 0..3 {
+            result.movement[i] = lerp(self.movement[i], other.movement[i]);
+            result.translation[i] = lerp(self.translation[i], other.translation[i]);
+        }
+        result.spinning = slerp(self.spinning, other.spinning);
+        result.rotation = slerp(self.rotation, other.rotation);
+        // Oscillators run on the unit circle, which a lerp between distant phases cuts inside of.
+        for (phase, (a, b)) in result.phases.iter_mut().zip(self.phases.iter().zip(other.phases.iter())) {
+            let [re, im] = [lerp(a[0], b[0]), lerp(a[1], b[1])];
+            let norm = (re * re + im * im).sqrt();
+            *phase = if norm > 1.0e-6 { [re / norm, im / norm] } else { *b };
+        }
+        result
+    }
+
+    /// Continue moving with the last known velocity.
+    fn extrapolate(&self, duration: f32) -> Snapshot {
+        let mut result = *self;
+        for i in 0..3 {
+            result.translation[i] += duration * self.linear_speed * self.movement[i];
+        }
+        result
+    }
+
+    fn apply(&self, player: &mut Player, transform: &mut Transform, quadruped: Option<&mut Quadruped>) {
+        player.set_linear_speed(self.linear_speed);
+        player.set_movement(Vector3::from(self.movement));
+        player.set_spinning(to_rotation(self.spinning));
+        *transform.translation_mut() = Vector3::from(self.translation);
+        *transform.rotation_mut() = to_rotation(self.rotation);
+        if let Some(quadruped) = quadruped {
+            let mut phases = [Complex::new(0.0, 0.0); 4];
+            for (signal, phase) in phases.iter_mut().zip(self.phases.iter()) {
+                *signal = Complex::new(phase[0], phase[1]);
+            }
+            quadruped.set_phases(phases);
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn to_rotation([x, y, z, w]: [f32; 4]) -> UnitQuaternion<f32> {
+    UnitQuaternion::from_quaternion(Quaternion::new(w, x, y, z))
+}
+
+/// Sends the state of the replicated player to a peer at a fixed rate.
+#[cfg(not(target_arch = "wasm32"))]
+pub struct ReplicationSendSystem {
+    socket: UdpSocket,
+    peer: SocketAddr,
+    interval: f32,
+    elapsed: f32,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl<'a> System<'a> for ReplicationSendSystem {
+    type SystemData = (
+        ReadStorage<'a, Replicated>,
+        ReadStorage<'a, Player>,
+        ReadStorage<'a, Transform>,
+        ReadStorage<'a, Quadruped>,
+        Read<'a, Time>,
+    );
+
+    fn run(&mut self, (replicated, players, transforms, quadrupeds, time): Self::SystemData) {
+        self.elapsed += time.delta_seconds();
+        if self.elapsed < self.interval { return; }
+        self.elapsed %= self.interval;
+
+        // Snapshots carry no id, so only one creature per client is replicated.
+        let replica = (&replicated, &players, &transforms, quadrupeds.maybe()).join().next();
+        if let Some((_, player, transform, quadruped)) = replica {
+            let snapshot = Snapshot::capture(time.absolute_time_seconds(), player, transform, quadruped);
+            match bincode::serialize(&snapshot) {
+                Ok(bytes) => if let Err(error) = self.socket.send_to(&bytes, self.peer) {
+                    eprintln!("Failed to send snapshot: {}", error);
+                },
+                Err(error) => eprintln!("Failed to serialize snapshot: {}", error),
+            }
+        }
+    }
+}
+
+/// Drives the replicated player from snapshots received from a peer.
+///
+/// Snapshots are played back `INTERPOLATION_DELAY` behind the sender and interpolated;
+/// when they stop arriving the last one is extrapolated for a short while.
+#[cfg(not(target_arch = "wasm32"))]
+pub struct ReplicationReceiveSystem {
+    socket: UdpSocket,
+    snapshots: VecDeque<Snapshot>,
+    /// Sender clock minus local clock, averaged over the recent snapshots.
+    offset: Option<f64>,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl ReplicationReceiveSystem {
+    fn receive(&mut self, now: f64) {
+        let mut buffer = [0; 512];
+        loop {
+            match self.socket.recv_from(&mut buffer) {
+                Ok((size, _)) => match bincode::deserialize::<Snapshot>(&buffer[..size]) {
+                    Ok(snapshot) => self.insert(snapshot, now),
+                    Err(error) => eprintln!("Dropped invalid snapshot: {}", error),
+                },
+                Err(ref error) if error.kind() == ErrorKind::WouldBlock => break,
+                Err(error) => {
+                    eprintln!("Failed to receive snapshot: {}", error);
+                    break;
+                }
+            }
+        }
+    }
+
+    fn insert(&mut self, snapshot: Snapshot, now: f64) {
+        let sample = snapshot.time - now;
+        let offset = match self.offset {
+            Some(offset) if (sample - offset).abs() < OFFSET_RESET => offset,
+            _ => {
+                // The first snapshot, or one from a restarted sender whose buffered snapshots no longer apply.
+                self.snapshots.clear();
+                sample
+            }
+        };
+
+        // Datagrams may arrive out of order; late ones are simply dropped.
+        if self.snapshots.back().map_or(false, |last| last.time >= snapshot.time) { return; }
+        self.offset = Some(offset + (sample - offset) * OFFSET_SMOOTHING);
+        self.snapshots.push_back(snapshot);
+        if self.snapshots.len() > BUFFER_SIZE {
+            self.snapshots.pop_front();
+        }
+    }
+
+    fn sample(&self, time: f64) -> Option<Snapshot> {
+        let index = self.snapshots.iter().position(|snapshot| snapshot.time > time);
+        match index {
+            Some(0) => self.snapshots.front().copied(),
+            Some(index) => {
+                let ref from = self.snapshots[index - 1];
+                let ref to = self.snapshots[index];
+                let t = (time - from.time) / (to.time - from.time);
+                Some(from.interpolate(to, t as f32))
+            }
+            None => self.snapshots.back().map(|last| {
+                let duration = (time - last.time).min(MAX_EXTRAPOLATION);
+                last.extrapolate(duration as f32)
+            }),
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl<'a> System<'a> for ReplicationReceiveSystem {
+    type SystemData = (
+        ReadStorage<'a, Replicated>,
+        WriteStorage<'a, Player>,
+        WriteStorage<'a, Transform>,
+        WriteStorage<'a, Quadruped>,
+        Read<'a, Time>,
+    );
+
+    fn run(&mut self, (replicated, mut players, mut transforms, mut quadrupeds, time): Self::SystemData) {
+        let now = time.absolute_time_seconds();
+        self.receive(now);
+
+        let offset = match self.offset {
+            Some(offset) => offset,
+            None => return,
+        };
+        let snapshot = match self.sample(now + offset - INTERPOLATION_DELAY) {
+            Some(snapshot) => snapshot,
+            None => return,
+        };
+
+        let replica = (&replicated, &mut players, &mut transforms, (&mut quadrupeds).maybe()).join().next();
+        if let Some((_, player, transform, quadruped)) = replica {
+            snapshot.apply(player, transform, quadruped);
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Debug, Clone)]
+enum Role {
+    /// Send the local player to the given peer.
+    Send(SocketAddr),
+    /// Listen on the given address and mirror the player received there.
+    Receive(SocketAddr),
+}
+
+/// Mirrors the controlled cat from one client to another over UDP.
+///
+/// The receiving side overrides its own player after `PlayerSystem` ran,
+/// so the quadruped there is driven entirely by the remote input.
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Debug, Clone)]
+pub struct ReplicationBundle {
+    role: Role,
+    rate: f32,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl ReplicationBundle {
+    pub fn sender(peer: SocketAddr) -> Self {
+        ReplicationBundle { role: Role::Send(peer), rate: 20.0 }
+    }
+
+    pub fn receiver(address: SocketAddr) -> Self {
+        ReplicationBundle { role: Role::Receive(address), rate: 20.0 }
+    }
+
+    /// Snapshots sent per second.
+    pub fn with_rate(mut self, rate: f32) -> Self {
+        self.rate = rate;
+        self
+    }
+
+    /// Read the role from the `--replicate-to <address>` or `--replicate-from <address>` command line options.
+    pub fn from_args() -> Option<Self> {
+        let args: Vec<String> = std::env::args().collect();
+        let value = |name: &str| args
+            .iter()
+            .position(|arg| arg == name)
+            .and_then(|index| args.get(index + 1))
+            .and_then(|address| address.parse().ok());
+
+        value("--replicate-to")
+            .map(Self::sender)
+            .or_else(|| value("--replicate-from").map(Self::receiver))
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl<'a, 'b> SystemBundle<'a, 'b> for ReplicationBundle {
+    fn build(
+        self,
+        _world: &mut World,
+        builder: &mut DispatcherBuilder<'a, 'b>,
+    ) -> Result<(), Error> {
+        match self.role {
+            Role::Send(peer) => {
+                let socket = UdpSocket::bind("0.0.0.0:0")?;
+                socket.set_nonblocking(true)?;
+                let system = ReplicationSendSystem {
+                    socket,
+                    peer,
+                    interval: 1.0 / self.rate,
+                    elapsed: 0.0,
+                };
+                builder.add(system, "replication_send", &["player"]);
+                println!("Replicating player to {}", peer);
+            }
+            Role::Receive(address) => {
+                let socket = UdpSocket::bind(address)?;
+                socket.set_nonblocking(true)?;
+                let system = ReplicationReceiveSystem {
+                    socket,
+                    snapshots: VecDeque::new(),
+                    offset: None,
+                };
+                builder.add(system, "replication_receive", &["player"]);
+                println!("Mirroring player received on {}", address);
+            }
+        }
+        Ok(())
+    }
+}
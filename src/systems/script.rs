@@ -0,0 +1,277 @@
+use std::{collections::HashMap, sync::Arc};
+
+use amethyst::{
+    assets::{Asset, AssetStorage, Format, Handle, Loader, PrefabData, ProgressCounter},
+    core::{
+        math::{Point3, UnitQuaternion, Vector3},
+        Time,
+        Transform,
+    },
+    derive::SystemDesc,
+    ecs::{Component, prelude::*},
+    error::Error,
+    input::{InputHandler, StringBindings},
+};
+use rhai::{AST, CallFnOptions, Dynamic, Engine, Map, Scope};
+use serde::{Deserialize, Serialize};
+
+use ceramic_animation::{
+    systems::player::Player,
+    utils::transform::TransformTrait,
+};
+
+/// Source text of a rhai script.
+#[derive(Debug, Clone)]
+pub struct Script(pub String);
+
+impl Asset for Script {
+    const NAME: &'static str = "ceramic::Script";
+    type Data = Self;
+    type HandleStorage = DenseVecStorage<Handle<Self>>;
+}
+
+#[derive(Debug, Default, Copy, Clone, Serialize, Deserialize)]
+pub struct ScriptFormat;
+
+impl Format<Script> for ScriptFormat {
+    fn name(&self) -> &'static str {
+        "Script"
+    }
+
+    fn import_simple(&self, bytes: Vec<u8>) -> Result<Script, Error> {
+        Ok(Script(String::from_utf8(bytes)?))
+    }
+}
+
+/// A node driven by a script.
+///
+/// The script defines `fn update()`, called every frame with `this` bound to the node's context.
+#[derive(Debug, Clone, Component)]
+#[storage(HashMapStorage)]
+pub struct Scripted {
+    script: Handle<Script>,
+    ast: Option<AST>,
+    failed: bool,
+    state: Map,
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ScriptPrefab {
+    /// Path of the script asset.
+    pub script: String,
+
+    #[serde(skip)]
+    handle: Option<Handle<Script>>,
+}
+
+impl<'a> PrefabData<'a> for ScriptPrefab {
+    type SystemData = (
+        ReadExpect<'a, Loader>,
+        Read<'a, AssetStorage<Script>>,
+        WriteStorage<'a, Scripted>,
+    );
+    type Result = ();
+
+    fn add_to_entity(
+        &self,
+        entity: Entity,
+        (_, _, scripts): &mut Self::SystemData,
+        _entities: &[Entity],
+        _children: &[Entity],
+    ) -> Result<Self::Result, Error> {
+        if let Some(script) = &self.handle {
+            let component = Scripted {
+                script: script.clone(),
+                ast: None,
+                failed: false,
+                state: Map::new(),
+            };
+            scripts.insert(entity, component)?;
+        }
+        Ok(())
+    }
+
+    fn load_sub_assets(
+        &mut self,
+        progress: &mut ProgressCounter,
+        (loader, storage, _): &mut Self::SystemData,
+    ) -> Result<bool, Error> {
+        let handle = loader.load(self.script.as_str(), ScriptFormat, progress, storage);
+        self.handle.replace(handle);
+        Ok(true)
+    }
+}
+
+/// Input state of the current frame, as seen by scripts.
+#[derive(Debug, Default)]
+struct InputSnapshot {
+    actions: HashMap<String, bool>,
+    axes: HashMap<String, f32>,
+}
+
+impl InputSnapshot {
+    fn new(input: &InputHandler<StringBindings>) -> Self {
+        let actions = input.bindings
+            .actions()
+            .map(|action| (action.clone(), input.action_is_down(action).unwrap_or(false)))
+            .collect();
+        let axes = input.bindings
+            .axes()
+            .map(|axis| (axis.clone(), input.axis_value(axis).unwrap_or(0.0)))
+            .collect();
+        Self { actions, axes }
+    }
+}
+
+/// What a script can see and change, bound to `this` inside `update`.
+#[derive(Debug, Clone)]
+struct ScriptContext {
+    translation: Vector3<f32>,
+    rotation: UnitQuaternion<f32>,
+    position: Point3<f32>,
+    player: Option<Point3<f32>>,
+    delta: f32,
+    time: f32,
+    input: Arc<InputSnapshot>,
+    state: Map,
+}
+
+fn register_api(engine: &mut Engine) {
+    engine
+        .register_type_with_name::<ScriptContext>("Context")
+        .register_get("dt", |context: &mut ScriptContext| context.delta)
+        .register_get("time", |context: &mut ScriptContext| context.time)
+        .register_get_set(
+            "x",
+            |context: &mut ScriptContext| context.translation.x,
+            |context: &mut ScriptContext, value: f32| context.translation.x = value,
+        )
+        .register_get_set(
+            "y",
+            |context: &mut ScriptContext| context.translation.y,
+            |context: &mut ScriptContext, value: f32| context.translation.y = value,
+        )
+        .register_get_set(
+            "z",
+            |context: &mut ScriptContext| context.translation.z,
+            |context: &mut ScriptContext, value: f32| context.translation.z = value,
+        )
+        .register_get_set(
+            "state",
+            |context: &mut ScriptContext| context.state.clone(),
+            |context: &mut ScriptContext, state: Map| context.state = state,
+        )
+        .register_fn("translate", |context: &mut ScriptContext, x: f32, y: f32, z: f32| {
+            context.translation += Vector3::new(x, y, z);
+        })
+        .register_fn("rotate_x", |context: &mut ScriptContext, angle: f32| {
+            context.rotation = context.rotation * UnitQuaternion::from_axis_angle(&Vector3::x_axis(), angle);
+        })
+        .register_fn("rotate_y", |context: &mut ScriptContext, angle: f32| {
+            context.rotation = context.rotation * UnitQuaternion::from_axis_angle(&Vector3::y_axis(), angle);
+        })
+        .register_fn("rotate_z", |context: &mut ScriptContext, angle: f32| {
+            context.rotation = context.rotation * UnitQuaternion::from_axis_angle(&Vector3::z_axis(), angle);
+        })
+        .register_fn("action", |context: &mut ScriptContext, name: &str| {
+            context.input.actions.get(name).copied().unwrap_or(false)
+        })
+        .register_fn("axis", |context: &mut ScriptContext, name: &str| {
+            context.input.axes.get(name).copied().unwrap_or(0.0)
+        })
+        .register_fn("player_distance", |context: &mut ScriptContext| {
+            context.player
+                .map(|player| (player - context.position).norm())
+                .unwrap_or(std::f32::INFINITY)
+        });
+}
+
+/// Compiles loaded scripts and runs their `update` function every frame.
+pub struct ScriptSystem {
+    engine: Engine,
+}
+
+impl Default for ScriptSystem {
+    fn default() -> Self {
+        let mut engine = Engine::new();
+        register_api(&mut engine);
+        Self { engine }
+    }
+}
+
+impl<'a> System<'a> for ScriptSystem {
+    type SystemData = (
+        Entities<'a>,
+        ReadStorage<'a, Player>,
+        WriteStorage<'a, Transform>,
+        WriteStorage<'a, Scripted>,
+        Read<'a, AssetStorage<Script>>,
+        Read<'a, InputHandler<StringBindings>>,
+        Read<'a, Time>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (entities, players, mut transforms, mut scripts, storage, input, time) = data;
+        if scripts.is_empty() { return; }
+
+        let player = (&players, &transforms)
+            .join()
+            .map(|(_, transform)| transform.global_position())
+            .next();
+        // Scripts share one read-only snapshot of the input per run.
+        let input = Arc::new(InputSnapshot::new(&input));
+
+        for (entity, scripted) in (&entities, &mut scripts).join() {
+            if scripted.failed { continue; }
+
+            if scripted.ast.is_none() {
+                let script = match storage.get(&scripted.script) {
+                    Some(script) => script,
+                    None => continue,
+                };
+                match self.engine.compile(&script.0) {
+                    Ok(ast) => scripted.ast = Some(ast),
+                    Err(error) => {
+                        eprintln!("Failed to compile script on {:?}: {}", entity, error);
+                        scripted.failed = true;
+                        continue;
+                    }
+                }
+            }
+
+            let transform = match transforms.get(entity) {
+                Some(transform) => transform,
+                None => continue,
+            };
+            let (translation, rotation) = (*transform.translation(), *transform.rotation());
+            let context = ScriptContext {
+                translation,
+                rotation,
+                position: transform.global_position(),
+                player,
+                delta: time.delta_seconds(),
+                time: time.absolute_time_seconds() as f32,
+                input: input.clone(),
+                state: std::mem::take(&mut scripted.state),
+            };
+
+            let mut this = Dynamic::from(context);
+            let ref ast = scripted.ast.as_ref().unwrap();
+            let options = CallFnOptions::new().eval_ast(false).bind_this_ptr(&mut this);
+            if let Err(error) = self.engine.call_fn_with_options::<()>(options, &mut Scope::new(), ast, "update", ()) {
+                eprintln!("Script on {:?} failed: {}", entity, error);
+                scripted.failed = true;
+            }
+
+            // Only write back what the script changed, so idle scripts don't flag their transforms as modified.
+            let context = this.cast::<ScriptContext>();
+            if context.translation != translation || context.rotation != rotation {
+                if let Some(transform) = transforms.get_mut(entity) {
+                    transform.set_translation(context.translation).set_rotation(context.rotation);
+                }
+            }
+            scripted.state = context.state;
+        }
+    }
+}
@@ -0,0 +1,48 @@
+use amethyst::{
+    renderer::skinning::JointTransforms,
+    core::math::Matrix4,
+    derive::{PrefabData, SystemDesc},
+    assets::PrefabData,
+    ecs::{Component, prelude::*},
+    error::Error,
+};
+use serde::{Deserialize, Serialize};
+
+/// Render debugging of skinned meshes, toggled at runtime.
+#[derive(Debug, Default, Copy, Clone)]
+pub struct SkinDebug {
+    /// Show the bind pose instead of the skinned pose.
+    pub bind_pose: bool,
+}
+
+/// Limits `SkinDebug` to the marked meshes; without any marked mesh all skinned meshes are affected.
+#[derive(Debug, Default, Copy, Clone, Serialize, Deserialize, Component, PrefabData)]
+#[storage(NullStorage)]
+#[prefab(Component)]
+pub struct SkinDebugTarget {}
+
+/// Resets the joint matrices of debugged meshes to identity after skinning, which renders the bind pose.
+///
+/// Per-vertex weights cannot be shown this way, as the PBR pass has no input for them.
+#[derive(Default, SystemDesc)]
+pub struct SkinDebugSystem;
+
+impl<'a> System<'a> for SkinDebugSystem {
+    type SystemData = (
+        WriteStorage<'a, JointTransforms>,
+        ReadStorage<'a, SkinDebugTarget>,
+        Read<'a, SkinDebug>,
+    );
+
+    fn run(&mut self, (mut joint_transforms, targets, debug): Self::SystemData) {
+        if !debug.bind_pose { return; }
+
+        let selected = !targets.is_empty();
+        for (joint_transforms, target) in (&mut joint_transforms, targets.maybe()).join() {
+            if selected && target.is_none() { continue; }
+            joint_transforms.matrices
+                .iter_mut()
+                .for_each(|matrix| *matrix = Matrix4::identity());
+        }
+    }
+}
@@ -0,0 +1,96 @@
+use amethyst::{
+    assets::PrefabData,
+    core::Hidden,
+    derive::SystemDesc,
+    ecs::{Component, prelude::*},
+    error::{Error, format_err},
+};
+use serde::{Deserialize, Serialize};
+
+/// Visibility flags of a node, set through its extras, e.g. `"hidden": true` on IK targets and pole empties.
+///
+/// Hidden nodes keep their entity and transform, so animation can still reference them.
+#[derive(Debug, Default, Copy, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Visibility {
+    /// Never render the node.
+    pub hidden: bool,
+    /// Render the node only while its layer is enabled in `RenderLayers`; ignored on hidden nodes.
+    pub render_layer: Option<u32>,
+}
+
+impl<'a> PrefabData<'a> for Visibility {
+    type SystemData = (
+        WriteStorage<'a, Hidden>,
+        WriteStorage<'a, RenderLayer>,
+    );
+    type Result = ();
+
+    fn add_to_entity(
+        &self,
+        entity: Entity,
+        (hiddens, layers): &mut Self::SystemData,
+        _entities: &[Entity],
+        _children: &[Entity],
+    ) -> Result<Self::Result, Error> {
+        if self.hidden {
+            return hiddens.insert(entity, Hidden).map(|_| ()).map_err(Into::into);
+        }
+        match self.render_layer {
+            Some(layer) if layer >= 32 => Err(format_err!("Render layer {} is out of range, there are 32 layers", layer)),
+            Some(layer) => layers.insert(entity, RenderLayer(layer)).map(|_| ()).map_err(Into::into),
+            None => Ok(()),
+        }
+    }
+}
+
+/// The render layer of a node.
+#[derive(Debug, Copy, Clone, Component)]
+#[storage(HashMapStorage)]
+pub struct RenderLayer(pub u32);
+
+/// Bit mask of the render layers shown, all of them by default.
+#[derive(Debug, Copy, Clone)]
+pub struct RenderLayers {
+    pub visible: u32,
+}
+
+impl Default for RenderLayers {
+    fn default() -> Self {
+        RenderLayers { visible: !0 }
+    }
+}
+
+impl RenderLayers {
+    pub fn is_visible(&self, layer: u32) -> bool {
+        self.visible & (1 << layer) != 0
+    }
+}
+
+/// Hides the nodes on render layers that are switched off in `RenderLayers`.
+#[derive(Default, SystemDesc)]
+pub struct RenderLayerSystem;
+
+impl<'a> System<'a> for RenderLayerSystem {
+    type SystemData = (
+        Entities<'a>,
+        ReadStorage<'a, RenderLayer>,
+        WriteStorage<'a, Hidden>,
+        Read<'a, RenderLayers>,
+    );
+
+    fn run(&mut self, (entities, layers, mut hiddens, render_layers): Self::SystemData) {
+        for (entity, RenderLayer(layer)) in (&*entities, &layers).join() {
+            let visible = render_layers.is_visible(*layer);
+            match (visible, hiddens.contains(entity)) {
+                (true, true) => { hiddens.remove(entity); }
+                (false, false) => {
+                    if let Err(error) = hiddens.insert(entity, Hidden) {
+                        eprintln!("Failed to hide {:?}: {}", entity, error);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+}
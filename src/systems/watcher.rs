@@ -0,0 +1,156 @@
+use std::{
+    any::{Any, TypeId},
+    collections::HashMap,
+    fs,
+    marker::PhantomData,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::SystemTime,
+};
+
+use amethyst::{
+    config::Config,
+    core::Time,
+    ecs::prelude::*,
+    error::Error,
+};
+
+use ceramic_animation::systems::animal::GaitPresets;
+
+use crate::platform;
+
+type Value = Arc<dyn Any + Send + Sync>;
+
+type Loader = Box<dyn Fn(&Path) -> Result<Value, Error> + Send + Sync>;
+
+struct Watch {
+    path: PathBuf,
+    modified: Option<SystemTime>,
+    load: Loader,
+    /// The value of the latest reload, if the file changed since it was watched.
+    value: Option<Value>,
+    /// Counts the reloads, so subscribers can tell whether they have seen the latest one.
+    revision: u64,
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|metadata| metadata.modified()).ok()
+}
+
+/// Config files polled for changes, with their latest values by type.
+///
+/// Watch a config once with `watch`, and then have any system ask for new values with `changed`.
+/// Configs compiled into web builds never change, so nothing is watched there.
+#[derive(Default)]
+pub struct ConfigWatcher {
+    watches: HashMap<TypeId, Watch>,
+}
+
+impl ConfigWatcher {
+    /// Watch the config file `name` for values of `T`, replacing an earlier watch for `T`.
+    pub fn watch<T: Config + Send + Sync + 'static>(&mut self, name: &str) {
+        let path = match platform::config_path(name) {
+            Some(path) => path,
+            None => return,
+        };
+        let load: Loader = Box::new(|path: &Path| T::load(path)
+            .map(|value| Arc::new(value) as Value)
+            .map_err(Into::into));
+        let modified = modified(&path);
+        self.watches.insert(TypeId::of::<T>(), Watch { path, modified, load, value: None, revision: 0 });
+    }
+
+    /// The latest value of `T` if it was reloaded since revision `seen`, which is then updated.
+    pub fn changed<T: Send + Sync + 'static>(&self, seen: &mut u64) -> Option<Arc<T>> {
+        let watch = self.watches.get(&TypeId::of::<T>())?;
+        if watch.revision == *seen { return None; }
+        *seen = watch.revision;
+        watch.value.clone()?.downcast::<T>().ok()
+    }
+
+    /// Reload the files modified since the last poll, keeping the old values of those that fail to load.
+    pub fn poll(&mut self) {
+        for watch in self.watches.values_mut() {
+            let modified = modified(&watch.path);
+            if modified == watch.modified { continue; }
+            watch.modified = modified;
+
+            match (watch.load)(&watch.path) {
+                Ok(value) => {
+                    println!("Reloaded {}", watch.path.display());
+                    watch.value = Some(value);
+                    watch.revision += 1;
+                }
+                Err(err) => eprintln!("Failed to reload {}: {}", watch.path.display(), err),
+            }
+        }
+    }
+}
+
+/// Polls the watched config files every `interval` seconds.
+pub struct ConfigWatchSystem {
+    interval: f32,
+    elapsed: f32,
+}
+
+impl ConfigWatchSystem {
+    pub fn new(interval: f32) -> Self {
+        ConfigWatchSystem { interval, elapsed: 0.0 }
+    }
+}
+
+impl<'a> System<'a> for ConfigWatchSystem {
+    type SystemData = (
+        Write<'a, ConfigWatcher>,
+        Read<'a, Time>,
+    );
+
+    fn run(&mut self, (mut watcher, time): Self::SystemData) {
+        self.elapsed += time.delta_real_seconds();
+        if self.elapsed >= self.interval {
+            self.elapsed = 0.0;
+            watcher.poll();
+        }
+    }
+}
+
+/// A resource that takes new values from its config file.
+pub trait Reload: Clone + Send + Sync + 'static {
+    /// Take the values read from the file, keeping any state that isn't part of the file.
+    fn reload(&mut self, value: &Self) {
+        *self = value.clone();
+    }
+}
+
+impl Reload for GaitPresets {
+    fn reload(&mut self, value: &Self) {
+        let active = self.active.take();
+        *self = value.clone();
+        self.active = active;
+    }
+}
+
+/// Reloads the resource `T` whenever its watched config changes.
+pub struct ReloadResourceSystem<T> {
+    seen: u64,
+    marker: PhantomData<T>,
+}
+
+impl<T> Default for ReloadResourceSystem<T> {
+    fn default() -> Self {
+        ReloadResourceSystem { seen: 0, marker: PhantomData }
+    }
+}
+
+impl<'a, T: Reload> System<'a> for ReloadResourceSystem<T> {
+    type SystemData = (
+        Read<'a, ConfigWatcher>,
+        WriteExpect<'a, T>,
+    );
+
+    fn run(&mut self, (watcher, mut resource): Self::SystemData) {
+        if let Some(value) = watcher.changed::<T>(&mut self.seen) {
+            resource.reload(&value);
+        }
+    }
+}
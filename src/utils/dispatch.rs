@@ -0,0 +1,91 @@
+use std::{fmt::Write as _, fs, path::PathBuf};
+
+use amethyst::{
+    core::bundle::SystemBundle,
+    ecs::prelude::*,
+    error::Error,
+};
+
+/// Writes the layout of the dispatcher as a DOT graph when built.
+///
+/// The bundle must be added last, as it only sees systems registered before it.
+/// Systems of the same stage group run in sequence; groups of a stage run in parallel
+/// and every stage waits for the previous one.
+#[derive(Debug, Clone)]
+pub struct DispatchGraphBundle {
+    path: PathBuf,
+}
+
+impl DispatchGraphBundle {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        DispatchGraphBundle { path: path.into() }
+    }
+
+    /// Read the output path from the `--dispatch-graph <path>` command line option.
+    pub fn from_args() -> Option<Self> {
+        let mut args = std::env::args().skip_while(|arg| arg != "--dispatch-graph");
+        args.next()?;
+        args.next().map(Self::new)
+    }
+}
+
+/// Parse the `seq![par![seq![...]]]` layout printed by `DispatcherBuilder`.
+fn parse_stages(layout: &str) -> Vec<Vec<Vec<String>>> {
+    let mut stages: Vec<Vec<Vec<String>>> = Vec::new();
+    let mut depth = 0;
+    for line in layout.lines().map(str::trim) {
+        match line {
+            "seq![" | "par![" => {
+                depth += 1;
+                match depth {
+                    2 => stages.push(Vec::new()),
+                    3 => if let Some(stage) = stages.last_mut() { stage.push(Vec::new()) },
+                    _ => {}
+                }
+            }
+            "]" | "]," => depth -= 1,
+            name => if let Some(group) = stages.last_mut().and_then(|stage| stage.last_mut()) {
+                group.push(name.trim_end_matches(',').to_string());
+            }
+        }
+    }
+    stages
+}
+
+fn to_dot(stages: &[Vec<Vec<String>>]) -> String {
+    let mut dot = String::from("digraph dispatcher {\n    rankdir=LR;\n    node [shape=box];\n");
+    for (index, stage) in stages.iter().enumerate() {
+        let _ = writeln!(dot, "    subgraph cluster_{} {{\n        label=\"stage {}\";", index, index);
+        for group in stage {
+            for name in group {
+                let _ = writeln!(dot, "        \"{}\";", name);
+            }
+            for (from, to) in group.iter().zip(group.iter().skip(1)) {
+                let _ = writeln!(dot, "        \"{}\" -> \"{}\";", from, to);
+            }
+        }
+        dot.push_str("    }\n");
+    }
+    for (stage, next) in stages.iter().zip(stages.iter().skip(1)) {
+        for from in stage.iter().filter_map(|group| group.last()) {
+            for to in next.iter().filter_map(|group| group.first()) {
+                let _ = writeln!(dot, "    \"{}\" -> \"{}\" [style=dashed];", from, to);
+            }
+        }
+    }
+    dot.push_str("}\n");
+    dot
+}
+
+impl<'a, 'b> SystemBundle<'a, 'b> for DispatchGraphBundle {
+    fn build(
+        self,
+        _world: &mut World,
+        builder: &mut DispatcherBuilder<'a, 'b>,
+    ) -> Result<(), Error> {
+        let stages = parse_stages(&format!("{:?}", builder));
+        fs::write(&self.path, to_dot(&stages))?;
+        println!("Dispatcher graph written to {}", self.path.display());
+        Ok(())
+    }
+}
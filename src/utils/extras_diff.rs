@@ -0,0 +1,161 @@
+//! Comparing the node extras of two glTF files, e.g. a model before and after re-exporting it from Blender.
+//!
+//! Extras are parsed as `Extras` and compared by value after being written back, so defaults filled in and
+//! the order of keys don't show up as changes; extras that fail to parse are compared as they are in the file.
+
+use std::{collections::{BTreeMap, BTreeSet}, fs, path::Path};
+
+use amethyst::error::{Error, format_err};
+use serde_json::Value;
+
+use amethyst_gltf::read_node_extras;
+
+use crate::{console::Console, scene::Extras};
+
+/// A difference between the extras of a node in the old file and in the new one.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Change {
+    Added(String, Value),
+    Removed(String, Value),
+    Changed(String, Value, Value),
+}
+
+/// Differences between the nodes of two files, by node name.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct ExtrasDiff {
+    pub added_nodes: Vec<String>,
+    pub removed_nodes: Vec<String>,
+    pub changed_nodes: BTreeMap<String, Vec<Change>>,
+    /// Nodes whose extras don't parse, with the error, in either file.
+    pub invalid: Vec<(String, String)>,
+}
+
+impl ExtrasDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added_nodes.is_empty() && self.removed_nodes.is_empty() && self.changed_nodes.is_empty()
+    }
+
+    /// Compares the extras of the nodes of the files at `old` and `new`.
+    pub fn between(old: &Path, new: &Path) -> Result<Self, Error> {
+        let old_nodes = Self::read(old)?;
+        let new_nodes = Self::read(new)?;
+        let mut diff = ExtrasDiff::default();
+
+        for (name, (old_extras, old_error)) in &old_nodes {
+            if let Some(error) = old_error {
+                diff.invalid.push((format!("{} in {}", name, old.display()), error.clone()));
+            }
+            let new_extras = match new_nodes.get(name) {
+                Some((new_extras, _)) => new_extras,
+                None => {
+                    diff.removed_nodes.push(name.clone());
+                    continue;
+                }
+            };
+            let mut changes = Vec::new();
+            compare("", old_extras, new_extras, &mut changes);
+            if !changes.is_empty() {
+                diff.changed_nodes.insert(name.clone(), changes);
+            }
+        }
+        for (name, (_, new_error)) in &new_nodes {
+            if !old_nodes.contains_key(name) {
+                diff.added_nodes.push(name.clone());
+            }
+            if let Some(error) = new_error {
+                diff.invalid.push((format!("{} in {}", name, new.display()), error.clone()));
+            }
+        }
+        Ok(diff)
+    }
+
+    /// The extras of each named node as written back from `Extras`, with the error of those that don't parse.
+    fn read(path: &Path) -> Result<BTreeMap<String, (Value, Option<String>)>, Error> {
+        let data = fs::read(path).map_err(|error| format_err!("Failed to read {}: {}", path.display(), error))?;
+        let nodes = read_node_extras(&data)?
+            .into_iter()
+            .map(|(name, extras)| {
+                let extras = extras.unwrap_or_else(|| Value::Object(Default::default()));
+                let parsed = serde_json::from_value::<Extras>(extras.clone())
+                    .and_then(|parsed| serde_json::to_value(&parsed));
+                let node = match parsed {
+                    Ok(parsed) => (parsed, None),
+                    Err(error) => (extras, Some(error.to_string())),
+                };
+                (name, node)
+            })
+            .collect();
+        Ok(nodes)
+    }
+
+    pub fn print(&self) {
+        if self.is_empty() {
+            println!("No node extras changed");
+        }
+        for name in &self.removed_nodes {
+            println!("- {}", name);
+        }
+        for name in &self.added_nodes {
+            println!("+ {}", name);
+        }
+        for (name, changes) in &self.changed_nodes {
+            println!("~ {}", name);
+            for change in changes {
+                match change {
+                    Change::Added(path, value) => println!("    + {}: {}", path, value),
+                    Change::Removed(path, value) => println!("    - {}: {}", path, value),
+                    Change::Changed(path, old, new) => println!("    ~ {}: {} -> {}", path, old, new),
+                }
+            }
+        }
+        for (node, error) in &self.invalid {
+            println!("! {}: {}", node, error);
+        }
+    }
+}
+
+/// Collects the differences between `old` and `new` under `path`, by key into objects and by index into
+/// arrays of the same length. Keys set to `null`, like unset options, count as missing.
+fn compare(path: &str, old: &Value, new: &Value, changes: &mut Vec<Change>) {
+    let join = |key: &str| if path.is_empty() { key.to_string() } else { format!("{}.{}", path, key) };
+    match (old, new) {
+        (Value::Object(old), Value::Object(new)) => {
+            let keys = old.keys().chain(new.keys()).collect::<BTreeSet<_>>();
+            for key in keys {
+                let old = old.get(key).filter(|value| !value.is_null());
+                let new = new.get(key).filter(|value| !value.is_null());
+                match (old, new) {
+                    (Some(old), Some(new)) => compare(&join(key), old, new, changes),
+                    (Some(old), None) => changes.push(Change::Removed(join(key), old.clone())),
+                    (None, Some(new)) => changes.push(Change::Added(join(key), new.clone())),
+                    (None, None) => unreachable!(),
+                }
+            }
+        }
+        (Value::Array(old), Value::Array(new)) if old.len() == new.len() => {
+            for (index, (old, new)) in old.iter().zip(new).enumerate() {
+                compare(&format!("{}[{}]", path, index), old, new, changes);
+            }
+        }
+        (old, new) if old != new => changes.push(Change::Changed(path.to_string(), old.clone(), new.clone())),
+        _ => {}
+    }
+}
+
+/// The two paths given after `--diff-extras` on the command line.
+pub fn paths_from_args() -> Option<(String, String)> {
+    let mut args = std::env::args().skip_while(|arg| arg != "--diff-extras");
+    args.next()?;
+    Some((args.next()?, args.next()?))
+}
+
+/// Registers `diff <old> <new>`.
+pub fn register_commands(console: &mut Console) {
+    console.register("diff", "diff <old> <new>", |_, args| match args {
+        [old, new] => {
+            ExtrasDiff::between(Path::new(old), Path::new(new))?.print();
+            Ok(())
+        }
+        _ => Err(format_err!("Usage: diff <old> <new>")),
+    });
+}